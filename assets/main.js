@@ -12,9 +12,17 @@ function addRouteListener(callback) {
 }
 
 function pushHistoryState(url) {
+  // Don't push the same location twice, e.g. after navigating back.
+  if (new URL(url, document.baseURI).pathname === window.location.pathname) {
+    return;
+  }
   window.history.pushState({}, '', url);
 }
 
+function setTitle(title) {
+  document.title = title;
+}
+
 function getRandom() {
   return Math.random()
 }
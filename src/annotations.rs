@@ -1,20 +1,50 @@
 use std::collections::HashMap;
 
-use egui::{Button, Color32, Grid, TextEdit};
+use egui::{ahash::HashSet, Button, Color32, Grid, TextEdit};
 use serde::{Deserialize, Serialize};
 
 use crate::{bitcoin::Txid, export};
 
-#[derive(PartialEq, Eq, Debug, Default, Serialize, Deserialize, Clone)]
+#[derive(Eq, Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Annotations {
     tx_color: HashMap<Txid, [u8; 3]>,
     tx_label: HashMap<Txid, String>,
     coin_color: HashMap<(Txid, usize), [u8; 3]>,
     coin_label: HashMap<(Txid, usize), String>,
+    /// Counts the edits, so that [crate::history] notices them without
+    /// comparing all annotations.
+    #[serde(skip)]
+    revision: u64,
+}
+
+impl PartialEq for Annotations {
+    fn eq(&self, other: &Self) -> bool {
+        self.tx_color == other.tx_color
+            && self.tx_label == other.tx_label
+            && self.coin_color == other.coin_color
+            && self.coin_label == other.coin_label
+    }
+}
+
+/// Set or, with `None`, remove the value of `key`. Returns whether that
+/// changed anything.
+fn update<K: Eq + std::hash::Hash, T: PartialEq>(
+    map: &mut HashMap<K, T>,
+    key: K,
+    value: Option<T>,
+) -> bool {
+    match value {
+        Some(value) if map.get(&key) != Some(&value) => {
+            map.insert(key, value);
+            true
+        }
+        Some(_) => false,
+        None => map.remove(&key).is_some(),
+    }
 }
 
 impl Annotations {
-    const COLORS: [Color32; 7] = [
+    pub const COLORS: [Color32; 7] = [
         Color32::RED,
         Color32::GREEN,
         Color32::GOLD,
@@ -57,6 +87,7 @@ impl Annotations {
             tx_label: txids_from_strings(&annotations.tx_label)?,
             coin_color: txos_from_strings(&annotations.coin_color)?,
             coin_label: txos_from_strings(&annotations.coin_label)?,
+            revision: 0,
         };
 
         Ok(result)
@@ -83,14 +114,102 @@ impl Annotations {
         }
     }
 
+    /// Only keep the annotations of the given transactions and coins.
+    pub fn subset(&self, txids: &HashSet<Txid>, coins: &HashSet<(Txid, usize)>) -> Self {
+        self.filter(|txid| txids.contains(txid), |coin| coins.contains(coin))
+    }
+
+    /// The annotations of a transaction and its outputs.
+    pub fn of_tx(&self, txid: Txid) -> Self {
+        self.filter(|t| *t == txid, |coin| coin.0 == txid)
+    }
+
+    fn filter(
+        &self,
+        keep_tx: impl Fn(&Txid) -> bool,
+        keep_coin: impl Fn(&(Txid, usize)) -> bool,
+    ) -> Self {
+        fn filter<K: Eq + std::hash::Hash + Copy, T: Clone>(
+            map: &HashMap<K, T>,
+            keep: &impl Fn(&K) -> bool,
+        ) -> HashMap<K, T> {
+            map.iter()
+                .filter(|(k, _)| keep(k))
+                .map(|(k, v)| (*k, v.clone()))
+                .collect()
+        }
+
+        Self {
+            tx_color: filter(&self.tx_color, &keep_tx),
+            tx_label: filter(&self.tx_label, &keep_tx),
+            coin_color: filter(&self.coin_color, &keep_coin),
+            coin_label: filter(&self.coin_label, &keep_coin),
+            revision: 0,
+        }
+    }
+
+    /// Add the annotations of `other` that aren't set here yet. Returns how
+    /// many were added.
+    pub fn merge(&mut self, other: Self) -> usize {
+        fn merge<K: Eq + std::hash::Hash, T>(
+            map: &mut HashMap<K, T>,
+            other: HashMap<K, T>,
+        ) -> usize {
+            let mut added = 0;
+            for (k, v) in other {
+                if let std::collections::hash_map::Entry::Vacant(e) = map.entry(k) {
+                    e.insert(v);
+                    added += 1;
+                }
+            }
+            added
+        }
+
+        let added = merge(&mut self.tx_color, other.tx_color)
+            + merge(&mut self.tx_label, other.tx_label)
+            + merge(&mut self.coin_color, other.coin_color)
+            + merge(&mut self.coin_label, other.coin_label);
+        if added > 0 {
+            self.revision += 1;
+        }
+        added
+    }
+
+    /// Changes whenever the annotations are edited.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn edit(&mut self, changed: bool) {
+        if changed {
+            self.revision += 1;
+        }
+    }
+
+    /// All transaction and coin labels, e.g. for searching.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.tx_label
+            .values()
+            .chain(self.coin_label.values())
+            .map(String::as_str)
+    }
+
     pub fn set_tx_color(&mut self, txid: Txid, color: Color32) {
-        self.tx_color
-            .insert(txid, [color.r(), color.g(), color.b()]);
+        let changed = update(
+            &mut self.tx_color,
+            txid,
+            Some([color.r(), color.g(), color.b()]),
+        );
+        self.edit(changed);
     }
 
     pub fn set_coin_color(&mut self, coin: (Txid, usize), color: Color32) {
-        self.coin_color
-            .insert(coin, [color.r(), color.g(), color.b()]);
+        let changed = update(
+            &mut self.coin_color,
+            coin,
+            Some([color.r(), color.g(), color.b()]),
+        );
+        self.edit(changed);
     }
 
     pub fn tx_color(&self, txid: Txid) -> Option<Color32> {
@@ -107,12 +226,14 @@ impl Annotations {
 
     #[allow(dead_code)]
     pub fn set_tx_label(&mut self, txid: Txid, label: String) {
-        self.tx_label.insert(txid, label);
+        let changed = update(&mut self.tx_label, txid, Some(label));
+        self.edit(changed);
     }
 
     #[allow(dead_code)]
     pub fn set_coin_label(&mut self, coin: (Txid, usize), label: String) {
-        self.coin_label.insert(coin, label);
+        let changed = update(&mut self.coin_label, coin, Some(label));
+        self.edit(changed);
     }
 
     pub fn tx_label(&self, txid: Txid) -> Option<String> {
@@ -154,18 +275,20 @@ impl Annotations {
                     }
                 }
                 if ui.button("✖").clicked() {
-                    self.coin_color.remove(&coin);
+                    let changed = update(&mut self.coin_color, coin, None);
+                    self.edit(changed);
                     ui.close_menu();
                 }
             });
             ui.end_row();
         });
 
-        if label.is_empty() {
-            self.coin_label.remove(&coin);
-        } else {
-            self.coin_label.insert(coin, label);
-        }
+        let changed = update(
+            &mut self.coin_label,
+            coin,
+            (!label.is_empty()).then_some(label),
+        );
+        self.edit(changed);
     }
 
     pub fn tx_menu(&mut self, txid: Txid, ui: &mut egui::Ui) {
@@ -203,17 +326,19 @@ impl Annotations {
                     }
                 }
                 if ui.button("✖").clicked() {
-                    self.tx_color.remove(&txid);
+                    let changed = update(&mut self.tx_color, txid, None);
+                    self.edit(changed);
                     ui.close_menu();
                 }
             });
             ui.end_row();
         });
 
-        if label.is_empty() {
-            self.tx_label.remove(&txid);
-        } else {
-            self.tx_label.insert(txid, label);
-        }
+        let changed = update(
+            &mut self.tx_label,
+            txid,
+            (!label.is_empty()).then_some(label),
+        );
+        self.edit(changed);
     }
 }
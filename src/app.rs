@@ -1,23 +1,58 @@
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 
-use egui::{Context, CursorIcon, Frame, Key, Pos2, Rect, RichText, Sense, Vec2};
+use egui::{
+    ahash::HashSet, Context, CursorIcon, Frame, Key, Pos2, ProgressBar, Rect, Rounding, Sense,
+    Stroke, Vec2,
+};
+use uuid::Uuid;
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::bitcoind::BitcoindImport;
 use crate::{
     annotations::Annotations,
-    bitcoin::{Transaction, Txid},
-    components::{about::About, custom_tx::CustomTx},
+    axis,
+    bitcoin::{Network, Transaction, TxRef, Txid},
+    client::{Client, FetchError},
+    components::{
+        about::About,
+        address_book::AddressBook,
+        address_stats::AddressStats,
+        annotate_trace::{AnnotateTrace, Trace},
+        block_strip::BlockStrip,
+        collections::{Collections, Entry},
+        custom_tx::CustomTx,
+        follow_money::FollowMoney,
+        layout_json::LayoutJson,
+        ownership::OwnershipAssistant,
+        peel_chains::PeelChains,
+        removed::Removed,
+        search::Search,
+        unspent::{Accounted, Found, UnspentDescendants},
+        wallet_import::{WalletImport, WalletTx},
+        watch_list::WatchList,
+        xpub_import::XpubImport,
+    },
+    evidence::{Evidence, Source},
     export::{self, Workspace},
     flight::Flight,
     framerate::FrameRate,
-    graph::Graph,
-    layout::Layout,
+    graph::{self, Graph, HopDirection, TaintModel},
+    history::{Edit, GraphState, History, Snapshot},
+    instanced::SharedRenderer,
+    layout::{Layout, SpawnDirection},
     loading::Loading,
-    notifications::Notifications,
+    modal,
+    notifications::{Action, Notifications, NotifyExt},
     platform::inner as platform,
+    report::{self, ReportBuilder},
+    settings::Settings,
+    spends::SpendRefresh,
+    status_bar::StatusBar,
     workspaces::{Workspaces, WorkspacesHandle},
-    style::{Theme, ThemeSwitch},
+    style::{self, ColorBy, Theme, ThemeSwitch},
     transform::Transform,
     tx_cache::TxCache,
+    watches::{Check, Rule, Watches},
 };
 
 #[derive(Default, serde::Deserialize, serde::Serialize)]
@@ -26,9 +61,36 @@ pub struct AppStore {
     layout: Layout,
     transform: Transform,
     theme: Theme,
+    settings: Settings,
     about: About,
+    collections: Collections,
+    /// Alerts from before they were stored in the workspace. They move into
+    /// the first workspace that is loaded.
+    #[serde(rename = "watches", skip_serializing)]
+    legacy_watches: Watches,
+    ownership: OwnershipAssistant,
+    block_strip: BlockStrip,
+    /// Shared by all workspaces.
+    address_book: AddressBook,
+    #[cfg(not(target_arch = "wasm32"))]
+    bitcoind: BitcoindImport,
 }
 
+/// Longer address histories are cut off, newest transactions first.
+const MAX_ADDRESS_TXS: usize = 500;
+
+/// Expanding stops after this many new transactions, so that a few hops
+/// around an exchange don't flood the graph.
+const MAX_EXPAND_TXS: usize = 300;
+
+/// Tracing to the origin stops after this many new transactions. Coins of
+/// any age typically descend from thousands of coinbase transactions.
+const MAX_TRACE_TXS: usize = 1000;
+
+/// Discrepancies listed in the notification of an integrity check, so that it
+/// still fits on the screen.
+const MAX_LISTED_DISCREPANCIES: usize = 5;
+
 pub enum Update {
     LoadOrSelectTx {
         txid: Txid,
@@ -37,6 +99,21 @@ pub enum Update {
     SelectTx {
         txid: Txid,
     },
+    ToggleSelectTx {
+        txid: Txid,
+    },
+    SelectCoin {
+        coin: (Txid, usize),
+    },
+    SelectTxs {
+        txids: Vec<Txid>,
+    },
+    /// Fly to a transaction of the graph, select it and the coin, and make it
+    /// flash for a moment.
+    Highlight {
+        txid: Txid,
+        coin: Option<(Txid, usize)>,
+    },
     AddTx {
         txid: Txid,
         tx: Transaction,
@@ -45,9 +122,146 @@ pub enum Update {
     RemoveTx {
         txid: Txid,
     },
+    /// Remove several transactions as one step, keeping the protected ones.
+    RemoveTxs {
+        txids: Vec<Txid>,
+    },
+    /// Put removed transactions back where they were.
+    RestoreTxs {
+        txs: Vec<export::Transaction>,
+    },
     LoadWorkspace {
         data: Workspace,
     },
+    /// Add annotations from another workspace, keeping the ones set already.
+    MergeAnnotations {
+        annotations: Annotations,
+    },
+    /// Fetch the details of workspace transactions that came into view.
+    Hydrate {
+        txids: Vec<Txid>,
+    },
+    FillPlaceholder {
+        txid: Txid,
+        tx: Transaction,
+    },
+    PlaceholderFailed {
+        txid: Txid,
+    },
+    /// Copy the given transactions (or their connected components) into a new
+    /// workspace.
+    ExtractWorkspace {
+        txids: Vec<Txid>,
+        connected: bool,
+    },
+    /// Load the transactions from a wallet's history and label them with the
+    /// wallet name (and optionally the wallet's own labels).
+    ImportWallet {
+        name: String,
+        txs: Vec<WalletTx>,
+        apply_labels: bool,
+    },
+    /// Look up the histories of the addresses derived from an xpub and import
+    /// them all as one wallet.
+    ImportXpub {
+        name: String,
+        addresses: Vec<String>,
+    },
+    AddWatch {
+        rule: Rule,
+    },
+    /// Watch a list of coins and addresses, e.g. from another analyst.
+    ImportWatches {
+        rules: Vec<Rule>,
+    },
+    RemoveWatch {
+        id: Uuid,
+    },
+    WatchChecked {
+        id: Uuid,
+        check: Check,
+    },
+    /// Check whether the unspent outputs of the graph are still unspent.
+    RefreshSpends,
+    /// The spending transactions of the outputs of `txid`, by vout, or `None`
+    /// if the check failed.
+    OutspendsChecked {
+        txid: Txid,
+        spending: Option<Vec<Option<Txid>>>,
+    },
+    LabelCoins {
+        labels: Vec<((Txid, usize), String)>,
+    },
+    /// Load the ancestors and/or descendants of a transaction up to `depth`
+    /// hops away.
+    ExpandHops {
+        txid: Txid,
+        depth: usize,
+        direction: HopDirection,
+    },
+    /// Load the ancestors of a transaction until coinbase transactions are
+    /// reached.
+    TraceToOrigin {
+        txid: Txid,
+    },
+    /// Follow an output forward, hop by hop.
+    FollowMoney {
+        coin: (Txid, usize),
+    },
+    /// Follow the continuing output of a peel chain until the chain ends.
+    FollowPeelChain {
+        coin: (Txid, usize),
+    },
+    /// Load the descendants of a transaction, or of one of its outputs, until
+    /// the value rests in unspent outputs.
+    FindUnspent {
+        txid: Txid,
+        vout: Option<usize>,
+    },
+    /// Where the traced value went so far, while finding unspent
+    /// descendants.
+    UnspentProgress {
+        from: (Txid, Option<usize>),
+        accounted: Accounted,
+    },
+    /// Add the transactions leading to the unspent outputs that were found.
+    UnspentFound {
+        found: Found,
+        txs: Vec<(Transaction, Pos2)>,
+    },
+    /// Fetch the history of an address and place it as a chain.
+    LoadAddress {
+        address: String,
+    },
+    AddAddressTxs {
+        address: String,
+        txs: Vec<Transaction>,
+    },
+    /// Preview and apply an annotation to a whole trace, selecting it in the
+    /// meantime.
+    AnnotateTrace {
+        trace: Trace,
+    },
+    /// Move transactions to positions computed by another layout tool.
+    ArrangeTxs {
+        positions: Vec<(Txid, Pos2)>,
+    },
+}
+
+impl Update {
+    /// Whether the update merges data from the backend into the workspace,
+    /// which is refused while their networks don't match.
+    fn merges_backend_data(&self) -> bool {
+        matches!(
+            self,
+            Update::AddTx { .. }
+                | Update::FillPlaceholder { .. }
+                | Update::WatchChecked { .. }
+                | Update::OutspendsChecked { .. }
+                | Update::UnspentFound { .. }
+                | Update::AddAddressTxs { .. }
+        )
+    }
 }
 
 pub struct App {
@@ -58,14 +272,46 @@ pub struct App {
 
     annotations: Annotations,
     graph: Graph,
+    history: History,
+    /// Whether a transaction was being dragged in the last frame.
+    dragging: bool,
+    /// The graph when the pointer was last pressed, from before a drag that
+    /// starts a few frames later.
+    pressed: Option<GraphState>,
+    /// Inconsistent data from the backend is only pointed out once.
+    warned_dropped_edges: bool,
 
     flight: Flight,
+    follow_money: FollowMoney,
+    search: Search,
+    removed: Removed,
+    unspent: UnspentDescendants,
+    layout_json: LayoutJson,
+    peel_chains: PeelChains,
     ui_size: Vec2,
+    /// Where the selection rectangle was started, in screen coordinates.
+    band_start: Option<Pos2>,
     custom_tx: CustomTx,
+    wallet_import: WalletImport,
+    xpub_import: XpubImport,
+    address_stats: AddressStats,
+    annotate_trace: AnnotateTrace,
+    watches: Watches,
+    watch_list: WatchList,
+    spend_refresh: SpendRefresh,
+    report: ReportBuilder,
+    evidence: Evidence,
     framerate: FrameRate,
+    status_bar: StatusBar,
     about_rect: Option<egui::Rect>,
     notifications: Notifications,
     workspaces: Workspaces,
+    /// Last route and title we handed to the browser.
+    location: Option<String>,
+    title: String,
+    rect_renderer: Option<SharedRenderer>,
+    /// The network the open workspace was created on, if known.
+    network: Option<Network>,
 }
 
 impl App {
@@ -125,6 +371,11 @@ impl App {
 
         platform::add_route_listener(update_sender.clone(), cc.egui_ctx.clone());
 
+        let rect_renderer = cc
+            .gl
+            .as_ref()
+            .and_then(|gl| SharedRenderer::install(&cc.egui_ctx, gl));
+
         App {
             store,
             update_sender,
@@ -132,18 +383,114 @@ impl App {
 
             annotations: Default::default(),
             graph: Default::default(),
+            history: Default::default(),
+            dragging: false,
+            pressed: None,
+            warned_dropped_edges: false,
 
             flight: Flight::new(),
+            follow_money: Default::default(),
+            search: Default::default(),
+            removed: Default::default(),
+            unspent: Default::default(),
+            layout_json: Default::default(),
+            peel_chains: Default::default(),
+            band_start: None,
             ui_size: platform::get_viewport_dimensions().unwrap_or_default(),
             custom_tx: Default::default(),
+            wallet_import: Default::default(),
+            xpub_import: Default::default(),
+            address_stats: Default::default(),
+            annotate_trace: Default::default(),
+            watches: Default::default(),
+            watch_list: Default::default(),
+            spend_refresh: Default::default(),
+            report: Default::default(),
+            evidence: Default::default(),
             framerate: FrameRate::default(),
+            status_bar: StatusBar::default(),
             about_rect: None,
             notifications: Notifications::new(&cc.egui_ctx),
             workspaces,
+            location: None,
+            title: String::new(),
+            rect_renderer,
+            network: None,
+        }
+    }
+
+    /// Keep the URL and document title in sync with the current selection so
+    /// that browser history and bookmarks are meaningful.
+    fn sync_location(&mut self) {
+        let location = match (self.graph.selected_coin(), self.graph.selected()) {
+            (Some((txid, vout)), _) => Some(format!("tx/{}/{}", txid, vout)),
+            (None, Some(txid)) => Some(format!("tx/{}", txid)),
+            (None, None) => None,
+        };
+        if let Some(path) = &location {
+            if self.location.as_ref() != Some(path) {
+                platform::push_history_state(path);
+                self.location = location;
+            }
+        }
+
+        let mut parts = vec![];
+        if let Some(txid) = self.graph.selected() {
+            parts.push(
+                self.annotations
+                    .tx_label(txid)
+                    .unwrap_or_else(|| txid.hex_string()[..8].to_string()),
+            );
+        }
+        parts.push(self.workspaces.current_name().to_string());
+        parts.push("txgraph.info".to_string());
+
+        let title = parts.join(" · ");
+        if title != self.title {
+            platform::set_title(&title);
+            self.title = title;
+        }
+    }
+
+    /// The networks of the open workspace and of the backend, if they differ.
+    fn network_mismatch(&self) -> Option<(Network, Network)> {
+        match (self.network, self.status_bar.network()) {
+            (Some(workspace), Some(backend)) if workspace != backend => Some((workspace, backend)),
+            _ => None,
         }
     }
 
+    /// Block the app until the user opens a workspace of the backend's
+    /// network. Transactions of another chain would make for a graph that
+    /// looks fine but is wrong.
+    fn show_network_mismatch(&self, ctx: &Context) {
+        let Some((workspace, backend)) = self.network_mismatch() else {
+            return;
+        };
+        modal::show(ctx, "Network Mismatch", |ui| {
+            ui.label(format!(
+                "Workspace `{}` was created on {}, but the backend at {} serves {}.",
+                self.workspaces.current_name(),
+                workspace,
+                Client::base_url(ctx),
+                backend
+            ));
+            ui.label("Nothing from the backend is added to it until they match.");
+            ui.add_space(8.0);
+            if ui.button(format!("New {} workspace", backend)).clicked() {
+                let data = Workspace {
+                    network: Some(backend),
+                    ..Default::default()
+                };
+                WorkspacesHandle::new_workspace(ctx, "Unnamed".to_string(), data);
+            }
+        });
+    }
+
     pub fn apply_update(&mut self, ctx: &Context, update: Update) {
+        if update.merges_backend_data() && self.network_mismatch().is_some() {
+            return;
+        }
         match update {
             Update::LoadOrSelectTx { txid, pos } => {
                 if let Some(existing_pos) = self.graph.get_tx_pos(txid) {
@@ -184,34 +531,651 @@ impl App {
                     }
                 }
             }
+            Update::ToggleSelectTx { txid } => {
+                self.graph.toggle_selected(txid);
+            }
+            Update::SelectCoin { coin } => {
+                self.graph.select_coin(coin);
+            }
+            Update::SelectTxs { txids } => {
+                self.graph.select_all(txids);
+            }
+            Update::Highlight { txid, coin } => {
+                let Some(pos) = self.graph.get_tx_pos(txid) else {
+                    return;
+                };
+                self.graph.select(txid);
+                if let Some(coin) = coin {
+                    self.graph.select_coin(coin);
+                }
+                self.graph.flash(txid, ctx.input(|i| i.time));
+                self.flight.start(
+                    (self.ui_size / 2.0).to_pos2(),
+                    self.store.transform.pos_to_screen(pos),
+                );
+            }
             Update::AddTx { txid, tx, pos } => {
+                self.history.edited(Edit::Graph, &self.graph);
                 self.graph.add_tx(txid, tx, pos);
             }
             Update::RemoveTx { txid } => {
+                if self.graph.is_protected(txid) {
+                    ctx.notify_warn(
+                        format!("Transaction {} is protected.", txid),
+                        Some("Unprotect it from its context menu to remove it."),
+                    );
+                    return;
+                }
+                self.history.edited(Edit::Graph, &self.graph);
+                let removed = self.graph.export_subset(&HashSet::from_iter([txid]));
                 self.graph.remove_tx(txid);
+                for tx in removed {
+                    self.removed.push(tx);
+                }
+            }
+            Update::RemoveTxs { txids } => {
+                let (kept, txids): (Vec<Txid>, Vec<Txid>) = txids
+                    .into_iter()
+                    .partition(|txid| self.graph.is_protected(*txid));
+                if !kept.is_empty() {
+                    ctx.notify_warn(
+                        format!("Kept {} protected transactions.", kept.len()),
+                        Some("Unprotect them from their context menu to remove them."),
+                    );
+                }
+                if txids.is_empty() {
+                    return;
+                }
+                self.history.edited(Edit::Graph, &self.graph);
+                let removed = self.graph.export_subset(&txids.iter().copied().collect());
+                for txid in txids {
+                    self.graph.remove_tx(txid);
+                }
+                for tx in removed {
+                    self.removed.push(tx);
+                }
+            }
+            Update::RestoreTxs { txs } => {
+                self.history.edited(Edit::Graph, &self.graph);
+                for tx in txs {
+                    self.graph.set_protected([tx.txid], tx.protected);
+                    self.graph.set_pinned([tx.txid], tx.pinned);
+                    self.graph.add_placeholder(tx.txid, tx.position, tx.summary);
+                }
             }
             Update::LoadWorkspace { data } => {
+                self.history.clear();
+                self.removed.clear();
+                self.network = data.network;
                 self.annotations = data.annotations;
                 self.store.layout.import(&data.layout);
                 self.store.transform = Transform::import(data.transform);
                 self.graph = Graph::default();
+                self.graph.set_named_selections(data.selections);
+                self.watches = Watches::import(data.watches);
+                self.watches
+                    .extend(std::mem::take(&mut self.store.legacy_watches));
 
-                let txids: Vec<_> = data.transactions.iter().map(|tx| tx.txid).collect();
+                // Show the layout right away. Embedded transactions are used
+                // as they are, the others are fetched as they come into view.
+                let embedded: Vec<_> = data
+                    .transactions
+                    .iter()
+                    .filter_map(|ptx| ptx.data.clone())
+                    .collect();
+                TxCache::insert(ctx, embedded);
+                for ptx in data.transactions {
+                    if ptx.protected {
+                        self.graph.set_protected([ptx.txid], true);
+                    }
+                    if ptx.pinned {
+                        self.graph.set_pinned([ptx.txid], true);
+                    }
+                    match ptx.data {
+                        Some(tx) => self.graph.add_tx(ptx.txid, tx, ptx.position),
+                        None => self
+                            .graph
+                            .add_placeholder(ptx.txid, ptx.position, ptx.summary),
+                    }
+                }
+                self.graph.restore_groups(&data.groups);
+                self.spend_refresh
+                    .workspace_opened(ctx, &self.graph, self.update_sender.clone());
+            }
+            Update::MergeAnnotations { annotations } => match self.annotations.merge(annotations) {
+                0 => ctx.notify_success("No new annotations to import."),
+                n => ctx.notify_success(format!("Imported {} annotations.", n)),
+            },
+            Update::Hydrate { txids } => {
                 let sender = self.update_sender.clone();
-                TxCache::get_batch(ctx, &txids, move |txs| {
-                    for ptx in data.transactions {
-                        let tx = txs.get(&ptx.txid).unwrap();
+                TxCache::get_each(ctx, &txids, move |txid, result| {
+                    let update = match result {
+                        Ok(tx) => Update::FillPlaceholder { txid, tx },
+                        Err(_) => Update::PlaceholderFailed { txid },
+                    };
+                    sender.send(update).unwrap();
+                });
+            }
+            Update::FillPlaceholder { txid, tx } => {
+                self.graph.fill_placeholder(txid, tx);
+                self.spend_refresh
+                    .hydrated(ctx, &self.graph, txid, self.update_sender.clone());
+            }
+            Update::PlaceholderFailed { txid } => {
+                self.graph.placeholder_failed(txid);
+            }
+            Update::ExtractWorkspace { txids, connected } => {
+                let txids: HashSet<Txid> = if connected {
+                    txids
+                        .iter()
+                        .flat_map(|txid| self.graph.connected_component(*txid))
+                        .collect()
+                } else {
+                    txids.into_iter().collect()
+                };
+                let data = Workspace::subset(
+                    &self.graph,
+                    &self.annotations,
+                    &self.store.layout,
+                    &self.store.transform,
+                    &self.watches,
+                    self.network,
+                    &txids,
+                );
+                let name = format!("Extract of {}", self.workspaces.current_name());
+                ctx.notify_success(format!(
+                    "Extracted {} transactions to workspace `{}`.",
+                    txids.len(),
+                    name
+                ));
+                WorkspacesHandle::new_workspace(ctx, name, data);
+            }
+            Update::ImportWallet {
+                name,
+                txs,
+                apply_labels,
+            } => {
+                // Labels the user typed are kept, with the wallet added.
+                for tx in &txs {
+                    let label = match (&tx.label, apply_labels) {
+                        (Some(label), true) => format!("{}: {}", name, label),
+                        _ => name.clone(),
+                    };
+                    let existing = self.annotations.tx_label(tx.txid);
+                    let label = match existing.filter(|existing| !existing.is_empty()) {
+                        Some(existing) if existing.contains(&name) => continue,
+                        Some(existing) => format!("{} ({})", existing, label),
+                        None => label,
+                    };
+                    self.annotations.set_tx_label(tx.txid, label);
+                }
+
+                let txids: Vec<Txid> = txs
+                    .iter()
+                    .map(|tx| tx.txid)
+                    .filter(|txid| self.graph.get_tx_pos(*txid).is_none())
+                    .collect();
+                ctx.notify_success(format!(
+                    "Importing {} transactions from `{}`.",
+                    txids.len(),
+                    name
+                ));
+
+                let center = self
+                    .store
+                    .transform
+                    .pos_from_screen((self.ui_size / 2.0).to_pos2());
+                let sender = self.update_sender.clone();
+                TxCache::get_batch(ctx, &txids.clone(), move |loaded| {
+                    for txid in txids {
+                        if let Some(tx) = loaded.get(&txid) {
+                            sender
+                                .send(Update::AddTx {
+                                    txid,
+                                    tx: tx.clone(),
+                                    pos: center + platform::get_random_vec2(300.0),
+                                })
+                                .unwrap();
+                        }
+                    }
+                });
+            }
+            Update::ImportXpub { name, addresses } => {
+                ctx.notify_success(format!(
+                    "Looking up the history of {} addresses of `{}`.",
+                    addresses.len(),
+                    name
+                ));
+
+                // Addresses still to be looked up, what they had so far and
+                // the ones that failed.
+                let total = addresses.len();
+                let found = std::sync::Arc::new(egui::mutex::Mutex::new((
+                    total,
+                    Vec::<WalletTx>::new(),
+                    Vec::<String>::new(),
+                )));
+                for address in addresses {
+                    let found = found.clone();
+                    let name = name.clone();
+                    let sender = self.update_sender.clone();
+                    let ctx2 = ctx.clone();
+                    let lookup = address.clone();
+                    Client::fetch_address_txids(ctx, lookup, MAX_ADDRESS_TXS, move |result| {
+                        let mut found = found.lock();
+                        match result {
+                            Ok(txids) => {
+                                for txid in txids {
+                                    if !found.1.iter().any(|tx| tx.txid == txid) {
+                                        found.1.push(WalletTx { txid, label: None });
+                                    }
+                                }
+                            }
+                            Err(_) => found.2.push(address),
+                        }
+                        found.0 -= 1;
+                        if found.0 > 0 {
+                            return;
+                        }
+
+                        let failed = std::mem::take(&mut found.2);
+                        if !failed.is_empty() {
+                            let retry_name = name.clone();
+                            let retry_sender = sender.clone();
+                            ctx2.notify_error_with_action(
+                                format!(
+                                    "Could not look up {} of {} addresses of `{}`",
+                                    failed.len(),
+                                    total,
+                                    name
+                                ),
+                                Some("The imported history is incomplete."),
+                                Action::new("Retry", move || {
+                                    retry_sender
+                                        .send(Update::ImportXpub {
+                                            name: retry_name,
+                                            addresses: failed,
+                                        })
+                                        .unwrap();
+                                }),
+                            );
+                        } else if found.1.is_empty() {
+                            ctx2.notify_warn(
+                                format!("No transactions found for `{}`.", name),
+                                Some("Are there more unused addresses, or is the xpub on another network?"),
+                            );
+                        }
+                        if found.1.is_empty() {
+                            return;
+                        }
                         sender
-                            .send(Update::AddTx {
-                                txid: ptx.txid,
-                                tx: tx.clone(),
-                                pos: ptx.position,
+                            .send(Update::ImportWallet {
+                                name,
+                                txs: std::mem::take(&mut found.1),
+                                apply_labels: false,
                             })
                             .unwrap();
+                    });
+                }
+            }
+            Update::AddWatch { rule } => {
+                self.watches
+                    .add(ctx, rule, self.store.settings.number_format);
+            }
+            Update::ImportWatches { rules } => {
+                let total = rules.len();
+                let added = self.watches.add_all(rules);
+                ctx.notify_success(format!(
+                    "Watching {} more coins and addresses ({} were watched already).",
+                    added,
+                    total - added
+                ));
+            }
+            Update::RemoveWatch { id } => {
+                self.watches.remove(id);
+            }
+            Update::WatchChecked { id, check } => {
+                self.watches.checked(
+                    ctx,
+                    id,
+                    check,
+                    self.update_sender.clone(),
+                    self.store.settings.desktop_alerts,
+                    self.store.settings.number_format,
+                );
+            }
+            Update::RefreshSpends => {
+                self.spend_refresh
+                    .start(ctx, &self.graph, self.update_sender.clone(), true);
+            }
+            Update::OutspendsChecked { txid, spending } => {
+                self.spend_refresh
+                    .checked(ctx, &mut self.graph, txid, spending);
+            }
+            Update::LabelCoins { labels } => {
+                for (coin, label) in labels {
+                    self.annotations.set_coin_label(coin, label);
+                }
+            }
+            Update::ExpandHops {
+                txid,
+                depth,
+                direction,
+            } => {
+                let expansion = self.expansion(direction, MAX_EXPAND_TXS, false);
+                self.expand(ctx, txid, depth, expansion);
+            }
+            Update::FollowMoney { coin } => {
+                self.follow_money.start(coin, false);
+            }
+            Update::FollowPeelChain { coin } => {
+                self.follow_money.start(coin, true);
+            }
+            Update::FindUnspent { txid, vout } => {
+                self.unspent.start(
+                    ctx,
+                    &self.graph,
+                    txid,
+                    vout,
+                    self.store.layout.spawn.direction,
+                    self.update_sender.clone(),
+                );
+            }
+            Update::UnspentProgress { from, accounted } => {
+                self.unspent.progress(from, accounted);
+            }
+            Update::UnspentFound { found, txs } => {
+                if self
+                    .unspent
+                    .found(ctx, found, self.store.settings.number_format)
+                {
+                    self.history.edited(Edit::Graph, &self.graph);
+                    for (tx, pos) in txs {
+                        if self.graph.get_tx_pos(tx.txid).is_none() {
+                            self.graph.add_tx(tx.txid, tx, pos);
+                        }
+                    }
+                }
+            }
+            Update::TraceToOrigin { txid } => {
+                let expansion = self.expansion(HopDirection::Ancestors, MAX_TRACE_TXS, true);
+                self.expand(ctx, txid, usize::MAX, expansion);
+            }
+            Update::LoadAddress { address } => {
+                let sender = self.update_sender.clone();
+                let ctx2 = ctx.clone();
+                Client::fetch_address_txids(ctx, address.clone(), MAX_ADDRESS_TXS, move |result| {
+                    let txids = match result {
+                        Ok(txids) => txids,
+                        Err(FetchError::TimedOut { timeout }) => {
+                            ctx2.notify_error_with_action(
+                                format!("Looking up {} timed out", address),
+                                Some(format!("The backend didn't respond in {} s.", timeout)),
+                                Action::new("Retry", move || {
+                                    sender.send(Update::LoadAddress { address }).unwrap();
+                                }),
+                            );
+                            return;
+                        }
+                        // Other failures are already reported.
+                        Err(_) => return,
+                    };
+                    if txids.is_empty() {
+                        ctx2.notify_warn(
+                            format!("No transactions found for {}.", address),
+                            Some("Is the address on the network of the backend?"),
+                        );
+                        return;
                     }
+                    TxCache::get_batch(&ctx2, &txids.clone(), move |loaded| {
+                        let txs = txids
+                            .iter()
+                            .filter_map(|txid| loaded.get(txid).cloned())
+                            .collect();
+                        sender.send(Update::AddAddressTxs { address, txs }).unwrap();
+                    });
                 });
             }
+            Update::AddAddressTxs { address, txs } => {
+                let capped = if txs.len() == MAX_ADDRESS_TXS {
+                    " (the most recent ones)"
+                } else {
+                    ""
+                };
+                let mut txs: Vec<Transaction> = txs
+                    .into_iter()
+                    .filter(|tx| self.graph.get_tx_pos(tx.txid).is_none())
+                    .collect();
+                txs.sort_by_key(|tx| (tx.timestamp, tx.txid));
+                ctx.notify_success(format!(
+                    "Loaded {} transactions of {}{}.",
+                    txs.len(),
+                    address,
+                    capped
+                ));
+
+                let center = self
+                    .store
+                    .transform
+                    .pos_from_screen((self.ui_size / 2.0).to_pos2());
+                let positions = graph::chronological_positions(
+                    txs.len(),
+                    center,
+                    self.store.layout.spawn.direction,
+                );
+                let txids: Vec<Txid> = txs.iter().map(|tx| tx.txid).collect();
+                if !txs.is_empty() {
+                    self.history.edited(Edit::Graph, &self.graph);
+                }
+                for (tx, pos) in txs.into_iter().zip(positions) {
+                    self.graph.add_tx(tx.txid, tx, pos);
+                }
+                self.graph.select_all(txids);
+            }
+            Update::AnnotateTrace { trace } => {
+                self.graph.select_all(trace.txids.clone());
+                self.annotate_trace.open(trace);
+            }
+            Update::ArrangeTxs { positions } => {
+                let total = positions.len();
+                if !positions.iter().any(|(txid, _)| self.graph.contains(*txid)) {
+                    ctx.notify_warn("None of the nodes are in the graph.", None::<&str>);
+                    return;
+                }
+                self.history.edited(Edit::Drag, &self.graph);
+                let moved = self.graph.move_txs(positions);
+                // Otherwise the layout would move them right away.
+                self.store.layout.force_params.active = false;
+                ctx.notify_success(format!(
+                    "Moved {} of {} nodes. The layout is paused.",
+                    moved, total
+                ));
+            }
+        }
+    }
+}
+
+impl App {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            graph: GraphState::new(&self.graph),
+            annotations: self.annotations.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        snapshot.graph.restore(&mut self.graph);
+        self.annotations = snapshot.annotations;
+        self.removed.restored(&self.graph);
+    }
+
+    /// Undo with Ctrl+Z and redo with Ctrl+Shift+Z, unless a text field has
+    /// the focus and handles them itself.
+    fn undo_redo(&mut self, ctx: &Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let shortcut = |modifiers| egui::KeyboardShortcut::new(modifiers, Key::Z);
+        let redo = ctx.input_mut(|i| {
+            i.consume_shortcut(&shortcut(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT))
+        });
+        let undo =
+            !redo && ctx.input_mut(|i| i.consume_shortcut(&shortcut(egui::Modifiers::COMMAND)));
+
+        let snapshot = if undo {
+            self.history.undo(self.snapshot())
+        } else if redo {
+            self.history.redo(self.snapshot())
+        } else {
+            return;
+        };
+        if let Some(snapshot) = snapshot {
+            self.restore(snapshot);
+        }
+    }
+
+    fn expansion(&self, direction: HopDirection, limit: usize, to_origin: bool) -> Expansion {
+        Expansion {
+            direction,
+            spawn: self.store.layout.spawn.direction,
+            seen: self.graph.model().txs().map(|(txid, _)| *txid).collect(),
+            limit,
+            to_origin,
+            added: 0,
+            hops: 0,
+            coinbases: 0,
+        }
+    }
+
+    /// Run `expansion` from a transaction of the graph for `depth` hops.
+    fn expand(&self, ctx: &Context, txid: Txid, depth: usize, expansion: Expansion) {
+        let Some(pos) = self.graph.get_tx_pos(txid) else {
+            return;
+        };
+        let sender = self.update_sender.clone();
+        let ctx2 = ctx.clone();
+        TxCache::get_batch(ctx, &[txid], move |loaded| {
+            let frontier = loaded.into_values().map(|tx| (tx, pos)).collect();
+            expansion.expand(ctx2, sender, frontier, depth);
+        });
+    }
+}
+
+/// A breadth-first expansion of the graph, one hop per batch of requests.
+struct Expansion {
+    direction: HopDirection,
+    spawn: SpawnDirection,
+    /// Transactions that are or will be in the graph.
+    seen: HashSet<Txid>,
+    /// Stop after this many new transactions.
+    limit: usize,
+    /// Whether this traces a coin back to where it was mined.
+    to_origin: bool,
+    added: usize,
+    hops: usize,
+    coinbases: usize,
+}
+
+impl Expansion {
+    fn name(&self) -> &'static str {
+        if self.to_origin {
+            "Tracing to origin"
+        } else {
+            "Expanding"
+        }
+    }
+
+    fn finish(&self, ctx: &Context, capped: bool) {
+        Loading::set_task(ctx, None);
+        if capped {
+            ctx.notify_warn(
+                format!("Stopped after {} transactions.", self.added),
+                Some(if self.to_origin {
+                    "The coin has too many ancestors. Trace a younger or smaller coin."
+                } else {
+                    "Expand from fewer transactions or with a smaller depth."
+                }),
+            );
+        } else if self.to_origin {
+            ctx.notify_success(format!(
+                "Reached {} coinbase transactions in {} hops, loading {} transactions.",
+                self.coinbases, self.hops, self.added
+            ));
+        } else {
+            ctx.notify_success(format!("Expanded by {} transactions.", self.added));
+        }
+    }
+
+    /// Load the transactions one hop away from `frontier`, then go on from
+    /// them until `depth` hops are loaded.
+    fn expand(
+        mut self,
+        ctx: Context,
+        sender: Sender<Update>,
+        frontier: Vec<(Transaction, Pos2)>,
+        depth: usize,
+    ) {
+        let mut next: Vec<(Txid, Pos2)> = vec![];
+        for (tx, pos) in &frontier {
+            let neighbors: Vec<(Txid, bool)> = self
+                .direction
+                .neighbors(tx)
+                .into_iter()
+                .filter(|(txid, _)| self.seen.insert(*txid))
+                .collect();
+            for ancestor in [true, false] {
+                let side: Vec<Txid> = neighbors
+                    .iter()
+                    .filter(|(_, a)| *a == ancestor)
+                    .map(|(txid, _)| *txid)
+                    .collect();
+                let positions = graph::hop_positions(*pos, side.len(), ancestor, self.spawn);
+                next.extend(side.into_iter().zip(positions));
+            }
         }
+
+        let capped = self.added + next.len() > self.limit;
+        next.truncate(self.limit - self.added);
+        if next.is_empty() {
+            self.finish(&ctx, capped);
+            return;
+        }
+
+        self.hops += 1;
+        Loading::set_task(
+            &ctx,
+            Some(format!(
+                "{}: hop {}, {} transactions",
+                self.name(),
+                self.hops,
+                self.added + next.len()
+            )),
+        );
+        let txids: Vec<Txid> = next.iter().map(|(txid, _)| *txid).collect();
+        let ctx2 = ctx.clone();
+        TxCache::get_batch(&ctx, &txids, move |loaded| {
+            let frontier: Vec<(Transaction, Pos2)> = next
+                .into_iter()
+                .filter_map(|(txid, pos)| Some((loaded.get(&txid)?.clone(), pos)))
+                .collect();
+            for (tx, pos) in &frontier {
+                sender
+                    .send(Update::AddTx {
+                        txid: tx.txid,
+                        tx: tx.clone(),
+                        pos: *pos,
+                    })
+                    .unwrap();
+            }
+            self.added += frontier.len();
+            self.coinbases += frontier.iter().filter(|(tx, _)| tx.is_coinbase()).count();
+
+            if !capped && depth > 1 {
+                self.expand(ctx2, sender, frontier, depth - 1);
+            } else {
+                self.finish(&ctx2, capped);
+            }
+        });
     }
 }
 
@@ -221,11 +1185,46 @@ impl eframe::App for App {
         self.workspaces.save(storage);
     }
 
+    fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+        if let (Some(gl), Some(renderer)) = (gl, &self.rect_renderer) {
+            renderer.destroy(gl);
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         self.ui_size = platform::get_viewport_dimensions().unwrap_or(ctx.screen_rect().size());
 
         self.framerate
-            .on_new_frame(ctx.input(|i| i.time), frame.info().cpu_usage);
+            .on_new_frame(ctx, ctx.input(|i| i.time), frame.info().cpu_usage);
+
+        // Workspaces from before the network was recorded belong to the first
+        // backend they are opened with.
+        if self.network.is_none() {
+            self.network = self.status_bar.network();
+        }
+        Client::set_timeout(ctx, self.store.settings.request_timeout as f64);
+        Client::check_timeouts(ctx);
+        TxCache::set_max_concurrent(ctx, self.store.settings.max_concurrent_fetches);
+        self.undo_redo(ctx);
+        let search = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, Key::F);
+        if ctx.input_mut(|i| i.consume_shortcut(&search)) {
+            self.search.open();
+        }
+        let tooltip_delay = self.store.settings.tooltip_delay;
+        if ctx.style().interaction.tooltip_delay != tooltip_delay {
+            ctx.style_mut(|style| style.interaction.tooltip_delay = tooltip_delay);
+        }
+        self.watches.poll(
+            ctx,
+            60.0 * self.store.settings.alert_interval as f64,
+            self.update_sender.clone(),
+        );
+        self.spend_refresh.poll(
+            ctx,
+            &self.graph,
+            self.store.settings.spend_refresh_interval,
+            self.update_sender.clone(),
+        );
 
         let sender = self.update_sender.clone();
 
@@ -233,6 +1232,14 @@ impl eframe::App for App {
             sender.send(Update::LoadOrSelectTx { txid, pos }).unwrap();
         };
 
+        let select_coin = |coin: (Txid, usize)| {
+            sender.send(Update::SelectCoin { coin }).unwrap();
+        };
+
+        let load_address = |address: String| {
+            sender.send(Update::LoadAddress { address }).unwrap();
+        };
+
         let sender2 = sender.clone();
 
         let frame = Frame::side_top_panel(&ctx.style()).inner_margin(4.0);
@@ -248,22 +1255,221 @@ impl eframe::App for App {
 
                     ui.menu_button("Tx", |ui| {
                         ui.menu_button("Load Custom Txid", |ui| {
-                            self.custom_tx.ui(ui, load_tx);
+                            self.custom_tx.ui(ui, load_tx, select_coin, load_address);
                         });
 
-                        ui.menu_button("Hallo of Fame", |ui| {
-                            ui.allocate_space(Vec2::new(200., 0.));
+                        for collection in self.store.collections.all() {
+                            collection.menu_ui(ui, |entry| {
+                                entry.annotate(&mut self.annotations);
+                                load_tx(entry.txid, None);
+                            });
+                        }
 
-                            for (name, txid) in Txid::INTERESTING_TXS {
-                                if ui.button(name).clicked() {
-                                    load_tx(Txid::new(txid).unwrap(), None);
-                                    ui.close_menu();
+                        if ui.button("Manage Collections").clicked() {
+                            self.store.collections.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Watch List").clicked() {
+                            self.watch_list.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Import Wallet History").clicked() {
+                            self.wallet_import.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Import Wallet from Xpub").clicked() {
+                            self.xpub_import.open();
+                            ui.close_menu();
+                        }
+
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if ui.button("Import from bitcoind").clicked() {
+                            self.store.bitcoind.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Ownership Assistant").clicked() {
+                            self.store.ownership.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Address Types").clicked() {
+                            self.address_stats.open();
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .add_enabled(
+                                !self.spend_refresh.is_running(),
+                                egui::Button::new("Refresh Spends"),
+                            )
+                            .on_hover_text(
+                                "Check whether unspent outputs have been spent since they were loaded.",
+                            )
+                            .clicked()
+                        {
+                            sender.send(Update::RefreshSpends).unwrap();
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .button("Collapse Chains")
+                            .on_hover_text(
+                                "Draw runs of transactions with one input and one output as one node each.",
+                            )
+                            .clicked()
+                        {
+                            let before = GraphState::new(&self.graph);
+                            if self.graph.collapse_chains() == 0 {
+                                ctx.notify_success("There are no chains to collapse.");
+                            } else {
+                                self.history.edited_since(Edit::Graph, || before);
+                            }
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Search").on_hover_text("Ctrl+F").clicked() {
+                            self.search.open();
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .button(format!("Removed Transactions ({})", self.removed.len()))
+                            .clicked()
+                        {
+                            self.removed.open();
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .button("Peel Chains")
+                            .on_hover_text(
+                                "Runs of transactions that each pay out a small amount \
+                                 and pass the rest on.",
+                            )
+                            .clicked()
+                        {
+                            self.peel_chains.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Address Book").clicked() {
+                            self.store.address_book.open();
+                            ui.close_menu();
+                        }
+
+                        self.store.block_strip.toggle_ui(ui);
+
+                        ui.separator();
+
+                        if ui.button("Export Report").clicked() {
+                            self.report.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Evidence Stamp").clicked() {
+                            self.evidence.open();
+                            ui.close_menu();
+                        }
+
+                        if ui.button("Check Integrity").clicked() {
+                            let discrepancies = self.graph.check_integrity();
+                            if discrepancies.is_empty() {
+                                ctx.notify_success(format!(
+                                    "All inputs of {} transactions are consistent.",
+                                    self.graph.num_transactions()
+                                ));
+                            } else {
+                                let mut detail = discrepancies
+                                    .iter()
+                                    .take(MAX_LISTED_DISCREPANCIES)
+                                    .cloned()
+                                    .collect::<Vec<_>>()
+                                    .join("\n");
+                                if discrepancies.len() > MAX_LISTED_DISCREPANCIES {
+                                    detail += &format!(
+                                        "\n... and {} more",
+                                        discrepancies.len() - MAX_LISTED_DISCREPANCIES
+                                    );
                                 }
+                                ctx.notify_warn(
+                                    format!("Found {} inconsistent inputs.", discrepancies.len()),
+                                    Some(detail),
+                                );
+                            }
+                            ui.close_menu();
+                        }
+                    });
+
+                    ui.menu_button("Selections", |ui| {
+                        let selected = self.graph.selection().len();
+                        ui.horizontal(|ui| {
+                            let id = egui::Id::new("Selection name");
+                            let mut name: String =
+                                ui.data_mut(|d| d.get_temp_mut_or_default::<String>(id).clone());
+                            ui.add(
+                                egui::TextEdit::singleline(&mut name)
+                                    .hint_text("Name")
+                                    .desired_width(120.0),
+                            );
+                            let button =
+                                egui::Button::new(format!("Save Selection ({})", selected));
+                            if ui
+                                .add_enabled(selected > 0 && !name.trim().is_empty(), button)
+                                .clicked()
+                            {
+                                self.graph.save_selection(name.trim().to_string());
+                                name.clear();
+                                ui.close_menu();
                             }
+                            ui.data_mut(|d| d.insert_temp(id, name));
+                        });
 
+                        if !self.graph.named_selections().is_empty() {
                             ui.separator();
-                            ui.label(RichText::new("(from kycp.org)").strong());
-                        });
+                        }
+                        for selection in self.graph.named_selections().to_vec() {
+                            let title = format!("{} ({})", selection.name, selection.txids.len());
+                            ui.menu_button(title, |ui| {
+                                if ui.button("Select").clicked() {
+                                    self.graph.select_all(selection.txids.iter().copied());
+                                    ui.close_menu();
+                                }
+                                if ui.button("Add to Selection").clicked() {
+                                    self.graph.extend_selection(selection.txids.iter().copied());
+                                    ui.close_menu();
+                                }
+                                if ui.button("Extract to New Workspace").clicked() {
+                                    sender2
+                                        .send(Update::ExtractWorkspace {
+                                            txids: selection.txids.clone(),
+                                            connected: false,
+                                        })
+                                        .unwrap();
+                                    ui.close_menu();
+                                }
+                                if ui
+                                    .button("Remove Transactions")
+                                    .on_hover_text("Protected transactions are kept.")
+                                    .clicked()
+                                {
+                                    sender2
+                                        .send(Update::RemoveTxs {
+                                            txids: selection.txids.clone(),
+                                        })
+                                        .unwrap();
+                                    ui.close_menu();
+                                }
+                                ui.separator();
+                                if ui.button("Delete Selection").clicked() {
+                                    self.graph.remove_named_selection(&selection.name);
+                                    ui.close_menu();
+                                }
+                            });
+                        }
                     });
 
                     ui.menu_button("Reset", |ui| {
@@ -287,16 +1493,147 @@ impl eframe::App for App {
                         }
                     });
 
+                    ui.menu_button("View", |ui| {
+                        ui.label("Color transactions and coins by:");
+                        for color_by in ColorBy::ALL {
+                            if ui
+                                .radio_value(
+                                    &mut self.store.layout.color_by,
+                                    color_by,
+                                    color_by.name(),
+                                )
+                                .clicked()
+                            {
+                                ui.close_menu();
+                            }
+                        }
+
+                        ui.separator();
+
+                        self.store.layout.focus.ui(ui);
+
+                        let mut measuring = self.graph.is_measuring();
+                        if ui
+                            .checkbox(&mut measuring, "Measure")
+                            .on_hover_text(
+                                "Click two transactions to see how many blocks, how much \
+                                 time and how much value lie between them. Escape stops \
+                                 measuring.",
+                            )
+                            .changed()
+                        {
+                            self.graph.set_measuring(measuring);
+                            ui.close_menu();
+                        }
+
+                        ui.separator();
+
+                        ui.label("Taint from a marked coin by:");
+                        for model in TaintModel::ALL {
+                            ui.radio_value(
+                                &mut self.store.layout.taint_model,
+                                model,
+                                model.name(),
+                            )
+                            .on_hover_text(model.description());
+                        }
+                        if ui
+                            .add_enabled(
+                                self.graph.taint_source().is_some(),
+                                egui::Button::new("Clear Taint"),
+                            )
+                            .clicked()
+                        {
+                            self.graph.set_taint_source(None);
+                            ui.close_menu();
+                        }
+                    });
+
                     ui.menu_button("Layout", |ui| {
-                        self.store.layout.ui(ui);
+                        self.store
+                            .layout
+                            .ui(ui, self.store.settings.number_format);
+
+                        ui.separator();
+
+                        let style = style::get(ui);
+                        let metrics = self.graph.metrics(&style);
+                        ui.label(format!(
+                            "{} edge crossings, {} overlaps, edge length variance {:.2}",
+                            metrics.crossings, metrics.overlaps, metrics.edge_length_variance
+                        ));
+
+                        let tuning = self.graph.is_tuning();
+                        if ui
+                            .add_enabled(
+                                !tuning,
+                                egui::Button::new(if tuning {
+                                    "Auto-tuning…"
+                                } else {
+                                    "Auto-tune"
+                                }),
+                            )
+                            .on_hover_text(
+                                "Search for the scale that minimizes crossings, overlaps and \
+                                 uneven edge lengths of the current graph.",
+                            )
+                            .clicked()
+                        {
+                            self.graph.auto_tune(&self.store.layout.force_params);
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .button("Minimize Input/Output Crossings")
+                            .on_hover_text(
+                                "Order the inputs and outputs of all transactions by the \
+                                 position of the connected transactions.",
+                            )
+                            .clicked()
+                        {
+                            self.graph.sort_all_io();
+                            ui.close_menu();
+                        }
+
+                        if ui
+                            .button("Layout JSON")
+                            .on_hover_text("Exchange positions with D3.js and other layout tools.")
+                            .clicked()
+                        {
+                            self.layout_json.open();
+                            ui.close_menu();
+                        }
+                    });
+
+                    ui.menu_button("Settings", |ui| {
+                        self.store.settings.ui(ui);
+                        ui.separator();
+                        self.store.settings.transfer_ui(ui, &mut self.store.theme);
                     });
 
                     ui.add(ThemeSwitch::new(&mut self.store.theme));
 
-                    Loading::spinner(ui);
+                    if let Some((loaded, total)) = self.graph.hydration_progress() {
+                        ui.add(
+                            ProgressBar::new(loaded as f32 / total as f32)
+                                .desired_width(200.0)
+                                .text(format!("{} of {} transactions loaded", loaded, total)),
+                        );
+                    } else {
+                        Loading::spinner(ui);
+                    }
                 });
             });
 
+        self.status_bar
+            .show(ctx, &self.graph, &self.store.layout, &mut self.framerate);
+        self.store.block_strip.show(
+            ctx,
+            &self.graph,
+            self.store.layout.spawn.direction,
+            self.update_sender.clone(),
+        );
+
         let frame = Frame::canvas(&ctx.style())
             .inner_margin(0.0)
             .stroke(egui::Stroke::NONE);
@@ -307,25 +1644,6 @@ impl eframe::App for App {
                 Sense::click_and_drag().union(Sense::hover()),
             );
 
-            self.framerate.ui(&mut ui.child_ui(
-                Rect::from_min_max(
-                    response.rect.right_top() - Vec2::new(-10., -5.),
-                    response.rect.right_top() + Vec2::new(-5., 10.),
-                ),
-                egui::Layout::right_to_left(egui::Align::Min),
-                None,
-            ));
-
-            #[cfg(testnet)]
-            ui.child_ui(
-                Rect::from_min_max(
-                    response.rect.left_bottom() + Vec2::new(5., -20.),
-                    response.rect.left_bottom() + Vec2::new(30., -25.),
-                ),
-                egui::Layout::left_to_right(egui::Align::Max),
-                None,
-            ).colored_label(egui::Color32::LIGHT_RED, "TESTNET");
-
             ui.set_clip_rect(response.rect);
 
             if self.flight.is_active() {
@@ -351,8 +1669,28 @@ impl eframe::App for App {
                 }
             }
 
-            // Drag
-            if response.dragged_by(egui::PointerButton::Primary) {
+            // Drag, or select with a rectangle while holding shift
+            if response.drag_started_by(egui::PointerButton::Primary)
+                && ui.input(|i| i.modifiers.shift)
+            {
+                self.band_start = response.interact_pointer_pos();
+            }
+            let mut band = None;
+            if let Some(start) = self.band_start {
+                let end = response.interact_pointer_pos().unwrap_or(start);
+                if response.drag_stopped() {
+                    let transform = &self.store.transform;
+                    let rect = Rect::from_two_pos(
+                        transform.pos_from_screen(start),
+                        transform.pos_from_screen(end),
+                    );
+                    let extend = ui.input(|i| i.modifiers.command);
+                    self.graph.select_in(rect, extend);
+                    self.band_start = None;
+                } else {
+                    band = Some(Rect::from_two_pos(start, end));
+                }
+            } else if response.dragged_by(egui::PointerButton::Primary) {
                 response = response.on_hover_cursor(CursorIcon::Grabbing);
                 self.store.transform.translate(response.drag_delta());
                 self.flight.interrupt();
@@ -371,6 +1709,42 @@ impl eframe::App for App {
             if ui.input(|i| i.key_down(Key::ArrowRight)) {
                 pan += Vec2::RIGHT;
             }
+            if !ctx.wants_keyboard_input() && ui.input(|i| i.key_pressed(Key::Space)) {
+                let active = &mut self.store.layout.force_params.active;
+                *active = !*active;
+            }
+
+            // Quick-add: a pasted txid is loaded right where the pointer is.
+            if let Some(hover_pos) = response.hover_pos() {
+                let pasted = ui.input(|i| {
+                    i.events.iter().find_map(|event| match event {
+                        egui::Event::Paste(text) => Some(text.clone()),
+                        _ => None,
+                    })
+                });
+                if let Some(text) = pasted.filter(|_| !ctx.wants_keyboard_input()) {
+                    match TxRef::parse(&text) {
+                        Ok(TxRef { txid, vout }) => {
+                            let pos = self.store.transform.pos_from_screen(hover_pos);
+                            self.update_sender
+                                .send(Update::LoadOrSelectTx {
+                                    txid,
+                                    pos: Some(pos),
+                                })
+                                .unwrap();
+                            if let Some(vout) = vout {
+                                self.update_sender
+                                    .send(Update::SelectCoin {
+                                        coin: (txid, vout as usize),
+                                    })
+                                    .unwrap();
+                            }
+                        }
+                        Err(err) => ctx.notify_warn("Nothing to load from the clipboard", Some(err)),
+                    }
+                }
+            }
+
             if pan != Vec2::ZERO {
                 self.store.transform.translate(pan * 2.);
                 self.flight.interrupt();
@@ -384,24 +1758,208 @@ impl eframe::App for App {
                     Err(TryRecvError::Disconnected) => panic!("channel disconnected!"),
                 }
             }
+            if self.graph.take_dropped_edges() > 0 && !self.warned_dropped_edges {
+                self.warned_dropped_edges = true;
+                ctx.notify_warn(
+                    "Some coins couldn't be connected",
+                    Some("The backend sent inputs that spend outputs which don't exist. See the log for details."),
+                );
+            }
+
+            if let Some(scale) = self.graph.tune(ui) {
+                self.store.layout.force_params.scale = scale;
+                ctx.notify_success(format!("Set layout scale to {scale}."));
+            }
 
+            if ctx.input(|i| i.pointer.primary_pressed()) {
+                self.pressed = Some(GraphState::new(&self.graph));
+            }
             self.graph.draw(
                 ui,
                 &self.store.transform,
                 sender2,
                 &self.store.layout,
+                &self.store.settings,
                 &mut self.annotations,
+                &mut self.store.address_book,
             );
+
+            let dragging = self.graph.is_dragging();
+            if dragging && !self.dragging {
+                let pressed = self.pressed.take();
+                self.history.edited_since(Edit::Drag, || {
+                    pressed.unwrap_or_else(|| GraphState::new(&self.graph))
+                });
+            }
+            self.dragging = dragging;
+
+            if let Some(band) = band {
+                let color = ui.visuals().selection.bg_fill;
+                ui.painter().rect(
+                    band,
+                    Rounding::ZERO,
+                    color.gamma_multiply(0.2),
+                    Stroke::new(1.0, color),
+                );
+            }
+
+            if self.store.layout.show_time_axis {
+                let horizontal = self.store.layout.timeline.active;
+                let ticks = self.graph.time_ticks(
+                    &self.store.transform,
+                    &self.store.settings,
+                    horizontal,
+                );
+                if horizontal {
+                    axis::draw_horizontal(ui, response.rect, ticks);
+                } else {
+                    axis::draw_vertical(ui, response.rect, ticks);
+                }
+            }
         });
 
         self.about_rect = self.store.about.show_window(ctx, load_tx);
 
         WorkspacesHandle::update_workspace(
             ctx,
-            export::Workspace::new(&self.graph, &self.annotations, &self.store.layout, &self.store.transform),
+            export::Workspace::new(
+                &self.graph,
+                &self.annotations,
+                &self.store.layout,
+                &self.store.transform,
+                &self.watches,
+                self.network,
+            ),
         );
         self.workspaces.show_window(ctx);
+        self.show_network_mismatch(ctx);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.store.bitcoind.show(ctx, self.update_sender.clone());
+
+        self.store
+            .ownership
+            .show(ctx, &self.graph, self.update_sender.clone());
+        self.address_stats
+            .show(ctx, &self.graph, self.update_sender.clone());
+        self.annotate_trace.show(ctx, &mut self.annotations);
+        self.store.address_book.show(ctx);
+        let number_format = self.store.settings.number_format;
+        self.follow_money.show(ctx, &self.graph, number_format);
+        self.search.show(
+            ctx,
+            &self.graph,
+            &self.annotations,
+            &self.store.address_book,
+            number_format,
+            self.update_sender.clone(),
+        );
+        self.removed.show(
+            ctx,
+            &self.graph,
+            &self.annotations,
+            number_format,
+            self.update_sender.clone(),
+        );
+        let direction = self.store.layout.spawn.direction;
+        let flying = self.flight.is_active();
+        let request_timeout = self.store.settings.request_timeout as f64;
+        if let Some(update) =
+            self.follow_money
+                .step(ctx, &self.graph, direction, flying, request_timeout)
+        {
+            self.update_sender.send(update).unwrap();
+        }
+        self.watch_list.show(
+            ctx,
+            &self.watches,
+            number_format,
+            self.update_sender.clone(),
+        );
+        self.unspent.show(
+            ctx,
+            &self.annotations,
+            number_format,
+            self.update_sender.clone(),
+        );
+        self.layout_json.show(
+            ctx,
+            &self.graph,
+            &self.annotations,
+            self.update_sender.clone(),
+        );
+        self.peel_chains.show(
+            ctx,
+            &self.graph,
+            &self.annotations,
+            number_format,
+            self.update_sender.clone(),
+        );
+
+        let sender = self.update_sender.clone();
+        self.wallet_import.show(ctx, |name, txs, apply_labels| {
+            sender
+                .send(Update::ImportWallet {
+                    name,
+                    txs,
+                    apply_labels,
+                })
+                .unwrap();
+        });
+        let sender = self.update_sender.clone();
+        self.xpub_import.show(ctx, |name, addresses| {
+            sender.send(Update::ImportXpub { name, addresses }).unwrap();
+        });
+
+        let workspace = || {
+            export::Workspace::new(
+                &self.graph,
+                &self.annotations,
+                &self.store.layout,
+                &self.store.transform,
+                &self.watches,
+                self.network,
+            )
+        };
+        let report_html = |title: &str| {
+            report::render(
+                report::TEMPLATE,
+                title,
+                &chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+                self.store.settings.number_format,
+                &self.graph.report_data(),
+                &workspace(),
+            )
+        };
+        self.report.show(ctx, report_html);
+        self.store.collections.show(ctx, || {
+            self.graph
+                .export()
+                .into_iter()
+                .map(|t| Entry {
+                    name: self
+                        .annotations
+                        .tx_label(t.txid)
+                        .unwrap_or_else(|| t.txid.to_string()),
+                    txid: t.txid,
+                    description: String::new(),
+                    label: self.annotations.tx_label(t.txid),
+                    color: self
+                        .annotations
+                        .tx_color(t.txid)
+                        .map(|c| [c.r(), c.g(), c.b()]),
+                })
+                .collect()
+        });
+        self.evidence.show(ctx, |source| match source {
+            Source::Workspace => serde_json::to_string(&workspace()).unwrap(),
+            Source::Report => report_html("Investigation Report"),
+        });
+
+        self.sync_location();
 
         self.notifications.show(ctx);
+        self.history
+            .end_frame(ctx.input(|i| i.time), &self.graph, &self.annotations);
     }
 }
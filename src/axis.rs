@@ -0,0 +1,118 @@
+use egui::{Align2, Color32, FontId, Pos2, Rect, Rounding, Vec2};
+
+use crate::style;
+
+/// A label at a position along an axis, in screen coordinates.
+pub struct Tick {
+    pub pos: f32,
+    pub label: String,
+}
+
+const PADDING: f32 = 4.0;
+
+/// Draw labels along the left edge of `rect`, skipping ticks that would
+/// overlap the previous one so the axis stays readable at any zoom level.
+pub fn draw_vertical(ui: &egui::Ui, rect: Rect, mut ticks: Vec<Tick>) {
+    let style = style::get(ui);
+    let font_id = FontId::monospace(9.0);
+    let painter = ui.painter();
+
+    ticks.retain(|tick| tick.pos >= rect.top() && tick.pos <= rect.bottom());
+    ticks.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+
+    let mut galleys = vec![];
+    let mut last_pos = f32::NEG_INFINITY;
+    for tick in ticks {
+        let galley = painter.layout_no_wrap(tick.label, font_id.clone(), style.black_text_color());
+        if tick.pos - last_pos < galley.size().y {
+            continue;
+        }
+        last_pos = tick.pos;
+        galleys.push((tick.pos, galley));
+    }
+
+    if galleys.is_empty() {
+        return;
+    }
+
+    let width = galleys
+        .iter()
+        .map(|(_, galley)| galley.size().x)
+        .fold(0.0, f32::max)
+        + 2.0 * PADDING;
+
+    painter.rect_filled(
+        Rect::from_min_size(rect.left_top(), Vec2::new(width, rect.height())),
+        Rounding::ZERO,
+        ui.visuals().extreme_bg_color.gamma_multiply(0.8),
+    );
+
+    for (pos, galley) in galleys {
+        let anchor = Pos2::new(rect.left() + PADDING, pos);
+        painter.galley(
+            Align2::LEFT_CENTER.anchor_size(anchor, galley.size()).min,
+            galley,
+            Color32::TRANSPARENT,
+        );
+        painter.line_segment(
+            [
+                Pos2::new(rect.left() + width - PADDING / 2.0, pos),
+                Pos2::new(rect.left() + width, pos),
+            ],
+            style.tx_stroke(),
+        );
+    }
+}
+
+/// Like [draw_vertical], along the top edge of `rect`.
+pub fn draw_horizontal(ui: &egui::Ui, rect: Rect, mut ticks: Vec<Tick>) {
+    let style = style::get(ui);
+    let font_id = FontId::monospace(9.0);
+    let painter = ui.painter();
+
+    ticks.retain(|tick| tick.pos >= rect.left() && tick.pos <= rect.right());
+    ticks.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+
+    let mut galleys = vec![];
+    let mut last_end = f32::NEG_INFINITY;
+    for tick in ticks {
+        let galley = painter.layout_no_wrap(tick.label, font_id.clone(), style.black_text_color());
+        if tick.pos - galley.size().x / 2.0 < last_end + PADDING {
+            continue;
+        }
+        last_end = tick.pos + galley.size().x / 2.0;
+        galleys.push((tick.pos, galley));
+    }
+
+    if galleys.is_empty() {
+        return;
+    }
+
+    let height = galleys
+        .iter()
+        .map(|(_, galley)| galley.size().y)
+        .fold(0.0, f32::max)
+        + 2.0 * PADDING;
+
+    painter.rect_filled(
+        Rect::from_min_size(rect.left_top(), Vec2::new(rect.width(), height)),
+        Rounding::ZERO,
+        ui.visuals().extreme_bg_color.gamma_multiply(0.8),
+    );
+
+    for (pos, galley) in galleys {
+        let anchor = Pos2::new(pos, rect.top() + PADDING);
+        painter.galley(
+            Align2::CENTER_TOP.anchor_size(anchor, galley.size()).min,
+            galley,
+            Color32::TRANSPARENT,
+        );
+        painter.line_segment(
+            [
+                Pos2::new(pos, rect.top() + height - PADDING / 2.0),
+                Pos2::new(pos, rect.top() + height),
+            ],
+            style.tx_stroke(),
+        );
+    }
+}
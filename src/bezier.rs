@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use egui::{Color32, Mesh, Pos2, Sense, Vec2};
 
 use crate::{bitcoin::Txid, transform::Transform};
@@ -55,57 +57,165 @@ pub struct Edge {
 }
 
 impl Edge {
+    /// Halfway along the middle of the edge.
+    pub fn center(&self) -> Pos2 {
+        Cubic::sankey(
+            self.from + Vec2::new(self.from_width / 2.0, 0.0),
+            self.to + Vec2::new(self.to_width / 2.0, 0.0),
+        )
+        .eval(0.5)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn draw(
         &self,
         ui: &egui::Ui,
+        cache: &mut EdgeCache,
         color: Color32,
         draw_arrow: bool,
+        highlighted: bool,
         transform: &Transform,
         coin: &(Txid, usize),
     ) -> egui::Response {
-        let left = Cubic::sankey(self.from, self.to);
+        let cached = cache.get(self);
+
+        let pointer = ui.ctx().pointer_latest_pos();
+        let hovering = pointer.is_some_and(|p| cached.contains(transform.pos_from_screen(p)));
+
+        for mesh in cached.meshes(color, hovering || highlighted, draw_arrow) {
+            let mut mesh = mesh.clone();
+            mesh.transform(transform.to_ts_transform());
+            ui.painter().add(mesh);
+        }
+
+        let id = ui.id().with("edge").with(coin);
+        if let (Some(p), true) = (pointer, hovering) {
+            ui.interact(
+                egui::Rect::from_center_size(p, Vec2::splat(50.)),
+                id,
+                Sense::click(),
+            )
+        } else {
+            // We need a form of Response with the same id even when we're not hovering so that
+            // context menus don't disappear when leaving the edge.
+            ui.interact(egui::Rect::ZERO, id, Sense::hover())
+        }
+    }
+}
+
+/// Tessellated edges in world coordinates, so that they stay valid when
+/// panning and zooming. Since an entry is keyed by the geometry of its edge,
+/// moving a transaction invalidates the entries of its edges; the stale ones
+/// are dropped by [EdgeCache::sweep].
+#[derive(Default)]
+pub struct EdgeCache {
+    entries: HashMap<[u32; 6], CachedEdge>,
+}
+
+impl EdgeCache {
+    fn get(&mut self, edge: &Edge) -> &mut CachedEdge {
+        let key = [
+            edge.from.x.to_bits(),
+            edge.from.y.to_bits(),
+            edge.from_width.to_bits(),
+            edge.to.x.to_bits(),
+            edge.to.y.to_bits(),
+            edge.to_width.to_bits(),
+        ];
+        let cached = self
+            .entries
+            .entry(key)
+            .or_insert_with(|| CachedEdge::new(edge));
+        cached.used = true;
+        cached
+    }
+
+    /// Drop all entries that weren't drawn since the last call.
+    pub fn sweep(&mut self) {
+        self.entries
+            .retain(|_, cached| std::mem::replace(&mut cached.used, false));
+    }
+}
+
+struct CachedEdge {
+    lefts: Vec<Pos2>,
+    rights: Vec<Pos2>,
+    /// Arguments of [Self::tessellate] that `meshes` were built with.
+    mesh_style: Option<(Color32, bool, bool)>,
+    meshes: Vec<Mesh>,
+    used: bool,
+}
+
+const STEPS: usize = 15;
+
+impl CachedEdge {
+    fn new(edge: &Edge) -> Self {
+        let left = Cubic::sankey(edge.from, edge.to);
         let right = Cubic::sankey(
-            self.from + Vec2::new(self.from_width, 0.0),
-            self.to + Vec2::new(self.to_width, 0.0),
+            edge.from + Vec2::new(edge.from_width, 0.0),
+            edge.to + Vec2::new(edge.to_width, 0.0),
         );
 
-        let steps = 15;
+        let mut lefts = Vec::with_capacity(STEPS + 1);
+        let mut rights = Vec::with_capacity(STEPS + 1);
 
-        let mut lefts = Vec::with_capacity(steps + 1);
-        let mut rights = Vec::with_capacity(steps + 1);
+        for n in 0..=STEPS {
+            let t = n as f32 / STEPS as f32;
+            lefts.push(left.eval(t));
+            rights.push(right.eval(t));
+        }
 
-        for n in 0..=steps {
-            let t = n as f32 / steps as f32;
-            lefts.push(transform.pos_to_screen(left.eval(t)));
-            rights.push(transform.pos_to_screen(right.eval(t)));
+        Self {
+            lefts,
+            rights,
+            mesh_style: None,
+            meshes: Vec::new(),
+            used: false,
         }
+    }
 
-        let pointer = ui.ctx().pointer_latest_pos();
-        let mut hovering = false;
-        if let Some(p) = pointer {
-            for n in 1..=steps {
-                // Assuming that top and bot have the same x coords.
-                let lt = lefts[n - 1];
-                let lb = lefts[n];
-                if p.y >= lt.y && p.y <= lb.y {
-                    let rt = rights[n - 1];
-                    let rb = rights[n];
-                    if (lb - lt).rot90().dot(p - lt) >= 0. && (rb - rt).rot90().dot(p - rt) <= 0. {
-                        hovering = true;
-                        break;
-                    }
+    fn contains(&self, p: Pos2) -> bool {
+        let lefts = &self.lefts;
+        let rights = &self.rights;
+        for n in 1..=STEPS {
+            // Assuming that top and bot have the same x coords.
+            let lt = lefts[n - 1];
+            let lb = lefts[n];
+            if p.y >= lt.y && p.y <= lb.y {
+                let rt = rights[n - 1];
+                let rb = rights[n];
+                if (lb - lt).rot90().dot(p - lt) >= 0. && (rb - rt).rot90().dot(p - rt) <= 0. {
+                    return true;
                 }
             }
         }
+        false
+    }
+
+    fn meshes(&mut self, color: Color32, emphasized: bool, draw_arrow: bool) -> &[Mesh] {
+        let style = (color, emphasized, draw_arrow);
+        if self.mesh_style != Some(style) {
+            self.meshes = self.tessellate(color, emphasized, draw_arrow);
+            self.mesh_style = Some(style);
+        }
+        &self.meshes
+    }
+
+    fn tessellate(&self, color: Color32, emphasized: bool, draw_arrow: bool) -> Vec<Mesh> {
+        let lefts = &self.lefts;
+        let rights = &self.rights;
+        let steps = STEPS;
 
         let arrow_color = color.gamma_multiply(0.25);
 
-        let color = if hovering {
+        let color = if emphasized {
             color.gamma_multiply(0.5)
         } else {
             color.gamma_multiply(0.4)
         };
 
+        let mut meshes = Vec::new();
+
         if draw_arrow {
             let start = 0.3;
             let arrow_width = 2;
@@ -157,7 +267,7 @@ impl Edge {
                 mesh.add_triangle(i0 - 1, i0, i0 + 1);
             }
 
-            ui.painter().add(mesh);
+            meshes.push(mesh);
 
             // Arrow
             let mut mesh = Mesh::default();
@@ -171,7 +281,7 @@ impl Edge {
             mesh.add_triangle(1, 2, 3);
             mesh.add_triangle(2, 3, 4);
             mesh.add_triangle(3, 4, 5);
-            ui.painter().add(mesh);
+            meshes.push(mesh);
         } else {
             let mut mesh = Mesh::default();
             mesh.colored_vertex(lefts[0], color);
@@ -183,20 +293,9 @@ impl Edge {
                 mesh.add_triangle(i0, i0 + 1, i0 + 2);
                 mesh.add_triangle(i0 + 1, i0 + 2, i0 + 3);
             }
-            ui.painter().add(mesh);
+            meshes.push(mesh);
         }
 
-        let id = ui.id().with("edge").with(coin);
-        if let (Some(p), true) = (pointer, hovering) {
-            ui.interact(
-                egui::Rect::from_center_size(p, Vec2::splat(50.)),
-                id,
-                Sense::click(),
-            )
-        } else {
-            // We need a form of Response with the same id even when we're not hovering so that
-            // context menus don't disappear when leaving the edge.
-            ui.interact(egui::Rect::ZERO, id, Sense::hover())
-        }
+        meshes
     }
 }
@@ -1,10 +1,10 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Display},
 };
 
 use egui::{text::LayoutJob, Widget};
-use hex::{FromHex, ToHex};
+use hex::{FromHex, FromHexError, ToHex};
 use serde::{Deserialize, Serialize};
 
 use crate::{graph::sats_layout, platform::inner::get_random_int, style::Style};
@@ -16,7 +16,14 @@ impl Txid {
     pub fn new(string: &str) -> Result<Self, String> {
         match <[u8; 32]>::from_hex(string) {
             Ok(bytes) => Ok(Self(bytes)),
-            Err(e) => Err(e.to_string()),
+            Err(FromHexError::InvalidHexCharacter { c, index }) => Err(format!(
+                "Txid contains invalid character '{}' at position {}",
+                c, index
+            )),
+            Err(FromHexError::OddLength | FromHexError::InvalidStringLength) => Err(format!(
+                "Txid must be 64 hex characters, got {}",
+                string.chars().count()
+            )),
         }
     }
 
@@ -125,6 +132,110 @@ impl Txid {
     ];
 }
 
+/// What a user might paste to point at a transaction: a bare txid, an outpoint
+/// (`txid:vout`) or a block explorer URL.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TxRef {
+    pub txid: Txid,
+    pub vout: Option<u32>,
+}
+
+impl TxRef {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+
+        if input.is_empty() {
+            return Err("Enter a txid, outpoint or explorer URL".to_string());
+        }
+
+        if input.contains('/') {
+            return Self::parse_url(input);
+        }
+
+        match input.split_once(':') {
+            Some((txid, vout)) => Ok(Self {
+                txid: Txid::new(txid)?,
+                vout: Some(parse_vout(vout)?),
+            }),
+            None => Ok(Self {
+                txid: Txid::new(input)?,
+                vout: None,
+            }),
+        }
+    }
+
+    /// Understands mempool.space (`/tx/<txid>#vout=<n>`), blockstream.info
+    /// (`/tx/<txid>?output:<n>`) and our own (`/tx/<txid>/<n>`) URLs.
+    pub fn parse_url(url: &str) -> Result<Self, String> {
+        let (url, fragment) = match url.split_once('#') {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (url, None),
+        };
+        let (path, query) = match url.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (url, None),
+        };
+
+        let mut segments = path
+            .split('/')
+            .skip_while(|segment| *segment != "tx")
+            .skip(1);
+        let txid = segments
+            .next()
+            .ok_or("URL doesn't point to a transaction (expected `.../tx/<txid>`)")?;
+
+        let vout = fragment
+            .and_then(|f| f.split('&').find_map(|p| p.strip_prefix("vout=")))
+            .or_else(|| query.and_then(|q| q.strip_prefix("output:")))
+            .or_else(|| segments.next().filter(|segment| !segment.is_empty()))
+            .map(parse_vout)
+            .transpose()?;
+
+        Ok(Self {
+            txid: Txid::new(txid)?,
+            vout,
+        })
+    }
+}
+
+fn parse_vout(vout: &str) -> Result<u32, String> {
+    vout.parse()
+        .map_err(|_| format!("Invalid output index '{}'", vout))
+}
+
+/// Checks the syntax of a base58 or bech32 address, enough to tell it apart
+/// from a txid. Checksums are left to the backend.
+pub fn parse_address(input: &str) -> Result<String, String> {
+    const BASE58: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    const BECH32: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+    let input = input.trim();
+    let lower = input.to_lowercase();
+    let bech32 = ["bc1", "tb1", "bcrt1"]
+        .iter()
+        .find_map(|hrp| lower.strip_prefix(hrp));
+
+    let valid = match bech32 {
+        // Mixed case isn't allowed.
+        Some(data) => {
+            (input == lower || input == input.to_uppercase())
+                && (11..=87).contains(&data.len())
+                && data.chars().all(|c| BECH32.contains(c))
+        }
+        None => {
+            input.starts_with(['1', '3', 'm', 'n', '2'])
+                && (26..=35).contains(&input.len())
+                && input.chars().all(|c| BASE58.contains(c))
+        }
+    };
+
+    if valid {
+        Ok(input.to_string())
+    } else {
+        Err(format!("Invalid address '{}'", input))
+    }
+}
+
 impl Display for Txid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.hex_string())
@@ -140,7 +251,7 @@ impl Debug for Txid {
 impl<'de> Deserialize<'de> for Txid {
     fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let string = String::deserialize(deserializer)?;
-        Ok(Self::new(&string).unwrap()) // TODO better error handling
+        Self::new(&string).map_err(serde::de::Error::custom)
     }
 }
 
@@ -156,25 +267,233 @@ impl AsRef<[u8]> for Txid {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transaction {
     pub timestamp: i64,
     pub txid: Txid,
     pub block_height: u32,
     pub inputs: Vec<Input>,
     pub outputs: Vec<Output>,
+    /// Virtual size in vbytes. Older backends don't send it.
+    #[serde(default)]
+    pub vsize: u64,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Input {
     pub txid: Txid,
     pub vout: u32,
     pub value: u64,
     pub address: String,
     pub address_type: AddressType,
+    /// Hex encoded witness stack. Older backends don't send it.
+    #[serde(default)]
+    pub witness: Vec<String>,
+    /// Hex encoded scriptSig. Older backends don't send it.
+    #[serde(default)]
+    pub script_sig: String,
+    /// Hex encoded scriptPubKey of the spent output. Older backends don't
+    /// send it.
+    #[serde(default)]
+    pub script_pubkey: String,
+}
+
+impl Input {
+    /// How a P2TR output was spent, according to BIP 341.
+    pub fn taproot_spend(&self) -> Option<TaprootSpend> {
+        if !matches!(self.address_type, AddressType::P2TR) || self.witness.is_empty() {
+            return None;
+        }
+
+        let mut stack = self
+            .witness
+            .iter()
+            .map(hex::decode)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+
+        // Remove the annex, if present
+        if stack.len() >= 2 && stack.last()?.first() == Some(&0x50) {
+            stack.pop();
+        }
+
+        if stack.len() == 1 {
+            return Some(TaprootSpend::KeyPath);
+        }
+
+        let control_block = stack.pop()?;
+        let script = stack.pop()?;
+
+        if control_block.len() < 33 || (control_block.len() - 33) % 32 != 0 {
+            return None;
+        }
+
+        Some(TaprootSpend::ScriptPath {
+            leaf_version: control_block[0] & 0xfe,
+            merkle_depth: (control_block.len() - 33) / 32,
+            script,
+        })
+    }
+
+    /// Detect m-of-n multisig spends by looking at the revealed script.
+    pub fn multisig(&self) -> Option<Multisig> {
+        let last_witness = || hex::decode(self.witness.last()?).ok();
+
+        let script = match self.address_type {
+            AddressType::P2WSH => last_witness()?,
+            AddressType::P2SH => {
+                let script_sig = hex::decode(&self.script_sig).ok()?;
+                let redeem_script = script_pushes(&script_sig)?.pop()?;
+                // P2SH wrapped P2WSH
+                if redeem_script.len() == 34 && redeem_script[0..2] == [0x00, 0x20] {
+                    last_witness()?
+                } else {
+                    redeem_script
+                }
+            }
+            AddressType::P2TR => match self.taproot_spend()? {
+                TaprootSpend::ScriptPath { script, .. } => script,
+                TaprootSpend::KeyPath => return None,
+            },
+            _ => return None,
+        };
+
+        Multisig::from_script(&script)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Multisig {
+    pub m: u8,
+    pub n: u8,
+    pub pubkeys: Vec<Vec<u8>>,
+}
+
+impl Multisig {
+    const OP_CHECKSIG: u8 = 0xac;
+    const OP_CHECKSIGADD: u8 = 0xba;
+    const OP_CHECKMULTISIG: u8 = 0xae;
+    const OP_NUMEQUAL: u8 = 0x9c;
+
+    /// Understands `<m> <pubkey>... <n> CHECKMULTISIG` and the tapscript
+    /// equivalent `<pubkey> CHECKSIG <pubkey> CHECKSIGADD... <m> NUMEQUAL`.
+    pub fn from_script(script: &[u8]) -> Option<Self> {
+        match script {
+            [m, rest @ .., n, Self::OP_CHECKMULTISIG] => {
+                let m = small_int(*m)?;
+                let n = small_int(*n)?;
+                let pubkeys = script_pushes(rest)?;
+                let valid = pubkeys.len() == n as usize
+                    && pubkeys.iter().all(|k| k.len() == 33 || k.len() == 65)
+                    && m <= n;
+                valid.then_some(Self { m, n, pubkeys })
+            }
+            [rest @ .., m, Self::OP_NUMEQUAL] => {
+                let m = small_int(*m)?;
+                let mut pubkeys = vec![];
+                for (i, chunk) in rest.chunks(34).enumerate() {
+                    let op = if i == 0 {
+                        Self::OP_CHECKSIG
+                    } else {
+                        Self::OP_CHECKSIGADD
+                    };
+                    match chunk {
+                        [0x20, key @ .., last] if key.len() == 32 && *last == op => {
+                            pubkeys.push(key.to_vec())
+                        }
+                        _ => return None,
+                    }
+                }
+                let n = pubkeys.len() as u8;
+                (n > 0 && m <= n).then_some(Self { m, n, pubkeys })
+            }
+            _ => None,
+        }
+    }
+
+    /// Short identifiers of the cosigner keys.
+    pub fn key_ids(&self) -> Vec<String> {
+        self.pubkeys
+            .iter()
+            .map(|key| format!("{}…", &key.encode_hex::<String>()[..10]))
+            .collect()
+    }
+}
+
+impl Display for Multisig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-of-{}", self.m, self.n)
+    }
+}
+
+/// Decode `OP_1` to `OP_16`.
+fn small_int(op: u8) -> Option<u8> {
+    if (0x51..=0x60).contains(&op) {
+        Some(op - 0x50)
+    } else {
+        None
+    }
+}
+
+/// Split a script consisting only of data pushes into the pushed items.
+fn script_pushes(script: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut result = vec![];
+    let mut i = 0;
+    while i < script.len() {
+        let op = script[i];
+        let (len, header) = match op {
+            0x00 => (0, 1),
+            0x01..=0x4b => (op as usize, 1),
+            0x4c => (*script.get(i + 1)? as usize, 2),
+            0x4d => (
+                u16::from_le_bytes(script.get(i + 1..i + 3)?.try_into().ok()?) as usize,
+                3,
+            ),
+            _ => return None,
+        };
+        result.push(script.get(i + header..i + header + len)?.to_vec());
+        i += header + len;
+    }
+    Some(result)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TaprootSpend {
+    KeyPath,
+    ScriptPath {
+        leaf_version: u8,
+        /// Number of hashes in the control block's merkle path.
+        merkle_depth: usize,
+        script: Vec<u8>,
+    },
+}
+
+impl Display for TaprootSpend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaprootSpend::KeyPath => write!(f, "Key-path spend"),
+            TaprootSpend::ScriptPath {
+                leaf_version,
+                merkle_depth,
+                script,
+            } => {
+                writeln!(
+                    f,
+                    "Script-path spend (leaf version 0x{:02x}, depth {})",
+                    leaf_version, merkle_depth
+                )?;
+                let hex: String = script.encode_hex();
+                if hex.len() > 32 {
+                    write!(f, "Script: {} bytes {}…", script.len(), &hex[..32])
+                } else {
+                    write!(f, "Script: {} bytes {}", script.len(), hex)
+                }
+            }
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AddressType {
     P2PKH,
@@ -185,12 +504,107 @@ pub enum AddressType {
     Unknown,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl Display for AddressType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AddressType::P2PKH => "P2PKH",
+            AddressType::P2SH => "P2SH",
+            AddressType::P2WPKH => "P2WPKH",
+            AddressType::P2WSH => "P2WSH",
+            AddressType::P2TR => "P2TR",
+            AddressType::Unknown => "Unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Output {
     pub spending_txid: Option<Txid>,
     pub value: u64,
     pub address: String,
     pub address_type: AddressType,
+    /// Hex encoded scriptPubKey. Older backends don't send it.
+    #[serde(default)]
+    pub script_pubkey: String,
+}
+
+/// What stands in for the address of a coin in the graph. Scripts without an
+/// address, for which the backend sends a placeholder, are identified by their
+/// hex instead, or not at all if the backend doesn't send it.
+pub fn coin_address(address: &str, address_type: AddressType, script_pubkey: &str) -> String {
+    match address_type {
+        AddressType::Unknown => script_pubkey.to_string(),
+        _ => address.to_string(),
+    }
+}
+
+/// The kinds of scripts that don't have an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptType {
+    /// Pay to a bare public key, as in the early days.
+    PubKey,
+    /// Pay to a bare `CHECKMULTISIG`.
+    Multisig,
+    /// Provably unspendable, carrying data.
+    NullData,
+    /// A witness program of a version or length that isn't defined yet.
+    WitnessUnknown,
+    NonStandard,
+}
+
+impl ScriptType {
+    const OP_RETURN: u8 = 0x6a;
+    const OP_CHECKSIG: u8 = 0xac;
+
+    pub fn from_hex(script: &str) -> Self {
+        match hex::decode(script) {
+            Ok(script) => Self::from_script(&script),
+            Err(_) => ScriptType::NonStandard,
+        }
+    }
+
+    pub fn from_script(script: &[u8]) -> Self {
+        match script {
+            [Self::OP_RETURN, ..] => ScriptType::NullData,
+            [0x21, key @ .., Self::OP_CHECKSIG] if key.len() == 33 => ScriptType::PubKey,
+            [0x41, key @ .., Self::OP_CHECKSIG] if key.len() == 65 => ScriptType::PubKey,
+            _ if Multisig::from_script(script).is_some() => ScriptType::Multisig,
+            // OP_0 to OP_16 followed by a push of 2 to 40 bytes.
+            [version, len, program @ ..]
+                if (*version == 0 || (0x51..=0x60).contains(version))
+                    && (2..=40).contains(len)
+                    && program.len() == *len as usize =>
+            {
+                ScriptType::WitnessUnknown
+            }
+            _ => ScriptType::NonStandard,
+        }
+    }
+
+    /// Short name, as shown after the script like the type of an address.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            ScriptType::PubKey => "p2pk",
+            ScriptType::Multisig => "multisig",
+            ScriptType::NullData => "op_return",
+            ScriptType::WitnessUnknown => "witness",
+            ScriptType::NonStandard => "nonstandard",
+        }
+    }
+}
+
+impl Display for ScriptType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ScriptType::PubKey => "P2PK",
+            ScriptType::Multisig => "Bare Multisig",
+            ScriptType::NullData => "OP_RETURN",
+            ScriptType::WitnessUnknown => "Unknown Witness Version",
+            ScriptType::NonStandard => "Non-standard",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 impl Transaction {
@@ -207,16 +621,335 @@ impl Transaction {
         }
     }
 
+    fn sent(&self) -> u64 {
+        self.outputs.iter().map(|output| output.value).sum()
+    }
+
+    /// Inputs minus outputs. Zero if the outputs exceed the inputs, which only
+    /// happens with bad data from the backend and is reported by
+    /// [Self::validate].
     pub fn fees(&self) -> u64 {
-        let sent: u64 = self.outputs.iter().map(|output| output.value).sum();
-        let fees = self.amount() as i64 - sent as i64;
-        assert!(fees >= 0, "fees negative");
-        fees as u64
+        self.amount().saturating_sub(self.sent())
+    }
+
+    /// `None` for coinbase transactions, and if the size is unknown.
+    pub fn fee_rate(&self) -> Option<FeeRate> {
+        (!self.is_coinbase() && self.vsize > 0)
+            .then(|| FeeRate(self.fees() as f64 / self.vsize as f64))
+    }
+
+    /// Recompute the fees from the inputs and outputs and describe anything
+    /// that doesn't add up.
+    pub fn validate(&self) -> Vec<String> {
+        if self.is_coinbase() {
+            return vec![];
+        }
+
+        let received = self.amount();
+        let sent = self.sent();
+        if sent > received {
+            vec![format!(
+                "Outputs ({}) exceed inputs ({}).",
+                Sats(sent),
+                Sats(received)
+            )]
+        } else if received - sent > ABSURD_FEES {
+            vec![format!(
+                "Fees of {} are implausibly high.",
+                Sats(received - sent)
+            )]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Recognize Whirlpool, WabiSabi and JoinMarket CoinJoins from their
+    /// on-chain shape and assign a role to each input and output.
+    pub fn coinjoin(&self) -> Option<CoinJoin> {
+        let values: Vec<u64> = self.outputs.iter().map(|o| o.value).collect();
+
+        // Whirlpool mix: 5 inputs, 5 outputs of exactly the pool denomination.
+        for pool in WHIRLPOOL_POOLS {
+            if self.inputs.len() == 5 && values.len() == 5 && values.iter().all(|v| *v == pool) {
+                return Some(CoinJoin {
+                    kind: CoinJoinKind::WhirlpoolMix { pool },
+                    input_roles: self
+                        .inputs
+                        .iter()
+                        .map(|i| {
+                            if i.value == pool {
+                                CoinJoinRole::Remix
+                            } else {
+                                CoinJoinRole::Premix
+                            }
+                        })
+                        .collect(),
+                    output_roles: vec![CoinJoinRole::Postmix; 5],
+                });
+            }
+        }
+
+        // Whirlpool Tx0: several equal outputs of the pool denomination plus
+        // the allowance for the mining fee of the first mix, next to an
+        // OP_RETURN for the coordinator and the fee paid to it.
+        let op_return = self
+            .outputs
+            .iter()
+            .any(|o| ScriptType::from_hex(&o.script_pubkey) == ScriptType::NullData);
+        for pool in WHIRLPOOL_POOLS {
+            let is_premix = |v: u64| v > pool && v <= pool + pool / 10;
+            let is_fee = |v: u64| v > 0 && v <= pool * WHIRLPOOL_MAX_FEE_PERCENT / 100;
+            let premix = values.iter().copied().filter(|v| is_premix(*v)).max();
+            if let Some(premix) = premix {
+                if op_return
+                    && values.iter().filter(|v| **v == premix).count() >= 2
+                    && values.iter().any(|v| is_fee(*v))
+                {
+                    return Some(CoinJoin {
+                        kind: CoinJoinKind::WhirlpoolTx0 { pool },
+                        input_roles: vec![CoinJoinRole::Deposit; self.inputs.len()],
+                        output_roles: values
+                            .iter()
+                            .map(|v| {
+                                if *v == premix {
+                                    CoinJoinRole::Premix
+                                } else {
+                                    CoinJoinRole::Change
+                                }
+                            })
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        // WabiSabi: large rounds where most outputs share their value with
+        // other outputs (standard denominations).
+        if self.inputs.len() >= 50 && values.len() >= 50 {
+            let mut counts: HashMap<u64, usize> = HashMap::new();
+            for v in &values {
+                *counts.entry(*v).or_default() += 1;
+            }
+            let output_roles: Vec<CoinJoinRole> = values
+                .iter()
+                .map(|v| {
+                    if counts[v] >= 2 {
+                        CoinJoinRole::Mixed
+                    } else {
+                        CoinJoinRole::Change
+                    }
+                })
+                .collect();
+            let mixed = output_roles
+                .iter()
+                .filter(|r| **r == CoinJoinRole::Mixed)
+                .count();
+            if mixed * 2 >= values.len() {
+                return Some(CoinJoin {
+                    kind: CoinJoinKind::WabiSabi,
+                    input_roles: vec![CoinJoinRole::Deposit; self.inputs.len()],
+                    output_roles,
+                });
+            }
+        }
+
+        // JoinMarket: a taker and a few makers each get an output of the same
+        // value, and the makers get change on top, as does the taker unless
+        // they sweep. Unlike batched payments, every participant brings at
+        // least one input from a script of their own.
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+        for v in &values {
+            *counts.entry(*v).or_default() += 1;
+        }
+        if let Some((&denomination, &n)) = counts.iter().max_by_key(|(v, n)| (**n, **v)) {
+            let change = values.len() - n;
+            let scripts: HashSet<String> = self
+                .inputs
+                .iter()
+                .map(|i| coin_address(&i.address, i.address_type, &i.script_pubkey))
+                .collect();
+            if n >= JOINMARKET_MIN_PARTICIPANTS
+                && (n - 1..=n).contains(&change)
+                && scripts.len() >= n
+            {
+                return Some(CoinJoin {
+                    kind: CoinJoinKind::JoinMarket { denomination },
+                    input_roles: vec![CoinJoinRole::Deposit; self.inputs.len()],
+                    output_roles: values
+                        .iter()
+                        .map(|v| {
+                            if *v == denomination {
+                                CoinJoinRole::Mixed
+                            } else {
+                                CoinJoinRole::Change
+                            }
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// Fees above this are flagged by [Transaction::validate]. There have been
+/// transactions paying more by mistake, but they are rare.
+const ABSURD_FEES: u64 = 10_000_000;
+
+/// Denominations of the Whirlpool pools in sats.
+const WHIRLPOOL_POOLS: [u64; 4] = [100_000, 1_000_000, 5_000_000, 50_000_000];
+
+/// The coordinator's fee in a Tx0, in percent of the pool denomination. It's
+/// less for the larger pools and with a discount code.
+const WHIRLPOOL_MAX_FEE_PERCENT: u64 = 5;
+
+/// Fewer equal outputs are too common in ordinary transactions to call them a
+/// JoinMarket CoinJoin.
+const JOINMARKET_MIN_PARTICIPANTS: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoinJoinKind {
+    WhirlpoolTx0 { pool: u64 },
+    WhirlpoolMix { pool: u64 },
+    WabiSabi,
+    JoinMarket { denomination: u64 },
+}
+
+impl Display for CoinJoinKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoinJoinKind::WhirlpoolTx0 { pool } => {
+                write!(f, "Whirlpool Tx0 ({} BTC pool)", *pool as f64 / 1e8)
+            }
+            CoinJoinKind::WhirlpoolMix { pool } => {
+                write!(f, "Whirlpool Mix ({} BTC pool)", *pool as f64 / 1e8)
+            }
+            CoinJoinKind::WabiSabi => write!(f, "WabiSabi Round"),
+            CoinJoinKind::JoinMarket { denomination } => {
+                write!(f, "JoinMarket ({} BTC)", *denomination as f64 / 1e8)
+            }
+        }
+    }
+}
+
+/// What an input or output does in a CoinJoin. Whirlpool coins go from
+/// deposit to premix (Tx0) to postmix (mix), and postmix coins can be
+/// remixed. In WabiSabi, outputs of a shared denomination are mixed, the
+/// others are change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoinJoinRole {
+    Deposit,
+    Premix,
+    Remix,
+    Postmix,
+    Mixed,
+    Change,
+}
+
+impl CoinJoinRole {
+    pub const ALL: [CoinJoinRole; 6] = [
+        CoinJoinRole::Deposit,
+        CoinJoinRole::Premix,
+        CoinJoinRole::Remix,
+        CoinJoinRole::Postmix,
+        CoinJoinRole::Mixed,
+        CoinJoinRole::Change,
+    ];
+}
+
+impl Display for CoinJoinRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CoinJoinRole::Deposit => "Deposit",
+            CoinJoinRole::Premix => "Premix",
+            CoinJoinRole::Remix => "Remix",
+            CoinJoinRole::Postmix => "Postmix",
+            CoinJoinRole::Mixed => "Mixed",
+            CoinJoinRole::Change => "Change",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CoinJoin {
+    pub kind: CoinJoinKind,
+    pub input_roles: Vec<CoinJoinRole>,
+    pub output_roles: Vec<CoinJoinRole>,
+}
+
+/// The chain a backend serves and a workspace was created on. Transactions of
+/// different networks must never end up in the same graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Network::Mainnet => "Mainnet",
+            Network::Testnet => "Testnet",
+            Network::Signet => "Signet",
+            Network::Regtest => "Regtest",
+        };
+        write!(f, "{}", name)
     }
 }
 
 pub struct Sats(pub u64);
 
+/// Separators of the whole bitcoins and the decimal mark, by locale. The
+/// groups of sats are always set apart by spaces.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum NumberFormat {
+    #[default]
+    English,
+    German,
+    French,
+    Swiss,
+}
+
+impl NumberFormat {
+    pub const ALL: [NumberFormat; 4] = [
+        NumberFormat::English,
+        NumberFormat::German,
+        NumberFormat::French,
+        NumberFormat::Swiss,
+    ];
+
+    /// An example amount in this format.
+    pub fn name(self) -> &'static str {
+        match self {
+            NumberFormat::English => "1,234.56",
+            NumberFormat::German => "1.234,56",
+            NumberFormat::French => "1 234,56",
+            NumberFormat::Swiss => "1'234.56",
+        }
+    }
+
+    pub fn thousands(self) -> char {
+        match self {
+            NumberFormat::English => ',',
+            NumberFormat::German => '.',
+            NumberFormat::French => ' ',
+            NumberFormat::Swiss => '\'',
+        }
+    }
+
+    pub fn decimal(self) -> char {
+        match self {
+            NumberFormat::English | NumberFormat::Swiss => '.',
+            NumberFormat::German | NumberFormat::French => ',',
+        }
+    }
+}
+
 pub struct AmountComponents {
     pub sats: u64,
     pub ksats: Option<u64>,
@@ -261,24 +994,54 @@ impl Sats {
 pub struct SatsDisplay<'a> {
     sats: Sats,
     style: &'a Style,
+    format: NumberFormat,
 }
 
 impl<'a> SatsDisplay<'a> {
-    pub fn new(sats: Sats, style: &'a Style) -> Self {
-        Self { sats, style }
+    pub fn new(sats: Sats, style: &'a Style, format: NumberFormat) -> Self {
+        Self {
+            sats,
+            style,
+            format,
+        }
     }
 }
 
 impl Widget for SatsDisplay<'_> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         let mut job = LayoutJob::default();
-        sats_layout(&mut job, &self.sats, self.style);
+        sats_layout(&mut job, &self.sats, self.style, self.format);
         ui.label(job)
     }
 }
 
+/// Sats per vbyte.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeeRate(pub f64);
+
+impl Display for FeeRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} sat/vB", self.0)
+    }
+}
+
+/// In the default [NumberFormat]. Amounts shown to the user are written with
+/// [Sats::format] instead.
 impl Display for Sats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write(f, NumberFormat::default())
+    }
+}
+
+impl Sats {
+    /// With the separators of `format`, see [crate::settings::Settings].
+    pub fn format(&self, format: NumberFormat) -> String {
+        let mut s = String::new();
+        self.write(&mut s, format).unwrap();
+        s
+    }
+
+    fn write(&self, f: &mut impl std::fmt::Write, format: NumberFormat) -> std::fmt::Result {
         let AmountComponents {
             sats,
             ksats,
@@ -293,10 +1056,10 @@ impl Display for Sats {
             started = true;
 
             for amount in btc.iter().skip(1) {
-                write!(f, ",{:03}", amount)?;
+                write!(f, "{}{:03}", format.thousands(), amount)?;
             }
 
-            write!(f, ".")?;
+            write!(f, "{}", format.decimal())?;
         }
 
         if started {
@@ -335,6 +1098,7 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
             Transaction {
                 timestamp: 1,
                 block_height: 0,
+                vsize: 0,
                 txid: a,
                 inputs: vec![Input {
                     value: 140_600_000,
@@ -342,6 +1106,9 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                     vout: 0,
                     address: "fslkfjeslk".to_string(),
                     address_type: AddressType::P2PKH,
+                    witness: vec![],
+                    script_sig: String::new(),
+                    script_pubkey: String::new(),
                 }],
                 outputs: vec![
                     Output {
@@ -349,12 +1116,14 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                         value: 100_230_000,
                         address: "fsklefj".to_string(),
                         address_type: AddressType::P2PKH,
+                        script_pubkey: String::new(),
                     },
                     Output {
                         spending_txid: Some(c),
                         value: 12_300_000,
                         address: "fsklefj".to_string(),
                         address_type: AddressType::P2PKH,
+                        script_pubkey: String::new(),
                     },
                 ],
             },
@@ -364,6 +1133,7 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
             Transaction {
                 timestamp: 2,
                 block_height: 0,
+                vsize: 0,
                 txid: b,
                 inputs: vec![Input {
                     value: 100_230_000,
@@ -371,12 +1141,16 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                     vout: 0,
                     address: "fslkfjeslk".to_string(),
                     address_type: AddressType::P2PKH,
+                    witness: vec![],
+                    script_sig: String::new(),
+                    script_pubkey: String::new(),
                 }],
                 outputs: vec![Output {
                     spending_txid: Some(c),
                     value: 12_300_000,
                     address: "fsklefj".to_string(),
                     address_type: AddressType::P2PKH,
+                    script_pubkey: String::new(),
                 }],
             },
         ),
@@ -385,6 +1159,7 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
             Transaction {
                 timestamp: 2,
                 block_height: 0,
+                vsize: 0,
                 txid: c,
                 inputs: vec![
                     Input {
@@ -393,6 +1168,9 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                         vout: 1,
                         address: "fslkfjeslk".to_string(),
                         address_type: AddressType::P2PKH,
+                        witness: vec![],
+                        script_sig: String::new(),
+                        script_pubkey: String::new(),
                     },
                     Input {
                         value: 12_300_000,
@@ -400,6 +1178,9 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
                         vout: 0,
                         address: "fslkfjeslk".to_string(),
                         address_type: AddressType::P2PKH,
+                        witness: vec![],
+                        script_sig: String::new(),
+                        script_pubkey: String::new(),
                     },
                 ],
                 outputs: vec![],
@@ -408,9 +1189,61 @@ pub fn dummy_transactions() -> HashMap<Txid, Transaction> {
     ])
 }
 
+/// A txid for tests that is just a small number.
+#[cfg(test)]
+pub fn dummy_txid(n: u8) -> Txid {
+    Txid::new(&format!("{:064x}", n)).unwrap()
+}
+
+/// A transaction for tests. Each input spends output 0 of the given
+/// transaction, each output is spent by the given transaction, if any. Coins
+/// are P2WPKH without an address.
+#[cfg(test)]
+pub fn dummy_transaction(
+    n: u8,
+    inputs: &[(u8, u64)],
+    outputs: &[(Option<u8>, u64)],
+) -> Transaction {
+    Transaction {
+        timestamp: 0,
+        txid: dummy_txid(n),
+        block_height: 0,
+        vsize: 0,
+        inputs: inputs
+            .iter()
+            .map(|(funding, value)| Input {
+                txid: dummy_txid(*funding),
+                vout: 0,
+                value: *value,
+                address: String::new(),
+                address_type: AddressType::P2WPKH,
+                witness: vec![],
+                script_sig: String::new(),
+                script_pubkey: String::new(),
+            })
+            .collect(),
+        outputs: outputs
+            .iter()
+            .map(|(spending, value)| Output {
+                spending_txid: spending.map(dummy_txid),
+                value: *value,
+                address: String::new(),
+                address_type: AddressType::P2WPKH,
+                script_pubkey: String::new(),
+            })
+            .collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::bitcoin::{Sats, Txid};
+    use proptest::prelude::*;
+
+    use crate::bitcoin::{
+        coin_address, dummy_transaction, parse_address, AddressType, CoinJoinKind, CoinJoinRole,
+        FeeRate, Input, Multisig, NumberFormat, Output, Sats, ScriptType, TaprootSpend,
+        Transaction, TxRef, Txid,
+    };
 
     #[test]
     #[allow(clippy::inconsistent_digit_grouping)]
@@ -425,8 +1258,15 @@ mod tests {
         ];
 
         for case in cases {
-            assert_eq!(format!("{}", Sats(case.0)), case.1);
+            let mut s = String::new();
+            Sats(case.0).write(&mut s, NumberFormat::English).unwrap();
+            assert_eq!(s, case.1);
         }
+
+        assert_eq!(
+            Sats(1_234_56_789_012).format(NumberFormat::German),
+            "1.234,56 789 012"
+        );
     }
 
     #[test]
@@ -438,4 +1278,346 @@ mod tests {
             "afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382"
         );
     }
+
+    #[test]
+    fn tx_ref() {
+        let hex = "afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382";
+        let txid = Txid::new(hex).unwrap();
+
+        let cases = vec![
+            (format!("  {}\n", hex), None),
+            (format!("{}:3", hex), Some(3)),
+            (format!("https://mempool.space/tx/{}", hex), None),
+            (format!("https://mempool.space/tx/{}#vout=1", hex), Some(1)),
+            (format!("mempool.space/testnet/tx/{}/", hex), None),
+            (format!("/tx/{}/4", hex), Some(4)),
+            (
+                format!("https://blockstream.info/tx/{}?output:2", hex),
+                Some(2),
+            ),
+        ];
+
+        for (input, vout) in cases {
+            assert_eq!(TxRef::parse(&input), Ok(TxRef { txid, vout }));
+        }
+
+        let errors = vec![
+            "",
+            "afe8",
+            "xfe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382",
+            "afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382:x",
+            "https://mempool.space/address/bc1q",
+        ];
+
+        for input in errors {
+            assert!(TxRef::parse(input).is_err());
+        }
+    }
+
+    #[test]
+    fn address() {
+        let valid = [
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            " bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq ",
+            "BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ",
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx",
+        ];
+        for input in valid {
+            assert_eq!(parse_address(input), Ok(input.trim().to_string()));
+        }
+
+        let invalid = [
+            "afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382",
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7Divf0a",
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdb",
+            "Bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq",
+            "",
+        ];
+        for input in invalid {
+            assert!(parse_address(input).is_err());
+        }
+    }
+
+    #[test]
+    fn taproot_spend() {
+        let input = |witness: Vec<&str>| Input {
+            txid: Txid::new("afe8d3199cd68f973a7cba01cb6b59f733864b782e9be49f61bb7f3d928a8382")
+                .unwrap(),
+            vout: 0,
+            value: 1_000,
+            address: "bc1p".to_string(),
+            address_type: AddressType::P2TR,
+            witness: witness.into_iter().map(|w| w.to_string()).collect(),
+            script_sig: String::new(),
+            script_pubkey: String::new(),
+        };
+
+        let signature = "ab".repeat(64);
+        assert_eq!(
+            input(vec![&signature]).taproot_spend(),
+            Some(TaprootSpend::KeyPath)
+        );
+        assert_eq!(
+            input(vec![&signature, "50aa"]).taproot_spend(),
+            Some(TaprootSpend::KeyPath)
+        );
+
+        let control_block = format!("c1{}{}", "11".repeat(32), "22".repeat(32));
+        assert_eq!(
+            input(vec![&signature, "51", &control_block]).taproot_spend(),
+            Some(TaprootSpend::ScriptPath {
+                leaf_version: 0xc0,
+                merkle_depth: 1,
+                script: vec![0x51],
+            })
+        );
+
+        assert_eq!(input(vec![&signature, "51", "c1"]).taproot_spend(), None);
+    }
+
+    #[test]
+    fn multisig() {
+        let key = |b: u8| [vec![0x21, 0x02], vec![b; 32]].concat();
+        let script = [vec![0x52], key(1), key(2), key(3), vec![0x53, 0xae]].concat();
+        let multisig = Multisig::from_script(&script).unwrap();
+        assert_eq!(format!("{}", multisig), "2-of-3");
+        assert_eq!(multisig.pubkeys.len(), 3);
+
+        let xonly = |b: u8, op: u8| [vec![0x20], vec![b; 32], vec![op]].concat();
+        let tapscript = [xonly(1, 0xac), xonly(2, 0xba), vec![0x52, 0x9c]].concat();
+        assert_eq!(
+            format!("{}", Multisig::from_script(&tapscript).unwrap()),
+            "2-of-2"
+        );
+
+        let wrong_n = [vec![0x52], key(1), key(2), vec![0x53, 0xae]].concat();
+        assert_eq!(Multisig::from_script(&wrong_n), None);
+        assert_eq!(Multisig::from_script(&[0x51]), None);
+    }
+
+    #[test]
+    fn coinjoin() {
+        let tx = |inputs: Vec<u64>, outputs: Vec<u64>| {
+            let inputs: Vec<_> = inputs.into_iter().map(|value| (0, value)).collect();
+            let outputs: Vec<_> = outputs.into_iter().map(|value| (None, value)).collect();
+            let mut tx = dummy_transaction(0, &inputs, &outputs);
+            for (i, input) in tx.inputs.iter_mut().enumerate() {
+                input.address = format!("input {}", i);
+            }
+            tx
+        };
+
+        let mix = tx(
+            vec![1_000_000, 1_000_170, 1_000_000, 1_000_170, 1_000_170],
+            vec![1_000_000; 5],
+        )
+        .coinjoin()
+        .unwrap();
+        assert_eq!(mix.kind, CoinJoinKind::WhirlpoolMix { pool: 1_000_000 });
+        assert_eq!(mix.input_roles[0], CoinJoinRole::Remix);
+        assert_eq!(mix.input_roles[1], CoinJoinRole::Premix);
+        assert_eq!(mix.output_roles, vec![CoinJoinRole::Postmix; 5]);
+
+        let op_return = |mut tx: Transaction| {
+            tx.outputs[0].script_pubkey = format!("6a4c50{}", "ab".repeat(80));
+            tx
+        };
+        let tx0 = op_return(tx(
+            vec![5_000_000],
+            vec![0, 1_000_170, 1_000_170, 1_000_170, 42_500, 1_950_000],
+        ))
+        .coinjoin()
+        .unwrap();
+        assert_eq!(tx0.kind, CoinJoinKind::WhirlpoolTx0 { pool: 1_000_000 });
+        assert_eq!(
+            tx0.output_roles,
+            vec![
+                CoinJoinRole::Change,
+                CoinJoinRole::Premix,
+                CoinJoinRole::Premix,
+                CoinJoinRole::Premix,
+                CoinJoinRole::Change,
+                CoinJoinRole::Change,
+            ]
+        );
+
+        // Premix-like outputs alone don't make a Tx0: an ordinary payment,
+        // one without the OP_RETURN and one without the coordinator's fee.
+        assert_eq!(
+            tx(vec![2_200_000], vec![1_050_000, 1_050_000]).coinjoin(),
+            None
+        );
+        assert_eq!(
+            tx(
+                vec![5_000_000],
+                vec![0, 1_000_170, 1_000_170, 1_000_170, 42_500, 1_950_000]
+            )
+            .coinjoin(),
+            None
+        );
+        assert_eq!(
+            op_return(tx(
+                vec![5_000_000],
+                vec![0, 1_000_170, 1_000_170, 1_000_170, 1_992_500]
+            ))
+            .coinjoin(),
+            None
+        );
+
+        let mut outputs = vec![262_144; 40];
+        outputs.extend((0..20).map(|i| 300_000 + i));
+        let wabisabi = tx(vec![500_000; 60], outputs).coinjoin().unwrap();
+        assert_eq!(wabisabi.kind, CoinJoinKind::WabiSabi);
+        assert_eq!(wabisabi.output_roles[0], CoinJoinRole::Mixed);
+        assert_eq!(wabisabi.output_roles[59], CoinJoinRole::Change);
+
+        let joinmarket = tx(
+            vec![3_000_000, 2_500_000, 4_000_000, 1_200_000],
+            vec![1_000_000, 1_499_000, 1_000_000, 2_999_000, 1_000_000],
+        )
+        .coinjoin()
+        .unwrap();
+        assert_eq!(
+            joinmarket.kind,
+            CoinJoinKind::JoinMarket {
+                denomination: 1_000_000
+            }
+        );
+        assert_eq!(joinmarket.output_roles[0], CoinJoinRole::Mixed);
+        assert_eq!(joinmarket.output_roles[1], CoinJoinRole::Change);
+
+        // The same from a single wallet.
+        let mut single_wallet = tx(
+            vec![3_000_000, 2_500_000, 4_000_000, 1_200_000],
+            vec![1_000_000, 1_499_000, 1_000_000, 2_999_000, 1_000_000],
+        );
+        for input in &mut single_wallet.inputs {
+            input.address = "wallet".to_string();
+        }
+        assert_eq!(single_wallet.coinjoin(), None);
+
+        // A batched payment of equal amounts from coins of several addresses
+        // with a single change output.
+        assert_eq!(
+            tx(
+                vec![3_000_000, 2_500_000, 4_000_000],
+                vec![1_000_000, 1_000_000, 1_000_000, 6_490_000]
+            )
+            .coinjoin(),
+            None
+        );
+
+        // A batched payout of equal amounts from a single wallet.
+        assert_eq!(
+            tx(
+                vec![10_000_000],
+                vec![1_000_000, 1_000_000, 1_000_000, 6_990_000]
+            )
+            .coinjoin(),
+            None
+        );
+
+        assert_eq!(
+            tx(vec![2_000_000], vec![1_000_000, 990_000]).coinjoin(),
+            None
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let tx = |input: u64, output: u64| dummy_transaction(0, &[(1, input)], &[(None, output)]);
+
+        let ok = tx(100_000, 99_000);
+        assert_eq!(ok.fees(), 1_000);
+        assert!(ok.validate().is_empty());
+        assert_eq!(ok.fee_rate(), None);
+        let sized = Transaction { vsize: 110, ..ok };
+        assert_eq!(sized.fee_rate(), Some(FeeRate(1_000.0 / 110.0)));
+        assert_eq!(sized.fee_rate().unwrap().to_string(), "9.1 sat/vB");
+
+        let negative = tx(100_000, 100_001);
+        assert_eq!(negative.fees(), 0);
+        assert_eq!(negative.validate().len(), 1);
+
+        let absurd = tx(100_000_000, 50_000_000);
+        assert_eq!(absurd.fees(), 50_000_000);
+        assert_eq!(absurd.validate().len(), 1);
+    }
+
+    #[test]
+    fn scripts_without_address() {
+        let pubkey = format!("21{}ac", "02".repeat(33));
+        assert_eq!(ScriptType::from_hex(&pubkey), ScriptType::PubKey);
+        assert_eq!(ScriptType::from_hex("6a0568656c6c6f"), ScriptType::NullData);
+        let multisig = format!("5121{}51ae", "03".repeat(33));
+        assert_eq!(ScriptType::from_hex(&multisig), ScriptType::Multisig);
+        // Witness version 2.
+        assert_eq!(ScriptType::from_hex("52020000"), ScriptType::WitnessUnknown);
+        assert_eq!(ScriptType::from_hex("ff"), ScriptType::NonStandard);
+        assert_eq!(ScriptType::from_hex("not hex"), ScriptType::NonStandard);
+
+        // Older backends send a placeholder and no script.
+        let output: Output = serde_json::from_str(
+            r#"{"spending_txid": null, "value": 0, "address": "????", "address_type": "unknown"}"#,
+        )
+        .unwrap();
+        assert_eq!(output.script_pubkey, "");
+        assert_eq!(
+            coin_address(&output.address, output.address_type, &output.script_pubkey),
+            ""
+        );
+        assert_eq!(
+            coin_address("????", AddressType::Unknown, "6a00"),
+            "6a00".to_string()
+        );
+        assert_eq!(coin_address("1abc", AddressType::P2PKH, "76a9"), "1abc");
+    }
+
+    proptest! {
+        #[test]
+        fn txid_from_any_bytes(bytes: [u8; 32]) {
+            let txid = Txid(bytes);
+            prop_assert_eq!(Txid::new(&txid.hex_string()), Ok(txid));
+            let json = serde_json::to_string(&txid).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Txid>(&json).unwrap(), txid);
+        }
+
+        #[test]
+        fn txid_from_any_string(
+            string in prop_oneof![any::<String>(), "[0-9a-fA-F]{64}", "[0-9a-fA-Fg é]{60,68}"]
+        ) {
+            let valid = string.len() == 64 && string.chars().all(|c| c.is_ascii_hexdigit());
+            prop_assert_eq!(Txid::new(&string).is_ok(), valid);
+            let json = serde_json::to_string(&string).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Txid>(&json).is_ok(), valid);
+        }
+
+        #[test]
+        fn sats_in_any_format(sats in prop_oneof![any::<u64>(), 0..1_000_000_000u64]) {
+            for format in NumberFormat::ALL {
+                let s = Sats(sats).format(format);
+                prop_assert!(s
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || [' ', format.thousands(), format.decimal()].contains(&c)));
+                let digits: String = s.chars().filter(char::is_ascii_digit).collect();
+                prop_assert_eq!(digits.parse::<u64>(), Ok(sats));
+                // Whole bitcoins are set apart with the 8 decimals of sats.
+                match s.split_once(format.decimal()) {
+                    Some((_, decimals)) => {
+                        prop_assert!(sats >= 100_000_000);
+                        prop_assert_eq!(decimals.chars().filter(char::is_ascii_digit).count(), 8);
+                    }
+                    None => prop_assert!(sats < 100_000_000),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn txid_needs_64_characters() {
+        // Exactly 64 bytes, but not 64 characters.
+        let string = format!("{}é", "0".repeat(62));
+        assert!(Txid::new(&string).is_err());
+    }
 }
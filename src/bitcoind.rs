@@ -0,0 +1,353 @@
+//! Import the history of a Bitcoin Core wallet via JSON-RPC. Only available on
+//! desktop since browsers can't talk to a local node (CORS, no auth headers).
+
+use std::{collections::HashSet, sync::mpsc::Sender};
+
+use egui::{Button, Context, Grid, TextEdit};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+use crate::{
+    app::Update, bitcoin::Txid, components::wallet_import::WalletTx, loading::Loading, modal,
+    notifications::NotifyExt,
+};
+
+/// Connection settings. The password is deliberately not persisted.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RpcConfig {
+    url: String,
+    user: String,
+    wallet: String,
+    #[serde(skip)]
+    password: String,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://127.0.0.1:8332".to_string(),
+            user: String::new(),
+            wallet: String::new(),
+            password: String::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+fn call<T: 'static + DeserializeOwned>(
+    ctx: &Context,
+    config: &RpcConfig,
+    method: &str,
+    params: serde_json::Value,
+    on_success: impl 'static + Send + FnOnce(T),
+) {
+    let url = if config.wallet.is_empty() {
+        config.url.clone()
+    } else {
+        format!(
+            "{}/wallet/{}",
+            config.url.trim_end_matches('/'),
+            config.wallet
+        )
+    };
+    let body = json!({
+        "jsonrpc": "1.0",
+        "id": "txgraph",
+        "method": method,
+        "params": params,
+    });
+    let mut request = ehttp::Request::post(url, body.to_string().into_bytes());
+    request.headers.insert(
+        "Authorization",
+        format!(
+            "Basic {}",
+            base64::encode(format!("{}:{}", config.user, config.password))
+        ),
+    );
+    request.headers.insert("Content-Type", "application/json");
+
+    Loading::start_loading(ctx);
+    let ctx = ctx.clone();
+    let method = method.to_string();
+    ehttp::fetch(request, move |response| {
+        Loading::loading_done(&ctx);
+        let result = response.and_then(|response| {
+            if response.status == 401 {
+                return Err("Wrong user or password".to_string());
+            }
+            // bitcoind answers errors with status 500 but a regular body.
+            let text = response.text().unwrap_or_default();
+            match serde_json::from_str::<RpcResponse<T>>(text) {
+                Ok(RpcResponse {
+                    error: Some(RpcError { code, message }),
+                    ..
+                }) => Err(format!("{} (code {})", message, code)),
+                Ok(RpcResponse {
+                    result: Some(result),
+                    ..
+                }) => Ok(result),
+                Ok(_) => Err("Empty response".to_string()),
+                Err(e) => Err(format!("HTTP {}: {}", response.status, e)),
+            }
+        });
+        match result {
+            Ok(result) => on_success(result),
+            Err(e) => ctx.notify_error(format!("bitcoind `{}` failed", method), Some(e)),
+        }
+        ctx.request_repaint();
+    });
+}
+
+#[derive(Deserialize)]
+struct ListTransactionsEntry {
+    txid: Txid,
+    category: String,
+    vout: u32,
+    #[serde(default)]
+    label: String,
+}
+
+#[derive(Deserialize)]
+struct ListUnspentEntry {
+    txid: Txid,
+    vout: u32,
+    #[serde(default)]
+    label: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum Ownership {
+    Mine,
+    Change,
+    Theirs,
+}
+
+impl Ownership {
+    fn label(&self, wallet_label: &str) -> String {
+        let ownership = match self {
+            Ownership::Mine => "Mine",
+            Ownership::Change => "Change (mine)",
+            Ownership::Theirs => "Theirs",
+        };
+        if wallet_label.is_empty() {
+            ownership.to_string()
+        } else {
+            format!("{}: {}", ownership, wallet_label)
+        }
+    }
+}
+
+/// Classify the outputs that the wallet knows about. Received outputs are
+/// ours, outputs we sent to are someone else's. bitcoind hides change from
+/// `listtransactions`, so unspent outputs of transactions we sent from count
+/// as change. Spent change can't be told apart and stays unclassified.
+fn classify(
+    txs: &[ListTransactionsEntry],
+    unspent: &[ListUnspentEntry],
+) -> Vec<((Txid, usize), Ownership, String)> {
+    let mut coins = vec![];
+    let mut seen = HashSet::new();
+
+    for tx in txs {
+        let ownership = match tx.category.as_str() {
+            "receive" | "generate" | "immature" => Ownership::Mine,
+            "send" => Ownership::Theirs,
+            _ => continue,
+        };
+        let coin = (tx.txid, tx.vout as usize);
+        if seen.insert(coin) {
+            coins.push((coin, ownership, tx.label.clone()));
+        }
+    }
+
+    let sent_from: HashSet<Txid> = txs
+        .iter()
+        .filter(|tx| tx.category == "send")
+        .map(|tx| tx.txid)
+        .collect();
+
+    for utxo in unspent {
+        let coin = (utxo.txid, utxo.vout as usize);
+        if seen.insert(coin) {
+            let ownership = if sent_from.contains(&utxo.txid) {
+                Ownership::Change
+            } else {
+                Ownership::Mine
+            };
+            coins.push((coin, ownership, utxo.label.clone()));
+        }
+    }
+
+    coins
+}
+
+fn import(ctx: &Context, config: &RpcConfig, sender: Sender<Update>) {
+    let name = if config.wallet.is_empty() {
+        "bitcoind".to_string()
+    } else {
+        config.wallet.clone()
+    };
+
+    let ctx2 = ctx.clone();
+    let config2 = config.clone();
+    call(
+        ctx,
+        config,
+        "listtransactions",
+        json!(["*", 100_000, 0, true]),
+        move |txs: Vec<ListTransactionsEntry>| {
+            call(
+                &ctx2,
+                &config2,
+                "listunspent",
+                json!([0]),
+                move |unspent: Vec<ListUnspentEntry>| {
+                    let coins = classify(&txs, &unspent);
+
+                    let mut txids = HashSet::new();
+                    let txs = coins
+                        .iter()
+                        .filter(|((txid, _), _, _)| txids.insert(*txid))
+                        .map(|((txid, _), _, _)| WalletTx {
+                            txid: *txid,
+                            label: None,
+                        })
+                        .collect();
+
+                    sender
+                        .send(Update::ImportWallet {
+                            name,
+                            txs,
+                            apply_labels: false,
+                        })
+                        .unwrap();
+                    sender
+                        .send(Update::LabelCoins {
+                            labels: coins
+                                .into_iter()
+                                .map(|(coin, ownership, label)| (coin, ownership.label(&label)))
+                                .collect(),
+                        })
+                        .unwrap();
+                },
+            );
+        },
+    );
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BitcoindImport {
+    config: RpcConfig,
+    #[serde(skip)]
+    open: bool,
+}
+
+impl BitcoindImport {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show(&mut self, ctx: &Context, sender: Sender<Update>) {
+        if !self.open {
+            return;
+        }
+
+        modal::show(ctx, "Import from bitcoind", |ui| {
+            Grid::new("bitcoind").num_columns(2).show(ui, |ui| {
+                ui.label("RPC URL:");
+                ui.text_edit_singleline(&mut self.config.url);
+                ui.end_row();
+
+                ui.label("Wallet:");
+                ui.add(TextEdit::singleline(&mut self.config.wallet).hint_text("default wallet"));
+                ui.end_row();
+
+                ui.label("User:");
+                ui.text_edit_singleline(&mut self.config.user);
+                ui.end_row();
+
+                ui.label("Password:");
+                ui.add(TextEdit::singleline(&mut self.config.password).password(true));
+                ui.end_row();
+            });
+
+            ui.label("Outputs are labeled as mine, change or theirs.");
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Cancel").clicked() {
+                    self.open = false;
+                }
+                if ui
+                    .add_enabled(!self.config.url.is_empty(), Button::new("Import"))
+                    .clicked()
+                {
+                    import(ui.ctx(), &self.config, sender);
+                    self.open = false;
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bitcoin::dummy_txid;
+
+    use super::*;
+
+    #[test]
+    fn classify_outputs() {
+        let txs = vec![
+            ListTransactionsEntry {
+                txid: dummy_txid(1),
+                category: "receive".to_string(),
+                vout: 0,
+                label: "Salary".to_string(),
+            },
+            ListTransactionsEntry {
+                txid: dummy_txid(2),
+                category: "send".to_string(),
+                vout: 1,
+                label: String::new(),
+            },
+        ];
+        let unspent = vec![
+            ListUnspentEntry {
+                txid: dummy_txid(1),
+                vout: 0,
+                label: "Salary".to_string(),
+            },
+            ListUnspentEntry {
+                txid: dummy_txid(2),
+                vout: 0,
+                label: String::new(),
+            },
+        ];
+
+        assert_eq!(
+            classify(&txs, &unspent),
+            vec![
+                ((dummy_txid(1), 0), Ownership::Mine, "Salary".to_string()),
+                ((dummy_txid(2), 1), Ownership::Theirs, String::new()),
+                ((dummy_txid(2), 0), Ownership::Change, String::new()),
+            ]
+        );
+        assert_eq!(Ownership::Mine.label("Salary"), "Mine: Salary");
+        assert_eq!(Ownership::Change.label(""), "Change (mine)");
+    }
+}
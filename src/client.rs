@@ -1,17 +1,58 @@
-use egui::{Context, Id};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use egui::{mutex::Mutex, Context, Id};
 use serde::Deserialize;
 
-use crate::{loading::Loading, notifications::NotifyExt};
+use crate::{
+    bitcoin::Txid, loading::Loading, notifications::NotifyExt, platform::inner as platform,
+    settings::Settings,
+};
 
 #[derive(Clone)]
 pub struct Client {
     base_url: String,
+    /// Seconds after which a request is given up.
+    timeout: f64,
+}
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+#[derive(Default)]
+struct Requests {
+    next_id: u64,
+    /// Deadline and the callback that fails the request, by request id.
+    waiting: HashMap<u64, (f64, Callback)>,
+}
+
+/// Requests still waiting for a response.
+#[derive(Clone, Default)]
+struct InFlight(Arc<Mutex<Requests>>);
+
+impl InFlight {
+    fn get(ctx: &Context) -> Self {
+        ctx.data_mut(|d| d.get_temp_mut_or_default::<Self>(Id::NULL).clone())
+    }
+
+    fn next_id(&self) -> u64 {
+        let mut requests = self.0.lock();
+        requests.next_id += 1;
+        requests.next_id
+    }
+
+    fn insert(&self, id: u64, deadline: f64, fail: Callback) {
+        self.0.lock().waiting.insert(id, (deadline, fail));
+    }
+
+    fn remove(&self, id: u64) {
+        self.0.lock().waiting.remove(&id);
+    }
 }
 
 impl Client {
     pub fn new(base_url: &str) -> Self {
         Self {
             base_url: base_url.to_string(),
+            timeout: Settings::default().request_timeout as f64,
         }
     }
 
@@ -24,27 +65,145 @@ impl Client {
         ctx.data_mut(|d| d.insert_temp(Id::NULL, self))
     }
 
-    pub fn fetch_json<T: for<'de> Deserialize<'de>>(
+    pub fn base_url(ctx: &Context) -> String {
+        Self::load(ctx).base_url
+    }
+
+    pub fn last_fetch(ctx: &Context) -> Option<LastFetch> {
+        ctx.data(|d| d.get_temp(Id::NULL))
+    }
+
+    pub fn set_timeout(ctx: &Context, timeout: f64) {
+        let mut slf = Self::load(ctx);
+        slf.timeout = timeout;
+        slf.store(ctx);
+    }
+
+    /// Fail all requests that have been waiting longer than the timeout. Their
+    /// responses are ignored should they still arrive.
+    pub fn check_timeouts(ctx: &Context) {
+        let in_flight = InFlight::get(ctx);
+        let now = platform::now();
+        let expired: Vec<Callback> = {
+            let mut requests = in_flight.0.lock();
+            let ids: Vec<u64> = requests
+                .waiting
+                .iter()
+                .filter(|(_, (deadline, _))| *deadline <= now)
+                .map(|(id, _)| *id)
+                .collect();
+            ids.iter()
+                .filter_map(|id| requests.waiting.remove(id))
+                .map(|(_, fail)| fail)
+                .collect()
+        };
+        for fail in expired {
+            fail();
+        }
+        if !in_flight.0.lock().waiting.is_empty() {
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
+    }
+
+    pub fn fetch_json<T: 'static + for<'de> Deserialize<'de>>(
+        mk_request: impl FnOnce(&str) -> ehttp::Request,
+        ctx: &Context,
+        on_done: impl 'static + Send + FnOnce(Result<T, FetchError>),
+    ) {
+        let decode = |text: &str| {
+            serde_json::from_str::<T>(text).map_err(|err| FetchError::DecodeFailed(err.to_string()))
+        };
+        Self::fetch(mk_request, ctx, decode, false, on_done);
+    }
+
+    /// Like [Self::fetch_json], for requests made in the background. Failures
+    /// are left to the caller and the loading indicator isn't shown.
+    pub fn poll_json<T: 'static + for<'de> Deserialize<'de>>(
+        mk_request: impl FnOnce(&str) -> ehttp::Request,
+        ctx: &Context,
+        on_done: impl 'static + Send + FnOnce(Result<T, FetchError>),
+    ) {
+        let decode = |text: &str| {
+            serde_json::from_str::<T>(text).map_err(|err| FetchError::DecodeFailed(err.to_string()))
+        };
+        Self::fetch(mk_request, ctx, decode, true, on_done);
+    }
+
+    /// For the few endpoints of the backend that answer in plain text.
+    pub fn fetch_text(
+        mk_request: impl FnOnce(&str) -> ehttp::Request,
+        ctx: &Context,
+        on_done: impl 'static + Send + FnOnce(Result<String, FetchError>),
+    ) {
+        Self::fetch(
+            mk_request,
+            ctx,
+            |text| Ok(text.trim().to_string()),
+            false,
+            on_done,
+        );
+    }
+
+    fn fetch<T: 'static>(
         mk_request: impl FnOnce(&str) -> ehttp::Request,
         ctx: &Context,
+        decode: impl 'static + Send + FnOnce(&str) -> Result<T, FetchError>,
+        background: bool,
         on_done: impl 'static + Send + FnOnce(Result<T, FetchError>),
     ) {
         let slf = Self::load(ctx);
 
-        Loading::start_loading(ctx);
+        if !background {
+            Loading::start_loading(ctx);
+        }
         let request = mk_request(&slf.base_url);
+        let started = platform::now();
+
+        // Whichever comes first, the response or the timeout, takes `on_done`.
+        let on_done = Arc::new(Mutex::new(Some(on_done)));
+        let in_flight = InFlight::get(ctx);
+        let id = in_flight.next_id();
+        let finish = {
+            let ctx = ctx.clone();
+            let in_flight = in_flight.clone();
+            move |result: Result<T, FetchError>| {
+                let Some(on_done) = on_done.lock().take() else {
+                    return;
+                };
+                in_flight.remove(id);
+                if !background {
+                    Loading::loading_done(&ctx);
+                    if let Err(ref err) = result {
+                        err.notify(&ctx);
+                    }
+                }
+                ctx.data_mut(|d| {
+                    d.insert_temp(
+                        Id::NULL,
+                        LastFetch {
+                            latency: platform::now() - started,
+                            ok: result.is_ok(),
+                        },
+                    )
+                });
+                on_done(result);
+            }
+        };
+
+        let timeout = slf.timeout;
+        let on_timeout = finish.clone();
+        in_flight.insert(
+            id,
+            started + timeout,
+            Box::new(move || on_timeout(Err(FetchError::TimedOut { timeout }))),
+        );
 
-        let ctx = ctx.clone();
         ehttp::fetch(request, move |response| {
-            Loading::loading_done(&ctx);
             let result = match response {
                 Ok(response) => {
                     if response.status == 200 {
                         if let Some(text) = response.text() {
-                            match serde_json::from_str::<T>(text) {
-                                Ok(json) => Ok(json),
-                                Err(err) => Err(FetchError::DecodeFailed(err.to_string())),
-                            }
+                            decode(text)
                         } else {
                             Err(FetchError::ResponseEmpty)
                         }
@@ -56,22 +215,142 @@ impl Client {
                 }
                 Err(err) => Err(FetchError::RequestFailed(err)),
             };
-            if let Err(ref err) = result {
-                err.notify(&ctx);
-            }
-            on_done(result);
+            finish(result);
         });
     }
 }
 
+/// Confirmed transactions per page of the backend's address history.
+const ADDRESS_PAGE_SIZE: usize = 25;
+
+impl Client {
+    /// Transactions that fund or spend from `address`, newest first and at
+    /// most `limit` of them. Follows the backend's pagination.
+    pub fn fetch_address_txids(
+        ctx: &Context,
+        address: String,
+        limit: usize,
+        on_done: impl 'static + Send + FnOnce(Result<Vec<Txid>, FetchError>),
+    ) {
+        Self::fetch_address_page(ctx.clone(), address, None, vec![], limit, on_done);
+    }
+
+    fn fetch_address_page(
+        ctx: Context,
+        address: String,
+        after: Option<Txid>,
+        mut txids: Vec<Txid>,
+        limit: usize,
+        on_done: impl 'static + Send + FnOnce(Result<Vec<Txid>, FetchError>),
+    ) {
+        let path = match after {
+            Some(after) => format!("/address/{}/txs/chain/{}", address, after),
+            None => format!("/address/{}/txs", address),
+        };
+        let ctx2 = ctx.clone();
+        Self::fetch_json::<Vec<AddressTx>>(
+            move |base_url| ehttp::Request::get(format!("{}{}", base_url, path)),
+            &ctx,
+            move |result| match result {
+                Ok(page) => {
+                    let confirmed: Vec<Txid> = page
+                        .iter()
+                        .filter(|tx| tx.status.confirmed)
+                        .map(|tx| tx.txid)
+                        .collect();
+                    txids.extend(page.iter().map(|tx| tx.txid));
+                    let more = confirmed.len() == ADDRESS_PAGE_SIZE && txids.len() < limit;
+                    match confirmed.last() {
+                        Some(last) if more => {
+                            let after = Some(*last);
+                            Self::fetch_address_page(ctx2, address, after, txids, limit, on_done)
+                        }
+                        _ => {
+                            txids.truncate(limit);
+                            on_done(Ok(txids));
+                        }
+                    }
+                }
+                Err(err) => on_done(Err(err)),
+            },
+        );
+    }
+}
+
+impl Client {
+    /// All transactions of the block at `height`, in block order.
+    pub fn fetch_block_txids(
+        ctx: &Context,
+        height: u32,
+        on_done: impl 'static + Send + FnOnce(Result<Vec<Txid>, FetchError>),
+    ) {
+        let ctx2 = ctx.clone();
+        Self::fetch_text(
+            move |base_url| ehttp::Request::get(format!("{}/block-height/{}", base_url, height)),
+            ctx,
+            move |result| match result {
+                Ok(hash) => Self::fetch_json::<Vec<Txid>>(
+                    move |base_url| {
+                        ehttp::Request::get(format!("{}/block/{}/txids", base_url, hash))
+                    },
+                    &ctx2,
+                    on_done,
+                ),
+                Err(err) => on_done(Err(err)),
+            },
+        );
+    }
+}
+
+/// The part of the backend's address history entries we need.
+#[derive(Deserialize)]
+struct AddressTx {
+    txid: Txid,
+    status: AddressTxStatus,
+}
+
+#[derive(Deserialize)]
+struct AddressTxStatus {
+    confirmed: bool,
+}
+
+/// Outcome of the most recent API request, shown in the status bar.
+#[derive(Clone, Copy)]
+pub struct LastFetch {
+    /// Seconds from sending the request to receiving the response.
+    pub latency: f64,
+    pub ok: bool,
+}
+
 #[derive(Debug)]
 pub enum FetchError {
     RequestFailed(String),
     DecodeFailed(String),
     ResponseEmpty,
+    TimedOut { timeout: f64 },
 }
 
 impl FetchError {
+    /// Seconds after which the request was given up, if it timed out.
+    pub fn timeout(&self) -> Option<f64> {
+        match self {
+            Self::TimedOut { timeout } => Some(*timeout),
+            _ => None,
+        }
+    }
+
+    /// A short description, for showing next to what failed instead of in a
+    /// notification.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::RequestFailed(err) => format!("Request failed: {}", err),
+            Self::DecodeFailed(err) => format!("Could not decode response: {}", err),
+            Self::ResponseEmpty => "Response was empty".to_string(),
+            Self::TimedOut { timeout } => format!("Timed out after {}s", timeout),
+        }
+    }
+
+    /// Timeouts are left to the caller, which knows how to retry.
     fn notify(&self, ctx: &Context) {
         match self {
             Self::RequestFailed(err) => {
@@ -83,6 +362,7 @@ impl FetchError {
             Self::ResponseEmpty => {
                 ctx.notify_error("API esponse was empty", None::<&str>);
             }
+            Self::TimedOut { .. } => {}
         }
     }
 }
@@ -49,6 +49,9 @@ impl About {
                     "Click on inputs / outputs to expand to the next transaction.",
                     "Drag/pinch screen to pan/zoom.",
                     "Drag transactions to adjust layout.",
+                    "Drag inputs / outputs sideways to reorder them.",
+                    "Shift-click transactions to select several.",
+                    "Paste a txid with Ctrl+V to load it under the pointer.",
                     "Right-click transactions or inputs/outputs.",
                 ];
 
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use egui::{Context, Grid, RichText, ScrollArea, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::style;
+
+/// Entity labels of well-known addresses, like an exchange's hot wallet.
+/// Unlike [crate::annotations::Annotations], they aren't part of a workspace
+/// and apply in all of them. The label of a coin in the workspace takes
+/// precedence over the label of its address.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AddressBook {
+    labels: BTreeMap<String, String>,
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    search: String,
+}
+
+impl AddressBook {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn label(&self, address: &str) -> Option<&str> {
+        self.labels.get(address).map(String::as_str)
+    }
+
+    /// Removes the label if it's empty.
+    pub fn set_label(&mut self, address: &str, label: String) {
+        if label.is_empty() {
+            self.labels.remove(address);
+        } else {
+            self.labels.insert(address.to_string(), label);
+        }
+    }
+
+    /// Context menu to label an address in all workspaces.
+    pub fn address_menu(&mut self, address: &str, ui: &mut egui::Ui) {
+        let mut label = self.label(address).unwrap_or_default().to_string();
+        ui.label(RichText::new(address).monospace());
+        ui.horizontal(|ui| {
+            if ui
+                .add(
+                    TextEdit::singleline(&mut label)
+                        .hint_text("Entity, e.g. an exchange")
+                        .desired_width(300.0),
+                )
+                .lost_focus()
+            {
+                ui.close_menu();
+            }
+            if ui.button("✖").clicked() {
+                label = String::new();
+                ui.close_menu();
+            }
+        });
+        ui.weak("Shown in italics in all workspaces, unless the coin has a label of its own.");
+        self.set_label(address, label);
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        let mut open = self.open;
+        let mut remove = None;
+
+        egui::Window::new("Address Book")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.weak("Labels of addresses, shared by all workspaces.");
+                ui.add(TextEdit::singleline(&mut self.search).hint_text("Search"));
+                ui.separator();
+
+                if self.labels.is_empty() {
+                    ui.label("Label an address from the context menu of its coins.");
+                    return;
+                }
+
+                let search = self.search.to_lowercase();
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    Grid::new("Address book").num_columns(3).show(ui, |ui| {
+                        for (address, label) in &self.labels {
+                            if !address.to_lowercase().contains(&search)
+                                && !label.to_lowercase().contains(&search)
+                            {
+                                continue;
+                            }
+                            ui.label(label);
+                            ui.label(RichText::new(address).font(style::get(ui).font_id()));
+                            if ui.button("✖").clicked() {
+                                remove = Some(address.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+
+        if let Some(address) = remove {
+            self.labels.remove(&address);
+        }
+        self.open = open;
+    }
+}
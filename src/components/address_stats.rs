@@ -0,0 +1,293 @@
+use std::{collections::BTreeMap, sync::mpsc::Sender};
+
+use chrono::Datelike;
+use egui::{ahash::HashSet, Context, Rect, RichText, Rounding, Sense, Vec2};
+
+use crate::{
+    app::Update,
+    bitcoin::{AddressType, Txid},
+    graph::{Graph, Model},
+    modal,
+    style::{address_type_color, ADDRESS_TYPES},
+};
+
+/// The outputs created in one year, by address type.
+#[derive(Debug, PartialEq)]
+pub struct Bar {
+    pub year: i32,
+    pub segments: Vec<Segment>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Segment {
+    pub address_type: AddressType,
+    pub outputs: usize,
+    /// Transactions creating at least one of the outputs, sorted.
+    pub txids: Vec<Txid>,
+}
+
+impl Bar {
+    fn outputs(&self) -> usize {
+        self.segments.iter().map(|s| s.outputs).sum()
+    }
+}
+
+/// Count the outputs of the transactions in scope by year and address type.
+/// Fees aren't outputs. Empty segments are left out.
+pub fn bars(model: &Model, in_scope: impl Fn(&Txid) -> bool) -> Vec<Bar> {
+    let mut years: BTreeMap<i32, Vec<Segment>> = BTreeMap::new();
+
+    for (txid, tx) in model.txs().filter(|(txid, _)| in_scope(txid)) {
+        let year = chrono::NaiveDateTime::from_timestamp_opt(tx.timestamp, 0)
+            .unwrap_or_default()
+            .year();
+        let segments = years.entry(year).or_insert_with(|| {
+            ADDRESS_TYPES
+                .iter()
+                .map(|t| Segment {
+                    address_type: *t,
+                    outputs: 0,
+                    txids: vec![],
+                })
+                .collect()
+        });
+
+        for output in &tx.outputs {
+            let Some(address_type) = output.address_type() else {
+                continue;
+            };
+            let segment = segments
+                .iter_mut()
+                .find(|s| s.address_type == address_type)
+                .unwrap();
+            segment.outputs += 1;
+            if !segment.txids.contains(txid) {
+                segment.txids.push(*txid);
+            }
+        }
+    }
+
+    years
+        .into_iter()
+        .map(|(year, segments)| Bar {
+            year,
+            segments: segments
+                .into_iter()
+                .filter(|s| s.outputs > 0)
+                .map(|mut s| {
+                    s.txids.sort();
+                    s
+                })
+                .collect(),
+        })
+        .filter(|bar| !bar.segments.is_empty())
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum Scope {
+    #[default]
+    All,
+    Selection,
+    Component,
+}
+
+impl Scope {
+    const ALL: [Scope; 3] = [Scope::All, Scope::Selection, Scope::Component];
+
+    fn name(self) -> &'static str {
+        match self {
+            Scope::All => "All transactions",
+            Scope::Selection => "Selection",
+            Scope::Component => "Connected to selected",
+        }
+    }
+}
+
+/// Address type composition of a wallet or cluster over time, e.g. to follow
+/// its migration from P2PKH to segwit and taproot.
+#[derive(Default)]
+pub struct AddressStats {
+    open: bool,
+    scope: Scope,
+}
+
+impl AddressStats {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show(&mut self, ctx: &Context, graph: &Graph, sender: Sender<Update>) {
+        if !self.open {
+            return;
+        }
+
+        modal::show(ctx, "Address Types", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Outputs of:");
+                for scope in Scope::ALL {
+                    ui.radio_value(&mut self.scope, scope, scope.name());
+                }
+            });
+
+            let scope: HashSet<Txid> = match self.scope {
+                Scope::All => HashSet::default(),
+                Scope::Selection => graph.selection().clone(),
+                Scope::Component => graph
+                    .selected()
+                    .map(|txid| graph.connected_component(txid))
+                    .unwrap_or_default(),
+            };
+            let bars = bars(graph.model(), |txid| {
+                self.scope == Scope::All || scope.contains(txid)
+            });
+
+            if bars.is_empty() {
+                ui.label(RichText::new("No transactions in scope.").weak());
+            } else if chart(ui, &bars, &sender) {
+                self.open = false;
+            }
+
+            ui.horizontal(|ui| {
+                for address_type in ADDRESS_TYPES {
+                    ui.label(RichText::new("■").color(address_type_color(address_type)));
+                    ui.label(address_type.to_string());
+                }
+            });
+
+            ui.separator();
+            if ui.button("Close").clicked() {
+                self.open = false;
+            }
+        });
+    }
+}
+
+/// One stacked bar per year, showing the share of each address type. Clicking
+/// a segment selects its transactions. Returns whether one was clicked.
+fn chart(ui: &mut egui::Ui, bars: &[Bar], sender: &Sender<Update>) -> bool {
+    const BAR_WIDTH: f32 = 28.0;
+    const GAP: f32 = 8.0;
+    const HEIGHT: f32 = 160.0;
+
+    let width = (bars.len() as f32 * (BAR_WIDTH + GAP)).max(300.0);
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, HEIGHT + 16.0), Sense::hover());
+    let painter = ui.painter();
+    let mut clicked = false;
+
+    for (i, bar) in bars.iter().enumerate() {
+        let left = rect.left() + i as f32 * (BAR_WIDTH + GAP);
+        let total = bar.outputs() as f32;
+        let mut bottom = rect.top() + HEIGHT;
+
+        for segment in &bar.segments {
+            let height = HEIGHT * segment.outputs as f32 / total;
+            let segment_rect = Rect::from_min_max(
+                egui::pos2(left, bottom - height),
+                egui::pos2(left + BAR_WIDTH, bottom),
+            );
+            bottom -= height;
+
+            let response = ui
+                .interact(
+                    segment_rect,
+                    ui.id().with(bar.year).with(segment.address_type),
+                    Sense::click(),
+                )
+                .on_hover_text(format!(
+                    "{} {}: {} outputs ({:.0}%) in {} transactions\nClick to select them.",
+                    bar.year,
+                    segment.address_type,
+                    segment.outputs,
+                    100.0 * segment.outputs as f32 / total,
+                    segment.txids.len()
+                ));
+
+            let mut fill = address_type_color(segment.address_type);
+            if response.hovered() {
+                fill = fill.gamma_multiply(0.7);
+            }
+            painter.rect_filled(segment_rect, Rounding::ZERO, fill);
+
+            if response.clicked() {
+                sender
+                    .send(Update::SelectTxs {
+                        txids: segment.txids.clone(),
+                    })
+                    .unwrap();
+                clicked = true;
+            }
+        }
+
+        painter.text(
+            egui::pos2(left + BAR_WIDTH / 2.0, rect.top() + HEIGHT + 2.0),
+            egui::Align2::CENTER_TOP,
+            bar.year.to_string(),
+            egui::FontId::proportional(10.0),
+            ui.visuals().text_color(),
+        );
+    }
+
+    clicked
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bitcoin::{dummy_transaction, dummy_txid, Transaction};
+
+    use super::*;
+
+    fn tx(n: u8, timestamp: i64, outputs: &[AddressType]) -> Transaction {
+        let mut tx = dummy_transaction(
+            n,
+            &[(0, 1_000 * outputs.len() as u64)],
+            &vec![(None, 900); outputs.len()],
+        );
+        tx.timestamp = timestamp;
+        tx.inputs[0].address_type = AddressType::P2PKH;
+        for (output, address_type) in tx.outputs.iter_mut().zip(outputs) {
+            output.address_type = *address_type;
+        }
+        tx
+    }
+
+    #[test]
+    fn bars_by_year_and_type() {
+        // 2015, 2021, 2023
+        let txs = [
+            tx(1, 1_430_000_000, &[AddressType::P2PKH, AddressType::P2PKH]),
+            tx(2, 1_620_000_000, &[AddressType::P2WPKH, AddressType::P2PKH]),
+            tx(3, 1_630_000_000, &[AddressType::P2WPKH]),
+            tx(4, 1_690_000_000, &[AddressType::P2TR]),
+        ];
+        let mut model = Model::default();
+        for tx in &txs {
+            model.add_tx(tx.txid, tx);
+        }
+
+        let all = bars(&model, |_| true);
+        assert_eq!(
+            all.iter().map(|b| b.year).collect::<Vec<_>>(),
+            vec![2015, 2021, 2023]
+        );
+        assert_eq!(
+            all[1].segments,
+            vec![
+                Segment {
+                    address_type: AddressType::P2PKH,
+                    outputs: 1,
+                    txids: vec![dummy_txid(2)],
+                },
+                Segment {
+                    address_type: AddressType::P2WPKH,
+                    outputs: 2,
+                    txids: vec![dummy_txid(2), dummy_txid(3)],
+                },
+            ]
+        );
+
+        let in_2023 = bars(&model, |t| *t == dummy_txid(4));
+        assert_eq!(in_2023.len(), 1);
+        assert_eq!(in_2023[0].outputs(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+use egui::{Button, Color32, Context, Grid, RichText, ScrollArea, TextEdit};
+
+use crate::{annotations::Annotations, bitcoin::Txid, modal};
+
+/// The transactions and coins a coin flowed into.
+pub struct Trace {
+    pub txids: Vec<Txid>,
+    pub coins: Vec<(Txid, usize)>,
+}
+
+/// Label and color a whole trace in one go instead of annotating dozens of
+/// transactions and coins by hand.
+#[derive(Default)]
+pub struct AnnotateTrace {
+    trace: Option<Trace>,
+    label: String,
+    color: Option<Color32>,
+    skip_txs: bool,
+    skip_coins: bool,
+}
+
+impl AnnotateTrace {
+    pub fn open(&mut self, trace: Trace) {
+        self.trace = Some(trace);
+    }
+
+    pub fn show(&mut self, ctx: &Context, annotations: &mut Annotations) {
+        let Some(trace) = &self.trace else {
+            return;
+        };
+        let mut close = false;
+
+        modal::show(ctx, "Annotate Trace", |ui| {
+            Grid::new("Annotate trace").num_columns(2).show(ui, |ui| {
+                ui.label("Label:");
+                ui.add(
+                    TextEdit::singleline(&mut self.label)
+                        .hint_text("Keep existing labels")
+                        .desired_width(300.0),
+                );
+                ui.end_row();
+
+                ui.label("Color:");
+                ui.horizontal(|ui| {
+                    for color in Annotations::COLORS {
+                        let mut button = Button::new("  ").fill(color);
+                        if self.color == Some(color) {
+                            button = button.stroke(ui.visuals().selection.stroke);
+                        }
+                        if ui.add(button).clicked() {
+                            self.color = Some(color);
+                        }
+                    }
+                    if ui
+                        .button("✖")
+                        .on_hover_text("Keep existing colors")
+                        .clicked()
+                    {
+                        self.color = None;
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Apply to:");
+                ui.horizontal(|ui| {
+                    let mut txs = !self.skip_txs;
+                    ui.checkbox(&mut txs, format!("{} transactions", trace.txids.len()));
+                    self.skip_txs = !txs;
+                    let mut coins = !self.skip_coins;
+                    ui.checkbox(&mut coins, format!("{} coins", trace.coins.len()));
+                    self.skip_coins = !coins;
+                });
+                ui.end_row();
+            });
+
+            ui.separator();
+            ui.label(RichText::new("Preview").strong());
+            let label = (!self.label.is_empty()).then_some(self.label.as_str());
+            ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                Grid::new("Annotate trace preview")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        if !self.skip_txs {
+                            for txid in &trace.txids {
+                                let old = annotations.tx_label(*txid);
+                                preview_row(ui, short(txid), old, label, self.color);
+                            }
+                        }
+                        if !self.skip_coins {
+                            for coin in &trace.coins {
+                                let old = annotations.coin_label(*coin);
+                                let name = format!("{}:{}", short(&coin.0), coin.1);
+                                preview_row(ui, name, old, label, self.color);
+                            }
+                        }
+                    });
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                let nothing =
+                    (label.is_none() && self.color.is_none()) || (self.skip_txs && self.skip_coins);
+                if ui.add_enabled(!nothing, Button::new("Apply")).clicked() {
+                    if !self.skip_txs {
+                        for txid in &trace.txids {
+                            if let Some(label) = label {
+                                annotations.set_tx_label(*txid, label.to_string());
+                            }
+                            if let Some(color) = self.color {
+                                annotations.set_tx_color(*txid, color);
+                            }
+                        }
+                    }
+                    if !self.skip_coins {
+                        for coin in &trace.coins {
+                            if let Some(label) = label {
+                                annotations.set_coin_label(*coin, label.to_string());
+                            }
+                            if let Some(color) = self.color {
+                                annotations.set_coin_color(*coin, color);
+                            }
+                        }
+                    }
+                    close = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+            });
+        });
+
+        if close {
+            self.trace = None;
+        }
+    }
+}
+
+fn short(txid: &Txid) -> String {
+    txid.hex_string()[..16].to_string()
+}
+
+/// One transaction or coin, with its label before and after.
+fn preview_row(
+    ui: &mut egui::Ui,
+    name: String,
+    old: Option<String>,
+    new: Option<&str>,
+    color: Option<Color32>,
+) {
+    ui.monospace(name);
+    match &old {
+        Some(old) => ui.label(format!("[{}]", old)),
+        None => ui.label(RichText::new("Unlabeled").weak()),
+    };
+    let mut after = match (new, old) {
+        (Some(new), _) => RichText::new(format!("-> [{}]", new)),
+        (None, Some(old)) => RichText::new(format!("-> [{}]", old)),
+        (None, None) => RichText::new("->").weak(),
+    };
+    if let Some(color) = color {
+        after = after.color(color);
+    }
+    ui.label(after);
+    ui.end_row();
+}
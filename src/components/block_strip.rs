@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Sender, Arc},
+};
+
+use egui::{mutex::Mutex, Context, Frame, RichText, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Update, bitcoin::Txid, client::Client, graph::Graph, layout::SpawnDirection,
+    loading::Loading,
+};
+
+/// Transactions shown on either side of the selected one.
+const RADIUS: usize = 6;
+
+/// Distance between neighbors loaded from the strip, in graph coordinates.
+const GAP: f32 = 100.0;
+
+enum Block {
+    Loading,
+    Loaded(Vec<Txid>),
+    Failed,
+}
+
+/// The transactions next to the selected one in its block, to spot batched or
+/// sequential activity of the same actor.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockStrip {
+    open: bool,
+    /// Txids of the blocks looked at so far, by height.
+    #[serde(skip)]
+    blocks: Arc<Mutex<HashMap<u32, Block>>>,
+}
+
+impl BlockStrip {
+    pub fn toggle_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.open, "Block Neighbors")
+            .on_hover_text("Show the transactions next to the selected one in its block.");
+    }
+
+    fn fetch(&self, ctx: &Context, height: u32) {
+        let blocks = self.blocks.clone();
+        Client::fetch_block_txids(ctx, height, move |result| {
+            let block = match result {
+                Ok(txids) => Block::Loaded(txids),
+                Err(_) => Block::Failed,
+            };
+            blocks.lock().insert(height, block);
+        });
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        direction: SpawnDirection,
+        sender: Sender<Update>,
+    ) {
+        if !self.open {
+            return;
+        }
+        let Some(txid) = graph.selected() else {
+            return;
+        };
+        let Some(height) = graph.model().tx(&txid).map(|tx| tx.block_height) else {
+            return;
+        };
+        if height > 0 && !self.blocks.lock().contains_key(&height) {
+            self.blocks.lock().insert(height, Block::Loading);
+            self.fetch(ctx, height);
+        }
+
+        let frame = Frame::side_top_panel(&ctx.style()).inner_margin(4.0);
+
+        egui::TopBottomPanel::bottom("block_strip")
+            .frame(frame)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if height == 0 {
+                        ui.weak("Unconfirmed, not in a block yet.");
+                        return;
+                    }
+                    ui.label(RichText::new(format!("Block #{}", height)).strong());
+
+                    let mut retry = false;
+                    match self.blocks.lock().get(&height) {
+                        None | Some(Block::Loading) => Loading::spinner(ui),
+                        Some(Block::Failed) => {
+                            ui.weak("Could not load the block.");
+                            retry = ui.button("Retry").clicked();
+                        }
+                        Some(Block::Loaded(txids)) => {
+                            let Some(index) = txids.iter().position(|t| *t == txid) else {
+                                ui.weak("Transaction not found in the block.");
+                                return;
+                            };
+                            ui.weak(format!("{} of {}", index + 1, txids.len()));
+                            ui.separator();
+
+                            let start = index.saturating_sub(RADIUS);
+                            let end = (index + RADIUS + 1).min(txids.len());
+                            for (i, neighbor) in txids.iter().enumerate().take(end).skip(start) {
+                                let neighbor = (i, *neighbor);
+                                if let Some(update) =
+                                    neighbor_button(ui, graph, txid, neighbor, index, direction)
+                                {
+                                    sender.send(update).unwrap();
+                                }
+                            }
+                        }
+                    }
+                    if retry {
+                        self.blocks.lock().insert(height, Block::Loading);
+                        self.fetch(ctx, height);
+                    }
+                });
+            });
+    }
+}
+
+/// The `i`th transaction of the block. Loads it next to the selected one at
+/// `index`, or selects it if it's loaded already.
+fn neighbor_button(
+    ui: &mut egui::Ui,
+    graph: &Graph,
+    selected: Txid,
+    (i, txid): (usize, Txid),
+    index: usize,
+    direction: SpawnDirection,
+) -> Option<Update> {
+    let loaded = graph.get_tx_pos(txid).is_some();
+    let mut text = RichText::new(&txid.hex_string()[..8]).monospace();
+    if !loaded {
+        text = text.weak();
+    }
+    let hover = format!(
+        "#{} in the block{}\n{}",
+        i,
+        if loaded { ", loaded" } else { "" },
+        txid
+    );
+    let response = ui
+        .selectable_label(txid == selected, text)
+        .on_hover_text(hover);
+    if !response.clicked() || txid == selected {
+        return None;
+    }
+
+    let along = (i as f32 - index as f32) * GAP;
+    let offset = match direction {
+        SpawnDirection::Vertical => Vec2::new(along, 0.0),
+        SpawnDirection::Horizontal => Vec2::new(0.0, along),
+    };
+    let pos = graph.get_tx_pos(selected).map(|pos| pos + offset);
+    Some(Update::LoadOrSelectTx { txid, pos })
+}
@@ -0,0 +1,291 @@
+use egui::{Button, Color32, Context, Grid, ScrollArea, TextEdit, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::Annotations, bitcoin::Txid, export::validate_version, modal,
+    notifications::NotifyExt, style,
+};
+
+/// A named list of transactions worth a look, like the Hall of Fame.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub txid: Txid,
+    #[serde(default)]
+    pub description: String,
+    /// Suggested annotations, applied when the transaction is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<[u8; 3]>,
+}
+
+impl Entry {
+    pub fn annotate(&self, annotations: &mut Annotations) {
+        if let Some(label) = &self.label {
+            annotations.set_tx_label(self.txid, label.clone());
+        }
+        if let Some([r, g, b]) = self.color {
+            annotations.set_tx_color(self.txid, Color32::from_rgb(r, g, b));
+        }
+    }
+}
+
+impl Collection {
+    pub fn hall_of_fame() -> Self {
+        Self {
+            name: "Hall of Fame".to_string(),
+            description: "From kycp.org".to_string(),
+            entries: Txid::INTERESTING_TXS
+                .iter()
+                .map(|(name, txid)| Entry {
+                    name: name.to_string(),
+                    txid: Txid::new(txid).unwrap(),
+                    description: String::new(),
+                    label: None,
+                    color: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// The published file format, so that collections can be shared.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&Collection0 {
+            version: 0,
+            collection: self.clone(),
+        })
+        .unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let collection0: Collection0 = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if collection0.collection.entries.is_empty() {
+            return Err("The collection has no transactions".to_string());
+        }
+        Ok(collection0.collection)
+    }
+
+    /// Submenu with the entries. Calls `open` with the one that was clicked.
+    pub fn menu_ui(&self, ui: &mut egui::Ui, mut open: impl FnMut(&Entry)) {
+        let response = ui.menu_button(&self.name, |ui| {
+            ui.allocate_space(Vec2::new(200., 0.));
+
+            for entry in &self.entries {
+                let mut button = ui.button(&entry.name);
+                if !entry.description.is_empty() {
+                    button = button.on_hover_text(&entry.description);
+                }
+                if button.clicked() {
+                    open(entry);
+                    ui.close_menu();
+                }
+            }
+        });
+        if !self.description.is_empty() {
+            response.response.on_hover_text(&self.description);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Collection0 {
+    #[serde(deserialize_with = "validate_version")]
+    version: u32,
+    #[serde(flatten)]
+    collection: Collection,
+}
+
+/// The user's own collections, next to the built-in Hall of Fame.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Collections {
+    collections: Vec<Collection>,
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    input_name: String,
+    #[serde(skip)]
+    input_import_json: String,
+}
+
+impl Collections {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    /// The Hall of Fame first, then the user's collections.
+    pub fn all(&self) -> impl Iterator<Item = Collection> + '_ {
+        std::iter::once(Collection::hall_of_fame()).chain(self.collections.iter().cloned())
+    }
+
+    /// `from_workspace` returns the entries of the current workspace.
+    pub fn show(&mut self, ctx: &Context, from_workspace: impl FnOnce() -> Vec<Entry>) {
+        if !self.open {
+            return;
+        }
+
+        modal::show(ctx, "Collections", |ui| {
+            let mut remove = None;
+            if self.collections.is_empty() {
+                ui.weak("No collections of your own yet.");
+            } else {
+                Grid::new("Collections")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, collection) in self.collections.iter().enumerate() {
+                            ui.label(&collection.name);
+                            ui.weak(format!("{} transactions", collection.entries.len()));
+                            ui.horizontal(|ui| {
+                                if ui.button("Export").clicked() {
+                                    ui.output_mut(|o| o.copied_text = collection.to_json());
+                                    ui.ctx().notify_success(format!(
+                                        "Exported collection `{}` to clipboard.",
+                                        collection.name
+                                    ));
+                                }
+                                if ui.button("Remove").clicked() {
+                                    remove = Some(i);
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            }
+            if let Some(i) = remove {
+                self.collections.remove(i);
+            }
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.add(TextEdit::singleline(&mut self.input_name).hint_text("Collection name..."));
+                if ui
+                    .add_enabled(
+                        !self.input_name.is_empty(),
+                        Button::new("New from Workspace"),
+                    )
+                    .on_hover_text("Collect the transactions of the current workspace, with their labels and colors.")
+                    .clicked()
+                {
+                    let entries = from_workspace();
+                    if entries.is_empty() {
+                        ui.ctx()
+                            .notify_error("Could not create collection", Some("The workspace is empty."));
+                    } else {
+                        self.collections.push(Collection {
+                            name: std::mem::take(&mut self.input_name),
+                            description: String::new(),
+                            entries,
+                        });
+                    }
+                }
+            });
+
+            ui.separator();
+
+            ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                ui.add(
+                    TextEdit::multiline(&mut self.input_import_json)
+                        .font(style::get(ui).font_id())
+                        .hint_text("Paste a collection (JSON) to import it")
+                        .desired_rows(5)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Close").clicked() {
+                    self.open = false;
+                }
+                if ui
+                    .add_enabled(!self.input_import_json.is_empty(), Button::new("Import"))
+                    .clicked()
+                {
+                    match Collection::from_json(&self.input_import_json) {
+                        Ok(collection) => {
+                            ui.ctx().notify_success(format!(
+                                "Imported collection `{}`.",
+                                collection.name
+                            ));
+                            self.collections.push(collection);
+                            self.input_import_json.clear();
+                        }
+                        Err(e) => {
+                            ui.ctx()
+                                .notify_error("Could not import collection", Some(e));
+                        }
+                    }
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COLLECTION_FIXTURE_0: &str = r#"
+        {
+            "version": 0,
+            "name": "Exchange hacks",
+            "description": "Thefts and where the coins went",
+            "entries": [
+                {
+                    "name": "Pizza",
+                    "txid": "a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d",
+                    "label": "Pizza payment",
+                    "color": [255, 128, 0]
+                },
+                {
+                    "name": "Hal",
+                    "txid": "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+                    "description": "Satoshi to Hal Finney"
+                }
+            ]
+        }
+    "#;
+
+    #[test]
+    fn collection_fixture_0() {
+        let collection = Collection::from_json(COLLECTION_FIXTURE_0).unwrap();
+        assert_eq!(collection.name, "Exchange hacks");
+        assert_eq!(collection.entries.len(), 2);
+        assert_eq!(
+            collection.entries[0].label.as_deref(),
+            Some("Pizza payment")
+        );
+        assert_eq!(collection.entries[0].color, Some([255, 128, 0]));
+        assert_eq!(collection.entries[1].description, "Satoshi to Hal Finney");
+        assert_eq!(collection.entries[1].label, None);
+    }
+
+    #[test]
+    fn collection_roundtrip() {
+        let expected = Collection::hall_of_fame();
+        let actual = Collection::from_json(&expected.to_json()).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn collection_unsupported() {
+        assert!(Collection::from_json(
+            &COLLECTION_FIXTURE_0.replace("\"version\": 0", "\"version\": 1")
+        )
+        .is_err());
+        assert!(
+            Collection::from_json(r#"{"version": 0, "name": "Empty", "entries": []}"#).is_err()
+        );
+    }
+}
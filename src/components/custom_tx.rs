@@ -1,7 +1,7 @@
 use egui::{Button, Pos2, TextEdit, TextStyle, Vec2};
 use serde::{Deserialize, Serialize};
 
-use crate::bitcoin::Txid;
+use crate::bitcoin::{parse_address, TxRef, Txid};
 
 #[derive(Default, Serialize, Deserialize)]
 pub struct CustomTx {
@@ -9,27 +9,48 @@ pub struct CustomTx {
 }
 
 impl CustomTx {
-    pub fn ui(&mut self, ui: &mut egui::Ui, load_tx: impl Fn(Txid, Option<Pos2>)) {
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        load_tx: impl Fn(Txid, Option<Pos2>),
+        select_coin: impl Fn((Txid, usize)),
+        load_address: impl Fn(String),
+    ) {
         let glyph_width = ui.fonts(|f| f.glyph_width(&TextStyle::Body.resolve(ui.style()), '0'));
         ui.allocate_space(Vec2::new(glyph_width * 63.5, 0.0));
 
         ui.add(
             TextEdit::singleline(&mut self.tx)
-                .hint_text("Enter Txid")
+                .hint_text("Enter Txid, Txid:Vout, address or explorer URL")
                 .desired_width(f32::INFINITY),
         );
 
-        ui.horizontal(|ui| match Txid::new(&self.tx) {
-            Ok(txid) => {
+        ui.horizontal(|ui| match TxRef::parse(&self.tx) {
+            Ok(TxRef { txid, vout }) => {
                 if ui.button("Go").clicked() {
                     load_tx(txid, None);
+                    if let Some(vout) = vout {
+                        select_coin((txid, vout as usize));
+                    }
                     ui.close_menu();
                 }
             }
-            Err(e) => {
-                ui.add_enabled(false, Button::new("Go"));
-                ui.label(format!("Invalid Txid: {}", e));
-            }
+            Err(e) => match parse_address(&self.tx) {
+                Ok(address) => {
+                    if ui
+                        .button("Load Address")
+                        .on_hover_text("Load every transaction that funds or spends from it.")
+                        .clicked()
+                    {
+                        load_address(address);
+                        ui.close_menu();
+                    }
+                }
+                Err(_) => {
+                    ui.add_enabled(false, Button::new("Go"));
+                    ui.label(e);
+                }
+            },
         });
     }
 }
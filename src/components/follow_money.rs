@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use egui::{Context, Pos2, RichText, ScrollArea};
+
+use crate::{
+    app::Update,
+    bitcoin::{NumberFormat, Sats, Txid},
+    graph::{self, Graph, OutputType},
+    layout::SpawnDirection,
+    notifications::NotifyExt,
+};
+
+/// Seconds to stay at each transaction before moving on.
+const PAUSE: f64 = 1.0;
+
+/// Long walks usually end up in an exchange's hot wallet anyway.
+const MAX_HOPS: usize = 100;
+
+/// Request timeouts to wait for a spending transaction before giving up. The
+/// request may have to wait in the queue first.
+const LOAD_TIMEOUTS: f64 = 3.0;
+
+struct Walk {
+    /// The output being followed.
+    coin: (Txid, usize),
+    hops: usize,
+    /// Spending transaction that was requested but isn't in the graph yet, and
+    /// when to give up on it.
+    loading: Option<(Txid, f64)>,
+    /// Don't move on before this time.
+    wait_until: f64,
+    /// Outputs of this transaction to choose the next hop from.
+    choosing: Option<Txid>,
+    /// Follow the continuing outputs of a peel chain, and stop where it ends.
+    peel_chain: bool,
+}
+
+/// Follow an output forward hop by hop, loading each spending transaction
+/// and flying the camera along.
+#[derive(Default)]
+pub struct FollowMoney {
+    walk: Option<Walk>,
+    /// Let the user choose the output at each hop instead of the largest one.
+    choose: bool,
+}
+
+impl FollowMoney {
+    pub fn start(&mut self, coin: (Txid, usize), peel_chain: bool) {
+        self.walk = Some(Walk {
+            coin,
+            hops: 0,
+            loading: None,
+            wait_until: 0.0,
+            choosing: None,
+            peel_chain,
+        });
+    }
+
+    /// Move on to the next transaction if it's time. `flying` is whether the
+    /// camera is still on its way to the last one.
+    pub fn step(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        direction: SpawnDirection,
+        flying: bool,
+        request_timeout: f64,
+    ) -> Option<Update> {
+        let walk = self.walk.as_mut()?;
+        let now = ctx.input(|i| i.time);
+        if flying || walk.choosing.is_some() || now < walk.wait_until {
+            ctx.request_repaint();
+            return None;
+        }
+
+        let (txid, vout) = walk.coin;
+        let Some(output) = graph.model().tx(&txid).and_then(|tx| tx.outputs.get(vout)) else {
+            // Removed from the graph in the meantime.
+            self.stop();
+            return None;
+        };
+        let OutputType::Spent { spending_txid, .. } = output.output_type else {
+            ctx.notify_success(format!(
+                "Reached an unspent output after {} hops.",
+                walk.hops
+            ));
+            self.stop();
+            return None;
+        };
+
+        let Some(spending) = graph.model().tx(&spending_txid) else {
+            match walk.loading {
+                // The transaction repaints when it arrives.
+                Some((loading, give_up)) if loading == spending_txid && now < give_up => {
+                    ctx.request_repaint_after(Duration::from_secs_f64(give_up - now));
+                }
+                Some((loading, _)) if loading == spending_txid => {
+                    ctx.notify_error(
+                        format!("Stopped following after {} hops", walk.hops),
+                        Some("The spending transaction could not be loaded."),
+                    );
+                    self.stop();
+                }
+                _ => {
+                    walk.loading = Some((spending_txid, now + LOAD_TIMEOUTS * request_timeout));
+                    let pos = graph.get_tx_pos(txid).unwrap_or(Pos2::ZERO);
+                    let pos = graph::hop_positions(pos, 1, false, direction)[0];
+                    return Some(Update::LoadOrSelectTx {
+                        txid: spending_txid,
+                        pos: Some(pos),
+                    });
+                }
+            }
+            return None;
+        };
+
+        walk.loading = None;
+        walk.hops += 1;
+        walk.wait_until = now + PAUSE;
+        let mut ended = false;
+        if walk.peel_chain {
+            match graph.model().peel(&spending_txid) {
+                Some(next) => walk.coin = (spending_txid, next),
+                None => {
+                    ctx.notify_success(format!(
+                        "Reached the end of the peel chain after {} hops.",
+                        walk.hops
+                    ));
+                    ended = true;
+                }
+            }
+        } else if self.choose {
+            walk.choosing = Some(spending_txid);
+        } else if let Some(next) = largest_output(spending) {
+            walk.coin = (spending_txid, next);
+        }
+        if ended {
+            self.stop();
+        } else if walk.hops >= MAX_HOPS {
+            ctx.notify_warn(
+                format!("Stopped following after {} hops.", MAX_HOPS),
+                None::<&str>,
+            );
+            self.stop();
+        }
+        // Selects the transaction and flies to it.
+        Some(Update::LoadOrSelectTx {
+            txid: spending_txid,
+            pos: None,
+        })
+    }
+
+    fn stop(&mut self) {
+        self.walk = None;
+    }
+
+    pub fn show(&mut self, ctx: &Context, graph: &Graph, format: NumberFormat) {
+        let Some(walk) = &mut self.walk else {
+            return;
+        };
+        let mut stop = false;
+
+        egui::Window::new("Follow the Money")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("Hop {} of at most {}", walk.hops, MAX_HOPS));
+                if walk.loading.is_some() {
+                    ui.weak("Loading the spending transaction...");
+                }
+                if walk.peel_chain {
+                    ui.label("Following the continuing outputs of a peel chain.");
+                } else {
+                    ui.checkbox(&mut self.choose, "Choose the outputs myself")
+                        .on_hover_text("Otherwise, the largest output is followed.");
+                }
+
+                if let Some(txid) = walk.choosing {
+                    if let Some(tx) = graph.model().tx(&txid) {
+                        ui.separator();
+                        ui.label(RichText::new("Follow which output?").strong());
+                        ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                            for (vout, output) in tx.outputs.iter().enumerate() {
+                                let Some(address) = output.address() else {
+                                    continue;
+                                };
+                                let spent = matches!(output.output_type, OutputType::Spent { .. });
+                                let text = format!(
+                                    "#{} {} {}{}",
+                                    vout,
+                                    Sats(output.value).format(format),
+                                    address,
+                                    if spent { "" } else { " (unspent)" }
+                                );
+                                if ui.button(RichText::new(text).monospace()).clicked() {
+                                    walk.coin = (txid, vout);
+                                    walk.choosing = None;
+                                }
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                if ui.button("Stop").clicked() {
+                    stop = true;
+                }
+            });
+
+        if stop {
+            self.stop();
+        }
+    }
+}
+
+fn largest_output(tx: &graph::Tx) -> Option<usize> {
+    tx.outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| !matches!(output.output_type, OutputType::Fees))
+        .max_by_key(|(_, output)| output.value)
+        .map(|(vout, _)| vout)
+}
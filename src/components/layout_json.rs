@@ -0,0 +1,175 @@
+//! The graph as node-link JSON, the shape D3.js (`d3-force`), networkx and
+//! most other layout and analysis tools read and write. Positions computed by
+//! such a tool can be read back to lay out the graph.
+
+use std::sync::mpsc::Sender;
+
+use egui::{Button, Context, Pos2, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotations::Annotations, app::Update, bitcoin::Txid, graph::Graph, notifications::NotifyExt,
+    style,
+};
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NodeLink {
+    pub nodes: Vec<Node>,
+    pub links: Vec<Link>,
+}
+
+/// A transaction, centered at `x`/`y` in the graph's coordinates.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Node {
+    pub id: Txid,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub value: u64,
+    pub block_height: u32,
+}
+
+/// A coin, from output `vout` of `source` to input `vin` of `target`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Link {
+    pub source: Txid,
+    pub target: Txid,
+    pub vout: usize,
+    pub vin: usize,
+    pub value: u64,
+}
+
+/// Only the id and position of a node are read back. Tools add fields of
+/// their own, like `vx` and `index` in D3, which are ignored.
+#[derive(Deserialize)]
+struct Placed {
+    id: String,
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct Placement {
+    nodes: Vec<Placed>,
+}
+
+/// Where the nodes of node-link JSON are, by transaction.
+pub fn parse_positions(json: &str) -> Result<Vec<(Txid, Pos2)>, String> {
+    let placement: Placement = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    placement
+        .nodes
+        .into_iter()
+        .map(|node| {
+            let txid = Txid::new(&node.id).map_err(|e| format!("Node `{}`: {}", node.id, e))?;
+            Ok((txid, Pos2::new(node.x, node.y)))
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub struct LayoutJson {
+    open: bool,
+    /// Pasted JSON with new positions.
+    import: String,
+}
+
+impl LayoutJson {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        annotations: &Annotations,
+        sender: Sender<Update>,
+    ) {
+        egui::Window::new("Layout JSON")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    "Nodes with their positions and sizes, and the coins between them as links.",
+                );
+                if ui
+                    .add_enabled(graph.num_transactions() > 0, Button::new("Copy JSON"))
+                    .clicked()
+                {
+                    let node_link = graph.node_link(&style::get(ui), annotations);
+                    let json = serde_json::to_string_pretty(&node_link).unwrap();
+                    ui.output_mut(|o| o.copied_text = json);
+                    ui.ctx().notify_success(format!(
+                        "Copied {} nodes and {} links to clipboard.",
+                        graph.num_transactions(),
+                        graph.num_edges()
+                    ));
+                }
+
+                ui.separator();
+
+                ui.label(
+                    "Paste JSON with nodes that have an `id`, `x` and `y` to move them there.",
+                );
+                ui.add(
+                    TextEdit::multiline(&mut self.import)
+                        .font(style::get(ui).font_id())
+                        .hint_text(r#"{"nodes": [{"id": "<txid>", "x": 0, "y": 0}]}"#)
+                        .desired_rows(6)
+                        .desired_width(f32::INFINITY),
+                );
+                if ui
+                    .add_enabled(
+                        !self.import.trim().is_empty(),
+                        Button::new("Apply Positions"),
+                    )
+                    .clicked()
+                {
+                    match parse_positions(&self.import) {
+                        Ok(positions) => {
+                            sender.send(Update::ArrangeTxs { positions }).unwrap();
+                            self.import.clear();
+                        }
+                        Err(e) => {
+                            ui.ctx().notify_error("Could not read positions", Some(e));
+                        }
+                    }
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn positions_from_d3() {
+        let a = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+        let b = "ea44e97271691990157559d0bdd9959e02790c34db6c006d779e82fa5aee708e";
+        // As `JSON.stringify` leaves them after a simulation: with velocities,
+        // indices and the links resolved to their nodes.
+        let json = format!(
+            r#"{{
+                "nodes": [
+                    {{"id": "{a}", "x": 10.5, "y": -3, "vx": 0.1, "vy": 0, "index": 0}},
+                    {{"id": "{b}", "x": 20, "y": 40, "vx": 0, "vy": 0, "index": 1}}
+                ],
+                "links": [{{"source": {{"id": "{a}"}}, "target": {{"id": "{b}"}}, "index": 0}}]
+            }}"#
+        );
+        assert_eq!(
+            parse_positions(&json),
+            Ok(vec![
+                (Txid::new(a).unwrap(), Pos2::new(10.5, -3.0)),
+                (Txid::new(b).unwrap(), Pos2::new(20.0, 40.0)),
+            ])
+        );
+
+        assert!(parse_positions(r#"{"nodes": [{"id": "nope", "x": 0, "y": 0}]}"#).is_err());
+        assert!(parse_positions(r#"{"nodes": [{"id": "nope"}]}"#).is_err());
+    }
+}
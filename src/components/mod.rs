@@ -1,2 +1,17 @@
 pub mod about;
+pub mod address_book;
+pub mod address_stats;
+pub mod annotate_trace;
+pub mod block_strip;
+pub mod collections;
 pub mod custom_tx;
+pub mod follow_money;
+pub mod layout_json;
+pub mod ownership;
+pub mod peel_chains;
+pub mod removed;
+pub mod search;
+pub mod unspent;
+pub mod wallet_import;
+pub mod watch_list;
+pub mod xpub_import;
@@ -0,0 +1,436 @@
+use std::{collections::HashMap, sync::mpsc::Sender};
+
+use egui::{Button, Context, Grid, RichText, ScrollArea, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::Update,
+    bitcoin::Txid,
+    graph::{Graph, TxView},
+    hd::{self, Chain},
+    modal, style,
+};
+
+/// Addresses derived per chain of a wallet declared by its key.
+const ADDRESSES_PER_CHAIN: u32 = 100;
+
+/// A wallet declared by the user, given by its xpub or output descriptor, by
+/// addresses exported from the wallet (e.g. Sparrow's Addresses tab), or both.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wallet {
+    pub name: String,
+    /// Extended public key or output descriptor.
+    pub key: String,
+    /// One address per line.
+    pub addresses: String,
+}
+
+impl Wallet {
+    /// The wallet's addresses, with their chain where it is known from the
+    /// derivation path.
+    fn addresses(&self) -> Result<HashMap<String, Option<Chain>>, String> {
+        let mut addresses: HashMap<_, _> = self
+            .addresses
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| (line.to_string(), None))
+            .collect();
+        if !self.key.trim().is_empty() {
+            let wallet =
+                hd::Wallet::parse(&self.key).map_err(|e| format!("{}: {}", self.name, e))?;
+            for (chain, derived) in wallet.chains(ADDRESSES_PER_CHAIN) {
+                addresses.extend(derived.into_iter().map(|address| (address, Some(chain))));
+            }
+        }
+        Ok(addresses)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Class {
+    Mine(String),
+    Change(String),
+    Counterparty,
+    Unknown,
+}
+
+impl Class {
+    fn name(&self) -> &'static str {
+        match self {
+            Class::Mine(_) => "Mine",
+            Class::Change(_) => "Change (mine)",
+            Class::Counterparty => "Counterparty",
+            Class::Unknown => "Unknown",
+        }
+    }
+
+    /// The label to tag the coin with, if any.
+    fn label(&self) -> Option<String> {
+        match self {
+            Class::Mine(wallet) | Class::Change(wallet) => {
+                Some(format!("{}: {}", self.name(), wallet))
+            }
+            Class::Counterparty => Some(self.name().to_string()),
+            Class::Unknown => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Classification {
+    pub coin: (Txid, usize),
+    pub class: Class,
+    pub reason: String,
+}
+
+/// Classify every output by the declared wallets:
+/// - Outputs to a wallet's change chain are change, outputs to its receive
+///   chain are mine.
+/// - Outputs to a wallet's pasted address are mine, or change if the same
+///   wallet also funds the transaction.
+/// - Outputs of a transaction funded by a wallet go to a counterparty. If the
+///   wallet was declared by addresses only, one of them may be undeclared
+///   change: exactly one that has the script type of the (uniform) inputs.
+/// - Everything else is unknown.
+pub fn classify(txs: &[TxView<'_>], wallets: &[Wallet]) -> Result<Vec<Classification>, String> {
+    let wallets = wallets
+        .iter()
+        .map(|wallet| {
+            Ok((
+                wallet.name.clone(),
+                wallet.addresses()?,
+                wallet.key.trim().is_empty(),
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let wallet_of = |address: &str| {
+        wallets.iter().find_map(|(name, addresses, pasted_only)| {
+            let chain = addresses.get(address)?;
+            Some((name.clone(), *chain, *pasted_only))
+        })
+    };
+
+    let mut result = vec![];
+
+    for tx in txs {
+        let funding_wallet = tx
+            .inputs
+            .iter()
+            .find_map(|(address, _)| wallet_of(address))
+            .map(|(name, _, pasted_only)| (name, pasted_only));
+        let owners: Vec<_> = tx
+            .outputs
+            .iter()
+            .map(|(address, _)| wallet_of(address))
+            .collect();
+
+        let likely_change = match tx.inputs.first() {
+            Some((_, input_type))
+                if matches!(funding_wallet, Some((_, true)))
+                    && tx.inputs.iter().all(|(_, t)| t == input_type)
+                    && owners.iter().filter(|o| o.is_none()).count() > 1 =>
+            {
+                let mut matching = tx
+                    .outputs
+                    .iter()
+                    .zip(&owners)
+                    .enumerate()
+                    .filter(|(_, ((_, t), owner))| owner.is_none() && t == input_type)
+                    .map(|(vout, _)| vout);
+                match (matching.next(), matching.next()) {
+                    (Some(vout), None) => Some(vout),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        for (vout, owner) in owners.into_iter().enumerate() {
+            let (class, reason) = match (owner, &funding_wallet) {
+                (Some((owner, Some(Chain::Change), _)), _) => (
+                    Class::Change(owner.clone()),
+                    format!("Change address of {owner}"),
+                ),
+                (Some((owner, Some(Chain::Receive), _)), _) => (
+                    Class::Mine(owner.clone()),
+                    format!("Receive address of {owner}"),
+                ),
+                (Some((owner, None, _)), Some((funding, _))) if owner == *funding => (
+                    Class::Change(owner.clone()),
+                    format!("Address of {owner}, which also funds the transaction"),
+                ),
+                (Some((owner, None, _)), _) => {
+                    (Class::Mine(owner.clone()), format!("Address of {owner}"))
+                }
+                (None, Some((funding, _))) if likely_change == Some(vout) => (
+                    Class::Change(funding.clone()),
+                    format!(
+                        "Undeclared address, but the only one matching the script type of \
+                         the inputs from {funding}"
+                    ),
+                ),
+                (None, Some((funding, _))) => (
+                    Class::Counterparty,
+                    format!("Paid by {funding} to an undeclared address"),
+                ),
+                (None, None) => (Class::Unknown, "No declared wallet involved".to_string()),
+            };
+            result.push(Classification {
+                coin: (tx.txid, vout),
+                class,
+                reason,
+            });
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OwnershipAssistant {
+    wallets: Vec<Wallet>,
+    #[serde(skip)]
+    open: bool,
+    #[serde(skip)]
+    results: Vec<Classification>,
+    #[serde(skip)]
+    error: Option<String>,
+}
+
+impl OwnershipAssistant {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.results.clear();
+        self.error = None;
+    }
+
+    pub fn show(&mut self, ctx: &Context, graph: &Graph, sender: Sender<Update>) {
+        if !self.open {
+            return;
+        }
+
+        modal::show(ctx, "Ownership Assistant", |ui| {
+            ui.label(
+                "Declare your wallets by their xpub or descriptor, or by their addresses, one \
+                 per line.",
+            );
+
+            let mut remove = None;
+            ScrollArea::vertical()
+                .id_source("wallets")
+                .max_height(250.0)
+                .show(ui, |ui| {
+                    for (i, wallet) in self.wallets.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(TextEdit::singleline(&mut wallet.name).hint_text("Name"));
+                            if ui.button("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                        ui.add(
+                            TextEdit::singleline(&mut wallet.key)
+                                .font(style::get(ui).font_id())
+                                .hint_text("xpub/ypub/zpub or wpkh([...]xpub.../<0;1>/*)")
+                                .desired_width(f32::INFINITY),
+                        );
+                        ui.add(
+                            TextEdit::multiline(&mut wallet.addresses)
+                                .font(style::get(ui).font_id())
+                                .hint_text("bc1q...")
+                                .desired_rows(3)
+                                .desired_width(f32::INFINITY),
+                        );
+                    }
+                });
+            if let Some(i) = remove {
+                self.wallets.remove(i);
+            }
+            if ui.button("Add Wallet").clicked() {
+                self.wallets.push(Wallet::default());
+            }
+
+            ui.separator();
+
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            if !self.results.is_empty() {
+                ScrollArea::vertical()
+                    .id_source("results")
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        Grid::new("ownership")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for c in &self.results {
+                                    ui.label(
+                                        RichText::new(format!("{}:{}", c.coin.0, c.coin.1))
+                                            .font(style::get(ui).font_id()),
+                                    );
+                                    ui.label(c.class.name());
+                                    ui.label(RichText::new(&c.reason).weak());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                ui.add_space(3.0);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Close").clicked() {
+                    self.open = false;
+                }
+                if ui
+                    .add_enabled(!self.wallets.is_empty(), Button::new("Classify Outputs"))
+                    .clicked()
+                {
+                    match classify(&graph.tx_views(), &self.wallets) {
+                        Ok(results) => {
+                            self.results = results;
+                            self.error = None;
+                        }
+                        Err(e) => {
+                            self.results.clear();
+                            self.error = Some(e);
+                        }
+                    }
+                }
+                if ui
+                    .add_enabled(!self.results.is_empty(), Button::new("Apply as Labels"))
+                    .on_hover_text("Unknown outputs are left as they are.")
+                    .clicked()
+                {
+                    sender
+                        .send(Update::LabelCoins {
+                            labels: self
+                                .results
+                                .iter()
+                                .filter_map(|c| Some((c.coin, c.class.label()?)))
+                                .collect(),
+                        })
+                        .unwrap();
+                    self.open = false;
+                }
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bitcoin::{dummy_txid, AddressType};
+
+    #[test]
+    fn classify_outputs() {
+        let wallets = vec![
+            Wallet {
+                name: "Cold".to_string(),
+                key: String::new(),
+                addresses: "bc1qcold0\n bc1qcold1 \n".to_string(),
+            },
+            Wallet {
+                name: "Hot".to_string(),
+                key: String::new(),
+                addresses: "bc1qhot0".to_string(),
+            },
+        ];
+        let txs = vec![
+            // Cold pays someone, change goes to an undeclared segwit address.
+            TxView {
+                txid: dummy_txid(1),
+                inputs: vec![("bc1qcold0", AddressType::P2WPKH)],
+                outputs: vec![
+                    ("3shop", AddressType::P2SH),
+                    ("bc1qfresh", AddressType::P2WPKH),
+                ],
+            },
+            // Cold sends to Hot with change back to Cold.
+            TxView {
+                txid: dummy_txid(2),
+                inputs: vec![("bc1qcold1", AddressType::P2WPKH)],
+                outputs: vec![
+                    ("bc1qhot0", AddressType::P2WPKH),
+                    ("bc1qcold1", AddressType::P2WPKH),
+                ],
+            },
+            TxView {
+                txid: dummy_txid(3),
+                inputs: vec![("1other", AddressType::P2PKH)],
+                outputs: vec![("1another", AddressType::P2PKH)],
+            },
+        ];
+
+        let classes: Vec<_> = classify(&txs, &wallets)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.coin, c.class))
+            .collect();
+        assert_eq!(
+            classes,
+            vec![
+                ((dummy_txid(1), 0), Class::Counterparty),
+                ((dummy_txid(1), 1), Class::Change("Cold".to_string())),
+                ((dummy_txid(2), 0), Class::Mine("Hot".to_string())),
+                ((dummy_txid(2), 1), Class::Change("Cold".to_string())),
+                ((dummy_txid(3), 0), Class::Unknown),
+            ]
+        );
+        assert_eq!(
+            Class::Change("Cold".to_string()).label(),
+            Some("Change (mine): Cold".to_string())
+        );
+        assert_eq!(Class::Unknown.label(), None);
+    }
+
+    #[test]
+    fn classify_by_derivation_path() {
+        // Receive and change addresses of the "abandon ... about" zpub.
+        let wallets = vec![Wallet {
+            name: "Savings".to_string(),
+            key: "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs".to_string(),
+            addresses: String::new(),
+        }];
+        let txs = vec![TxView {
+            txid: dummy_txid(1),
+            inputs: vec![(
+                "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu",
+                AddressType::P2WPKH,
+            )],
+            outputs: vec![
+                ("bc1qfresh0", AddressType::P2WPKH),
+                (
+                    "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el",
+                    AddressType::P2WPKH,
+                ),
+                ("bc1qfresh1", AddressType::P2WPKH),
+            ],
+        }];
+
+        let classes: Vec<_> = classify(&txs, &wallets)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.class)
+            .collect();
+        // No guessing: the undeclared outputs aren't change.
+        assert_eq!(
+            classes,
+            vec![
+                Class::Counterparty,
+                Class::Change("Savings".to_string()),
+                Class::Counterparty,
+            ]
+        );
+
+        let invalid = vec![Wallet {
+            name: "Typo".to_string(),
+            key: "xpub123".to_string(),
+            addresses: String::new(),
+        }];
+        assert!(classify(&txs, &invalid).is_err());
+    }
+}
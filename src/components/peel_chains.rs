@@ -0,0 +1,127 @@
+use std::sync::mpsc::Sender;
+
+use egui::{Button, Context, Grid, RichText, ScrollArea};
+
+use crate::{
+    annotations::Annotations,
+    app::Update,
+    bitcoin::{NumberFormat, Sats},
+    graph::{Graph, OutputType, MIN_PEEL_CHAIN},
+};
+
+/// Likely peel chains in the graph: runs of transactions that each pay out a
+/// small amount and pass the rest on to the next one.
+#[derive(Default)]
+pub struct PeelChains {
+    open: bool,
+}
+
+impl PeelChains {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        annotations: &Annotations,
+        format: NumberFormat,
+        sender: Sender<Update>,
+    ) {
+        if !self.open {
+            return;
+        }
+        let model = graph.model();
+        let chains = model.peel_chains(MIN_PEEL_CHAIN);
+
+        egui::Window::new("Peel Chains")
+            .open(&mut self.open)
+            .show(ctx, |ui| {
+                if chains.is_empty() {
+                    ui.label(format!(
+                        "No runs of {} or more transactions that each pay out a small \
+                         amount and pass the rest on.",
+                        MIN_PEEL_CHAIN
+                    ));
+                    return;
+                }
+
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    Grid::new("peel_chains").striped(true).show(ui, |ui| {
+                        for chain in &chains {
+                            let first = chain[0];
+                            let last = chain[chain.len() - 1];
+                            let name = annotations
+                                .tx_label(first)
+                                .unwrap_or_else(|| first.hex_string()[..16].to_string());
+                            ui.label(RichText::new(name).monospace())
+                                .on_hover_text(first.hex_string());
+
+                            let payouts: u64 = chain
+                                .iter()
+                                .filter_map(|txid| {
+                                    let tx = model.tx(txid)?;
+                                    let vout = model.peel(txid)?;
+                                    Some(
+                                        tx.outputs
+                                            .iter()
+                                            .enumerate()
+                                            .filter(|(i, output)| {
+                                                *i != vout
+                                                    && !matches!(
+                                                        output.output_type,
+                                                        OutputType::Fees
+                                                    )
+                                            })
+                                            .map(|(_, output)| output.value)
+                                            .sum::<u64>(),
+                                    )
+                                })
+                                .sum();
+                            ui.label(format!("{} peels", chain.len()));
+                            ui.label(format!("{} paid out", Sats(payouts).format(format)));
+
+                            if ui
+                                .button("Select")
+                                .on_hover_text("Select the transactions of the chain.")
+                                .clicked()
+                            {
+                                sender
+                                    .send(Update::SelectTxs {
+                                        txids: chain.clone(),
+                                    })
+                                    .unwrap();
+                            }
+
+                            // Spent by a transaction that isn't loaded yet.
+                            let next = model.peel(&last).filter(|vout| {
+                                model.tx(&last).is_some_and(|tx| {
+                                    matches!(
+                                        tx.outputs[*vout].output_type,
+                                        OutputType::Spent { spending_txid, .. }
+                                            if !graph.contains(spending_txid)
+                                    )
+                                })
+                            });
+                            let follow = ui
+                                .add_enabled(next.is_some(), Button::new("Follow"))
+                                .on_hover_text(
+                                    "Load the rest of the chain, peel by peel, until it ends.",
+                                )
+                                .on_disabled_hover_text(
+                                    "The chain ends in an unspent output, or in a \
+                                     transaction that isn't a peel.",
+                                );
+                            if let (true, Some(vout)) = (follow.clicked(), next) {
+                                sender
+                                    .send(Update::FollowPeelChain { coin: (last, vout) })
+                                    .unwrap();
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::mpsc::Sender;
+
+use egui::{Context, Grid, RichText, ScrollArea};
+
+use crate::{
+    annotations::Annotations,
+    app::Update,
+    bitcoin::{NumberFormat, Sats},
+    export,
+    graph::Graph,
+};
+
+/// Older removals are forgotten.
+const MAX_REMOVED: usize = 500;
+
+/// Transactions removed from the graph and where they were, so they can be
+/// put back without losing the layout.
+#[derive(Default)]
+pub struct Removed {
+    open: bool,
+    /// Most recently removed last.
+    txs: Vec<export::Transaction>,
+}
+
+impl Removed {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    pub fn push(&mut self, tx: export::Transaction) {
+        self.txs.retain(|removed| removed.txid != tx.txid);
+        self.txs.push(tx);
+        if self.txs.len() > MAX_REMOVED {
+            self.txs.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.txs.clear();
+    }
+
+    /// Forget the transactions that are back in the graph, e.g. by undo or by
+    /// loading them again.
+    pub fn restored(&mut self, graph: &Graph) {
+        self.txs.retain(|tx| !graph.contains(tx.txid));
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        annotations: &Annotations,
+        format: NumberFormat,
+        sender: Sender<Update>,
+    ) {
+        self.restored(graph);
+
+        let restore = |txs: Vec<export::Transaction>| {
+            sender.send(Update::RestoreTxs { txs }).unwrap();
+        };
+
+        let mut clear = false;
+        egui::Window::new("Removed Transactions")
+            .open(&mut self.open)
+            .show(ctx, |ui| {
+                if self.txs.is_empty() {
+                    ui.label("Removed transactions can be restored here.");
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(format!("Restore All ({})", self.txs.len()))
+                        .clicked()
+                    {
+                        restore(self.txs.clone());
+                    }
+                    if ui.button("Forget All").clicked() {
+                        clear = true;
+                    }
+                });
+                ui.separator();
+
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    Grid::new("removed").striped(true).show(ui, |ui| {
+                        for tx in self.txs.iter().rev() {
+                            let name = annotations
+                                .tx_label(tx.txid)
+                                .unwrap_or_else(|| tx.txid.hex_string()[..16].to_string());
+                            ui.label(RichText::new(name).monospace())
+                                .on_hover_text(tx.txid.hex_string());
+                            match tx.summary {
+                                Some(summary) => ui.label(Sats(summary.value).format(format)),
+                                None => ui.weak("Not loaded"),
+                            };
+                            if ui
+                                .button("Restore")
+                                .on_hover_text("Put it back where it was.")
+                                .clicked()
+                            {
+                                restore(vec![tx.clone()]);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        if clear {
+            self.clear();
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::mpsc::Sender;
+
+use egui::{Context, Key, RichText, ScrollArea, TextEdit};
+
+use crate::{
+    annotations::Annotations,
+    app::Update,
+    bitcoin::{NumberFormat, Sats, Txid},
+    components::address_book::AddressBook,
+    graph::Graph,
+};
+
+/// More would only be useful with a narrower query anyway.
+const MAX_HITS: usize = 200;
+
+struct Hit {
+    txid: Txid,
+    /// The output or spent input that matched, if any.
+    coin: Option<(Txid, usize)>,
+    what: String,
+}
+
+/// Find loaded transactions by txid, address, amount or label, and fly to
+/// them.
+#[derive(Default)]
+pub struct Search {
+    open: bool,
+    query: String,
+    /// Focus the query field in the next frame.
+    focus: bool,
+}
+
+impl Search {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.focus = true;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        annotations: &Annotations,
+        address_book: &AddressBook,
+        format: NumberFormat,
+        sender: Sender<Update>,
+    ) {
+        let highlight = |hit: &Hit| {
+            sender
+                .send(Update::Highlight {
+                    txid: hit.txid,
+                    coin: hit.coin,
+                })
+                .unwrap();
+        };
+
+        egui::Window::new("Search")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut self.query)
+                        .hint_text("Txid, address, amount or label")
+                        .desired_width(300.0),
+                );
+                if std::mem::take(&mut self.focus) {
+                    response.request_focus();
+                }
+
+                let query = self.query.trim();
+                if query.is_empty() {
+                    ui.weak("Searches the transactions in the graph.");
+                    return;
+                }
+                let hits = find(graph, annotations, address_book, format, query);
+                ui.separator();
+                if hits.is_empty() {
+                    ui.label("Nothing found among the loaded transactions.");
+                    return;
+                }
+
+                if response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter)) {
+                    highlight(&hits[0]);
+                }
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for hit in &hits {
+                        let text = format!("{} {}", &hit.txid.hex_string()[..8], hit.what);
+                        if ui.button(RichText::new(text).monospace()).clicked() {
+                            highlight(hit);
+                        }
+                    }
+                });
+                if hits.len() == MAX_HITS {
+                    ui.weak(format!("Showing the first {} matches.", MAX_HITS));
+                }
+            });
+    }
+}
+
+fn find(
+    graph: &Graph,
+    annotations: &Annotations,
+    address_book: &AddressBook,
+    format: NumberFormat,
+    query: &str,
+) -> Vec<Hit> {
+    let query = query.to_lowercase();
+    let amounts = parse_amount(&query, format);
+    let matches = |text: &str| text.to_lowercase().contains(&query);
+    let matches_address =
+        |address: &str| matches(address) || address_book.label(address).is_some_and(matches);
+
+    let mut txs: Vec<_> = graph.model().txs().collect();
+    // Stable order while typing.
+    txs.sort_by_key(|(txid, tx)| (tx.block_height, **txid));
+
+    let mut hits = vec![];
+    for (txid, tx) in txs {
+        let txid = *txid;
+        let mut hit = |coin, what| hits.push(Hit { txid, coin, what });
+
+        if txid.hex_string().contains(&query) {
+            hit(None, "txid".to_string());
+        }
+        if let Some(label) = annotations.tx_label(txid).filter(|label| matches(label)) {
+            hit(None, format!("labeled \"{}\"", label));
+        }
+        for (vout, output) in tx.outputs.iter().enumerate() {
+            let coin = (txid, vout);
+            let Some(address) = output.address() else {
+                continue;
+            };
+            if matches_address(address) {
+                hit(Some(coin), format!("output #{} to {}", vout, address));
+            } else if amounts.contains(&output.value) {
+                hit(
+                    Some(coin),
+                    format!("output #{} of {}", vout, Sats(output.value).format(format)),
+                );
+            } else if let Some(label) = annotations.coin_label(coin).filter(|label| matches(label))
+            {
+                hit(
+                    Some(coin),
+                    format!("output #{} labeled \"{}\"", vout, label),
+                );
+            }
+        }
+        for (vin, input) in tx.inputs.iter().enumerate() {
+            let coin = (input.funding_txid, input.funding_vout as usize);
+            if matches_address(&input.address) {
+                hit(Some(coin), format!("input #{} from {}", vin, input.address));
+            } else if amounts.contains(&input.value) {
+                hit(
+                    Some(coin),
+                    format!("input #{} of {}", vin, Sats(input.value).format(format)),
+                );
+            }
+        }
+
+        if hits.len() >= MAX_HITS {
+            hits.truncate(MAX_HITS);
+            break;
+        }
+    }
+    hits
+}
+
+/// The amounts in sats that the query could mean. Numbers with a decimal
+/// mark are in BTC, whole numbers in either sats or BTC, unless a unit is
+/// given.
+fn parse_amount(query: &str, format: NumberFormat) -> Vec<u64> {
+    let query = query.trim().to_lowercase();
+    let (number, btc, sats) = if let Some(number) = query.strip_suffix("btc") {
+        (number, true, false)
+    } else if let Some(number) = query
+        .strip_suffix("sats")
+        .or_else(|| query.strip_suffix("sat"))
+    {
+        (number, false, true)
+    } else {
+        (query.as_str(), true, true)
+    };
+    let number: String = number
+        .chars()
+        .filter(|c| *c != format.thousands() && !c.is_whitespace() && *c != '_')
+        .map(|c| if c == format.decimal() { '.' } else { c })
+        .collect();
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return vec![];
+    }
+
+    let mut amounts = vec![];
+    if number.contains('.') {
+        if let Ok(value) = number.parse::<f64>() {
+            if btc && value < 21e6 {
+                amounts.push((value * 1e8).round() as u64);
+            }
+        }
+    } else if let Ok(value) = number.parse::<u64>() {
+        if sats {
+            amounts.push(value);
+        }
+        if btc {
+            amounts.extend(value.checked_mul(100_000_000));
+        }
+    }
+    amounts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_amount() {
+        let english = NumberFormat::English;
+        assert_eq!(parse_amount("0.5", english), vec![50_000_000]);
+        assert_eq!(parse_amount("1,000", english), vec![1000, 100_000_000_000]);
+        assert_eq!(parse_amount("12 345 sats", english), vec![12345]);
+        assert_eq!(parse_amount("2 BTC", english), vec![200_000_000]);
+        assert_eq!(parse_amount("0.1 sats", english), Vec::<u64>::new());
+        assert_eq!(parse_amount("bc1q", english), Vec::<u64>::new());
+        assert_eq!(parse_amount("0,001", NumberFormat::German), vec![100_000]);
+        assert_eq!(
+            parse_amount("1.500", NumberFormat::German),
+            vec![1500, 150_000_000_000]
+        );
+    }
+}
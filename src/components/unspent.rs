@@ -0,0 +1,495 @@
+use std::{cmp::Reverse, collections::HashMap, sync::mpsc::Sender};
+
+use egui::{
+    ahash::HashSet, Color32, Context, Grid, Pos2, Rect, RichText, Rounding, ScrollArea, Sense, Vec2,
+};
+
+use crate::{
+    annotations::Annotations,
+    app::Update,
+    bitcoin::{NumberFormat, Sats, Transaction, Txid},
+    graph::{self, Graph},
+    layout::SpawnDirection,
+    loading::Loading,
+    notifications::NotifyExt,
+    tx_cache::TxCache,
+};
+
+/// The search stops after loading this many transactions.
+const MAX_TXS: usize = 1000;
+
+/// An unspent output that value from the start ended up in.
+pub struct Utxo {
+    pub coin: (Txid, usize),
+    pub value: u64,
+    /// The part of `value` that came from the start, in proportion to the
+    /// inputs of each transaction on the way.
+    pub traced: u64,
+    pub address: String,
+    /// Transactions between the start and this output.
+    pub hops: usize,
+}
+
+/// Where the traced value went so far. Every transaction on the way passes it
+/// on to its outputs and fees in proportion to their share of its inputs.
+#[derive(Clone, Copy, Default)]
+pub struct Accounted {
+    /// The value of the outputs that the search started from.
+    pub total: u64,
+    pub unspent: u64,
+    pub fees: u64,
+    /// CoinJoins mix the value with everyone else's, so the search stops
+    /// there.
+    pub coinjoins: u64,
+    /// On its way to transactions that aren't loaded yet, or won't be as the
+    /// search stopped or they couldn't be loaded.
+    pub unexpanded: u64,
+}
+
+impl Accounted {
+    fn parts(&self) -> [(&'static str, u64, Color32); 4] {
+        [
+            ("Unspent", self.unspent, Color32::from_rgb(0x6f, 0xc0, 0x7a)),
+            ("Fees", self.fees, Color32::from_rgb(0xe0, 0xa4, 0x58)),
+            (
+                "CoinJoins",
+                self.coinjoins,
+                Color32::from_rgb(0x8f, 0x6f, 0xd6),
+            ),
+            ("Unexpanded", self.unexpanded, Color32::GRAY),
+        ]
+    }
+}
+
+/// Where the value of a transaction or one of its outputs is now, as far as
+/// the search got.
+pub struct Found {
+    /// The transaction and, if only one of its outputs was followed, which.
+    pub from: (Txid, Option<usize>),
+    /// Most traced value first.
+    pub utxos: Vec<Utxo>,
+    pub coinjoins: Vec<Txid>,
+    pub accounted: Accounted,
+    /// Whether the search stopped at [MAX_TXS].
+    pub capped: bool,
+}
+
+/// Follows the outputs of a transaction forward, loading a hop at a time,
+/// until all of the traced value ends in unspent outputs or a stop condition
+/// is hit.
+struct Search {
+    from: (Txid, Option<usize>),
+    spawn: SpawnDirection,
+    /// Loaded transactions, where to put them and how many hops they are
+    /// from the start.
+    txs: HashMap<Txid, (Transaction, Pos2, usize)>,
+    /// Value on its way to transactions that aren't loaded yet, with where to
+    /// put them and how many hops they are from the start.
+    pending: HashMap<Txid, (u64, Pos2, usize)>,
+    /// The transactions within the search that each one spends from.
+    spends_from: HashMap<Txid, Vec<Txid>>,
+    utxos: HashMap<(Txid, usize), Utxo>,
+    coinjoins: Vec<Txid>,
+    accounted: Accounted,
+    /// Value that won't be followed.
+    unexplored: u64,
+    capped: bool,
+    hops: usize,
+}
+
+impl Search {
+    fn new(tx: Transaction, vout: Option<usize>, pos: Pos2, spawn: SpawnDirection) -> Self {
+        let txid = tx.txid;
+        let shares: Vec<(usize, u64)> = tx
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| vout.map_or(true, |vout| vout == *i))
+            .map(|(i, output)| (i, output.value))
+            .collect();
+        let mut search = Self {
+            from: (txid, vout),
+            spawn,
+            txs: HashMap::from_iter([(txid, (tx, pos, 0))]),
+            pending: HashMap::new(),
+            spends_from: HashMap::new(),
+            utxos: HashMap::new(),
+            coinjoins: vec![],
+            accounted: Accounted {
+                total: shares.iter().map(|(_, share)| share).sum(),
+                ..Default::default()
+            },
+            unexplored: 0,
+            capped: false,
+            hops: 0,
+        };
+        let mut arrivals = vec![];
+        search.pass_on(txid, shares, &mut arrivals);
+        search.arrive(arrivals);
+        search
+    }
+
+    fn accounted(&self) -> Accounted {
+        let pending: u64 = self.pending.values().map(|(value, _, _)| value).sum();
+        Accounted {
+            unexpanded: pending + self.unexplored,
+            ..self.accounted
+        }
+    }
+
+    /// Pass on value arriving at loaded transactions to their outputs and
+    /// fees, and on from there.
+    fn arrive(&mut self, mut arrivals: Vec<(Txid, u64)>) {
+        while let Some((txid, value)) = arrivals.pop() {
+            let (tx, _, _) = &self.txs[&txid];
+            let received = tx.amount();
+            if received == 0 {
+                self.accounted.fees += value;
+                continue;
+            }
+            if tx.coinjoin().is_some() {
+                self.accounted.coinjoins += value;
+                if !self.coinjoins.contains(&txid) {
+                    self.coinjoins.push(txid);
+                }
+                continue;
+            }
+            let shares: Vec<(usize, u64)> = tx
+                .outputs
+                .iter()
+                .enumerate()
+                .map(|(i, output)| {
+                    let share = value as u128 * output.value as u128 / received as u128;
+                    (i, share as u64)
+                })
+                .collect();
+            // Including what's lost to rounding.
+            self.accounted.fees += value - shares.iter().map(|(_, share)| share).sum::<u64>();
+            self.pass_on(txid, shares, &mut arrivals);
+        }
+    }
+
+    /// Credit the outputs of `txid` with their share of the traced value.
+    /// Value for transactions that are already loaded arrives there next.
+    fn pass_on(&mut self, txid: Txid, shares: Vec<(usize, u64)>, arrivals: &mut Vec<(Txid, u64)>) {
+        let (tx, pos, hops) = &self.txs[&txid];
+        let (pos, hops) = (*pos, *hops);
+        let mut new = vec![];
+        for (vout, share) in shares {
+            let output = &tx.outputs[vout];
+            if share == 0 {
+                continue;
+            }
+            let Some(spending_txid) = output.spending_txid else {
+                self.accounted.unspent += share;
+                self.utxos
+                    .entry((txid, vout))
+                    .or_insert_with(|| Utxo {
+                        coin: (txid, vout),
+                        value: output.value,
+                        traced: 0,
+                        address: output.address.clone(),
+                        hops,
+                    })
+                    .traced += share;
+                continue;
+            };
+            let spends_from = self.spends_from.entry(spending_txid).or_default();
+            if !spends_from.contains(&txid) {
+                spends_from.push(txid);
+            }
+            if self.txs.contains_key(&spending_txid) {
+                arrivals.push((spending_txid, share));
+            } else if let Some((pending, _, _)) = self.pending.get_mut(&spending_txid) {
+                *pending += share;
+            } else if let Some((_, pending)) = new.iter_mut().find(|(s, _)| *s == spending_txid) {
+                *pending += share;
+            } else {
+                new.push((spending_txid, share));
+            }
+        }
+        let positions = graph::hop_positions(pos, new.len(), false, self.spawn);
+        for ((spending_txid, value), pos) in new.into_iter().zip(positions) {
+            self.pending.insert(spending_txid, (value, pos, hops + 1));
+        }
+    }
+
+    /// Load the transactions that value is on its way to, most value first,
+    /// until there are none left or the limit is reached.
+    fn step(mut self, ctx: Context, sender: Sender<Update>) {
+        sender
+            .send(Update::UnspentProgress {
+                from: self.from,
+                accounted: self.accounted(),
+            })
+            .unwrap();
+
+        let mut next: Vec<(Txid, (u64, Pos2, usize))> = self.pending.drain().collect();
+        next.sort_by_key(|(txid, (value, _, _))| (Reverse(*value), *txid));
+        let room = MAX_TXS.saturating_sub(self.txs.len());
+        if next.len() > room {
+            self.capped = true;
+            self.unexplored += next[room..]
+                .iter()
+                .map(|(_, (value, _, _))| value)
+                .sum::<u64>();
+            next.truncate(room);
+        }
+        if next.is_empty() {
+            self.finish(&ctx, sender);
+            return;
+        }
+
+        self.hops += 1;
+        Loading::set_task(
+            &ctx,
+            Some(format!(
+                "Finding unspent descendants: hop {}, {} transactions",
+                self.hops,
+                self.txs.len() + next.len()
+            )),
+        );
+        let txids: Vec<Txid> = next.iter().map(|(txid, _)| *txid).collect();
+        let ctx2 = ctx.clone();
+        TxCache::get_batch(&ctx, &txids, move |loaded| {
+            // All of them are loaded before value arrives, so that value
+            // passed on between them doesn't wait for the next hop.
+            let mut arrivals = vec![];
+            for (txid, (value, pos, hops)) in next {
+                match loaded.get(&txid) {
+                    Some(tx) => {
+                        self.txs.insert(txid, (tx.clone(), pos, hops));
+                        arrivals.push((txid, value));
+                    }
+                    None => self.unexplored += value,
+                }
+            }
+            self.arrive(arrivals);
+            self.step(ctx2, sender);
+        });
+    }
+
+    /// Send the result along with the transactions on the way to the unspent
+    /// outputs and CoinJoins, leaving out the ones that lead elsewhere.
+    fn finish(self, ctx: &Context, sender: Sender<Update>) {
+        Loading::set_task(ctx, None);
+        let mut skeleton = HashSet::default();
+        let mut todo: Vec<Txid> = self
+            .utxos
+            .keys()
+            .map(|(txid, _)| *txid)
+            .chain(self.coinjoins.iter().copied())
+            .collect();
+        while let Some(txid) = todo.pop() {
+            if skeleton.insert(txid) {
+                todo.extend(self.spends_from.get(&txid).into_iter().flatten());
+            }
+        }
+        let accounted = self.accounted();
+        let mut txs: Vec<(Transaction, Pos2, usize)> = self
+            .txs
+            .into_values()
+            .filter(|(tx, _, _)| tx.txid != self.from.0 && skeleton.contains(&tx.txid))
+            .collect();
+        txs.sort_by_key(|(tx, _, hops)| (*hops, tx.txid));
+
+        let mut utxos: Vec<Utxo> = self.utxos.into_values().collect();
+        utxos.sort_by_key(|utxo| (Reverse(utxo.traced), utxo.coin));
+        let found = Found {
+            from: self.from,
+            utxos,
+            coinjoins: self.coinjoins,
+            accounted,
+            capped: self.capped,
+        };
+        let txs = txs.into_iter().map(|(tx, pos, _)| (tx, pos)).collect();
+        sender.send(Update::UnspentFound { found, txs }).unwrap();
+    }
+}
+
+/// The progress and result of the last search for unspent descendants.
+#[derive(Default)]
+pub struct UnspentDescendants {
+    open: bool,
+    /// What the last search started from. Updates of earlier ones are
+    /// ignored.
+    from: Option<(Txid, Option<usize>)>,
+    accounted: Accounted,
+    found: Option<Found>,
+}
+
+impl UnspentDescendants {
+    /// Search forward from the outputs of `txid`, or only from `vout`.
+    pub fn start(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        txid: Txid,
+        vout: Option<usize>,
+        spawn: SpawnDirection,
+        sender: Sender<Update>,
+    ) {
+        let Some(pos) = graph.get_tx_pos(txid) else {
+            return;
+        };
+        *self = Self {
+            open: true,
+            from: Some((txid, vout)),
+            ..Default::default()
+        };
+        let ctx2 = ctx.clone();
+        TxCache::get_batch(ctx, &[txid], move |loaded| {
+            let Some(tx) = loaded.get(&txid) else {
+                return;
+            };
+            Search::new(tx.clone(), vout, pos, spawn).step(ctx2, sender);
+        });
+    }
+
+    pub fn progress(&mut self, from: (Txid, Option<usize>), accounted: Accounted) {
+        if self.from == Some(from) {
+            self.accounted = accounted;
+        }
+    }
+
+    /// Returns false if the search was superseded by another one.
+    pub fn found(&mut self, ctx: &Context, found: Found, format: NumberFormat) -> bool {
+        if self.from != Some(found.from) {
+            return false;
+        }
+        ctx.notify_success(format!(
+            "Found {} unspent outputs holding {} of the traced value.",
+            found.utxos.len(),
+            Sats(found.accounted.unspent).format(format)
+        ));
+        self.accounted = found.accounted;
+        self.found = Some(found);
+        true
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        annotations: &Annotations,
+        format: NumberFormat,
+        sender: Sender<Update>,
+    ) {
+        let Some((txid, vout)) = self.from else {
+            return;
+        };
+        let found = &self.found;
+        let accounted = &self.accounted;
+        egui::Window::new("Unspent Descendants")
+            .open(&mut self.open)
+            .show(ctx, |ui| {
+                let from = match vout {
+                    Some(vout) => format!("{}:{}", &txid.hex_string()[..16], vout),
+                    None => txid.hex_string()[..16].to_string(),
+                };
+                ui.label(format!("Where the value of {} is now:", from));
+                meter(ui, accounted, format);
+
+                let Some(found) = found else {
+                    ui.weak("Searching...");
+                    return;
+                };
+                if found.capped {
+                    ui.label(format!(
+                        "The search stopped after {} transactions.",
+                        MAX_TXS
+                    ));
+                }
+                ui.separator();
+
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    Grid::new("unspent").striped(true).show(ui, |ui| {
+                        ui.strong("Output");
+                        ui.strong("Traced");
+                        ui.strong("Amount");
+                        ui.strong("Address");
+                        ui.strong("Hops");
+                        ui.end_row();
+                        for utxo in &found.utxos {
+                            let (txid, vout) = utxo.coin;
+                            let name = annotations
+                                .coin_label(utxo.coin)
+                                .unwrap_or_else(|| format!("{}:{}", &txid.hex_string()[..8], vout));
+                            if ui.link(RichText::new(name).monospace()).clicked() {
+                                sender
+                                    .send(Update::Highlight {
+                                        txid,
+                                        coin: Some(utxo.coin),
+                                    })
+                                    .unwrap();
+                            }
+                            ui.label(Sats(utxo.traced).format(format));
+                            ui.label(Sats(utxo.value).format(format));
+                            ui.label(RichText::new(&utxo.address).monospace());
+                            ui.label(utxo.hops.to_string());
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+}
+
+/// A bar split by where the traced value went, with a legend below.
+fn meter(ui: &mut egui::Ui, accounted: &Accounted, format: NumberFormat) {
+    const HEIGHT: f32 = 14.0;
+    let width = ui.available_width().max(300.0);
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(width, HEIGHT), Sense::hover());
+    let painter = ui.painter().clone();
+    painter.rect_filled(rect, Rounding::ZERO, ui.visuals().faint_bg_color);
+
+    let total = accounted.total.max(1) as f32;
+    let mut left = rect.left();
+    for (label, value, color) in accounted.parts() {
+        let part = width * value as f32 / total;
+        if part <= 0.0 {
+            continue;
+        }
+        let part_rect = Rect::from_min_size(Pos2::new(left, rect.top()), Vec2::new(part, HEIGHT));
+        painter.rect_filled(part_rect, Rounding::ZERO, color);
+        ui.interact(part_rect, ui.id().with(label), Sense::hover())
+            .on_hover_text(format!("{}: {}", label, Sats(value).format(format)));
+        left += part;
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        for (label, value, color) in accounted.parts() {
+            ui.label(RichText::new("■").color(color));
+            ui.label(format!("{} {:.1}%", label, 100.0 * value as f32 / total));
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bitcoin::{dummy_transaction, dummy_txid};
+
+    use super::*;
+
+    #[test]
+    fn value_is_passed_on_in_proportion() {
+        // 1 sends 600 to 2 and keeps 400. 2 merges it with 600 from
+        // elsewhere and pays 200 in fees.
+        let start = dummy_transaction(1, &[(0, 1100)], &[(Some(2), 600), (None, 400)]);
+        let mut search = Search::new(start, None, Pos2::ZERO, SpawnDirection::Vertical);
+        let accounted = search.accounted();
+        assert_eq!(accounted.total, 1000);
+        assert_eq!(accounted.unspent, 400);
+        assert_eq!(accounted.unexpanded, 600);
+
+        let merge = dummy_transaction(2, &[(1, 600), (9, 600)], &[(None, 1000)]);
+        search.txs.insert(merge.txid, (merge, Pos2::ZERO, 1));
+        let (value, _, _) = search.pending.remove(&dummy_txid(2)).unwrap();
+        search.arrive(vec![(dummy_txid(2), value)]);
+
+        let accounted = search.accounted();
+        assert_eq!(accounted.unspent, 400 + 500);
+        assert_eq!(accounted.fees, 100);
+        assert_eq!(accounted.unexpanded, 0);
+        assert_eq!(search.utxos[&(dummy_txid(2), 0)].traced, 500);
+    }
+}
@@ -0,0 +1,276 @@
+use egui::{Button, Context, TextEdit};
+
+use crate::{bitcoin::Txid, modal, notifications::NotifyExt, style};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum WalletFormat {
+    SparrowCsv,
+    ElectrumJson,
+}
+
+impl WalletFormat {
+    const ALL: [WalletFormat; 2] = [WalletFormat::SparrowCsv, WalletFormat::ElectrumJson];
+
+    fn name(self) -> &'static str {
+        match self {
+            WalletFormat::SparrowCsv => "Sparrow (CSV)",
+            WalletFormat::ElectrumJson => "Electrum (JSON)",
+        }
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            WalletFormat::SparrowCsv => "Paste the file from Transactions > Export CSV",
+            WalletFormat::ElectrumJson => "Paste the file from History > Export (JSON)",
+        }
+    }
+
+    pub fn parse(self, data: &str) -> Result<Vec<WalletTx>, String> {
+        let txs = match self {
+            WalletFormat::SparrowCsv => parse_sparrow_csv(data)?,
+            WalletFormat::ElectrumJson => parse_electrum_json(data)?,
+        };
+        if txs.is_empty() {
+            return Err("No transactions found".to_string());
+        }
+        Ok(txs)
+    }
+}
+
+/// A transaction from a wallet's history, with the label the user gave it in
+/// the wallet.
+#[derive(Debug, PartialEq)]
+pub struct WalletTx {
+    pub txid: Txid,
+    pub label: Option<String>,
+}
+
+fn push_unique(txs: &mut Vec<WalletTx>, txid: Txid, label: &str) {
+    if txs.iter().any(|tx| tx.txid == txid) {
+        return;
+    }
+    let label = label.trim();
+    txs.push(WalletTx {
+        txid,
+        label: (!label.is_empty()).then(|| label.to_string()),
+    });
+}
+
+/// Split a CSV line into fields, honoring quotes and `""` escapes.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+fn parse_sparrow_csv(data: &str) -> Result<Vec<WalletTx>, String> {
+    let mut lines = data.lines().filter(|line| !line.trim().is_empty());
+
+    let header = csv_fields(lines.next().ok_or("Empty file")?);
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    };
+    let txid_column = column("txid").ok_or("No `Txid` column in header")?;
+    let label_column = column("label");
+
+    let mut txs = vec![];
+    for (i, line) in lines.enumerate() {
+        let fields = csv_fields(line);
+        let txid = fields
+            .get(txid_column)
+            .ok_or_else(|| format!("Row {}: missing txid", i + 1))?;
+        let txid = Txid::new(txid.trim()).map_err(|e| format!("Row {}: {}", i + 1, e))?;
+        let label = label_column
+            .and_then(|c| fields.get(c))
+            .map_or("", |l| l.as_str());
+        push_unique(&mut txs, txid, label);
+    }
+
+    Ok(txs)
+}
+
+fn parse_electrum_json(data: &str) -> Result<Vec<WalletTx>, String> {
+    let json: serde_json::Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+
+    // Depending on the version and command, Electrum exports either a plain
+    // list or an object with the list under `transactions`.
+    let entries = json
+        .as_array()
+        .or_else(|| json.get("transactions").and_then(|t| t.as_array()))
+        .ok_or("Expected a list of transactions")?;
+
+    let mut txs = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        let txid = entry
+            .get("txid")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| format!("Entry {}: missing txid", i + 1))?;
+        let txid = Txid::new(txid).map_err(|e| format!("Entry {}: {}", i + 1, e))?;
+        let label = entry.get("label").and_then(|l| l.as_str()).unwrap_or("");
+        push_unique(&mut txs, txid, label);
+    }
+
+    Ok(txs)
+}
+
+struct Input {
+    format: WalletFormat,
+    name: String,
+    data: String,
+    apply_labels: bool,
+}
+
+#[derive(Default)]
+pub struct WalletImport {
+    input: Option<Input>,
+}
+
+impl WalletImport {
+    pub fn open(&mut self) {
+        self.input = Some(Input {
+            format: WalletFormat::SparrowCsv,
+            name: String::new(),
+            data: String::new(),
+            apply_labels: true,
+        });
+    }
+
+    /// `import` is called with the wallet name, the transactions and whether
+    /// to apply the wallet's labels.
+    pub fn show(&mut self, ctx: &Context, import: impl FnOnce(String, Vec<WalletTx>, bool)) {
+        let Some(input) = &mut self.input else {
+            return;
+        };
+
+        let mut close = false;
+
+        modal::show(ctx, "Import Wallet History", |ui| {
+            ui.horizontal(|ui| {
+                for format in WalletFormat::ALL {
+                    ui.radio_value(&mut input.format, format, format.name());
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Wallet name:");
+                ui.text_edit_singleline(&mut input.name);
+            });
+            ui.checkbox(&mut input.apply_labels, "Apply labels from the wallet");
+
+            egui::ScrollArea::vertical()
+                .max_height(300.0)
+                .show(ui, |ui| {
+                    ui.add(
+                        TextEdit::multiline(&mut input.data)
+                            .font(style::get(ui).font_id())
+                            .hint_text(input.format.hint())
+                            .desired_rows(10)
+                            .desired_width(f32::INFINITY),
+                    );
+                });
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+                if ui
+                    .add_enabled(
+                        !input.name.is_empty() && !input.data.is_empty(),
+                        Button::new("Import"),
+                    )
+                    .clicked()
+                {
+                    match input.format.parse(&input.data) {
+                        Ok(txs) => {
+                            import(input.name.clone(), txs, input.apply_labels);
+                            close = true;
+                        }
+                        Err(e) => {
+                            ui.ctx()
+                                .notify_error("Could not import wallet history", Some(e));
+                        }
+                    }
+                }
+            });
+        });
+
+        if close {
+            self.input = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TXID_A: &str = "0627052b6f28912f2703066a912ea577f2ce4da4caa5a5fbd8a57286c345c2f2";
+    const TXID_B: &str = "a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d";
+
+    fn tx(txid: &str, label: Option<&str>) -> WalletTx {
+        WalletTx {
+            txid: Txid::new(txid).unwrap(),
+            label: label.map(|l| l.to_string()),
+        }
+    }
+
+    #[test]
+    fn sparrow_csv() {
+        let data = format!(
+            "Date (UTC),Label,Value,Balance,Fee,Txid\n\
+             2024-01-01 10:00,\"Rent, \"\"January\"\"\",-100000,50000,250,{TXID_A}\n\
+             2024-01-02 10:00,,150000,200000,,{TXID_B}\n\
+             2024-01-02 10:00,Duplicate,150000,200000,,{TXID_B}\n"
+        );
+        assert_eq!(
+            WalletFormat::SparrowCsv.parse(&data),
+            Ok(vec![
+                tx(TXID_A, Some("Rent, \"January\"")),
+                tx(TXID_B, None),
+            ])
+        );
+
+        assert!(WalletFormat::SparrowCsv
+            .parse("Date,Label,Value\n2024-01-01,x,1\n")
+            .is_err());
+        assert!(WalletFormat::SparrowCsv
+            .parse("Date,Label,Txid\n2024-01-01,x,nope\n")
+            .is_err());
+    }
+
+    #[test]
+    fn electrum_json() {
+        let list =
+            format!(r#"[{{"txid": "{TXID_A}", "label": "Salary"}}, {{"txid": "{TXID_B}"}}]"#);
+        let expected = Ok(vec![tx(TXID_A, Some("Salary")), tx(TXID_B, None)]);
+        assert_eq!(WalletFormat::ElectrumJson.parse(&list), expected);
+
+        let object = format!(r#"{{"summary": {{}}, "transactions": {list}}}"#);
+        assert_eq!(WalletFormat::ElectrumJson.parse(&object), expected);
+
+        assert!(WalletFormat::ElectrumJson.parse("[]").is_err());
+        assert!(WalletFormat::ElectrumJson
+            .parse(r#"[{"label": "x"}]"#)
+            .is_err());
+    }
+}
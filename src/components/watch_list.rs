@@ -0,0 +1,198 @@
+use std::sync::mpsc::Sender;
+
+use egui::{Button, Context, Grid, RichText, TextEdit};
+
+use crate::{
+    app::Update,
+    bitcoin::{NumberFormat, Sats, Txid},
+    notifications::NotifyExt,
+    style,
+    watches::{self, Rule, Watches},
+};
+
+/// The watched coins and addresses of the workspace with what their last
+/// checks found out.
+#[derive(Default)]
+pub struct WatchList {
+    open: bool,
+    /// Pasted list of outpoints and addresses, while importing.
+    import: Option<String>,
+}
+
+impl WatchList {
+    pub fn open(&mut self) {
+        self.open = true;
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        watches: &Watches,
+        format: NumberFormat,
+        sender: Sender<Update>,
+    ) {
+        let load = |txid: Txid| {
+            sender
+                .send(Update::LoadOrSelectTx { txid, pos: None })
+                .unwrap();
+        };
+
+        egui::Window::new("Watch List")
+            .open(&mut self.open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(self.import.is_none(), Button::new("Import List"))
+                        .clicked()
+                    {
+                        self.import = Some(String::new());
+                    }
+                    if ui
+                        .add_enabled(!watches.is_empty(), Button::new("Copy List"))
+                        .on_hover_text("One outpoint or address and threshold in sats per line.")
+                        .clicked()
+                    {
+                        ui.output_mut(|o| o.copied_text = watches.to_text());
+                    }
+                });
+
+                let mut done = false;
+                if let Some(text) = &mut self.import {
+                    ui.add(
+                        TextEdit::multiline(text)
+                            .font(style::get(ui).font_id())
+                            .hint_text("txid:vout\naddress [threshold in sats]")
+                            .desired_rows(6)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button("Cancel").clicked() {
+                            done = true;
+                        }
+                        if ui
+                            .add_enabled(!text.trim().is_empty(), Button::new("Watch All"))
+                            .clicked()
+                        {
+                            match watches::parse_list(text) {
+                                Ok(rules) => {
+                                    sender.send(Update::ImportWatches { rules }).unwrap();
+                                    done = true;
+                                }
+                                Err(e) => {
+                                    ui.ctx()
+                                        .notify_error("Could not import watch list", Some(e));
+                                }
+                            }
+                        }
+                    });
+                }
+                if done {
+                    self.import = None;
+                }
+                ui.separator();
+
+                if watches.is_empty() {
+                    ui.weak("Set alerts from the context menu of an output.");
+                    return;
+                }
+
+                Grid::new("Watch list")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Watching");
+                        ui.strong("Status");
+                        ui.strong("Last activity");
+                        ui.label("");
+                        ui.end_row();
+
+                        for watch in watches.iter() {
+                            let status = watch.status();
+                            match &watch.rule {
+                                Rule::Spent { coin } => {
+                                    ui.monospace(format!("{}:{}", short(&coin.0), coin.1));
+                                    if let Some(error) = &status.error {
+                                        check_failed(ui, error);
+                                    } else if !status.checked {
+                                        ui.weak("Not checked yet");
+                                    } else if status.spending_txid.is_some() {
+                                        ui.label("Spent");
+                                    } else {
+                                        ui.label("Unspent");
+                                    }
+                                    last_activity(ui, status.spending_txid);
+                                    ui.horizontal(|ui| {
+                                        if ui.button("Load Coin").clicked() {
+                                            load(coin.0);
+                                            sender
+                                                .send(Update::SelectCoin { coin: *coin })
+                                                .unwrap();
+                                        }
+                                        if let Some(txid) = status.spending_txid {
+                                            if ui.button("Load Spending Tx").clicked() {
+                                                load(txid);
+                                            }
+                                        }
+                                        remove_button(ui, watch.id(), &sender);
+                                    });
+                                }
+                                Rule::Received { address, threshold } => {
+                                    ui.monospace(address).on_hover_text(format!(
+                                        "Alert on more than {} received.",
+                                        Sats(*threshold).format(format)
+                                    ));
+                                    match (&status.error, status.balance) {
+                                        (Some(error), _) => check_failed(ui, error),
+                                        (None, Some(balance)) => {
+                                            ui.label(Sats(balance).format(format))
+                                        }
+                                        (None, None) => ui.weak("Not checked yet"),
+                                    };
+                                    last_activity(ui, status.last_txid);
+                                    ui.horizontal(|ui| {
+                                        if ui.button("Load History").clicked() {
+                                            sender
+                                                .send(Update::LoadAddress {
+                                                    address: address.clone(),
+                                                })
+                                                .unwrap();
+                                        }
+                                        if let Some(txid) = status.last_txid {
+                                            if ui.button("Load Last Tx").clicked() {
+                                                load(txid);
+                                            }
+                                        }
+                                        remove_button(ui, watch.id(), &sender);
+                                    });
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
+
+fn short(txid: &Txid) -> String {
+    txid.hex_string()[..16].to_string()
+}
+
+fn last_activity(ui: &mut egui::Ui, txid: Option<Txid>) {
+    match txid {
+        Some(txid) => ui.monospace(short(&txid)).on_hover_text(txid.hex_string()),
+        None => ui.label(RichText::new("None").weak()),
+    };
+}
+
+/// The last check failed; the details are in the tooltip.
+fn check_failed(ui: &mut egui::Ui, error: &str) -> egui::Response {
+    ui.colored_label(ui.visuals().warn_fg_color, "Check failed")
+        .on_hover_text(error)
+}
+
+fn remove_button(ui: &mut egui::Ui, id: uuid::Uuid, sender: &Sender<Update>) {
+    if ui.button("✖").on_hover_text("Stop watching").clicked() {
+        sender.send(Update::RemoveWatch { id }).unwrap();
+    }
+}
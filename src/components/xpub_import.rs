@@ -0,0 +1,120 @@
+use egui::{Button, Context, DragValue, RichText, TextEdit};
+
+use crate::{
+    hd::{ScriptType, Wallet},
+    modal, style,
+};
+
+/// Addresses shown before the import.
+const PREVIEW: usize = 3;
+
+type Derived = Result<(ScriptType, Vec<String>), String>;
+
+struct Input {
+    name: String,
+    key: String,
+    per_chain: u32,
+    /// Derived for the key and count above, to not redo it every frame.
+    derived: Option<(String, u32, Derived)>,
+}
+
+impl Input {
+    fn addresses(&mut self) -> &Derived {
+        let stale = self.derived.as_ref().map_or(true, |(key, per_chain, _)| {
+            *key != self.key || *per_chain != self.per_chain
+        });
+        if stale {
+            let addresses = Wallet::parse(&self.key)
+                .map(|wallet| (wallet.script_type(), wallet.addresses(self.per_chain)));
+            self.derived = Some((self.key.clone(), self.per_chain, addresses));
+        }
+        &self.derived.as_ref().unwrap().2
+    }
+}
+
+/// Load a wallet's history from its xpub or output descriptor. The addresses
+/// are derived here, so the backend only ever sees single addresses.
+#[derive(Default)]
+pub struct XpubImport {
+    input: Option<Input>,
+}
+
+impl XpubImport {
+    pub fn open(&mut self) {
+        self.input = Some(Input {
+            name: String::new(),
+            key: String::new(),
+            per_chain: 20,
+            derived: None,
+        });
+    }
+
+    /// `import` is called with the wallet name and the derived addresses.
+    pub fn show(&mut self, ctx: &Context, import: impl FnOnce(String, Vec<String>)) {
+        let Some(input) = &mut self.input else {
+            return;
+        };
+
+        let mut close = false;
+
+        modal::show(ctx, "Import Wallet from Xpub", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Wallet name:");
+                ui.text_edit_singleline(&mut input.name);
+            });
+            ui.add(
+                TextEdit::singleline(&mut input.key)
+                    .font(style::get(ui).font_id())
+                    .hint_text("xpub/ypub/zpub or wpkh([...]xpub.../<0;1>/*)")
+                    .desired_width(500.0),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Addresses per chain:");
+                ui.add(DragValue::new(&mut input.per_chain).range(1..=200));
+            })
+            .response
+            .on_hover_text("Receive and change addresses are derived separately.");
+
+            ui.add_space(3.0);
+
+            let addresses = match (input.key.trim().is_empty(), input.addresses()) {
+                (true, _) => None,
+                (false, Ok((script_type, addresses))) => {
+                    for address in addresses.iter().take(PREVIEW) {
+                        ui.monospace(address);
+                    }
+                    ui.label(
+                        RichText::new(format!(
+                            "{} {} addresses in total",
+                            addresses.len(),
+                            script_type
+                        ))
+                        .weak(),
+                    );
+                    Some(addresses.clone())
+                }
+                (false, Err(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                    None
+                }
+            };
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+                let enabled = !input.name.is_empty() && addresses.is_some();
+                if ui.add_enabled(enabled, Button::new("Import")).clicked() {
+                    import(input.name.clone(), addresses.unwrap_or_default());
+                    close = true;
+                }
+            });
+        });
+
+        if close {
+            self.input = None;
+        }
+    }
+}
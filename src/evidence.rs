@@ -0,0 +1,277 @@
+//! Chain of custody for exported workspaces and reports: a SHA-256 hash of the
+//! exact exported bytes, when it was taken and optionally an OpenTimestamps
+//! proof that the hash existed at that time.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use egui::{mutex::Mutex, Button, Context, RichText, TextEdit};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{loading::Loading, modal, notifications::NotifyExt, style};
+
+const CALENDAR_URL: &str = "https://a.pool.opentimestamps.org/digest";
+
+/// Magic bytes of an `.ots` file, followed by the major version.
+const OTS_HEADER: &[u8] =
+    b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94\x01";
+const OTS_OP_SHA256: u8 = 0x08;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stamp {
+    /// Hex encoded.
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+    /// Base64 encoded `.ots` file with a pending attestation. It can be
+    /// upgraded and verified against the blockchain with the `ots` client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ots: Option<String>,
+}
+
+impl Stamp {
+    pub fn new(data: &str, created_at: DateTime<Utc>) -> Self {
+        Self {
+            sha256: hex::encode(Sha256::digest(data.as_bytes())),
+            created_at,
+            ots: None,
+        }
+    }
+
+    /// Check that `data` is what was stamped and that the proof, if any,
+    /// commits to the same hash.
+    pub fn verify(&self, data: &str) -> Result<(), String> {
+        let digest = Sha256::digest(data.as_bytes());
+        if hex::encode(digest) != self.sha256 {
+            return Err(format!(
+                "Hash mismatch: the data hashes to {}, the stamp says {}",
+                hex::encode(digest),
+                self.sha256
+            ));
+        }
+        if let Some(ots) = &self.ots {
+            let ots = base64::decode(ots).map_err(|e| format!("Invalid proof: {}", e))?;
+            let prefix_len = OTS_HEADER.len() + 1;
+            if ots.len() < prefix_len + 32
+                || &ots[..OTS_HEADER.len()] != OTS_HEADER
+                || ots[OTS_HEADER.len()] != OTS_OP_SHA256
+            {
+                return Err("Invalid proof: not a SHA-256 OpenTimestamps file".to_string());
+            }
+            if ots[prefix_len..prefix_len + 32] != digest[..] {
+                return Err("The proof is for a different hash".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wrap the calendar's answer into a detached `.ots` file for `digest`.
+fn ots_file(digest: &[u8], timestamp: &[u8]) -> Vec<u8> {
+    let mut file = OTS_HEADER.to_vec();
+    file.push(OTS_OP_SHA256);
+    file.extend_from_slice(digest);
+    file.extend_from_slice(timestamp);
+    file
+}
+
+/// The answer to the last proof request, for the hex encoded hash it was made
+/// for.
+type Pending = Arc<Mutex<Option<(String, Result<String, String>)>>>;
+
+fn request_proof(ctx: &Context, sha256: &str, pending: Pending) {
+    let sha256 = sha256.to_string();
+    let digest = hex::decode(&sha256).unwrap();
+    let mut request = ehttp::Request::post(CALENDAR_URL, digest.clone());
+    request
+        .headers
+        .insert("Accept", "application/vnd.opentimestamps.v1");
+
+    Loading::start_loading(ctx);
+    let ctx = ctx.clone();
+    ehttp::fetch(request, move |response| {
+        Loading::loading_done(&ctx);
+        let result = response.and_then(|response| {
+            if response.ok {
+                Ok(base64::encode(ots_file(&digest, &response.bytes)))
+            } else {
+                Err(format!("{} {}", response.status, response.status_text))
+            }
+        });
+        *pending.lock() = Some((sha256, result));
+        ctx.request_repaint();
+    });
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Source {
+    #[default]
+    Workspace,
+    Report,
+}
+
+#[derive(Default)]
+struct State {
+    source: Source,
+    opentimestamps: bool,
+    data: String,
+    stamp: Option<Stamp>,
+    pending: Pending,
+    verify_data: String,
+    verify_stamp: String,
+}
+
+#[derive(Default)]
+pub struct Evidence {
+    state: Option<State>,
+}
+
+impl Evidence {
+    pub fn open(&mut self) {
+        self.state = Some(State {
+            opentimestamps: true,
+            ..Default::default()
+        });
+    }
+
+    /// `export` produces the data to stamp. It's only called when stamping so
+    /// that the copied data is exactly what was hashed.
+    pub fn show(&mut self, ctx: &Context, export: impl FnOnce(Source) -> String) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        // Answers for an earlier stamp are of no use anymore.
+        let pending = state.pending.lock().take();
+        let current = |sha256: &String| {
+            state
+                .stamp
+                .as_ref()
+                .is_some_and(|stamp| stamp.sha256 == *sha256)
+        };
+        if let Some((_, result)) = pending.filter(|(sha256, _)| current(sha256)) {
+            match (result, &mut state.stamp) {
+                (Ok(ots), Some(stamp)) => {
+                    stamp.ots = Some(ots);
+                    ctx.notify_success("Received OpenTimestamps proof.");
+                }
+                (Err(e), _) => ctx.notify_error("Could not get timestamp proof", Some(e)),
+                _ => {}
+            }
+        }
+
+        let mut close = false;
+
+        modal::show(ctx, "Evidence Stamp", |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut state.source, Source::Workspace, "Workspace JSON");
+                ui.radio_value(&mut state.source, Source::Report, "Report HTML");
+            });
+            ui.checkbox(&mut state.opentimestamps, "Request OpenTimestamps proof")
+                .on_hover_text("Sends the hash (not the data) to a public calendar server.");
+
+            if ui.button("Stamp").clicked() {
+                state.data = export(state.source);
+                let stamp = Stamp::new(&state.data, Utc::now());
+                if state.opentimestamps {
+                    request_proof(ui.ctx(), &stamp.sha256, state.pending.clone());
+                }
+                state.stamp = Some(stamp);
+            }
+
+            if let Some(stamp) = &state.stamp {
+                ui.label(RichText::new(&stamp.sha256).font(style::get(ui).font_id()));
+                ui.label(format!("Taken at {}", stamp.created_at.to_rfc3339()));
+                ui.horizontal(|ui| {
+                    if ui.button("Copy Data").clicked() {
+                        ui.output_mut(|o| o.copied_text = state.data.clone());
+                    }
+                    if ui.button("Copy Stamp").clicked() {
+                        ui.output_mut(|o| {
+                            o.copied_text = serde_json::to_string_pretty(stamp).unwrap()
+                        });
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.label("Verify");
+            for (text, hint) in [
+                (&mut state.verify_data, "Paste the stamped data"),
+                (&mut state.verify_stamp, "Paste the stamp"),
+            ] {
+                egui::ScrollArea::vertical()
+                    .id_source(hint)
+                    .max_height(100.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            TextEdit::multiline(text)
+                                .font(style::get(ui).font_id())
+                                .hint_text(hint)
+                                .desired_rows(3)
+                                .desired_width(f32::INFINITY),
+                        );
+                    });
+            }
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Close").clicked() {
+                    close = true;
+                }
+                if ui
+                    .add_enabled(
+                        !state.verify_data.is_empty() && !state.verify_stamp.is_empty(),
+                        Button::new("Verify"),
+                    )
+                    .clicked()
+                {
+                    let result = serde_json::from_str::<Stamp>(&state.verify_stamp)
+                        .map_err(|e| format!("Invalid stamp: {}", e))
+                        .and_then(|stamp| stamp.verify(&state.verify_data).map(|()| stamp));
+                    match result {
+                        Ok(stamp) => ui.ctx().notify_success(format!(
+                            "The data is unchanged since {}.",
+                            stamp.created_at.to_rfc3339()
+                        )),
+                        Err(e) => ui.ctx().notify_error("Verification failed", Some(e)),
+                    }
+                }
+            });
+        });
+
+        if close {
+            self.state = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stamp_and_verify() {
+        let created_at = DateTime::parse_from_rfc3339("2024-06-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut stamp = Stamp::new("abc", created_at);
+        assert_eq!(
+            stamp.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(stamp.verify("abc"), Ok(()));
+        assert!(stamp.verify("abd").is_err());
+
+        let digest = hex::decode(&stamp.sha256).unwrap();
+        stamp.ots = Some(base64::encode(ots_file(&digest, b"\xf0\x00")));
+        assert_eq!(stamp.verify("abc"), Ok(()));
+
+        stamp.ots = Some(base64::encode(ots_file(&[0; 32], b"")));
+        assert!(stamp.verify("abc").is_err());
+
+        let json = serde_json::to_string(&stamp).unwrap();
+        assert_eq!(serde_json::from_str::<Stamp>(&json).unwrap(), stamp);
+    }
+}
@@ -1,9 +1,16 @@
 use std::collections::HashMap;
 
-use egui::Pos2;
+use egui::{ahash::HashSet, Pos2, Vec2};
 use serde::{Deserialize, Serialize};
 
-use crate::{annotations, bitcoin::Txid, graph::Graph, layout::Layout, transform::Transform};
+use crate::{
+    annotations,
+    bitcoin::{self, Txid},
+    graph::Graph,
+    layout::{default_as_true, Layout},
+    transform::Transform,
+    watches::{Rule, Watch, Watches},
+};
 
 // Public interface
 
@@ -13,6 +20,20 @@ pub struct Workspace {
     pub layout: Layout0,
     pub transform: Transform0,
     pub transactions: Vec<Transaction>,
+    pub selections: Vec<Selection>,
+    /// Named clusters, collapsed or not, and collapsed chains of hops.
+    pub groups: Vec<Group>,
+    /// Alert rules and what their checks found out.
+    pub watches: Vec<Watch>,
+    /// Unknown for workspaces from before the backend reported it.
+    pub network: Option<bitcoin::Network>,
+}
+
+/// Transactions saved under a name, to recall them later.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Selection {
+    pub name: String,
+    pub txids: Vec<Txid>,
 }
 
 impl Workspace {
@@ -21,12 +42,80 @@ impl Workspace {
         annotations: &annotations::Annotations,
         layout: &Layout,
         transform: &Transform,
+        watches: &Watches,
+        network: Option<bitcoin::Network>,
     ) -> Self {
+        let mut transactions = graph.export();
+        // Stable order, so that unchanged workspaces compare equal.
+        transactions.sort_by_key(|t| t.txid);
         Self {
             annotations: (*annotations).clone(),
             layout: layout.export(),
             transform: transform.export(),
-            transactions: graph.export(),
+            transactions,
+            selections: graph.named_selections().to_vec(),
+            groups: graph.export_groups(),
+            watches: watches.export(),
+            network,
+        }
+    }
+
+    /// Like [Self::new], but only with the given transactions and the
+    /// annotations and coin watches that concern them.
+    pub fn subset(
+        graph: &Graph,
+        annotations: &annotations::Annotations,
+        layout: &Layout,
+        transform: &Transform,
+        watches: &Watches,
+        network: Option<bitcoin::Network>,
+        txids: &HashSet<Txid>,
+    ) -> Self {
+        let coins = graph.coins(txids);
+        Self {
+            annotations: annotations.subset(txids, &coins),
+            layout: layout.export(),
+            transform: transform.export(),
+            transactions: graph.export_subset(txids),
+            selections: graph
+                .named_selections()
+                .iter()
+                .map(|s| Selection {
+                    name: s.name.clone(),
+                    txids: s
+                        .txids
+                        .iter()
+                        .filter(|t| txids.contains(t))
+                        .copied()
+                        .collect(),
+                })
+                .filter(|s| !s.txids.is_empty())
+                .collect(),
+            groups: graph
+                .export_groups()
+                .into_iter()
+                .filter(|g| txids.contains(&g.first))
+                .filter(|g| g.rest.iter().all(|(txid, _)| txids.contains(txid)))
+                .collect(),
+            watches: watches
+                .iter()
+                .filter(|w| match &w.rule {
+                    Rule::Spent { coin } => coins.contains(coin),
+                    Rule::Received { .. } => true,
+                })
+                .cloned()
+                .collect(),
+            network,
+        }
+    }
+
+    /// Include the full data of the given transactions so that the workspace
+    /// can be opened without a backend.
+    pub fn embed(&mut self, txs: impl IntoIterator<Item = bitcoin::Transaction>) {
+        let mut txs: HashMap<Txid, bitcoin::Transaction> =
+            txs.into_iter().map(|tx| (tx.txid, tx)).collect();
+        for t in &mut self.transactions {
+            t.data = txs.remove(&t.txid);
         }
     }
 }
@@ -34,7 +123,7 @@ impl Workspace {
 impl Serialize for Workspace {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         Workspace0 {
-            version: 0,
+            version: WORKSPACE_VERSION,
             annotations: self.annotations.export(),
             layout: self.layout.clone(),
             transform: self.transform.clone(),
@@ -43,6 +132,17 @@ impl Serialize for Workspace {
                 .iter()
                 .map(Transaction::to_transaction0)
                 .collect(),
+            selections: self
+                .selections
+                .iter()
+                .map(|s| Selection0 {
+                    name: s.name.clone(),
+                    txids: s.txids.clone(),
+                })
+                .collect(),
+            groups: self.groups.iter().map(Group::to_group0).collect(),
+            watches: self.watches.clone(),
+            network: self.network,
         }
         .serialize(serializer)
     }
@@ -61,25 +161,113 @@ impl<'de> Deserialize<'de> for Workspace {
                 .into_iter()
                 .map(Transaction::from_transaction0)
                 .collect(),
+            selections: workspace0
+                .selections
+                .into_iter()
+                .map(|s| Selection {
+                    name: s.name,
+                    txids: s.txids,
+                })
+                .collect(),
+            groups: workspace0
+                .groups
+                .into_iter()
+                .map(Group::from_group0)
+                .collect(),
+            watches: workspace0.watches,
+            network: workspace0.network,
         })
     }
 }
 
+/// Transactions drawn as one node in place of the first one.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Group {
+    pub first: Txid,
+    /// Clusters are named, chains of hops aren't.
+    pub name: Option<String>,
+    /// The other transactions and, while collapsed, where they were relative
+    /// to the first one.
+    pub rest: Vec<(Txid, Vec2)>,
+    pub expanded: bool,
+}
+
+impl Group {
+    fn from_group0(g: Group0) -> Self {
+        Self {
+            first: g.first,
+            name: g.name,
+            rest: g
+                .rest
+                .into_iter()
+                .map(|m| (m.txid, m.offset.to_pos2().to_vec2()))
+                .collect(),
+            expanded: g.expanded,
+        }
+    }
+
+    fn to_group0(&self) -> Group0 {
+        Group0 {
+            first: self.first,
+            name: self.name.clone(),
+            rest: self
+                .rest
+                .iter()
+                .map(|(txid, offset)| Member0 {
+                    txid: *txid,
+                    offset: Position0::from_pos2(offset.to_pos2()),
+                })
+                .collect(),
+            expanded: self.expanded,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Transaction {
     pub txid: Txid,
     pub position: Pos2,
+    /// Enough to draw the transaction before its details are fetched.
+    pub summary: Option<Summary>,
+    /// Embedded transaction data of self-contained exports.
+    pub data: Option<bitcoin::Transaction>,
+    /// Can't be removed from the graph.
+    pub protected: bool,
+    /// Stays where it is when the layout moves the others.
+    pub pinned: bool,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Summary {
+    pub value: u64,
+    pub block_height: u32,
+    pub timestamp: i64,
 }
 
 impl Transaction {
-    pub fn new(txid: Txid, position: Pos2) -> Self {
-        Self { txid, position }
+    pub fn new(txid: Txid, position: Pos2, summary: Option<Summary>) -> Self {
+        Self {
+            txid,
+            position,
+            summary,
+            data: None,
+            protected: false,
+            pinned: false,
+        }
     }
 
     fn from_transaction0(t: Transaction0) -> Self {
         Self {
             txid: t.txid,
             position: t.position.to_pos2(),
+            summary: t.summary.map(|s| Summary {
+                value: s.value,
+                block_height: s.block_height,
+                timestamp: s.timestamp,
+            }),
+            data: t.data,
+            protected: t.protected,
+            pinned: t.pinned,
         }
     }
 
@@ -87,13 +275,41 @@ impl Transaction {
         Transaction0 {
             txid: self.txid,
             position: Position0::from_pos2(self.position),
+            summary: self.summary.map(|s| Summary0 {
+                value: s.value,
+                block_height: s.block_height,
+                timestamp: s.timestamp,
+            }),
+            data: self.data.clone(),
+            protected: self.protected,
+            pinned: self.pinned,
         }
     }
 }
 
-// Version 0 of the workspace file format
+// Version 0 of the workspace file format, and version 1, which adds groups.
+// Older versions of the app refuse version 1 rather than dropping them.
+
+const WORKSPACE_VERSION: u32 = 1;
+
+fn validate_workspace_version<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
+    let version = u32::deserialize(deserializer)?;
+    if version <= WORKSPACE_VERSION {
+        Ok(version)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "Unsupported version: {}",
+            version
+        )))
+    }
+}
 
-fn validate_version<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+// Public so that the collection file format can use it, too.
+pub fn validate_version<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
     let version = u32::deserialize(deserializer)?;
     if version == 0 {
         Ok(version)
@@ -107,7 +323,7 @@ fn validate_version<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result
 
 #[derive(Serialize, Deserialize)]
 struct Workspace0 {
-    #[serde(deserialize_with = "validate_version")]
+    #[serde(deserialize_with = "validate_workspace_version")]
     version: u32,
     annotations: Annotations0,
     #[serde(default)]
@@ -115,6 +331,38 @@ struct Workspace0 {
     #[serde(default)]
     transform: Transform0,
     transactions: Vec<Transaction0>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    selections: Vec<Selection0>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<Group0>,
+    /// Same format as the app's own storage.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    watches: Vec<Watch>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    network: Option<bitcoin::Network>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Selection0 {
+    name: String,
+    txids: Vec<Txid>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Group0 {
+    first: Txid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    rest: Vec<Member0>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    expanded: bool,
+}
+
+/// Where a transaction of a collapsed group was relative to the first one.
+#[derive(Serialize, Deserialize)]
+struct Member0 {
+    txid: Txid,
+    offset: Position0,
 }
 
 // This is public because it's used in the conversion code in annotations.rs
@@ -130,6 +378,12 @@ pub struct Annotations0 {
 #[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone)]
 pub struct Layout0 {
     pub scale: u64,
+    /// Whether the force simulation runs. When paused, the stored positions
+    /// are authoritative.
+    #[serde(default = "default_as_true")]
+    pub active: bool,
+    #[serde(default)]
+    pub parents_above: bool,
     pub x1: u64,
     pub y1: u64,
     pub x2: u64,
@@ -169,6 +423,22 @@ impl Default for Transform0 {
 struct Transaction0 {
     txid: Txid,
     position: Position0,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<Summary0>,
+    /// Same format as the backend's `/tx` endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<bitcoin::Transaction>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    protected: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pinned: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Summary0 {
+    value: u64,
+    block_height: u32,
+    timestamp: i64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -258,6 +528,8 @@ mod test {
             annotations: a,
             layout: Layout0 {
                 scale: 50,
+                active: true,
+                parents_above: false,
                 x1: 1000000,
                 y1: 30,
                 x2: 10000000000000,
@@ -275,12 +547,24 @@ mod test {
                     )
                     .unwrap(),
                     position: Pos2::new(711.0, 351.0),
+                    summary: None,
+                    data: None,
+                    protected: false,
+                    pinned: false,
                 },
                 Transaction {
                     txid,
                     position: Pos2::new(755.0, 242.0),
+                    summary: None,
+                    data: None,
+                    protected: false,
+                    pinned: false,
                 },
             ],
+            selections: vec![],
+            groups: vec![],
+            watches: vec![],
+            network: None,
         }
     }
 
@@ -297,4 +581,144 @@ mod test {
         let actual = serde_json::from_str(&string).unwrap();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_workspace_summary_roundtrip() {
+        let mut expected = workspace_expected();
+        expected.transactions[0].summary = Some(Summary {
+            value: 5_000_000_000,
+            block_height: 170,
+            timestamp: 1231731025,
+        });
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"block_height\":170"));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_selections_roundtrip() {
+        let mut expected = workspace_expected();
+        expected.selections.push(Selection {
+            name: "Payout".to_string(),
+            txids: vec![expected.transactions[1].txid],
+        });
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"Payout\""));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_groups_roundtrip() {
+        let mut expected = workspace_expected();
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(!string.contains("groups"));
+
+        expected.groups.push(Group {
+            first: expected.transactions[0].txid,
+            name: Some("Cluster 1".to_string()),
+            rest: vec![(expected.transactions[1].txid, Vec2::new(44.0, -109.0))],
+            expanded: false,
+        });
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"Cluster 1\""));
+        assert!(string.contains("\"version\":1"));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+
+        // A collapsed chain of hops.
+        expected.groups[0].name = None;
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(!string.contains("name"));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_version() {
+        let newer = WORKSPACE_FIXTURE_0.replace("\"version\": 0", "\"version\": 2");
+        assert!(serde_json::from_str::<Workspace>(&newer).is_err());
+    }
+
+    #[test]
+    fn test_workspace_watches_roundtrip() {
+        let mut expected = workspace_expected();
+        expected.watches.push(Watch::new(Rule::Spent {
+            coin: (expected.transactions[0].txid, 1),
+        }));
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"watches\""));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_network_roundtrip() {
+        let mut expected = workspace_expected();
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(!string.contains("network"));
+
+        expected.network = Some(bitcoin::Network::Testnet);
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"network\":\"testnet\""));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_protected_roundtrip() {
+        let mut expected = workspace_expected();
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(!string.contains("protected"));
+
+        expected.transactions[0].protected = true;
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"protected\":true"));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_pinned_roundtrip() {
+        let mut expected = workspace_expected();
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(!string.contains("pinned"));
+
+        expected.transactions[1].pinned = true;
+        let string = serde_json::to_string(&expected).unwrap();
+        assert!(string.contains("\"pinned\":true"));
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_workspace_embedded_roundtrip() {
+        let mut expected = workspace_expected();
+
+        let txs = expected
+            .transactions
+            .iter()
+            .map(|t| bitcoin::Transaction {
+                timestamp: 1231731025,
+                txid: t.txid,
+                block_height: 170,
+                vsize: 0,
+                inputs: vec![],
+                outputs: vec![bitcoin::Output {
+                    spending_txid: None,
+                    value: 5_000_000_000,
+                    address: "12cbQLTFMXRnSzktFkuoG3eHoMeFtpTu3S".to_string(),
+                    address_type: bitcoin::AddressType::P2PKH,
+                    script_pubkey: String::new(),
+                }],
+            })
+            .collect::<Vec<_>>();
+        expected.embed(txs);
+        assert!(expected.transactions.iter().all(|t| t.data.is_some()));
+
+        let string = serde_json::to_string(&expected).unwrap();
+        let actual = serde_json::from_str(&string).unwrap();
+        assert_eq!(expected, actual);
+    }
 }
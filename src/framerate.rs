@@ -1,7 +1,85 @@
-use egui::{util::History, RichText};
+use std::sync::Arc;
+
+use egui::{mutex::Mutex, util::History, Align2, Context, Grid, Id, RichText, Sense};
+
+use crate::platform::inner as platform;
+
+/// Parts of drawing the graph that are timed separately.
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Rects,
+    Edges,
+    Nodes,
+    Text,
+    Forces,
+}
+
+impl Phase {
+    const COUNT: usize = 5;
+    const ALL: [Phase; Self::COUNT] = [
+        Phase::Rects,
+        Phase::Edges,
+        Phase::Nodes,
+        Phase::Text,
+        Phase::Forces,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Rects => "Rects",
+            Phase::Edges => "Edges",
+            Phase::Nodes => "Nodes",
+            Phase::Text => "Text",
+            Phase::Forces => "Forces",
+        }
+    }
+}
+
+/// Seconds spent per [Phase] in the current frame. Shared via the context so
+/// that drawing code can report into it without access to [FrameRate].
+#[derive(Clone, Default)]
+struct PhaseTimes(Arc<Mutex<[f32; Phase::COUNT]>>);
+
+impl PhaseTimes {
+    fn get(ctx: &Context) -> Self {
+        ctx.data_mut(|d| d.get_temp_mut_or_default::<Self>(Id::NULL).clone())
+    }
+}
+
+/// Measures consecutive phases of a frame. Works in wasm, unlike puffin.
+pub struct Stopwatch {
+    last: f64,
+    laps: [f32; Phase::COUNT],
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self {
+            last: platform::now(),
+            laps: [0.0; Phase::COUNT],
+        }
+    }
+
+    /// Attribute the time since the previous lap to `phase`.
+    pub fn lap(&mut self, phase: Phase) {
+        let now = platform::now();
+        self.laps[phase as usize] += (now - self.last) as f32;
+        self.last = now;
+    }
+
+    pub fn finish(self, ctx: &Context) {
+        let times = PhaseTimes::get(ctx);
+        let mut times = times.0.lock();
+        for (time, lap) in times.iter_mut().zip(self.laps) {
+            *time += lap;
+        }
+    }
+}
 
 pub struct FrameRate {
     frame_times: History<f32>,
+    phase_times: [History<f32>; Phase::COUNT],
+    show_phases: bool,
 }
 
 impl Default for FrameRate {
@@ -10,18 +88,25 @@ impl Default for FrameRate {
         let max_len = (max_age * 300.0).round() as usize;
         Self {
             frame_times: History::new(0..max_len, max_age),
+            phase_times: std::array::from_fn(|_| History::new(0..max_len, max_age)),
+            show_phases: false,
         }
     }
 }
 
 impl FrameRate {
     // Called first
-    pub fn on_new_frame(&mut self, now: f64, previous_frame_time: Option<f32>) {
+    pub fn on_new_frame(&mut self, ctx: &Context, now: f64, previous_frame_time: Option<f32>) {
         let previous_frame_time = previous_frame_time.unwrap_or_default();
         if let Some(latest) = self.frame_times.latest_mut() {
             *latest = previous_frame_time; // rewrite history now that we know
         }
         self.frame_times.add(now, previous_frame_time); // projected
+
+        let times = std::mem::take(&mut *PhaseTimes::get(ctx).0.lock());
+        for (history, time) in self.phase_times.iter_mut().zip(times) {
+            history.add(now, time);
+        }
     }
 
     pub fn mean_frame_time(&self) -> f32 {
@@ -33,14 +118,51 @@ impl FrameRate {
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) {
-        ui.label(
-            RichText::new(format!(
-                "{:.0} fps ({:.2} ms / frame)",
-                self.fps(),
-                1e3 * self.mean_frame_time()
-            ))
-            .weak()
-            .small(),
-        );
+        let response = ui
+            .add(
+                egui::Label::new(
+                    RichText::new(format!(
+                        "{:.0} fps ({:.2} ms / frame)",
+                        self.fps(),
+                        1e3 * self.mean_frame_time()
+                    ))
+                    .weak()
+                    .small(),
+                )
+                .sense(Sense::click()),
+            )
+            .on_hover_text("Click to toggle the breakdown per phase.");
+        if response.clicked() {
+            self.show_phases = !self.show_phases;
+        }
+
+        if self.show_phases {
+            self.phases_ui(ui.ctx(), response.rect);
+        }
+    }
+
+    fn phases_ui(&self, ctx: &Context, above: egui::Rect) {
+        egui::Area::new(Id::new("frame_phases"))
+            .pivot(Align2::RIGHT_BOTTOM)
+            .fixed_pos(above.right_top())
+            .interactable(false)
+            .show(ctx, |ui| {
+                Grid::new("frame_phases_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (phase, history) in Phase::ALL.iter().zip(&self.phase_times) {
+                            ui.label(RichText::new(phase.name()).weak().small());
+                            ui.label(
+                                RichText::new(format!(
+                                    "{:.2} ms",
+                                    1e3 * history.average().unwrap_or_default()
+                                ))
+                                .weak()
+                                .small(),
+                            );
+                            ui.end_row();
+                        }
+                    });
+            });
     }
 }
@@ -0,0 +1,148 @@
+//! Wallets inferred with the common-input-ownership heuristic: addresses spent
+//! together in a transaction most likely belong to the same wallet. CoinJoins
+//! are built to break the heuristic and are left out.
+
+use std::collections::HashMap;
+
+use crate::bitcoin::Txid;
+
+use super::model::Model;
+
+/// Addresses of the graph's inputs grouped into inferred wallets. Only groups
+/// of more than one address count as a cluster, the others don't tell
+/// anything about ownership.
+pub struct Clusters {
+    by_address: HashMap<String, usize>,
+    /// Number of addresses, by cluster.
+    sizes: Vec<usize>,
+}
+
+/// The representative of the set of `i`, halving the path on the way.
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+impl Clusters {
+    /// Clusters are numbered in the order of their smallest address, so that
+    /// they mostly keep their number as the graph grows.
+    pub fn new(model: &Model) -> Self {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut parent: Vec<usize> = vec![];
+        for (_, tx) in model.txs() {
+            if tx.coinjoin.is_some() {
+                continue;
+            }
+            let mut first = None;
+            for input in tx.inputs.iter().filter(|input| !input.address.is_empty()) {
+                let i = *index.entry(&input.address).or_insert_with(|| {
+                    parent.push(parent.len());
+                    parent.len() - 1
+                });
+                let i = root(&mut parent, i);
+                match first {
+                    None => first = Some(i),
+                    Some(first) => parent[i] = first,
+                }
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<&str>> = HashMap::new();
+        for (address, i) in &index {
+            groups
+                .entry(root(&mut parent, *i))
+                .or_default()
+                .push(address);
+        }
+        let mut groups: Vec<Vec<&str>> = groups
+            .into_values()
+            .filter(|addresses| addresses.len() > 1)
+            .collect();
+        for addresses in &mut groups {
+            addresses.sort();
+        }
+        groups.sort();
+
+        let mut by_address = HashMap::new();
+        for (cluster, addresses) in groups.iter().enumerate() {
+            for address in addresses {
+                by_address.insert(address.to_string(), cluster);
+            }
+        }
+        Self {
+            by_address,
+            sizes: groups.iter().map(Vec::len).collect(),
+        }
+    }
+
+    pub fn of_address(&self, address: &str) -> Option<usize> {
+        self.by_address.get(address).copied()
+    }
+
+    /// The cluster of the wallet that signed the transaction's inputs.
+    pub fn of_tx(&self, model: &Model, txid: &Txid) -> Option<usize> {
+        let tx = model.tx(txid)?;
+        if tx.coinjoin.is_some() {
+            return None;
+        }
+        tx.inputs
+            .iter()
+            .find_map(|input| self.of_address(&input.address))
+    }
+
+    /// How many addresses are in `cluster`.
+    pub fn size(&self, cluster: usize) -> usize {
+        self.sizes[cluster]
+    }
+
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bitcoin::{dummy_transaction, dummy_txid, Transaction};
+
+    use super::*;
+
+    fn tx(n: u8, inputs: &[&str]) -> Transaction {
+        let mut tx = dummy_transaction(n, &vec![(0xff, 1000); inputs.len()], &[(None, 900)]);
+        for (input, address) in tx.inputs.iter_mut().zip(inputs) {
+            input.address = address.to_string();
+        }
+        tx.outputs[0].address = format!("out{}", n);
+        tx
+    }
+
+    #[test]
+    fn addresses_spent_together() {
+        let mut model = Model::default();
+        for tx in [
+            tx(1, &["d", "b"]),
+            tx(2, &["b", "c"]),
+            tx(3, &["a", "x"]),
+            tx(4, &["e"]),
+        ] {
+            model.add_tx(tx.txid, &tx);
+        }
+        let clusters = Clusters::new(&model);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters.of_address("a"), Some(0));
+        assert_eq!(clusters.of_address("x"), Some(0));
+        for address in ["b", "c", "d"] {
+            assert_eq!(clusters.of_address(address), Some(1));
+        }
+        assert_eq!(clusters.size(1), 3);
+        // Spent alone, or not at all.
+        assert_eq!(clusters.of_address("e"), None);
+        assert_eq!(clusters.of_address("out1"), None);
+
+        assert_eq!(clusters.of_tx(&model, &dummy_txid(2)), Some(1));
+        assert_eq!(clusters.of_tx(&model, &dummy_txid(4)), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! Colors of transactions and coins by the attribute chosen in the view menu.
+
+use std::collections::HashMap;
+
+use egui::Color32;
+
+use crate::{
+    annotations::Annotations,
+    bitcoin::Txid,
+    layout::Resting,
+    style::{self, ColorBy},
+};
+
+use super::{
+    clusters::Clusters,
+    model::{Edge, Model},
+    privacy::Privacy,
+    taint::{self, Taint, Tainted},
+};
+
+/// What's needed to color the graph by [ColorBy], computed once per frame.
+pub struct Coloring {
+    color_by: ColorBy,
+    /// By transaction, for [ColorBy::Component].
+    components: HashMap<Txid, usize>,
+    /// The shortest time a coin rested before it was spent by a transaction,
+    /// for [ColorBy::CoinAge].
+    fastest_in: HashMap<Txid, i64>,
+    /// For [ColorBy::Cluster].
+    clusters: Option<Clusters>,
+    /// For [ColorBy::Privacy].
+    entropy: HashMap<Txid, Option<f64>>,
+    /// Overrides the colors by [ColorBy] while a coin is marked as tainted.
+    tainted: Option<Tainted>,
+}
+
+impl Coloring {
+    pub fn new(color_by: ColorBy, model: &Model, taint: Option<&Taint>, privacy: &Privacy) -> Self {
+        let mut coloring = Self {
+            color_by,
+            components: HashMap::new(),
+            fastest_in: HashMap::new(),
+            clusters: None,
+            entropy: HashMap::new(),
+            tainted: taint.map(|taint| taint.compute(model)),
+        };
+        match color_by {
+            ColorBy::Component => coloring.components = model.components(),
+            ColorBy::Cluster => coloring.clusters = Some(Clusters::new(model)),
+            ColorBy::Privacy => {
+                for (txid, _) in model.txs() {
+                    if let Some(analysis) = privacy.get(txid) {
+                        let bits = analysis.map(|analysis| analysis.entropy());
+                        coloring.entropy.insert(*txid, bits);
+                    }
+                }
+            }
+            ColorBy::CoinAge => {
+                for edge in model.edges() {
+                    let rested = model.seconds_rested(edge);
+                    let fastest = coloring.fastest_in.entry(edge.target).or_insert(rested);
+                    *fastest = (*fastest).min(rested);
+                }
+            }
+            _ => {}
+        }
+        coloring
+    }
+
+    /// The color of the coin on `edge`, or `None` for the default.
+    pub fn coin(&self, model: &Model, edge: &Edge, annotations: &Annotations) -> Option<Color32> {
+        if let Some(tainted) = &self.tainted {
+            return Some(taint::color(tainted.coin((edge.source, edge.source_pos))));
+        }
+        match self.color_by {
+            ColorBy::Annotation => annotations.coin_color((edge.source, edge.source_pos)),
+            ColorBy::CoinAge => Some(Resting::color(model.seconds_rested(edge))),
+            ColorBy::Value => Some(style::value_color(model.edge_input(edge).value)),
+            ColorBy::AddressType => Some(style::address_type_color(
+                model.edge_input(edge).address_type,
+            )),
+            ColorBy::Component => self
+                .components
+                .get(&edge.source)
+                .copied()
+                .map(style::component_color),
+            ColorBy::Cluster => self
+                .clusters
+                .as_ref()?
+                .of_address(&model.edge_input(edge).address)
+                .map(style::component_color),
+            ColorBy::Privacy => None,
+        }
+    }
+
+    /// The tainted share of `coin`, while a coin is marked as tainted.
+    pub fn taint(&self, coin: (Txid, usize)) -> Option<f64> {
+        Some(self.tainted.as_ref()?.coin(coin))
+    }
+
+    /// The tainted share of the value of several coins together.
+    pub fn edges_taint(&self, model: &Model, edges: &[&Edge]) -> Option<f64> {
+        let tainted = self.tainted.as_ref()?;
+        let (value, carried) = edges.iter().fold((0, 0.0), |(value, carried), edge| {
+            let input = model.edge_input(edge).value;
+            let coin = (edge.source, edge.source_pos);
+            (value + input, carried + input as f64 * tainted.coin(coin))
+        });
+        Some(if value == 0 {
+            0.0
+        } else {
+            carried / value as f64
+        })
+    }
+
+    /// The wallet cluster that signed `txid`, how many there are and how many
+    /// addresses it has, when coloring by [ColorBy::Cluster].
+    pub fn cluster(&self, model: &Model, txid: &Txid) -> Option<(usize, usize, usize)> {
+        let clusters = self.clusters.as_ref()?;
+        let cluster = clusters.of_tx(model, txid)?;
+        Some((cluster, clusters.len(), clusters.size(cluster)))
+    }
+
+    /// The color of the transaction `txid`, or `None` for the default.
+    pub fn tx(&self, model: &Model, txid: Txid, annotations: &Annotations) -> Option<Color32> {
+        if let Some(tainted) = &self.tainted {
+            let share = tainted.tx(model, &txid);
+            return (share > 0.0).then(|| taint::color(share));
+        }
+        match self.color_by {
+            ColorBy::Annotation => annotations.tx_color(txid),
+            ColorBy::CoinAge => self.fastest_in.get(&txid).copied().map(Resting::color),
+            ColorBy::Value => model.tx(&txid).map(|tx| style::value_color(tx.value)),
+            ColorBy::AddressType => model.tx(&txid).map(|_| {
+                model
+                    .output_address_type(&txid)
+                    .map_or(style::MIXED, style::address_type_color)
+            }),
+            ColorBy::Component => self
+                .components
+                .get(&txid)
+                .copied()
+                .map(style::component_color),
+            ColorBy::Cluster => self
+                .clusters
+                .as_ref()?
+                .of_tx(model, &txid)
+                .map(style::component_color),
+            ColorBy::Privacy => self.entropy.get(&txid).copied().map(style::entropy_color),
+        }
+    }
+}
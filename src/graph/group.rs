@@ -0,0 +1,317 @@
+//! Transactions drawn as a single node: chains of hops and clusters picked by
+//! the user.
+
+use std::iter;
+
+use egui::{ahash::HashSet, Pos2, Rect, Vec2};
+
+use crate::{bitcoin::Txid, export, style::Style};
+
+use super::{Graph, OutputType, Rects};
+
+/// An input or output of one of the transactions of a group, by index.
+type Io = (Txid, usize);
+
+/// Shorter chains of hops aren't worth collapsing all at once.
+const MIN_CHAIN_HOPS: usize = 3;
+
+/// Transactions drawn as one node in place of the first one while collapsed.
+/// The node takes in the coins that come from outside the group and sends
+/// out the ones that leave it. Fees aren't drawn.
+pub struct Group {
+    /// Clusters are named, chains of hops aren't.
+    pub name: Option<String>,
+    /// The other transactions and, while collapsed, where they were relative
+    /// to the first one.
+    pub rest: Vec<(Txid, Vec2)>,
+    /// Expanded clusters are framed. Chains are gone once expanded.
+    pub expanded: bool,
+}
+
+impl Group {
+    pub fn txids(&self, first: Txid) -> impl Iterator<Item = Txid> + '_ {
+        iter::once(first).chain(self.rest.iter().map(|(txid, _)| *txid))
+    }
+}
+
+impl Graph {
+    /// The transaction that stands in for `txid` on screen, which is the first
+    /// one of its group if it's collapsed.
+    pub(super) fn shown(&self, txid: Txid) -> Txid {
+        self.collapsed.get(&txid).copied().unwrap_or(txid)
+    }
+
+    /// The first transaction of the group that `txid` is part of, collapsed or
+    /// not.
+    fn group_of(&self, txid: Txid) -> Option<Txid> {
+        if self.groups.contains_key(&txid) {
+            return Some(txid);
+        }
+        self.groups
+            .iter()
+            .find(|(_, group)| group.rest.iter().any(|(other, _)| *other == txid))
+            .map(|(first, _)| *first)
+    }
+
+    /// Collapse the given transactions into a new group drawn in place of the
+    /// first one. They leave the groups they were part of.
+    fn add_group(&mut self, txids: Vec<Txid>, name: Option<String>) -> bool {
+        let mut seen = HashSet::default();
+        let txids: Vec<Txid> = txids
+            .into_iter()
+            .filter(|txid| self.nodes.contains_key(txid) && seen.insert(*txid))
+            .collect();
+        if txids.len() < 2 {
+            return false;
+        }
+        for txid in &txids {
+            self.ungroup(*txid);
+        }
+        let group = Group {
+            name,
+            rest: txids[1..].iter().map(|txid| (*txid, Vec2::ZERO)).collect(),
+            expanded: true,
+        };
+        self.groups.insert(txids[0], group);
+        self.set_expanded(txids[0], false);
+        true
+    }
+
+    /// Draw the chain of hops through `txid` as a single node. Returns false
+    /// if there's no chain of at least two hops. See [super::Model::hops_through].
+    pub fn collapse_chain(&mut self, txid: Txid) -> bool {
+        let hops = self.model.hops_through(txid);
+        hops.len() > 1 && self.add_group(hops, None)
+    }
+
+    /// Collapse all chains of at least [MIN_CHAIN_HOPS] hops that aren't
+    /// already. Returns how many were collapsed.
+    pub fn collapse_chains(&mut self) -> usize {
+        let mut collapsed = 0;
+        for hops in self.model.hop_chains(MIN_CHAIN_HOPS) {
+            let done = self.groups.get(&hops[0]).is_some_and(|group| {
+                group.name.is_none() && !group.expanded && group.rest.len() + 1 == hops.len()
+            });
+            if !done && self.collapse_chain(hops[0]) {
+                collapsed += 1;
+            }
+        }
+        collapsed
+    }
+
+    /// Group the transactions into a cluster drawn in place of the first one,
+    /// named after how many clusters there are.
+    pub fn cluster(&mut self, txids: Vec<Txid>) -> bool {
+        let names: HashSet<&str> = self
+            .groups
+            .values()
+            .filter_map(|group| group.name.as_deref())
+            .collect();
+        let name = (1..)
+            .map(|i| format!("Cluster {}", i))
+            .find(|name| !names.contains(name.as_str()))
+            .unwrap();
+        self.add_group(txids, Some(name))
+    }
+
+    /// Collapse or expand the group of `first`. Expanded transactions go back
+    /// where they were relative to the first one, as it may have been moved
+    /// in the meantime.
+    pub fn set_expanded(&mut self, first: Txid, expanded: bool) {
+        let Some(group) = self.groups.get_mut(&first) else {
+            return;
+        };
+        if group.expanded == expanded {
+            return;
+        }
+        group.expanded = expanded;
+        let pos = self.nodes.get(&first).map(|node| node.pos);
+        for (txid, offset) in &mut group.rest {
+            let node = self.nodes.get_mut(txid);
+            if expanded {
+                self.collapsed.remove(txid);
+                if let (Some(node), Some(pos)) = (node, pos) {
+                    node.pos = pos + *offset;
+                    node.velocity = Vec2::ZERO;
+                }
+            } else {
+                self.collapsed.insert(*txid, first);
+                self.selection.remove(txid);
+                if let (Some(node), Some(pos)) = (node, pos) {
+                    *offset = node.pos - pos;
+                }
+            }
+        }
+        if group.name.is_none() && expanded {
+            self.groups.remove(&first);
+        }
+        if self
+            .selected_node
+            .is_some_and(|txid| self.collapsed.contains_key(&txid))
+        {
+            self.selected_node = Some(first);
+        }
+    }
+
+    pub fn export_groups(&self) -> Vec<export::Group> {
+        let mut groups: Vec<export::Group> = self
+            .groups
+            .iter()
+            .map(|(first, group)| export::Group {
+                first: *first,
+                name: group.name.clone(),
+                rest: group.rest.clone(),
+                expanded: group.expanded,
+            })
+            .collect();
+        groups.sort_by_key(|group| group.first);
+        groups
+    }
+
+    /// Go back to the groups of an earlier [Graph::export_groups], after the
+    /// transactions were restored. Groups with transactions that aren't in
+    /// the graph are left out.
+    pub fn restore_groups(&mut self, groups: &[export::Group]) {
+        self.groups.clear();
+        self.collapsed.clear();
+        for group in groups {
+            let mut txids = iter::once(group.first).chain(group.rest.iter().map(|(txid, _)| *txid));
+            if !txids.all(|txid| self.contains(txid)) {
+                continue;
+            }
+            if !group.expanded {
+                for (txid, _) in &group.rest {
+                    self.collapsed.insert(*txid, group.first);
+                    self.selection.remove(txid);
+                }
+            }
+            self.groups.insert(
+                group.first,
+                Group {
+                    name: group.name.clone(),
+                    rest: group.rest.clone(),
+                    expanded: group.expanded,
+                },
+            );
+        }
+        self.selected_node = self.selected_node.map(|txid| self.shown(txid));
+    }
+
+    /// Dissolve the group that `txid` is part of, expanding it first.
+    pub fn ungroup(&mut self, txid: Txid) {
+        if let Some(first) = self.group_of(txid) {
+            self.set_expanded(first, true);
+            self.groups.remove(&first);
+        }
+    }
+
+    /// Dissolve the chains that aren't a run of hops anymore since
+    /// transactions were added. Chains of a workspace that was just opened are
+    /// left alone until all their transactions are loaded.
+    pub(super) fn prune_chains(&mut self) {
+        let broken: Vec<Txid> = self
+            .groups
+            .iter()
+            .filter(|(first, group)| {
+                let chain: Vec<Txid> = group.txids(**first).collect();
+                if chain.iter().any(|txid| !self.nodes.contains_key(txid)) {
+                    return false;
+                }
+                let hops = self.model.hops_through(**first);
+                group.name.is_none() && !hops.windows(chain.len()).any(|hops| hops == chain)
+            })
+            .map(|(first, _)| *first)
+            .collect();
+        for first in broken {
+            self.ungroup(first);
+        }
+    }
+
+    /// The inputs and outputs of the group's transactions that connect it to
+    /// the rest of the graph, in the order they are drawn.
+    pub(super) fn group_ios(&self, first: Txid, group: &Group) -> (Vec<Io>, Vec<Io>) {
+        let txids: HashSet<Txid> = group.txids(first).collect();
+        let mut inputs = vec![];
+        let mut outputs = vec![];
+        for txid in group.txids(first) {
+            let (Some(tx), Some(node)) = (self.model.tx(&txid), self.nodes.get(&txid)) else {
+                continue;
+            };
+            for i in node.input_order() {
+                let funding = tx.inputs.get(i).map(|input| input.funding_txid);
+                if funding.is_some_and(|funding| !txids.contains(&funding)) {
+                    inputs.push((txid, i));
+                }
+            }
+            for o in node.output_order() {
+                let leaves = match tx.outputs.get(o).map(|output| &output.output_type) {
+                    Some(OutputType::Utxo { .. }) => true,
+                    Some(OutputType::Spent { spending_txid, .. }) => !txids.contains(spending_txid),
+                    _ => false,
+                };
+                if leaves {
+                    outputs.push((txid, o));
+                }
+            }
+        }
+        (inputs, outputs)
+    }
+
+    /// A collapsed group is as wide as the coins going in or out, whichever
+    /// is more. They are lined up along its top and bottom edges, as wide as
+    /// they are on their own transactions.
+    pub(super) fn group_rects(
+        &self,
+        rects: &mut Rects,
+        style: &Style,
+        first: Txid,
+        group: &Group,
+        pos: Pos2,
+    ) {
+        let (inputs, outputs) = self.group_ios(first, group);
+        let widths = |ios: Vec<Io>, input: bool| -> Vec<(Io, f32)> {
+            ios.into_iter()
+                .filter_map(|(txid, i)| {
+                    let node = self.nodes.get(&txid)?;
+                    let span = if input {
+                        node.inputs.get(i)?
+                    } else {
+                        node.outputs.get(i)?
+                    };
+                    Some(((txid, i), span.end - span.start))
+                })
+                .collect()
+        };
+        let inputs = widths(inputs, true);
+        let outputs = widths(outputs, false);
+        let total = |ios: &[(Io, f32)]| ios.iter().map(|(_, w)| w).sum::<f32>();
+        let size = total(&inputs).max(total(&outputs)).max(style.tx_width);
+
+        let outer_rect =
+            Rect::from_center_size(pos, Vec2::new(size, style.tx_width + 2.0 * style.io_width));
+        rects.outer.insert(first, outer_rect);
+        rects.inner.insert(
+            first,
+            Rect::from_center_size(pos, Vec2::new(size, style.tx_width)),
+        );
+
+        let mut x = outer_rect.left();
+        for (key, width) in inputs {
+            let min = Pos2::new(x, outer_rect.top());
+            rects.inputs.insert(
+                key,
+                Rect::from_min_size(min, Vec2::new(width, style.io_width)),
+            );
+            x += width;
+        }
+        let mut x = outer_rect.left();
+        for (key, width) in outputs {
+            let min = Pos2::new(x, outer_rect.bottom() - style.io_width);
+            rects.outputs.insert(
+                key,
+                Rect::from_min_size(min, Vec2::new(width, style.io_width)),
+            );
+            x += width;
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Differences between two transactions picked by clicking them, to help
+//! reconstruct timelines on wide graphs.
+
+use egui::{Align2, Key, RichText, Rounding, Shape, Stroke};
+
+use crate::{
+    bitcoin::{NumberFormat, Sats, Txid},
+    style::Style,
+    transform::Transform,
+};
+
+use super::{model::format_relative, Graph, Rects};
+
+#[derive(Default)]
+pub struct Measure {
+    from: Option<Txid>,
+    to: Option<Txid>,
+}
+
+impl Measure {
+    /// The first click picks where to measure from and the second where to.
+    /// The one after that starts over.
+    pub fn pick(&mut self, txid: Txid) {
+        match (self.from, self.to) {
+            (Some(from), None) if from != txid => self.to = Some(txid),
+            _ => {
+                self.from = Some(txid);
+                self.to = None;
+            }
+        }
+    }
+}
+
+/// Signed difference, e.g. `+3` or `-3`.
+fn signed(delta: i64, show: impl Fn(u64) -> String) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{}{}", sign, show(delta.unsigned_abs()))
+}
+
+impl Graph {
+    pub fn is_measuring(&self) -> bool {
+        self.measure.is_some()
+    }
+
+    /// While measuring, clicking transactions picks them instead of selecting
+    /// them.
+    pub fn set_measuring(&mut self, measuring: bool) {
+        self.measure = measuring.then(Measure::default);
+    }
+
+    /// Draw a ruler between the picked transactions with the difference in
+    /// block height, time and value next to it. It follows the pointer until
+    /// the second one is picked. Escape stops measuring.
+    pub(super) fn draw_measure(
+        &mut self,
+        ui: &egui::Ui,
+        transform: &Transform,
+        rects: &Rects,
+        style: &Style,
+        format: NumberFormat,
+    ) {
+        if self.measure.is_some() && ui.input(|i| i.key_pressed(Key::Escape)) {
+            self.measure = None;
+        }
+        let Some(measure) = &self.measure else {
+            return;
+        };
+        let rect = |txid: Option<Txid>| {
+            let rect = rects.inner.get(&txid?)?;
+            Some(transform.rect_to_screen(*rect))
+        };
+        let painter = ui.painter();
+        let stroke = Stroke::new(2.0, style.io_highlight_color);
+        for rect in [rect(measure.from), rect(measure.to)].into_iter().flatten() {
+            painter.rect_stroke(rect.expand(4.0), Rounding::ZERO, stroke);
+        }
+
+        let Some(from) = rect(measure.from) else {
+            return;
+        };
+        let (to, lines) = match (rect(measure.to), measure.from, measure.to) {
+            (Some(to), Some(from), Some(to_txid)) => {
+                let (Some(from), Some(to_tx)) = (self.model.tx(&from), self.model.tx(&to_txid))
+                else {
+                    return;
+                };
+                let blocks = to_tx.block_height as i64 - from.block_height as i64;
+                let value = to_tx.value as i64 - from.value as i64;
+                let lines = vec![
+                    format!("Blocks: {}", signed(blocks, |n| n.to_string())),
+                    format!(
+                        "Time: {}",
+                        format_relative(to_tx.timestamp - from.timestamp)
+                    ),
+                    format!("Value: {}", signed(value, |n| Sats(n).format(format))),
+                ];
+                (to.center(), lines)
+            }
+            _ => {
+                let Some(pointer) = ui.input(|i| i.pointer.hover_pos()) else {
+                    return;
+                };
+                ui.ctx().request_repaint();
+                (pointer, vec!["Click another transaction.".to_string()])
+            }
+        };
+
+        let from = from.center();
+        painter.extend(Shape::dashed_line(&[from, to], stroke, 6.0, 4.0));
+
+        egui::Area::new(ui.id().with("Measure"))
+            .order(egui::Order::Foreground)
+            .pivot(Align2::CENTER_CENTER)
+            .fixed_pos(from.lerp(to, 0.5))
+            .interactable(false)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for line in lines {
+                        ui.label(RichText::new(line).monospace());
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,3948 @@
+mod clusters;
+mod coloring;
+mod group;
+mod measure;
+mod model;
+mod privacy;
+mod reuse;
+mod taint;
+mod tune;
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    iter,
+    sync::mpsc::Sender,
+};
+
+use egui::{
+    ahash::HashSet, text::LayoutJob, Align, Align2, Button, Color32, CursorIcon, FontId, Mesh,
+    Painter, Pos2, Rect, Response, RichText, Rounding, Sense, Shape, Stroke, TextFormat, Vec2,
+};
+use serde::{Deserialize, Serialize};
+
+pub use model::{Model, OutputType, Tx, TxView, MIN_PEEL_CHAIN};
+pub use taint::TaintModel;
+
+use crate::{
+    annotations::Annotations,
+    app::Update,
+    axis::Tick,
+    bezier::{Edge, EdgeCache},
+    bitcoin::{
+        AddressType, AmountComponents, CoinJoinRole, NumberFormat, Sats, SatsDisplay, ScriptType,
+        TaprootSpend, Transaction, Txid,
+    },
+    components::{
+        address_book::AddressBook,
+        annotate_trace::Trace,
+        layout_json::{Link, Node, NodeLink},
+    },
+    export,
+    framerate::{Phase, Stopwatch},
+    instanced::RectBatch,
+    layout::{ForceParams, Layout, Scale, SpawnDirection, Timeline, TimelineSpacing},
+    loading::Loading,
+    platform::inner::absolute_url,
+    report::{GraphData, ReportFlow, ReportTx},
+    settings::{IoClick, Settings, TooltipAnchor},
+    style::{self, ColorBy, Style},
+    transform::Transform,
+    watches::Rule,
+    workspaces::WorkspacesHandle,
+};
+
+use self::{
+    coloring::Coloring,
+    group::Group,
+    measure::Measure,
+    model::{format_duration, format_relative, format_timestamp},
+    privacy::Privacy,
+    taint::taint_button,
+    tune::Tuning,
+};
+
+/// The egui view of a [Model]: where its transactions are, how they move and
+/// how they are drawn.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Graph {
+    model: Model,
+    nodes: HashMap<Txid, DrawableNode>,
+    selected_node: Option<Txid>,
+    /// All selected transactions, including [Self::selected_node]. More than
+    /// one can be selected by shift-clicking.
+    #[serde(skip)]
+    selection: HashSet<Txid>,
+    /// Transactions that can't be removed, to guard against accidental
+    /// clicks. Includes placeholders.
+    #[serde(skip)]
+    protected: HashSet<Txid>,
+    /// Transactions that the layout doesn't move. Includes placeholders.
+    #[serde(skip)]
+    pinned: HashSet<Txid>,
+    /// Selections saved by the user, stored in the workspace.
+    #[serde(skip)]
+    named_selections: Vec<export::Selection>,
+    /// Highlighted coin. The transaction doesn't need to be loaded yet.
+    selected_coin: Option<(Txid, usize)>,
+    /// Coin whose edge, output or input was hovered in the last frame. Shared
+    /// between the edge and node drawing passes so that all three light up.
+    #[serde(skip)]
+    hovered_coin: Option<(Txid, usize)>,
+    #[serde(skip)]
+    edge_cache: EdgeCache,
+    /// Coins whose tooltip is kept open in a window, and where it opened.
+    #[serde(skip)]
+    pinned_coins: Vec<((Txid, usize), Pos2)>,
+    /// Transactions of the workspace that haven't been fetched yet, drawn at
+    /// their saved positions until they are.
+    #[serde(skip)]
+    placeholders: HashMap<Txid, Placeholder>,
+    /// Number of placeholders requested since the last time none were
+    /// loading, for the progress bar.
+    #[serde(skip)]
+    hydration_total: usize,
+    /// Transaction that flashes to be found, e.g. a search hit, and since
+    /// when.
+    #[serde(skip)]
+    flash: Option<(Txid, f64)>,
+    #[serde(skip)]
+    dropped_edges: usize,
+    /// Chains and clusters by their first transaction.
+    #[serde(skip)]
+    groups: HashMap<Txid, Group>,
+    /// The first transaction of the collapsed group of each hidden one.
+    #[serde(skip)]
+    collapsed: HashMap<Txid, Txid>,
+    /// Transactions picked to measure between, while measuring.
+    #[serde(skip)]
+    measure: Option<Measure>,
+    /// Coin whose value is followed downstream, see [taint].
+    #[serde(skip)]
+    taint: Option<(Txid, usize)>,
+    /// How ambiguous each transaction is, see [privacy].
+    #[serde(skip)]
+    privacy: Privacy,
+    /// Whether a transaction was being dragged in the last frame, to settle
+    /// the layout once it's released.
+    #[serde(skip)]
+    was_dragging: bool,
+    /// The last [LayoutMetrics] and the fingerprint of the layout they were
+    /// measured on.
+    #[serde(skip)]
+    metrics: Option<(u64, LayoutMetrics)>,
+    /// A running [Graph::auto_tune].
+    #[serde(skip)]
+    tuning: Option<Tuning>,
+}
+
+/// A transaction that is only known from the workspace file. Its details are
+/// fetched once it comes into view.
+struct Placeholder {
+    pos: Pos2,
+    summary: Option<export::Summary>,
+    hydration: Hydration,
+}
+
+#[derive(PartialEq)]
+enum Hydration {
+    Lazy,
+    Requested,
+    Failed,
+}
+
+/// Geometry of a transaction of the [Model].
+#[derive(Serialize, Deserialize)]
+pub struct DrawableNode {
+    /// Center of tx rect.
+    pos: Pos2,
+    velocity: Vec2,
+    dragged: bool,
+    size: f32,
+    /// Horizontal extent of each input/output, relative to the left edge of
+    /// the node, in blockchain order.
+    inputs: Vec<Span>,
+    outputs: Vec<Span>,
+    /// Display order of the inputs/outputs from left to right, as indices into
+    /// `inputs`/`outputs`, which always stay in blockchain order so that vin
+    /// and vout indices remain valid. Empty means blockchain order.
+    #[serde(default)]
+    input_order: Vec<usize>,
+    #[serde(default)]
+    output_order: Vec<usize>,
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Span {
+    start: f32,
+    end: f32,
+}
+
+impl DrawableNode {
+    fn new(tx: &Tx, pos: Pos2) -> Self {
+        Self {
+            pos,
+            velocity: Vec2::ZERO,
+            dragged: false,
+            size: 0.0,
+            inputs: vec![Span::default(); tx.inputs.len()],
+            outputs: vec![Span::default(); tx.outputs.len()],
+            input_order: vec![],
+            output_order: vec![],
+        }
+    }
+
+    fn input_order(&self) -> Vec<usize> {
+        display_order(&self.input_order, self.inputs.len())
+    }
+
+    fn output_order(&self) -> Vec<usize> {
+        display_order(&self.output_order, self.outputs.len())
+    }
+
+    fn input_x(&self, i: usize) -> f32 {
+        self.pos.x - self.size / 2.0 + (self.inputs[i].start + self.inputs[i].end) / 2.0
+    }
+
+    fn output_x(&self, o: usize) -> f32 {
+        self.pos.x - self.size / 2.0 + (self.outputs[o].start + self.outputs[o].end) / 2.0
+    }
+
+    /// Move input `i` to the display slot under `x`, relative to the left edge
+    /// of the node.
+    fn move_input(&mut self, i: usize, x: f32) {
+        let spans: Vec<(f32, f32)> = self.inputs.iter().map(|i| (i.start, i.end)).collect();
+        self.input_order = move_to_slot(self.input_order(), &spans, i, x, false);
+    }
+
+    /// Move output `o` to the display slot under `x`, relative to the left
+    /// edge of the node. The fees stay last if there are any.
+    fn move_output(&mut self, o: usize, x: f32, has_fees: bool) {
+        let spans: Vec<(f32, f32)> = self.outputs.iter().map(|o| (o.start, o.end)).collect();
+        self.output_order = move_to_slot(self.output_order(), &spans, o, x, has_fees);
+    }
+
+    fn scale(&mut self, tx: &Tx, scale: &Scale) {
+        self.size = scale.size(tx) as f32;
+
+        let input_size: f32 = tx
+            .inputs
+            .iter()
+            .map(|input| scale.apply(input.value) as f32)
+            .sum();
+
+        let output_size: f32 = tx
+            .outputs
+            .iter()
+            .map(|output| scale.apply(output.value) as f32)
+            .sum();
+
+        let mut end = 0.0;
+
+        for i in self.input_order() {
+            let h = scale.apply(tx.inputs[i].value) as f32 * self.size / input_size;
+            let input = &mut self.inputs[i];
+            input.start = end;
+            end += h;
+            input.end = end;
+        }
+
+        end = 0.0;
+
+        for o in self.output_order() {
+            let h = scale.apply(tx.outputs[o].value) as f32 * self.size / output_size;
+            let output = &mut self.outputs[o];
+            output.start = end;
+            end += h;
+            output.end = end;
+        }
+    }
+}
+
+/// Rules for [crate::watches] about an output.
+fn alert_menu(
+    ui: &mut egui::Ui,
+    coin: (Txid, usize),
+    output_type: &OutputType,
+    update_sender: &Sender<Update>,
+) {
+    let (OutputType::Utxo { address, .. } | OutputType::Spent { address, .. }) = output_type else {
+        return;
+    };
+
+    if matches!(output_type, OutputType::Utxo { .. }) && ui.button("When this coin moves").clicked()
+    {
+        let rule = Rule::Spent { coin };
+        update_sender.send(Update::AddWatch { rule }).unwrap();
+        ui.close_menu();
+    }
+
+    ui.horizontal(|ui| {
+        let id = egui::Id::new("Alert threshold");
+        let mut btc: f64 = ui.data_mut(|d| *d.get_temp_mut_or(id, 1.0));
+        let clicked = ui.button("When the address receives more than").clicked();
+        ui.add(
+            egui::DragValue::new(&mut btc)
+                .speed(0.01)
+                .range(0.0..=21e6)
+                .suffix(" BTC"),
+        );
+        ui.data_mut(|d| d.insert_temp(id, btc));
+        if clicked {
+            let rule = Rule::Received {
+                address: address.clone(),
+                threshold: (btc * 1e8).round() as u64,
+            };
+            update_sender.send(Update::AddWatch { rule }).unwrap();
+            ui.close_menu();
+        }
+    });
+}
+
+/// Tooltips of the graph, shown as configured in the settings.
+trait HoverExt {
+    /// Like `on_hover_ui`.
+    fn on_hover_info(self, settings: &Settings, add_contents: impl FnOnce(&mut egui::Ui)) -> Self;
+
+    /// Like `on_hover_ui_at_pointer`.
+    fn on_hover_info_at_pointer(
+        self,
+        settings: &Settings,
+        add_contents: impl FnOnce(&mut egui::Ui),
+    ) -> Self;
+}
+
+impl HoverExt for Response {
+    fn on_hover_info(self, settings: &Settings, add_contents: impl FnOnce(&mut egui::Ui)) -> Self {
+        match tooltip_anchor(&self, settings) {
+            Some(TooltipAnchor::Pointer) => self.on_hover_ui(add_contents),
+            Some(TooltipAnchor::Panel) => {
+                hover_panel(&self, add_contents);
+                self
+            }
+            None => self,
+        }
+    }
+
+    fn on_hover_info_at_pointer(
+        self,
+        settings: &Settings,
+        add_contents: impl FnOnce(&mut egui::Ui),
+    ) -> Self {
+        match tooltip_anchor(&self, settings) {
+            Some(TooltipAnchor::Pointer) => self.on_hover_ui_at_pointer(add_contents),
+            Some(TooltipAnchor::Panel) => {
+                hover_panel(&self, add_contents);
+                self
+            }
+            None => self,
+        }
+    }
+}
+
+/// Tooltip heading of a coin with its label from the workspace, or else the
+/// global label of its address in italics.
+fn coin_heading(
+    title: &str,
+    annotations: &Annotations,
+    address_book: &AddressBook,
+    coin: (Txid, usize),
+    address: &str,
+) -> RichText {
+    let (label, global) = match annotations.coin_label(coin) {
+        Some(label) => (Some(label), false),
+        None => (address_book.label(address).map(str::to_string), true),
+    };
+    match label {
+        Some(label) => {
+            let text = RichText::new(format!("{} [{}]", title, label))
+                .heading()
+                .monospace();
+            if global {
+                text.italics()
+            } else {
+                text
+            }
+        }
+        None => RichText::new(title).heading().monospace(),
+    }
+}
+
+/// Label, value and address of the coin flowing along `edge`, and the
+/// transactions on both ends.
+fn coin_info(
+    ui: &mut egui::Ui,
+    model: &Model,
+    edge: &model::Edge,
+    annotations: &Annotations,
+    address_book: &AddressBook,
+    style: &Style,
+    format: NumberFormat,
+) {
+    let input = model.edge_input(edge);
+    let coin = (edge.source, edge.source_pos);
+    if let Some(label) = annotations.coin_label(coin) {
+        ui.label(RichText::new(format!("[{}]", label)).heading().monospace());
+    } else if let Some(label) = address_book.label(&input.address) {
+        ui.label(
+            RichText::new(format!("[{}]", label))
+                .heading()
+                .monospace()
+                .italics(),
+        );
+    }
+    let mut job = LayoutJob::default();
+    sats_layout(&mut job, &Sats(input.value), style, format);
+    newline(&mut job, &style.font_id());
+    address_layout(&mut job, &input.address, input.address_type, style);
+    ui.label(job);
+
+    ui.separator();
+    egui::Grid::new("Coin ends").num_columns(2).show(ui, |ui| {
+        for (side, txid) in [("From:", edge.source), ("To:", edge.target)] {
+            ui.label(side);
+            ui.vertical(|ui| {
+                match annotations.tx_label(txid) {
+                    Some(label) => ui.label(RichText::new(format!("[{}]", label)).monospace()),
+                    None => ui.label(RichText::new("Unlabeled").weak()),
+                };
+                let value = model.tx(&txid).unwrap().value;
+                ui.add(SatsDisplay::new(Sats(value), style, format));
+            });
+            ui.end_row();
+        }
+    });
+    let blocks = match model.blocks_rested(edge) {
+        0 => "Spent in the same block".to_string(),
+        1 => "Rested 1 block".to_string(),
+        n => format!("Rested {} blocks", n),
+    };
+    let time = format_duration(model.seconds_rested(edge));
+    ui.label(format!("{} ({})", blocks, time));
+}
+
+/// Where to show the tooltip of `response`, if at all.
+fn tooltip_anchor(response: &Response, settings: &Settings) -> Option<TooltipAnchor> {
+    let hidden = settings.alt_hides_tooltips && response.ctx.input(|i| i.modifiers.alt);
+    (!hidden).then_some(settings.tooltip_anchor)
+}
+
+/// Show the tooltip in the bottom right corner of the graph, right away since
+/// it isn't in the way there.
+fn hover_panel(response: &Response, add_contents: impl FnOnce(&mut egui::Ui)) {
+    if !response.hovered() || response.dragged() {
+        return;
+    }
+    let corner = response.ctx.available_rect().right_bottom() - Vec2::splat(8.0);
+    egui::Area::new(egui::Id::new("Hover panel"))
+        .order(egui::Order::Tooltip)
+        .pivot(Align2::RIGHT_BOTTOM)
+        .fixed_pos(corner)
+        .interactable(false)
+        .show(&response.ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, add_contents);
+        });
+}
+
+/// What a click on the input or output of `coin` does. `neighbor` is the
+/// transaction on the other side and where to put it, `remove` whether the
+/// command key is held.
+fn io_click_update(
+    click: IoClick,
+    remove: bool,
+    coin: (Txid, usize),
+    neighbor: Option<(Txid, Pos2)>,
+    loaded: bool,
+    protected: bool,
+) -> Option<Update> {
+    match (neighbor, click) {
+        (Some((txid, _)), _) if remove => {
+            (loaded && !protected).then_some(Update::RemoveTx { txid })
+        }
+        (_, IoClick::SelectCoin) => Some(Update::SelectCoin { coin }),
+        (Some((txid, _)), IoClick::Toggle) if loaded && !protected => {
+            Some(Update::RemoveTx { txid })
+        }
+        (Some((txid, pos)), _) => Some(Update::LoadOrSelectTx {
+            txid,
+            pos: Some(pos),
+        }),
+        (None, _) => None,
+    }
+}
+
+/// Load, select or remove the `side` ("Funding"/"Spending") transaction of a
+/// coin, independent of what a click does.
+fn neighbor_menu(
+    ui: &mut egui::Ui,
+    side: &str,
+    (txid, pos): (Txid, Pos2),
+    loaded: bool,
+    protected: bool,
+    update_sender: &Sender<Update>,
+) {
+    let label = if loaded { "Select" } else { "Load" };
+    if ui
+        .button(format!("{} {} Transaction", label, side))
+        .clicked()
+    {
+        update_sender
+            .send(Update::LoadOrSelectTx {
+                txid,
+                pos: Some(pos),
+            })
+            .unwrap();
+        ui.close_menu();
+    }
+    if loaded
+        && ui
+            .add_enabled(
+                !protected,
+                Button::new(format!("Remove {} Transaction", side)),
+            )
+            .on_hover_text("Or command-click.")
+            .clicked()
+    {
+        update_sender.send(Update::RemoveTx { txid }).unwrap();
+        ui.close_menu();
+    }
+}
+
+impl Graph {
+    /// Placeholders are included so that a workspace doesn't lose
+    /// transactions that haven't been fetched.
+    pub fn export(&self) -> Vec<export::Transaction> {
+        self.nodes
+            .iter()
+            .map(|(txid, node)| self.export_tx(*txid, node))
+            .chain(self.placeholders.iter().map(|(txid, placeholder)| {
+                let mut tx = export::Transaction::new(*txid, placeholder.pos, placeholder.summary);
+                tx.protected = self.protected.contains(txid);
+                tx.pinned = self.pinned.contains(txid);
+                tx
+            }))
+            .collect()
+    }
+
+    fn export_tx(&self, txid: Txid, node: &DrawableNode) -> export::Transaction {
+        let summary = self.model.tx(&txid).map(Tx::summary);
+        let mut tx = export::Transaction::new(txid, node.pos, summary);
+        tx.protected = self.protected.contains(&txid);
+        tx.pinned = self.pinned.contains(&txid);
+        tx
+    }
+
+    pub fn add_placeholder(&mut self, txid: Txid, pos: Pos2, summary: Option<export::Summary>) {
+        if !self.nodes.contains_key(&txid) {
+            self.placeholders.insert(
+                txid,
+                Placeholder {
+                    pos,
+                    summary,
+                    hydration: Hydration::Lazy,
+                },
+            );
+        }
+    }
+
+    /// Replace the placeholder of `txid` with the transaction. Does nothing if
+    /// there is no such placeholder, e.g. because another workspace has been
+    /// opened in the meantime.
+    pub fn fill_placeholder(&mut self, txid: Txid, tx: Transaction) {
+        if let Some(placeholder) = self.placeholders.remove(&txid) {
+            self.add_tx(txid, tx, placeholder.pos);
+        }
+    }
+
+    /// Keep the placeholder so it's still part of the workspace, but don't
+    /// request it again.
+    pub fn placeholder_failed(&mut self, txid: Txid) {
+        if let Some(placeholder) = self.placeholders.get_mut(&txid) {
+            placeholder.hydration = Hydration::Failed;
+        }
+    }
+
+    /// How many of the requested placeholders have been loaded, out of how
+    /// many, while any are still loading.
+    pub fn hydration_progress(&mut self) -> Option<(usize, usize)> {
+        let pending = self
+            .placeholders
+            .values()
+            .filter(|p| p.hydration == Hydration::Requested)
+            .count();
+        if pending == 0 {
+            self.hydration_total = 0;
+            return None;
+        }
+        Some((self.hydration_total - pending, self.hydration_total))
+    }
+
+    pub fn export_subset(&self, txids: &HashSet<Txid>) -> Vec<export::Transaction> {
+        self.nodes
+            .iter()
+            .filter(|(txid, _)| txids.contains(txid))
+            .map(|(txid, node)| self.export_tx(*txid, node))
+            .collect()
+    }
+
+    /// Whether `txid` is in the graph, loaded or not.
+    pub fn contains(&self, txid: Txid) -> bool {
+        self.nodes.contains_key(&txid) || self.placeholders.contains_key(&txid)
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    /// Transactions with unspent outputs, which may have been spent since
+    /// they were fetched.
+    pub fn txids_with_utxos(&self) -> Vec<Txid> {
+        self.model
+            .txs()
+            .map(|(txid, _)| *txid)
+            .filter(|txid| self.has_utxos(*txid))
+            .collect()
+    }
+
+    pub fn has_utxos(&self, txid: Txid) -> bool {
+        self.model.tx(&txid).is_some_and(|tx| {
+            tx.outputs
+                .iter()
+                .any(|output| matches!(output.output_type, OutputType::Utxo { .. }))
+        })
+    }
+
+    /// See [Model::set_spent].
+    pub fn set_spent(&mut self, coin: (Txid, usize), spending_txid: Txid) -> bool {
+        self.model.set_spent(coin, spending_txid)
+    }
+
+    /// Hidden transactions are where their collapsed group is.
+    pub fn get_tx_pos(&self, txid: Txid) -> Option<Pos2> {
+        self.nodes.get(&self.shown(txid)).map(|node| node.pos)
+    }
+
+    /// See [Model::check_integrity].
+    pub fn check_integrity(&self) -> Vec<String> {
+        self.model.check_integrity()
+    }
+
+    /// See [Model::tx_views].
+    pub fn tx_views(&self) -> Vec<TxView<'_>> {
+        self.model.tx_views()
+    }
+
+    /// Transactions and the value flowing along each edge, sorted by
+    /// block height, for [crate::report].
+    pub fn report_data(&self) -> GraphData {
+        let mut txs: Vec<_> = self
+            .model
+            .txs()
+            .map(|(txid, tx)| ReportTx {
+                txid: *txid,
+                pos: self.nodes[txid].pos,
+                width: self.nodes[txid].size,
+                value: tx.value,
+                fees: tx.fees(),
+                timestamp: format_timestamp(tx.timestamp),
+                block_height: tx.block_height,
+            })
+            .collect();
+        txs.sort_by_key(|tx| (tx.block_height, tx.txid));
+
+        let flows = self
+            .model
+            .edges()
+            .iter()
+            .map(|edge| ReportFlow {
+                source: edge.source,
+                target: edge.target,
+                value: self.model.edge_input(edge).value,
+            })
+            .collect();
+
+        GraphData { txs, flows }
+    }
+
+    /// The loaded transactions with their rects and the coins between them,
+    /// for [crate::components::layout_json].
+    pub fn node_link(&self, style: &Style, annotations: &Annotations) -> NodeLink {
+        let mut nodes: Vec<Node> = self
+            .model
+            .txs()
+            .map(|(txid, tx)| Node {
+                id: *txid,
+                x: self.nodes[txid].pos.x,
+                y: self.nodes[txid].pos.y,
+                width: self.nodes[txid].size,
+                height: style.tx_width,
+                label: annotations.tx_label(*txid),
+                value: tx.value,
+                block_height: tx.block_height,
+            })
+            .collect();
+        nodes.sort_by_key(|node| (node.block_height, node.id));
+
+        let links = self
+            .model
+            .edges()
+            .iter()
+            .map(|edge| Link {
+                source: edge.source,
+                target: edge.target,
+                vout: edge.source_pos,
+                vin: edge.target_pos,
+                value: self.model.edge_input(edge).value,
+            })
+            .collect();
+
+        NodeLink { nodes, links }
+    }
+
+    /// Move transactions, loaded or not, to the given positions. Returns how
+    /// many of them are in the graph.
+    pub fn move_txs(&mut self, positions: impl IntoIterator<Item = (Txid, Pos2)>) -> usize {
+        let mut moved = 0;
+        for (txid, pos) in positions {
+            if let Some(node) = self.nodes.get_mut(&txid) {
+                node.pos = pos;
+                node.velocity = Vec2::ZERO;
+            } else if let Some(placeholder) = self.placeholders.get_mut(&txid) {
+                placeholder.pos = pos;
+            } else {
+                continue;
+            }
+            moved += 1;
+        }
+        moved
+    }
+
+    /// One tick per transaction, showing its block height and date at its
+    /// screen position, vertical or `horizontal`.
+    pub fn time_ticks(
+        &self,
+        transform: &Transform,
+        settings: &Settings,
+        horizontal: bool,
+    ) -> Vec<Tick> {
+        self.model
+            .txs()
+            .filter_map(|(txid, tx)| Some((transform.pos_to_screen(self.nodes.get(txid)?.pos), tx)))
+            .map(|(pos, tx)| Tick {
+                pos: if horizontal { pos.x } else { pos.y },
+                label: format!(
+                    "#{} {}",
+                    tx.block_height,
+                    settings.format_date(tx.timestamp)
+                ),
+            })
+            .collect()
+    }
+
+    pub fn num_transactions(&self) -> usize {
+        self.model.len()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.model.edges().len()
+    }
+
+    pub fn select(&mut self, txid: Txid) {
+        self.selected_node = Some(txid);
+        self.selection = HashSet::from_iter([txid]);
+        if self
+            .selected_coin
+            .is_some_and(|(coin_txid, _)| coin_txid != txid)
+        {
+            self.selected_coin = None;
+        }
+    }
+
+    /// Replace the selection with the given transactions, as far as they are
+    /// loaded.
+    pub fn select_all(&mut self, txids: impl IntoIterator<Item = Txid>) {
+        self.selection = txids
+            .into_iter()
+            .filter(|txid| self.nodes.contains_key(txid))
+            .collect();
+        self.selected_node = self.selection.iter().next().copied();
+    }
+
+    /// Select the transactions whose center is inside `rect`, in graph
+    /// coordinates. With `extend`, they are added to the selection.
+    pub fn select_in(&mut self, rect: Rect, extend: bool) {
+        let txids: Vec<Txid> = self
+            .nodes
+            .iter()
+            .filter(|(txid, node)| rect.contains(node.pos) && !self.collapsed.contains_key(txid))
+            .map(|(txid, _)| *txid)
+            .collect();
+        if extend {
+            self.extend_selection(txids);
+        } else {
+            self.select_all(txids);
+        }
+    }
+
+    /// Add the given transactions to the selection, as far as they are loaded.
+    pub fn extend_selection(&mut self, txids: impl IntoIterator<Item = Txid>) {
+        let txids: Vec<Txid> = txids
+            .into_iter()
+            .filter(|txid| self.nodes.contains_key(txid))
+            .collect();
+        self.selection.extend(txids);
+        if self.selected_node.is_none() {
+            self.selected_node = self.selection.iter().next().copied();
+        }
+    }
+
+    pub fn named_selections(&self) -> &[export::Selection] {
+        &self.named_selections
+    }
+
+    pub fn set_named_selections(&mut self, selections: Vec<export::Selection>) {
+        self.named_selections = selections;
+    }
+
+    /// Save the current selection under `name`, replacing an earlier one of
+    /// the same name.
+    pub fn save_selection(&mut self, name: String) {
+        let mut txids: Vec<Txid> = self.selection.iter().copied().collect();
+        txids.sort();
+        self.named_selections.retain(|s| s.name != name);
+        self.named_selections
+            .push(export::Selection { name, txids });
+    }
+
+    pub fn remove_named_selection(&mut self, name: &str) {
+        self.named_selections.retain(|s| s.name != name);
+    }
+
+    pub fn select_coin(&mut self, coin: (Txid, usize)) {
+        self.selected_coin = Some(coin);
+    }
+
+    pub fn flash(&mut self, txid: Txid, time: f64) {
+        self.flash = Some((txid, time));
+    }
+
+    /// Add `txid` to the selection, or remove it if it's already selected.
+    pub fn toggle_selected(&mut self, txid: Txid) {
+        if !self.selection.remove(&txid) {
+            self.selection.insert(txid);
+            self.selected_node = Some(txid);
+        } else if self.selected_node == Some(txid) {
+            self.selected_node = self.selection.iter().next().copied();
+        }
+    }
+
+    pub fn selected(&self) -> Option<Txid> {
+        self.selected_node
+    }
+
+    pub fn selection(&self) -> &HashSet<Txid> {
+        &self.selection
+    }
+
+    /// See [Model::connected_component].
+    pub fn connected_component(&self, txid: Txid) -> HashSet<Txid> {
+        self.model.connected_component(txid)
+    }
+
+    /// See [Model::coins].
+    pub fn coins(&self, txids: &HashSet<Txid>) -> HashSet<(Txid, usize)> {
+        self.model.coins(txids)
+    }
+
+    pub fn selected_coin(&self) -> Option<(Txid, usize)> {
+        self.selected_coin
+    }
+
+    /// The transactions in focus, unless focus mode is off or nothing is
+    /// selected: the ones within a few hops of the funding and spending
+    /// transactions of the selected coin, or else of the selected
+    /// transaction.
+    fn focus(&self, layout: &Layout) -> Option<HashSet<Txid>> {
+        if !layout.focus.active {
+            return None;
+        }
+        let seeds: Vec<Txid> = match self.selected_coin {
+            Some(coin) => iter::once(coin.0)
+                .chain(
+                    self.model
+                        .edges()
+                        .iter()
+                        .filter(|edge| (edge.source, edge.source_pos) == coin)
+                        .map(|edge| edge.target),
+                )
+                .collect(),
+            None => self.selected_node.into_iter().collect(),
+        };
+        let focus = self.model.within_hops(seeds, layout.focus.hops);
+        (!focus.is_empty()).then_some(focus)
+    }
+
+    pub fn is_protected(&self, txid: Txid) -> bool {
+        self.protected.contains(&txid)
+    }
+
+    pub fn set_protected(&mut self, txids: impl IntoIterator<Item = Txid>, protected: bool) {
+        for txid in txids {
+            if protected {
+                self.protected.insert(txid);
+            } else {
+                self.protected.remove(&txid);
+            }
+        }
+    }
+
+    pub fn set_pinned(&mut self, txids: impl IntoIterator<Item = Txid>, pinned: bool) {
+        for txid in txids {
+            if pinned {
+                self.pinned.insert(txid);
+            } else {
+                self.pinned.remove(&txid);
+            }
+        }
+    }
+
+    /// Does nothing and returns false if the transaction is protected.
+    pub fn remove_tx(&mut self, txid: Txid) -> bool {
+        if self.protected.contains(&txid) {
+            return false;
+        }
+        self.ungroup(txid);
+        self.pinned.remove(&txid);
+        self.placeholders.remove(&txid);
+        self.nodes.remove(&txid);
+        self.model.remove_tx(txid);
+        true
+    }
+
+    /// Inputs that spend outputs which don't exist aren't connected, see
+    /// [Model::add_tx], and are counted for [Self::take_dropped_edges].
+    pub fn add_tx(&mut self, txid: Txid, tx: Transaction, pos: Pos2) {
+        for problem in self.model.add_tx(txid, &tx) {
+            log::warn!("Dropped an edge: {}", problem);
+            self.dropped_edges += 1;
+        }
+        let Some(tx) = self.model.tx(&txid) else {
+            return;
+        };
+        self.nodes.insert(txid, DrawableNode::new(tx, pos));
+        self.prune_chains();
+    }
+
+    /// How many edges were dropped because of inconsistent data since the
+    /// last call.
+    pub fn take_dropped_edges(&mut self) -> usize {
+        std::mem::take(&mut self.dropped_edges)
+    }
+
+    /// Go back to the transactions of an earlier [Graph::export], including
+    /// their positions and flags. Transactions that are no longer in the graph
+    /// come back as placeholders.
+    pub fn restore(&mut self, txs: &[export::Transaction]) {
+        let keep: HashSet<Txid> = txs.iter().map(|tx| tx.txid).collect();
+        let current: Vec<Txid> = self
+            .nodes
+            .keys()
+            .chain(self.placeholders.keys())
+            .copied()
+            .collect();
+        for txid in current {
+            if !keep.contains(&txid) {
+                self.protected.remove(&txid);
+                self.remove_tx(txid);
+            }
+        }
+
+        for tx in txs {
+            if let Some(node) = self.nodes.get_mut(&tx.txid) {
+                node.pos = tx.position;
+                node.velocity = Vec2::ZERO;
+            } else if let Some(placeholder) = self.placeholders.get_mut(&tx.txid) {
+                placeholder.pos = tx.position;
+            } else {
+                self.add_placeholder(tx.txid, tx.position, tx.summary);
+            }
+            self.set_protected([tx.txid], tx.protected);
+            self.set_pinned([tx.txid], tx.pinned);
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.nodes.values().any(|node| node.dragged)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        ui: &egui::Ui,
+        transform: &Transform,
+        update_sender: Sender<Update>,
+        layout: &Layout,
+        settings: &Settings,
+        annotations: &mut Annotations,
+        address_book: &mut AddressBook,
+    ) {
+        let style = style::get(ui);
+        // Laying out text is expensive, so it's skipped when it's too small
+        // to read anyway.
+        let text_style = style.zoomed_text(transform.scale());
+        let io_click = settings.io_click;
+        let expand_depth = settings.expand_depth;
+
+        let clip_rect = ui.clip_rect();
+
+        for (txid, node) in &mut self.nodes {
+            if let Some(tx) = self.model.tx(txid) {
+                node.scale(tx, &layout.scale);
+            }
+        }
+
+        let mut stopwatch = Stopwatch::start();
+
+        // PREPARE RECTS //
+
+        let rects = self.rects(&style);
+        let Rects {
+            inputs: input_rects,
+            outputs: output_rects,
+            inner: inner_rects,
+            outer: outer_rects,
+        } = &rects;
+
+        stopwatch.lap(Phase::Rects);
+
+        let hovered_coin = self.hovered_coin;
+        let mut new_hovered_coin = None;
+        let conflicts = self.model.conflicts();
+        let taint = self.taint(layout);
+        if self.privacy.update(&self.model, settings.privacy_budget) {
+            ui.ctx().request_repaint();
+        }
+        let coloring = Coloring::new(layout.color_by, &self.model, taint.as_ref(), &self.privacy);
+        let mut mark_taint = None;
+        let focus = self.focus(layout);
+        let in_focus = |txid: &Txid| focus.as_ref().map_or(true, |focus| focus.contains(txid));
+
+        // Dim everything that isn't linked to the hovered coin.
+        let io_fill = |coin: (Txid, usize), color: Color32| {
+            if hovered_coin.is_some_and(|hovered| hovered != coin) {
+                color.gamma_multiply(0.25)
+            } else {
+                color.gamma_multiply(0.4)
+            }
+        };
+
+        // Calculate edge multiplicities to deal with transactions sharing
+        // multiple inputs/outputs.
+        let mut edge_multiplicities: HashMap<(Txid, Txid), usize> = HashMap::new();
+        for edge in self.model.edges() {
+            let key = (edge.source, edge.target);
+            *edge_multiplicities.entry(key).or_insert(0) += 1;
+        }
+
+        // DRAW EDGES //
+
+        let mut merged_edges: HashMap<(Txid, Txid), Vec<&model::Edge>> = HashMap::new();
+        // Amounts to write on edges, by where on the screen.
+        let mut edge_labels: Vec<(Pos2, u64)> = vec![];
+
+        for edge in self.model.edges() {
+            let (Some(from_rect), Some(to_rect)) = (
+                output_rects.get(&(edge.source, edge.source_pos)),
+                input_rects.get(&(edge.target, edge.target_pos)),
+            ) else {
+                continue;
+            };
+
+            let key = (edge.source, edge.target);
+            if layout.merge_edges && edge_multiplicities[&key] > 1 {
+                merged_edges.entry(key).or_default().push(edge);
+                continue;
+            }
+
+            let bounding_rect = transform.rect_to_screen(from_rect.union(*to_rect));
+            if !clip_rect.intersects(bounding_rect) {
+                continue;
+            }
+
+            let coin = (edge.source, edge.source_pos);
+            let rested = self.model.seconds_rested(edge);
+            let mut color = coloring
+                .coin(&self.model, edge, annotations)
+                .unwrap_or(Color32::GOLD);
+            if conflicts.contains_key(&coin) {
+                color = style.conflict;
+            }
+            if layout.resting.hides(rested) {
+                color = color.gamma_multiply(0.15);
+            }
+            if hovered_coin.is_some_and(|hovered| hovered != coin) {
+                color = color.gamma_multiply(0.6);
+            }
+            if !in_focus(&edge.source) || !in_focus(&edge.target) {
+                color = color.gamma_multiply(DIMMED);
+            }
+
+            let flow = Edge {
+                from: from_rect.left_bottom(),
+                from_width: from_rect.width(),
+                to: to_rect.left_top(),
+                to_width: to_rect.width(),
+            };
+            if layout.edge_labels {
+                let value = self.model.edge_input(edge).value;
+                edge_labels.push((transform.pos_to_screen(flow.center()), value));
+            }
+
+            let response = flow
+                .draw(
+                    ui,
+                    &mut self.edge_cache,
+                    color,
+                    layout.show_arrows,
+                    hovered_coin == Some(coin),
+                    transform,
+                    &coin,
+                )
+                .on_hover_info_at_pointer(settings, |ui| {
+                    coin_info(
+                        ui,
+                        &self.model,
+                        edge,
+                        annotations,
+                        address_book,
+                        &style,
+                        settings.number_format,
+                    );
+                    if let Some(share) = coloring.taint(coin) {
+                        ui.label(taint::describe(share));
+                    }
+                });
+            response.context_menu(|ui| {
+                annotations.coin_menu(coin, ui);
+                ui.separator();
+                if ui.button("Copy Outpoint").clicked() {
+                    ui.output_mut(|o| o.copied_text = format!("{}:{}", coin.0, coin.1));
+                    ui.close_menu();
+                }
+                for (label, txid) in [
+                    ("Go to Funding Transaction", edge.source),
+                    ("Go to Spending Transaction", edge.target),
+                ] {
+                    if ui.button(label).clicked() {
+                        update_sender
+                            .send(Update::LoadOrSelectTx { txid, pos: None })
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                }
+                if ui
+                    .button("Trace Forward")
+                    .on_hover_text("Select the loaded transactions this coin flowed into.")
+                    .clicked()
+                {
+                    let txids = self.model.descendants(edge.target).into_iter().collect();
+                    update_sender.send(Update::SelectTxs { txids }).unwrap();
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Annotate Trace...")
+                    .on_hover_text("Label or color everything this coin flowed into.")
+                    .clicked()
+                {
+                    let txids = self.model.descendants(edge.target);
+                    let mut coins = vec![coin];
+                    coins.extend(self.model.coins_between(&txids));
+                    let trace = Trace {
+                        txids: txids.into_iter().collect(),
+                        coins,
+                    };
+                    update_sender.send(Update::AnnotateTrace { trace }).unwrap();
+                    ui.close_menu();
+                }
+                if let Some(source) = taint_button(ui, coin, self.taint) {
+                    mark_taint = Some(source);
+                }
+                if ui.button("Pin Tooltip").clicked() {
+                    let pos = ui.ctx().pointer_latest_pos().unwrap_or_default();
+                    if !self.pinned_coins.iter().any(|(c, _)| *c == coin) {
+                        self.pinned_coins.push((coin, pos));
+                    }
+                    ui.close_menu();
+                }
+            });
+
+            if response.hovered() {
+                new_hovered_coin = Some(coin);
+            }
+
+            if response.clicked {
+                ui.output_mut(|o| o.copied_text = self.model.edge_input(edge).address.clone());
+            }
+        }
+
+        for ((source, target), edges) in &merged_edges {
+            let from_rects: Vec<Rect> = edges
+                .iter()
+                .map(|edge| output_rects[&(edge.source, edge.source_pos)])
+                .collect();
+            let to_rects: Vec<Rect> = edges
+                .iter()
+                .map(|edge| input_rects[&(edge.target, edge.target_pos)])
+                .collect();
+
+            let bounding_rect = from_rects
+                .iter()
+                .chain(to_rects.iter())
+                .fold(Rect::NOTHING, |acc, rect| acc.union(*rect));
+            if !clip_rect.intersects(transform.rect_to_screen(bounding_rect)) {
+                continue;
+            }
+
+            let flow = Edge {
+                from: Pos2::new(
+                    from_rects
+                        .iter()
+                        .map(|r| r.left())
+                        .fold(f32::INFINITY, f32::min),
+                    from_rects[0].bottom(),
+                ),
+                from_width: from_rects.iter().map(|r| r.width()).sum(),
+                to: Pos2::new(
+                    to_rects
+                        .iter()
+                        .map(|r| r.left())
+                        .fold(f32::INFINITY, f32::min),
+                    to_rects[0].top(),
+                ),
+                to_width: to_rects.iter().map(|r| r.width()).sum(),
+            };
+
+            let value: u64 = edges
+                .iter()
+                .map(|edge| self.model.edge_input(edge).value)
+                .sum();
+
+            // Parallel edges connect the same transactions and rested equally
+            // long.
+            let rested = self.model.seconds_rested(edges[0]);
+            let taint = coloring.edges_taint(&self.model, edges);
+            let mut color = match (taint, layout.color_by) {
+                (Some(share), _) => taint::color(share),
+                // The coins may be annotated differently.
+                (None, ColorBy::Annotation) => Color32::GOLD,
+                (None, ColorBy::Value) => style::value_color(value),
+                _ => coloring
+                    .coin(&self.model, edges[0], annotations)
+                    .unwrap_or(Color32::GOLD),
+            };
+            if edges
+                .iter()
+                .any(|edge| conflicts.contains_key(&(edge.source, edge.source_pos)))
+            {
+                color = style.conflict;
+            }
+            if layout.resting.hides(rested) {
+                color = color.gamma_multiply(0.15);
+            }
+            if hovered_coin.is_some() {
+                color = color.gamma_multiply(0.6);
+            }
+            if !in_focus(source) || !in_focus(target) {
+                color = color.gamma_multiply(DIMMED);
+            }
+
+            flow.draw(
+                ui,
+                &mut self.edge_cache,
+                color,
+                layout.show_arrows,
+                false,
+                transform,
+                &(*source, edges[0].source_pos),
+            )
+            .on_hover_info_at_pointer(settings, |ui| {
+                ui.label(
+                    RichText::new(format!("{} Coins", edges.len()))
+                        .heading()
+                        .monospace(),
+                );
+                ui.add(SatsDisplay::new(
+                    Sats(value),
+                    &style,
+                    settings.number_format,
+                ));
+                if let Some(share) = taint {
+                    ui.label(taint::describe(share));
+                }
+            });
+
+            let center = transform.pos_to_screen(flow.center());
+            let count = ui.painter().text(
+                center,
+                Align2::CENTER_CENTER,
+                format!("×{}", edges.len()),
+                style.font_id(),
+                style.black_text_color(),
+            );
+            if layout.edge_labels {
+                // Below the count.
+                let below = Vec2::new(0.0, count.height());
+                edge_labels.push((center + below, value));
+            }
+        }
+
+        if let Some(text_style) = &text_style {
+            draw_edge_labels(
+                ui.painter(),
+                edge_labels,
+                text_style,
+                settings.number_format,
+            );
+        }
+
+        self.edge_cache.sweep();
+
+        let legend = match &taint {
+            Some(taint) => Some((format!("Taint ({})", taint.model.name()), taint::legend())),
+            None => layout
+                .color_by
+                .legend()
+                .map(|(title, entries)| (title.to_string(), entries)),
+        };
+        if let Some((title, entries)) = legend {
+            egui::Area::new(ui.id().with("Color legend"))
+                .order(egui::Order::Foreground)
+                .pivot(Align2::RIGHT_TOP)
+                .fixed_pos(clip_rect.right_top() + Vec2::new(-8.0, 8.0))
+                .interactable(false)
+                .show(ui.ctx(), |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.label(RichText::new(title).strong());
+                        for (color, label) in entries {
+                            ui.horizontal(|ui| {
+                                ui.label(RichText::new("■").color(color));
+                                ui.label(label);
+                            });
+                        }
+                    });
+                });
+        }
+
+        // Pins go away with their edge.
+        self.pinned_coins.retain(|(coin, pos)| {
+            let Some(edge) = self
+                .model
+                .edges()
+                .iter()
+                .find(|e| (e.source, e.source_pos) == *coin)
+            else {
+                return false;
+            };
+            let mut open = true;
+            egui::Window::new("Coin")
+                .id(ui.id().with("pinned").with(coin))
+                .default_pos(*pos)
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut open)
+                .show(ui.ctx(), |ui| {
+                    let mut job = LayoutJob::default();
+                    txid_layout(&mut job, &coin.0, &style);
+                    let format = TextFormat {
+                        font_id: style.font_id(),
+                        color: style.black_text_color(),
+                        ..Default::default()
+                    };
+                    job.append(&format!(":{}", coin.1), 0.0, format);
+                    ui.label(job);
+                    coin_info(
+                        ui,
+                        &self.model,
+                        edge,
+                        annotations,
+                        address_book,
+                        &style,
+                        settings.number_format,
+                    );
+                });
+            open
+        });
+
+        stopwatch.lap(Phase::Edges);
+
+        // DRAW PLACEHOLDERS //
+
+        let mut hydrate = vec![];
+
+        for (txid, placeholder) in &mut self.placeholders {
+            // Hidden in a collapsed group, which is drawn once they arrive.
+            if self.collapsed.contains_key(txid) {
+                if placeholder.hydration == Hydration::Lazy {
+                    placeholder.hydration = Hydration::Requested;
+                    hydrate.push(*txid);
+                }
+                continue;
+            }
+            let width = match placeholder.summary {
+                Some(summary) => layout.scale.estimate(summary.value) as f32,
+                None => style.tx_width + 2.0 * style.io_width,
+            };
+            let rect = transform.rect_to_screen(Rect::from_center_size(
+                placeholder.pos,
+                Vec2::new(width, style.tx_width + 2.0 * style.io_width),
+            ));
+            if !clip_rect.intersects(rect) {
+                continue;
+            }
+
+            if placeholder.hydration == Hydration::Lazy {
+                placeholder.hydration = Hydration::Requested;
+                hydrate.push(*txid);
+            }
+
+            let painter = ui.painter();
+            painter.rect_filled(rect, Rounding::ZERO, style.tx_bg.gamma_multiply(0.1));
+            if let (Some(summary), Some(text_style)) = (placeholder.summary, &text_style) {
+                let tx_painter = painter.with_clip_rect(rect);
+                tx_painter.galley(
+                    rect.left_top() + Vec2::new(2.0, style.io_width + 2.0),
+                    tx_painter.layout_job(tx_content(
+                        txid,
+                        &annotations.tx_label(*txid),
+                        &settings.format_time(summary.timestamp, true),
+                        &Sats(summary.value),
+                        settings.number_format,
+                        text_style,
+                    )),
+                    Color32::TRANSPARENT,
+                );
+            }
+            if placeholder.hydration == Hydration::Requested {
+                rect_striped(ui, rect, style.black_text_color().gamma_multiply(0.1));
+                ui.ctx().request_repaint();
+            }
+            painter.rect_stroke(
+                rect,
+                Rounding::ZERO,
+                if placeholder.hydration == Hydration::Failed {
+                    Stroke::new(style.tx_stroke_width, style.warn)
+                } else {
+                    style.tx_stroke()
+                },
+            );
+        }
+
+        if !hydrate.is_empty() {
+            self.hydration_total += hydrate.len();
+            update_sender
+                .send(Update::Hydrate { txids: hydrate })
+                .unwrap();
+        }
+
+        // DRAW NODES //
+
+        let painter = ui.painter();
+
+        // With GPU rendering, all fills are collected and painted by a single
+        // callback that sits below the strokes and text of the nodes.
+        let mut batch = if layout.gpu_rendering {
+            RectBatch::new(ui.ctx())
+        } else {
+            None
+        };
+        // Expanded clusters are framed, with their name on top to collapse
+        // them again.
+        let mut collapse_group = None;
+        for (first, group) in &self.groups {
+            let Some(name) = group.name.as_ref().filter(|_| group.expanded) else {
+                continue;
+            };
+            let frame = group
+                .txids(*first)
+                .filter_map(|txid| outer_rects.get(&txid))
+                .fold(Rect::NOTHING, |frame, rect| frame.union(*rect));
+            if !frame.is_positive() {
+                continue;
+            }
+            let frame = transform.rect_to_screen(frame.expand(CLUSTER_MARGIN));
+            if !clip_rect.intersects(frame) {
+                continue;
+            }
+            painter.rect_stroke(
+                frame,
+                Rounding::same(4.0),
+                Stroke::new(
+                    style.tx_stroke_width,
+                    style.tx_stroke_color.gamma_multiply(0.5),
+                ),
+            );
+            let title = painter.text(
+                frame.left_top() + Vec2::new(4.0, -2.0),
+                Align2::LEFT_BOTTOM,
+                name,
+                style.font_id(),
+                style.black_text_color(),
+            );
+            let response = ui
+                .interact(title, ui.id().with("cluster").with(first), Sense::click())
+                .on_hover_text("Click to collapse.");
+            if response.clicked() {
+                collapse_group = Some((*first, false));
+            }
+            response.context_menu(|ui| {
+                if ui.button("Collapse").clicked() {
+                    collapse_group = Some((*first, false));
+                    ui.close_menu();
+                }
+                if ui.button("Ungroup").clicked() {
+                    collapse_group = Some((*first, true));
+                    ui.close_menu();
+                }
+            });
+        }
+        match collapse_group {
+            Some((first, false)) => self.set_expanded(first, false),
+            Some((first, true)) => self.ungroup(first),
+            None => {}
+        }
+
+        let batch_idx = painter.add(Shape::Noop);
+        let txids: HashSet<Txid> = self.nodes.keys().copied().collect();
+        let discrepancies: HashMap<Txid, Vec<String>> = txids
+            .iter()
+            .map(|txid| (*txid, self.model.discrepancies(*txid)))
+            .filter(|(_, d)| !d.is_empty())
+            .collect();
+        // Which peel of how many, by transaction.
+        let peels: HashMap<Txid, (usize, usize)> = self
+            .model
+            .peel_chains(MIN_PEEL_CHAIN)
+            .into_iter()
+            .flat_map(|chain| {
+                let len = chain.len();
+                chain
+                    .into_iter()
+                    .enumerate()
+                    .map(move |(i, txid)| (txid, (i, len)))
+            })
+            .collect();
+
+        let selected_time = self
+            .selected_node
+            .filter(|_| settings.relative_times)
+            .and_then(|txid| Some((txid, self.model.tx(&txid)?.timestamp)));
+
+        let mut sort_io = None;
+        let mut collapse = None;
+        let mut cluster = None;
+        let mut protect = None;
+        let mut pin = None;
+        // Selected transactions are dragged together.
+        let mut group_drag = None;
+
+        for (txid, node) in &mut self.nodes {
+            // Drawn as one node below, or hidden in it.
+            let group = self.groups.get(txid).filter(|group| !group.expanded);
+            if group.is_some() || self.collapsed.contains_key(txid) {
+                continue;
+            }
+            let Some(tx) = self.model.tx(txid) else {
+                continue;
+            };
+            let spawn = |rect: Rect, value: u64, ancestor: bool| {
+                spawn_pos(layout, &style, outer_rects[txid], rect, value, ancestor)
+            };
+            let outer_rect = transform.rect_to_screen(*outer_rects.get(txid).unwrap());
+            let mut io_drag = None;
+
+            if !clip_rect.intersects(outer_rect.expand(style.selected_stroke_width * 2.0)) {
+                continue;
+            }
+
+            let mut painter = painter.clone();
+            if !in_focus(txid) {
+                painter.multiply_opacity(DIMMED);
+            }
+            let painter = &painter;
+
+            if self.selection.contains(txid) {
+                painter.rect(
+                    outer_rect.expand(style.selected_stroke_width / 2.0),
+                    Rounding::ZERO,
+                    Color32::TRANSPARENT,
+                    style.selected_tx_stroke(),
+                );
+            }
+
+            let label = annotations.tx_label(*txid);
+            let rect = transform.rect_to_screen(*inner_rects.get(txid).unwrap());
+            let elsewhere = WorkspacesHandle::containing(ui.ctx(), *txid);
+            let response = ui
+                .interact(rect, ui.id().with(txid), Sense::click_and_drag())
+                .on_hover_info(settings, |ui| {
+                    let format = TextFormat {
+                        font_id: style.font_id(),
+                        color: style.black_text_color(),
+                        ..Default::default()
+                    };
+
+                    ui.label(RichText::new("Transaction").heading().monospace());
+                    let mut job = LayoutJob::default();
+                    txid_layout(&mut job, txid, &style);
+                    newline(&mut job, &style.font_id());
+                    if let Some(label) = label.clone() {
+                        job.append(&format!("[{}]", label), 0.0, format.clone());
+                        newline(&mut job, &style.font_id());
+                    }
+                    newline(&mut job, &FontId::monospace(5.0));
+                    sats_layout(&mut job, &Sats(tx.value), &style, settings.number_format);
+                    job.append(
+                        &format!(
+                            "\n{} (block {})",
+                            settings.format_time(tx.timestamp, false),
+                            tx.block_height
+                        ),
+                        0.0,
+                        format.clone(),
+                    );
+                    if let Some((_, selected)) = selected_time.filter(|(t, _)| t != txid) {
+                        job.append(
+                            &format!(
+                                "\n{} than the selected one",
+                                format_relative(tx.timestamp - selected)
+                            ),
+                            0.0,
+                            format.clone(),
+                        );
+                    }
+                    if let Some((cluster, count, size)) = coloring.cluster(&self.model, txid) {
+                        job.append(
+                            &format!(
+                                "\nWallet cluster {} of {}, {} addresses",
+                                cluster + 1,
+                                count,
+                                size
+                            ),
+                            0.0,
+                            format.clone(),
+                        );
+                    }
+                    if let Some(fee_rate) = tx.fee_rate {
+                        job.append(&format!("\nFee rate {}", fee_rate), 0.0, format.clone());
+                    }
+                    if let Some(privacy) = self.privacy.describe(txid) {
+                        job.append(&format!("\n{}", privacy), 0.0, format.clone());
+                    }
+                    ui.label(job);
+                });
+            response.context_menu(|ui| {
+                ui.menu_button("Annotate", |ui| annotations.tx_menu(*txid, ui));
+                ui.menu_button("Export to Clipboard", |ui| {
+                    if ui.button("Beancount").clicked() {
+                        ui.ctx().output_mut(|o| {
+                            o.copied_text = tx.export_beancount(txid, label.clone())
+                        });
+                        ui.close_menu();
+                    }
+                });
+                if ui.button("Copy Txid").clicked() {
+                    ui.output_mut(|o| o.copied_text = txid.hex_string());
+                    ui.close_menu();
+                }
+                if !elsewhere.is_empty() {
+                    ui.menu_button(format!("Other Workspaces ({})", elsewhere.len()), |ui| {
+                        for (id, name) in &elsewhere {
+                            ui.menu_button(name, |ui| {
+                                if ui.button("Open").clicked() {
+                                    WorkspacesHandle::select(ui.ctx(), *id);
+                                    ui.close_menu();
+                                }
+                                if ui
+                                    .button("Import Annotations")
+                                    .on_hover_text("Labels and colors set here are kept.")
+                                    .clicked()
+                                {
+                                    WorkspacesHandle::import_annotations(ui.ctx(), *id, *txid);
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+                    });
+                }
+                // Funding and spending transactions that aren't loaded yet.
+                let mut seen = HashSet::default();
+                let inputs: Vec<(Txid, Pos2)> = tx
+                    .inputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, input)| !txids.contains(&input.funding_txid))
+                    .filter(|(_, input)| seen.insert(input.funding_txid))
+                    .map(|(i, input)| {
+                        let rect = input_rects[&(*txid, i)];
+                        (input.funding_txid, spawn(rect, input.value, true))
+                    })
+                    .collect();
+                let outputs: Vec<(Txid, Pos2)> = tx
+                    .outputs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(o, output)| match output.output_type {
+                        OutputType::Spent { spending_txid, .. }
+                            if !txids.contains(&spending_txid) && seen.insert(spending_txid) =>
+                        {
+                            let rect = output_rects[&(*txid, o)];
+                            Some((spending_txid, spawn(rect, output.value, false)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                for (side, targets) in [("Inputs", inputs), ("Outputs", outputs)] {
+                    let button = Button::new(format!("Expand All {} ({})", side, targets.len()));
+                    if ui.add_enabled(!targets.is_empty(), button).clicked() {
+                        for (txid, pos) in targets {
+                            update_sender
+                                .send(Update::LoadOrSelectTx {
+                                    txid,
+                                    pos: Some(pos),
+                                })
+                                .unwrap();
+                        }
+                        ui.close_menu();
+                    }
+                }
+                ui.menu_button(format!("Expand {} Hops", expand_depth), |ui| {
+                    for direction in HopDirection::ALL {
+                        if ui.button(direction.name()).clicked() {
+                            update_sender
+                                .send(Update::ExpandHops {
+                                    txid: *txid,
+                                    depth: expand_depth,
+                                    direction,
+                                })
+                                .unwrap();
+                            ui.close_menu();
+                        }
+                    }
+                });
+                if ui
+                    .button("Trace to Origin")
+                    .on_hover_text("Load ancestors until the coinbase transactions are reached.")
+                    .clicked()
+                {
+                    update_sender
+                        .send(Update::TraceToOrigin { txid: *txid })
+                        .unwrap();
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Find Unspent Descendants")
+                    .on_hover_text(
+                        "Load descendants until the value of the outputs rests in unspent \
+                         outputs, and list them.",
+                    )
+                    .clicked()
+                {
+                    update_sender
+                        .send(Update::FindUnspent {
+                            txid: *txid,
+                            vout: None,
+                        })
+                        .unwrap();
+                    ui.close_menu();
+                }
+                if let Some(coinjoin) = &tx.coinjoin {
+                    ui.menu_button("CoinJoin", |ui| {
+                        ui.label(RichText::new(coinjoin.kind.to_string()).strong());
+                        ui.separator();
+
+                        // Expand the graph by role instead of clicking through
+                        // dozens of inputs/outputs.
+                        for role in CoinJoinRole::ALL {
+                            let mut seen = HashSet::default();
+                            let inputs: Vec<(Txid, Pos2)> = tx
+                                .inputs
+                                .iter()
+                                .enumerate()
+                                .filter(|(i, _)| coinjoin.input_roles[*i] == role)
+                                .filter(|(_, input)| !txids.contains(&input.funding_txid))
+                                .filter(|(_, input)| seen.insert(input.funding_txid))
+                                .map(|(i, input)| {
+                                    let rect = input_rects[&(*txid, i)];
+                                    (input.funding_txid, spawn(rect, input.value, true))
+                                })
+                                .collect();
+                            let outputs: Vec<(Txid, Pos2)> = tx
+                                .outputs
+                                .iter()
+                                .zip(&coinjoin.output_roles)
+                                .enumerate()
+                                .filter_map(|(o, (output, output_role))| match output.output_type {
+                                    OutputType::Spent { spending_txid, .. }
+                                        if *output_role == role
+                                            && !txids.contains(&spending_txid)
+                                            && seen.insert(spending_txid) =>
+                                    {
+                                        let rect = output_rects[&(*txid, o)];
+                                        Some((spending_txid, spawn(rect, output.value, false)))
+                                    }
+                                    _ => None,
+                                })
+                                .collect();
+
+                            let sides = [
+                                ("Inputs", coinjoin.input_roles.contains(&role), inputs),
+                                ("Outputs", coinjoin.output_roles.contains(&role), outputs),
+                            ];
+                            for (side, present, targets) in sides {
+                                if !present {
+                                    continue;
+                                }
+                                let button = Button::new(format!(
+                                    "Load {} {} ({})",
+                                    role,
+                                    side,
+                                    targets.len()
+                                ));
+                                if ui.add_enabled(!targets.is_empty(), button).clicked() {
+                                    for (txid, pos) in targets {
+                                        update_sender
+                                            .send(Update::LoadOrSelectTx {
+                                                txid,
+                                                pos: Some(pos),
+                                            })
+                                            .unwrap();
+                                    }
+                                    ui.close_menu();
+                                }
+                            }
+                        }
+                    });
+                }
+                let hops = self.model.hops_through(*txid).len();
+                if hops > 1
+                    && ui
+                        .button(format!("Collapse Chain ({} Hops)", hops))
+                        .on_hover_text(
+                            "Draw the run of transactions with one input and one output as one node.",
+                        )
+                        .clicked()
+                {
+                    collapse = Some(*txid);
+                    ui.close_menu();
+                }
+                ui.menu_button("Order Inputs/Outputs", |ui| {
+                    if ui
+                        .button("Minimize Crossings")
+                        .on_hover_text("Order by the position of the connected transactions.")
+                        .clicked()
+                    {
+                        sort_io = Some(*txid);
+                        ui.close_menu();
+                    }
+                    if ui.button("Blockchain Order").clicked() {
+                        node.input_order.clear();
+                        node.output_order.clear();
+                        ui.close_menu();
+                    }
+                });
+                ui.menu_button("Extract to New Workspace", |ui| {
+                    let txids = if self.selection.contains(txid) {
+                        self.selection.iter().copied().collect()
+                    } else {
+                        vec![*txid]
+                    };
+                    if ui
+                        .button(format!("Selected Transactions ({})", txids.len()))
+                        .clicked()
+                    {
+                        update_sender
+                            .send(Update::ExtractWorkspace {
+                                txids,
+                                connected: false,
+                            })
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                    if ui.button("Connected Component").clicked() {
+                        update_sender
+                            .send(Update::ExtractWorkspace {
+                                txids: vec![*txid],
+                                connected: true,
+                            })
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                });
+                let protected = self.protected.contains(txid);
+                let targets: Vec<Txid> = if self.selection.contains(txid) {
+                    self.selection.iter().copied().collect()
+                } else {
+                    vec![*txid]
+                };
+                let action = if protected { "Unprotect" } else { "Protect" };
+                let label = match targets.len() {
+                    1 => action.to_string(),
+                    n => format!("{} Selected ({})", action, n),
+                };
+                if ui
+                    .button(label)
+                    .on_hover_text("Protected transactions can't be removed.")
+                    .clicked()
+                {
+                    protect = Some((targets.clone(), !protected));
+                    ui.close_menu();
+                }
+                let pinned = self.pinned.contains(txid);
+                let action = if pinned { "Unpin" } else { "Pin" };
+                let label = match targets.len() {
+                    1 => action.to_string(),
+                    n => format!("{} Selected ({})", action, n),
+                };
+                if ui
+                    .button(label)
+                    .on_hover_text("Pinned transactions stay where they are put.")
+                    .clicked()
+                {
+                    pin = Some((targets.clone(), !pinned));
+                    ui.close_menu();
+                }
+                if targets.len() > 1 {
+                    if ui
+                        .button(format!("Group Selected ({})", targets.len()))
+                        .on_hover_text("Draw them as one node with the coins going in and out.")
+                        .clicked()
+                    {
+                        let mut txids = vec![*txid];
+                        txids.extend(targets.iter().filter(|other| *other != txid));
+                        cluster = Some(txids);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .button(format!("Annotate Selected ({})", targets.len()))
+                        .clicked()
+                    {
+                        let trace = Trace {
+                            txids: targets.clone(),
+                            coins: vec![],
+                        };
+                        update_sender.send(Update::AnnotateTrace { trace }).unwrap();
+                        ui.close_menu();
+                    }
+                    let removable: Vec<Txid> = targets
+                        .iter()
+                        .filter(|txid| !self.protected.contains(txid))
+                        .copied()
+                        .collect();
+                    if ui
+                        .add_enabled(
+                            !removable.is_empty(),
+                            Button::new(format!("Remove Selected ({})", removable.len())),
+                        )
+                        .on_hover_text("Protected transactions are kept.")
+                        .clicked()
+                    {
+                        update_sender
+                            .send(Update::RemoveTxs { txids: removable })
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                } else if ui
+                    .add_enabled(!protected, Button::new("Remove"))
+                    .on_disabled_hover_text("Protected, unprotect it first.")
+                    .clicked()
+                {
+                    update_sender
+                        .send(Update::RemoveTx { txid: *txid })
+                        .unwrap();
+                    ui.close_menu();
+                }
+            });
+
+            if response.clicked() {
+                if let Some(measure) = &mut self.measure {
+                    measure.pick(*txid);
+                } else if ui.input(|i| i.modifiers.shift) {
+                    update_sender
+                        .send(Update::ToggleSelectTx { txid: *txid })
+                        .unwrap();
+                } else {
+                    update_sender
+                        .send(Update::SelectTx { txid: *txid })
+                        .unwrap();
+                }
+            }
+
+            if response.hovered() {
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::Grab);
+            }
+
+            if response.dragged() {
+                let delta = transform.vec_from_screen(response.drag_delta());
+                node.dragged = true;
+                node.velocity = Vec2::ZERO;
+                node.pos += delta;
+                if self.selection.contains(txid) {
+                    group_drag = Some((*txid, delta));
+                }
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::Grabbing);
+            } else {
+                node.dragged = false;
+            }
+
+            fill_rect(
+                painter,
+                &mut batch,
+                rect,
+                coloring
+                    .tx(&self.model, *txid, annotations)
+                    .unwrap_or(style.tx_bg)
+                    .gamma_multiply(0.4),
+            );
+            painter.rect_stroke(rect, Rounding::ZERO, style.tx_stroke());
+            // Double border for protected transactions.
+            if self.protected.contains(txid) && rect.height() > 8.0 {
+                painter.rect_stroke(rect.shrink(3.0), Rounding::ZERO, style.tx_stroke());
+            }
+            // Pin head in the corner for pinned ones.
+            if self.pinned.contains(txid) {
+                painter.circle_filled(
+                    rect.left_top() + Vec2::splat(4.0),
+                    2.5,
+                    style.tx_stroke_color,
+                );
+            }
+            // Rings going out from a flashing one.
+            if let Some((_, since)) = self.flash.filter(|(flashing, _)| flashing == txid) {
+                let elapsed = ui.input(|i| i.time) - since;
+                if elapsed < FLASH_SECONDS {
+                    let t = elapsed.fract() as f32;
+                    painter.rect_stroke(
+                        rect.expand(4.0 + 16.0 * t),
+                        Rounding::ZERO,
+                        Stroke::new(2.0, style.io_highlight_color.gamma_multiply(1.0 - t)),
+                    );
+                    ui.ctx().request_repaint();
+                }
+            }
+
+            if let Some(text_style) = &text_style {
+                stopwatch.lap(Phase::Nodes);
+                let tx_painter = painter.with_clip_rect(rect);
+                tx_painter.galley(
+                    rect.left_top() + Vec2::new(2.0, 2.0),
+                    tx_painter.layout_job(tx_content(
+                        txid,
+                        &label,
+                        &settings.format_time(tx.timestamp, true),
+                        &Sats(tx.value),
+                        settings.number_format,
+                        text_style,
+                    )),
+                    Color32::TRANSPARENT,
+                );
+                stopwatch.lap(Phase::Text);
+            }
+
+            // Badges from the top right corner to the left.
+            let mut badge_rects = (1..).map(|i| {
+                Rect::from_min_size(
+                    rect.right_top() + Vec2::new(-14.0 * i as f32, 2.0),
+                    Vec2::splat(12.0),
+                )
+            });
+
+            if let Some(discrepancies) = discrepancies.get(txid) {
+                let badge_rect = badge_rects.next().unwrap();
+                painter.text(
+                    badge_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "▲",
+                    style.font_id(),
+                    style.warn,
+                );
+                ui.interact(
+                    badge_rect,
+                    ui.id().with("discrepancy").with(txid),
+                    Sense::hover(),
+                )
+                .on_hover_info(settings, |ui| {
+                    ui.label(RichText::new("Inconsistent Amounts").heading().monospace());
+                    for discrepancy in discrepancies {
+                        ui.label(discrepancy);
+                    }
+                });
+            }
+
+            let double_spends: Vec<(usize, (Txid, usize), Vec<Txid>)> = tx
+                .inputs
+                .iter()
+                .enumerate()
+                .filter_map(|(i, input)| {
+                    let coin = (input.funding_txid, input.funding_vout as usize);
+                    let others = conflicts.get(&coin)?;
+                    let others = others.iter().filter(|other| *other != txid).copied();
+                    Some((i, coin, others.collect()))
+                })
+                .collect();
+            if !double_spends.is_empty() {
+                let badge_rect = badge_rects.next().unwrap();
+                painter.text(
+                    badge_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "✖",
+                    style.font_id(),
+                    style.conflict,
+                );
+                ui.interact(
+                    badge_rect,
+                    ui.id().with("double spend").with(txid),
+                    Sense::hover(),
+                )
+                .on_hover_info(settings, |ui| {
+                    ui.label(RichText::new("Double Spend").heading().monospace());
+                    for (i, (coin_txid, vout), others) in &double_spends {
+                        let others: Vec<String> =
+                            others.iter().map(|other| other.to_string()).collect();
+                        ui.label(format!(
+                            "Input {} spends {}:{}, as does {}.",
+                            i,
+                            coin_txid,
+                            vout,
+                            others.join(" and ")
+                        ));
+                    }
+                    ui.weak("Only one of them can confirm. The others were replaced, or the data is stale.");
+                });
+            }
+
+            if let Some(coinjoin) = &tx.coinjoin {
+                let badge_rect = badge_rects.next().unwrap();
+                painter.text(
+                    badge_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "◑",
+                    style.font_id(),
+                    style.white_text_color(),
+                );
+                ui.interact(
+                    badge_rect,
+                    ui.id().with("coinjoin").with(txid),
+                    Sense::hover(),
+                )
+                .on_hover_info(settings, |ui| {
+                    ui.label(
+                        RichText::new(coinjoin.kind.to_string())
+                            .heading()
+                            .monospace(),
+                    );
+                    for role in CoinJoinRole::ALL {
+                        let count =
+                            |roles: &[CoinJoinRole]| roles.iter().filter(|r| **r == role).count();
+                        let (inputs, outputs) =
+                            (count(&coinjoin.input_roles), count(&coinjoin.output_roles));
+                        if inputs + outputs > 0 {
+                            ui.label(format!("{}: {} inputs, {} outputs", role, inputs, outputs));
+                        }
+                    }
+                    if let Some(privacy) = self.privacy.describe(txid) {
+                        ui.label(privacy);
+                    }
+                    ui.weak("Right-click to load the coins of a role.");
+                });
+            }
+
+            if let Some((i, len)) = peels.get(txid) {
+                let badge_rect = badge_rects.next().unwrap();
+                painter.text(
+                    badge_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "⏵",
+                    style.font_id(),
+                    style.white_text_color(),
+                );
+                ui.interact(badge_rect, ui.id().with("peel").with(txid), Sense::hover())
+                    .on_hover_info(settings, |ui| {
+                        ui.label(RichText::new("Peel Chain").heading().monospace());
+                        ui.label(format!("Peel {} of {}", i + 1, len));
+                        ui.weak("Pays out a small amount and passes the rest on.");
+                        ui.weak("Tx > Peel Chains lists them all.");
+                    });
+            }
+
+            if !elsewhere.is_empty() {
+                let badge_rect = badge_rects.next().unwrap();
+                painter.text(
+                    badge_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "●",
+                    style.font_id(),
+                    style.white_text_color().gamma_multiply(0.5),
+                );
+                ui.interact(
+                    badge_rect,
+                    ui.id().with("elsewhere").with(txid),
+                    Sense::hover(),
+                )
+                .on_hover_info(settings, |ui| {
+                    ui.label(RichText::new("Also in").heading().monospace());
+                    for (_, name) in &elsewhere {
+                        ui.label(name);
+                    }
+                });
+            }
+
+            let id = ui.id().with("i").with(txid);
+            for (i, input) in tx.inputs.iter().enumerate() {
+                let coin = (input.funding_txid, input.funding_vout as usize);
+
+                let rect = *input_rects.get(&(*txid, i)).unwrap();
+                let screen_rect = transform.rect_to_screen(rect);
+                let response = ui
+                    .interact(screen_rect, id.with(i), Sense::click_and_drag())
+                    .on_hover_info(settings, |ui| {
+                        ui.label(coin_heading(
+                            "⏴Input",
+                            annotations,
+                            address_book,
+                            coin,
+                            &input.address,
+                        ));
+                        if let Some(links) = self.privacy.input_links(txid, i) {
+                            ui.label(links);
+                        }
+                        let mut job = LayoutJob::default();
+                        sats_layout(&mut job, &Sats(input.value), &style, settings.number_format);
+                        newline(&mut job, &style.font_id());
+                        address_layout(&mut job, &input.address, input.address_type, &style);
+                        newline(&mut job, &style.font_id());
+                        newline(&mut job, &FontId::monospace(5.0));
+                        txid_layout(&mut job, &input.funding_txid, &style);
+                        if let Some(spend) = &input.taproot_spend {
+                            newline(&mut job, &style.font_id());
+                            newline(&mut job, &FontId::monospace(5.0));
+                            job.append(
+                                &spend.to_string(),
+                                0.0,
+                                TextFormat {
+                                    font_id: style.font_id(),
+                                    color: style.black_text_color(),
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        if let Some(multisig) = &input.multisig {
+                            newline(&mut job, &style.font_id());
+                            newline(&mut job, &FontId::monospace(5.0));
+                            job.append(
+                                &format!(
+                                    "{} multisig\nKeys: {}",
+                                    multisig,
+                                    multisig.key_ids().join(" ")
+                                ),
+                                0.0,
+                                TextFormat {
+                                    font_id: style.font_id(),
+                                    color: style.black_text_color(),
+                                    ..Default::default()
+                                },
+                            );
+                        }
+                        ui.label(job);
+                    });
+                let neighbor = (input.funding_txid, spawn(rect, input.value, true));
+                let loaded = txids.contains(&input.funding_txid);
+                let protected = self.protected.contains(&input.funding_txid);
+                response.context_menu(|ui| {
+                    annotations.coin_menu(coin, ui);
+                    ui.menu_button("Label Address Globally", |ui| {
+                        address_book.address_menu(&input.address, ui)
+                    });
+                    ui.separator();
+                    neighbor_menu(ui, "Funding", neighbor, loaded, protected, &update_sender);
+                });
+
+                if response.hovered() {
+                    new_hovered_coin = Some(coin);
+                }
+
+                if response.dragged() {
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        let x = transform.pos_from_screen(pointer).x - outer_rects[txid].left();
+                        io_drag = Some((Io::Input(i), x));
+                    }
+                    ui.output_mut(|o| o.cursor_icon = CursorIcon::ResizeHorizontal);
+                }
+
+                if response.clicked() {
+                    let remove = ui.input(|i| i.modifiers.command);
+                    let update =
+                        io_click_update(io_click, remove, coin, Some(neighbor), loaded, protected);
+                    if let Some(update) = update {
+                        update_sender.send(update).unwrap();
+                    }
+                }
+
+                fill_rect(
+                    painter,
+                    &mut batch,
+                    screen_rect,
+                    io_fill(coin, annotations.coin_color(coin).unwrap_or(style.io_bg)),
+                );
+
+                // Tag script-path spends
+                if let Some(TaprootSpend::ScriptPath { .. }) = input.taproot_spend {
+                    painter.circle_filled(
+                        screen_rect.center(),
+                        screen_rect.height().min(screen_rect.width()) / 4.0,
+                        style.tx_bg,
+                    );
+                }
+
+                if let Some(multisig) = &input.multisig {
+                    let font_id = FontId::monospace(screen_rect.height() * 0.9);
+                    let galley = painter.layout_no_wrap(
+                        multisig.to_string(),
+                        font_id,
+                        style.black_text_color(),
+                    );
+                    if galley.size().x < screen_rect.width() {
+                        painter.galley(
+                            screen_rect.center() - galley.size() / 2.0,
+                            galley,
+                            Color32::TRANSPARENT,
+                        );
+                    }
+                }
+
+                if Loading::is_txid_loading(ui, &input.funding_txid) {
+                    rect_striped(
+                        ui,
+                        screen_rect,
+                        style.black_text_color().gamma_multiply(0.1),
+                    );
+                    ui.ctx().request_repaint();
+                }
+
+                painter.rect(
+                    screen_rect,
+                    Rounding::ZERO,
+                    Color32::TRANSPARENT,
+                    if hovered_coin == Some(coin) {
+                        style.io_linked_stroke()
+                    } else {
+                        style.io_stroke(&response)
+                    },
+                );
+
+                if Some(coin) == self.selected_coin {
+                    painter.rect(
+                        screen_rect.expand(style.selected_stroke_width / 2.0),
+                        Rounding::ZERO,
+                        Color32::TRANSPARENT,
+                        style.selected_tx_stroke(),
+                    );
+                }
+            }
+
+            let id = ui.id().with("o").with(txid);
+            // rev() so that we paint the fees first and they get overdrawn by the
+            // hover boxes of the outpus.
+            for (o, output) in tx.outputs.iter().enumerate().rev() {
+                let coin = (*txid, o);
+
+                let rect = *output_rects.get(&(*txid, o)).unwrap();
+                let screen_rect = transform.rect_to_screen(rect);
+                let response = ui
+                    .interact(screen_rect, id.with(o), Sense::click_and_drag())
+                    .on_hover_info(settings, |ui| match &output.output_type {
+                        OutputType::Utxo {
+                            address,
+                            address_type,
+                        } => {
+                            ui.label(coin_heading(
+                                "Unspent Output",
+                                annotations,
+                                address_book,
+                                coin,
+                                address,
+                            ));
+                            let mut job = LayoutJob::default();
+                            sats_layout(
+                                &mut job,
+                                &Sats(output.value),
+                                &style,
+                                settings.number_format,
+                            );
+                            newline(&mut job, &style.font_id());
+                            address_layout(&mut job, address, *address_type, &style);
+                            ui.label(job);
+                            if let Some(share) = coloring.taint(coin) {
+                                ui.label(taint::describe(share));
+                            }
+                            if let Some(links) = self.privacy.output_links(txid, o) {
+                                ui.label(links);
+                            }
+                        }
+                        OutputType::Spent {
+                            spending_txid,
+                            address,
+                            address_type,
+                        } => {
+                            ui.label(coin_heading(
+                                "Output⏵",
+                                annotations,
+                                address_book,
+                                coin,
+                                address,
+                            ));
+                            let mut job = LayoutJob::default();
+                            sats_layout(
+                                &mut job,
+                                &Sats(output.value),
+                                &style,
+                                settings.number_format,
+                            );
+                            newline(&mut job, &style.font_id());
+                            address_layout(&mut job, address, *address_type, &style);
+                            newline(&mut job, &style.font_id());
+                            newline(&mut job, &FontId::monospace(5.0));
+                            txid_layout(&mut job, spending_txid, &style);
+                            ui.label(job);
+                            if let Some(share) = coloring.taint(coin) {
+                                ui.label(taint::describe(share));
+                            }
+                            if let Some(links) = self.privacy.output_links(txid, o) {
+                                ui.label(links);
+                            }
+                        }
+                        OutputType::Fees => {
+                            ui.label(RichText::new("Fees").heading().monospace());
+                            ui.add(SatsDisplay::new(
+                                Sats(output.value),
+                                &style,
+                                settings.number_format,
+                            ));
+                            if let Some(fee_rate) = tx.fee_rate {
+                                ui.label(fee_rate.to_string());
+                            }
+                        }
+                    });
+
+                match output.output_type {
+                    OutputType::Fees => {}
+                    _ => {
+                        let neighbor = match output.output_type {
+                            OutputType::Spent { spending_txid, .. } => {
+                                Some((spending_txid, spawn(rect, output.value, false)))
+                            }
+                            _ => None,
+                        };
+                        let loaded = neighbor.is_some_and(|(txid, _)| txids.contains(&txid));
+                        let protected =
+                            neighbor.is_some_and(|(txid, _)| self.protected.contains(&txid));
+
+                        if response.clicked() {
+                            let remove = ui.input(|i| i.modifiers.command);
+                            let update = io_click_update(
+                                io_click, remove, coin, neighbor, loaded, protected,
+                            );
+                            if let Some(update) = update {
+                                update_sender.send(update).unwrap();
+                            }
+                        }
+                        if response.hovered() {
+                            new_hovered_coin = Some(coin);
+                        }
+                        if response.dragged() {
+                            if let Some(pointer) = response.interact_pointer_pos() {
+                                let x =
+                                    transform.pos_from_screen(pointer).x - outer_rects[txid].left();
+                                io_drag = Some((Io::Output(o), x));
+                            }
+                            ui.output_mut(|o| o.cursor_icon = CursorIcon::ResizeHorizontal);
+                        }
+                        response.context_menu(|ui| {
+                            annotations.coin_menu(coin, ui);
+                            if let Some(address) = output.address() {
+                                ui.menu_button("Label Address Globally", |ui| {
+                                    address_book.address_menu(address, ui)
+                                });
+                            }
+                            ui.separator();
+                            if ui.button("Copy link to this output").clicked() {
+                                ui.output_mut(|o| {
+                                    o.copied_text =
+                                        absolute_url(&format!("tx/{}/{}", coin.0, coin.1))
+                                });
+                                ui.close_menu();
+                            }
+                            ui.menu_button("Alert me", |ui| {
+                                alert_menu(ui, coin, &output.output_type, &update_sender)
+                            });
+                            if neighbor.is_some()
+                                && ui
+                                    .button("Follow the Money")
+                                    .on_hover_text("Load the spending transactions hop by hop.")
+                                    .clicked()
+                            {
+                                update_sender.send(Update::FollowMoney { coin }).unwrap();
+                                ui.close_menu();
+                            }
+                            if neighbor.is_some()
+                                && ui
+                                    .button("Find Unspent Descendants")
+                                    .on_hover_text(
+                                        "Load descendants until the value of this coin rests \
+                                         in unspent outputs, and list them.",
+                                    )
+                                    .clicked()
+                            {
+                                update_sender
+                                    .send(Update::FindUnspent {
+                                        txid: coin.0,
+                                        vout: Some(coin.1),
+                                    })
+                                    .unwrap();
+                                ui.close_menu();
+                            }
+                            if let Some(source) = taint_button(ui, coin, self.taint) {
+                                mark_taint = Some(source);
+                            }
+                            if let Some(neighbor) = neighbor {
+                                ui.separator();
+                                neighbor_menu(
+                                    ui,
+                                    "Spending",
+                                    neighbor,
+                                    loaded,
+                                    protected,
+                                    &update_sender,
+                                );
+                            }
+                        });
+                    }
+                }
+
+                fill_rect(
+                    painter,
+                    &mut batch,
+                    screen_rect,
+                    match output.output_type {
+                        OutputType::Utxo {
+                            address: _,
+                            address_type: _,
+                        } => io_fill(
+                            coin,
+                            annotations.coin_color(coin).unwrap_or(style.utxo_fill()),
+                        ),
+                        OutputType::Spent {
+                            spending_txid: _,
+                            address: _,
+                            address_type: _,
+                        } => io_fill(coin, annotations.coin_color(coin).unwrap_or(style.io_bg)),
+                        OutputType::Fees => style.fees_fill(),
+                    },
+                );
+
+                if let OutputType::Spent { spending_txid, .. } = output.output_type {
+                    if Loading::is_txid_loading(ui, &spending_txid) {
+                        rect_striped(
+                            ui,
+                            screen_rect,
+                            style.black_text_color().gamma_multiply(0.1),
+                        );
+                        ui.ctx().request_repaint();
+                    }
+                }
+
+                painter.rect(
+                    screen_rect,
+                    Rounding::ZERO,
+                    Color32::TRANSPARENT,
+                    match output.output_type {
+                        OutputType::Spent {
+                            spending_txid: _,
+                            address: _,
+                            address_type: _,
+                        } => {
+                            if hovered_coin == Some(coin) {
+                                style.io_linked_stroke()
+                            } else {
+                                style.io_stroke(&response)
+                            }
+                        }
+                        _ => style.tx_stroke(),
+                    },
+                );
+
+                if Some(coin) == self.selected_coin {
+                    painter.rect(
+                        screen_rect.expand(style.selected_stroke_width / 2.0),
+                        Rounding::ZERO,
+                        Color32::TRANSPARENT,
+                        style.selected_tx_stroke(),
+                    );
+                }
+            }
+
+            match io_drag {
+                Some((Io::Input(i), x)) => node.move_input(i, x),
+                Some((Io::Output(o), x)) => node.move_output(o, x, tx.has_fees()),
+                None => {}
+            }
+        }
+
+        // Collapsed groups take in the coins coming from outside and send out
+        // the ones that leave.
+        let group_ios: HashMap<Txid, _> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| !group.expanded)
+            .map(|(first, group)| (*first, self.group_ios(*first, group)))
+            .collect();
+        let mut expand = None;
+        let mut ungroup = None;
+        for (first, group) in &mut self.groups {
+            let (Some((inputs, outputs)), Some(node), Some(outer_rect), Some(inner_rect)) = (
+                group_ios.get(first),
+                self.nodes.get_mut(first),
+                outer_rects.get(first),
+                inner_rects.get(first),
+            ) else {
+                continue;
+            };
+            let outer_rect = transform.rect_to_screen(*outer_rect);
+            if !clip_rect.intersects(outer_rect.expand(style.selected_stroke_width * 2.0)) {
+                continue;
+            }
+
+            let mut painter = painter.clone();
+            if !group.txids(*first).any(|txid| in_focus(&txid)) {
+                painter.multiply_opacity(DIMMED);
+            }
+            let painter = &painter;
+
+            let txs: Vec<&Tx> = group
+                .txids(*first)
+                .filter_map(|txid| self.model.tx(&txid))
+                .collect();
+            let value_in: u64 = inputs
+                .iter()
+                .filter_map(|(txid, i)| Some(self.model.tx(txid)?.inputs.get(*i)?.value))
+                .sum();
+            let value_out: u64 = outputs
+                .iter()
+                .filter_map(|(txid, o)| Some(self.model.tx(txid)?.outputs.get(*o)?.value))
+                .sum();
+            let fees: u64 = txs.iter().map(|tx| tx.fees()).sum();
+            let times = txs.iter().map(|tx| tx.timestamp);
+            let duration = format_duration(
+                times.clone().max().unwrap_or_default() - times.min().unwrap_or_default(),
+            );
+            let (title, detail) = match &group.name {
+                Some(name) => (name.clone(), format!("{} transactions", txs.len())),
+                None => (format!("{} hops", txs.len()), duration.clone()),
+            };
+
+            let rect = transform.rect_to_screen(*inner_rect);
+            let response = ui
+                .interact(
+                    rect,
+                    ui.id().with("group").with(first),
+                    Sense::click_and_drag(),
+                )
+                .on_hover_info(settings, |ui| {
+                    match (&group.name, group.rest.last()) {
+                        (Some(name), _) => {
+                            ui.label(RichText::new(name).heading().monospace());
+                            ui.label(format!(
+                                "Cluster of {} transactions over {}.",
+                                txs.len(),
+                                duration
+                            ));
+                        }
+                        (None, last) => {
+                            ui.label(RichText::new("Chain").heading().monospace());
+                            let mut job = LayoutJob::default();
+                            txid_layout(&mut job, first, &style);
+                            if let Some((last, _)) = last {
+                                newline(&mut job, &style.font_id());
+                                txid_layout(&mut job, last, &style);
+                            }
+                            ui.label(job);
+                            ui.label(format!(
+                                "{} hops with one input and one output, over {}.",
+                                txs.len(),
+                                duration
+                            ));
+                        }
+                    }
+                    ui.label(format!(
+                        "{} in, {} out, {} fees.",
+                        Sats(value_in).format(settings.number_format),
+                        Sats(value_out).format(settings.number_format),
+                        Sats(fees).format(settings.number_format)
+                    ));
+                    ui.weak("Click to expand.");
+                });
+            response.context_menu(|ui| {
+                if let Some(name) = &mut group.name {
+                    ui.horizontal(|ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(name);
+                    });
+                    ui.separator();
+                }
+                if ui.button("Expand").clicked() {
+                    expand = Some(*first);
+                    ui.close_menu();
+                }
+                if group.name.is_some() && ui.button("Ungroup").clicked() {
+                    ungroup = Some(*first);
+                    ui.close_menu();
+                }
+            });
+
+            if response.clicked() {
+                expand = Some(*first);
+            }
+            if response.hovered() {
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::Grab);
+            }
+            if response.dragged() {
+                node.dragged = true;
+                node.velocity = Vec2::ZERO;
+                node.pos += transform.vec_from_screen(response.drag_delta());
+                ui.output_mut(|o| o.cursor_icon = CursorIcon::Grabbing);
+            } else {
+                node.dragged = false;
+            }
+
+            let inputs = inputs.iter().filter_map(|(txid, i)| {
+                let input = self.model.tx(txid)?.inputs.get(*i)?;
+                let coin = (input.funding_txid, input.funding_vout as usize);
+                let fill = io_fill(coin, annotations.coin_color(coin).unwrap_or(style.io_bg));
+                Some((input_rects.get(&(*txid, *i))?, fill))
+            });
+            let outputs = outputs.iter().filter_map(|(txid, o)| {
+                let coin = (*txid, *o);
+                let fill = match self.model.tx(txid)?.outputs.get(*o)?.output_type {
+                    OutputType::Utxo { .. } => style.utxo_fill(),
+                    _ => style.io_bg,
+                };
+                let fill = io_fill(coin, annotations.coin_color(coin).unwrap_or(fill));
+                Some((output_rects.get(&coin)?, fill))
+            });
+            for (io_rect, fill) in inputs.chain(outputs) {
+                let io_rect = transform.rect_to_screen(*io_rect);
+                fill_rect(painter, &mut batch, io_rect, fill);
+                painter.rect_stroke(io_rect, Rounding::ZERO, style.tx_stroke());
+            }
+
+            fill_rect(
+                painter,
+                &mut batch,
+                rect,
+                coloring
+                    .tx(&self.model, *first, annotations)
+                    .unwrap_or(style.tx_bg)
+                    .gamma_multiply(0.4),
+            );
+            // Stacked like a deck of transactions.
+            painter.rect_stroke(
+                rect.translate(Vec2::splat(3.0)),
+                Rounding::ZERO,
+                style.tx_stroke(),
+            );
+            painter.rect_stroke(rect, Rounding::ZERO, style.tx_stroke());
+
+            if let Some(text_style) = &text_style {
+                let tx_painter = painter.with_clip_rect(rect);
+                tx_painter.galley(
+                    rect.left_top() + Vec2::new(2.0, 2.0),
+                    tx_painter.layout_job(group_content(
+                        &title,
+                        &Sats(value_out),
+                        settings.number_format,
+                        &detail,
+                        text_style,
+                    )),
+                    Color32::TRANSPARENT,
+                );
+            }
+        }
+
+        if let Some(batch) = batch {
+            painter.set(batch_idx, Shape::Callback(batch.into_callback(clip_rect)));
+        }
+
+        if layout.address_reuse {
+            self.draw_address_reuse(ui, transform, &rects);
+        }
+        self.draw_measure(ui, transform, &rects, &style, settings.number_format);
+
+        if let Some(txid) = sort_io {
+            self.sort_io(txid);
+        }
+
+        if let Some(txid) = collapse {
+            self.collapse_chain(txid);
+        }
+
+        if let Some(txids) = cluster {
+            self.cluster(txids);
+        }
+
+        if let Some(source) = mark_taint {
+            self.taint = source;
+        }
+
+        if let Some(txid) = expand {
+            self.set_expanded(txid, true);
+        }
+
+        if let Some(txid) = ungroup {
+            self.ungroup(txid);
+        }
+
+        if let Some((txids, protected)) = protect {
+            self.set_protected(txids, protected);
+        }
+
+        if let Some((txids, pinned)) = pin {
+            self.set_pinned(txids, pinned);
+        }
+
+        if let Some((dragged, delta)) = group_drag {
+            for txid in &self.selection {
+                if let Some(node) = self.nodes.get_mut(txid).filter(|_| *txid != dragged) {
+                    node.pos += delta;
+                    node.velocity = Vec2::ZERO;
+                }
+            }
+        }
+
+        if new_hovered_coin != self.hovered_coin {
+            self.hovered_coin = new_hovered_coin;
+            ui.ctx().request_repaint();
+        }
+
+        stopwatch.lap(Phase::Nodes);
+
+        // CALCULATE FORCES AND UPDATE VELOCITY //
+
+        if layout.force_params.active {
+            if self.step(&rects, &layout.force_params) {
+                ui.ctx().request_repaint();
+            }
+            let dragging = self.is_dragging();
+            if self.was_dragging && !dragging {
+                self.settle(&style, &layout.force_params);
+            }
+            self.was_dragging = dragging;
+            stopwatch.lap(Phase::Forces);
+        }
+        if layout.timeline.active {
+            self.snap_to_timeline(&layout.timeline, &rects);
+        }
+
+        stopwatch.finish(ui.ctx());
+    }
+
+    /// Order the inputs and outputs of `txid` by the horizontal position of
+    /// the coins they are connected to (barycenter heuristic), which removes
+    /// most crossings between edges to neighbouring transactions. Unconnected
+    /// inputs/outputs keep their current position.
+    pub fn sort_io(&mut self, txid: Txid) {
+        let Some(node) = self.nodes.get(&txid) else {
+            return;
+        };
+
+        let mut input_keys: Vec<f32> = (0..node.inputs.len()).map(|i| node.input_x(i)).collect();
+        let mut output_keys: Vec<f32> = (0..node.outputs.len()).map(|o| node.output_x(o)).collect();
+
+        for edge in self.model.edges() {
+            if edge.target == txid && edge.target_pos < input_keys.len() {
+                if let Some(source) = self.nodes.get(&edge.source) {
+                    input_keys[edge.target_pos] = source.output_x(edge.source_pos);
+                }
+            }
+            if edge.source == txid && edge.source_pos < output_keys.len() {
+                if let Some(target) = self.nodes.get(&edge.target) {
+                    output_keys[edge.source_pos] = target.input_x(edge.target_pos);
+                }
+            }
+        }
+
+        let node = self.nodes.get_mut(&txid).unwrap();
+        node.input_order = sort_by_keys(node.input_order(), &input_keys, false);
+        let has_fees = self.model.tx(&txid).unwrap().has_fees();
+        node.output_order = sort_by_keys(node.output_order(), &output_keys, has_fees);
+    }
+
+    pub fn sort_all_io(&mut self) {
+        let txids: Vec<Txid> = self.nodes.keys().copied().collect();
+        for txid in txids {
+            self.sort_io(txid);
+        }
+    }
+
+    fn rects(&self, style: &Style) -> Rects {
+        let mut rects = Rects::default();
+
+        for (txid, node) in &self.nodes {
+            if self.collapsed.contains_key(txid) {
+                continue;
+            }
+            if let Some(group) = self.groups.get(txid).filter(|group| !group.expanded) {
+                self.group_rects(&mut rects, style, *txid, group, node.pos);
+                continue;
+            }
+            let outer_rect = Rect::from_center_size(
+                node.pos,
+                Vec2::new(node.size, style.tx_width + 2.0 * style.io_width),
+            );
+            let inner_rect = Rect::from_center_size(node.pos, Vec2::new(node.size, style.tx_width));
+
+            rects.outer.insert(*txid, outer_rect);
+            rects.inner.insert(*txid, inner_rect);
+
+            let left_top = outer_rect.left_top();
+            for (i, input) in node.inputs.iter().enumerate() {
+                let rect = Rect::from_min_max(
+                    Pos2::new(left_top.x + input.start, left_top.y),
+                    Pos2::new(left_top.x + input.end, left_top.y + style.io_width),
+                );
+                rects.inputs.insert((*txid, i), rect);
+            }
+
+            let left_bot = outer_rect.left_bottom();
+            for (o, output) in node.outputs.iter().enumerate() {
+                let rect = Rect::from_min_max(
+                    Pos2::new(left_bot.x + output.start, left_bot.y - style.io_width),
+                    Pos2::new(left_bot.x + output.end, left_bot.y),
+                );
+                rects.outputs.insert((*txid, o), rect);
+            }
+        }
+
+        rects
+    }
+
+    /// Where the timeline puts each transaction horizontally, relative to the
+    /// earliest one. Unconfirmed transactions come last.
+    fn timeline_x(&self, timeline: &Timeline, rects: &Rects) -> HashMap<Txid, f32> {
+        let column = rects.outer.values().map(|r| r.width()).fold(0.0, f32::max) + timeline.gap;
+        let confirmed = |tx: &&Tx| tx.block_height > 0;
+        match timeline.spacing {
+            TimelineSpacing::Blocks => {
+                let height = |tx: &Tx| Some(tx.block_height).filter(|h| *h > 0).unwrap_or(u32::MAX);
+                let mut heights: Vec<u32> = self.model.txs().map(|(_, tx)| height(tx)).collect();
+                heights.sort();
+                heights.dedup();
+                self.model
+                    .txs()
+                    .map(|(txid, tx)| {
+                        let index = heights.binary_search(&height(tx)).unwrap_or_default();
+                        (*txid, index as f32 * column)
+                    })
+                    .collect()
+            }
+            TimelineSpacing::Time => {
+                let times = || self.model.txs().map(|(_, tx)| tx).filter(confirmed);
+                let first = times().map(|tx| tx.timestamp).min().unwrap_or_default();
+                let last = times().map(|tx| tx.timestamp).max().unwrap_or_default();
+                let day = 24.0 * 60.0 * 60.0;
+                let x = |timestamp: i64| (timestamp - first) as f32 / day * timeline.day_width;
+                self.model
+                    .txs()
+                    .map(|(txid, tx)| match confirmed(&tx) {
+                        true => (*txid, x(tx.timestamp)),
+                        false => (*txid, x(last) + column),
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Move the transactions to their place on the timeline, keeping the graph
+    /// centered where it is.
+    fn snap_to_timeline(&mut self, timeline: &Timeline, rects: &Rects) {
+        let xs = self.timeline_x(timeline, rects);
+        let Some(n) = (!self.nodes.is_empty()).then_some(self.nodes.len() as f32) else {
+            return;
+        };
+        let current = self.nodes.values().map(|node| node.pos.x).sum::<f32>() / n;
+        let target = self.nodes.keys().filter_map(|txid| xs.get(txid));
+        let target = target.sum::<f32>() / n;
+        for (txid, node) in &mut self.nodes {
+            if let Some(x) = xs.get(txid) {
+                node.pos.x = x + current - target;
+                node.velocity.x = 0.0;
+            }
+        }
+    }
+
+    /// Advance the force simulation by one time step. Returns whether any node
+    /// is still moving noticeably.
+    fn step(&mut self, rects: &Rects, params: &ForceParams) -> bool {
+        let scale2 = params.scale as f32 * params.scale as f32;
+        let tx_repulsion_radius = 2.0 * params.scale as f32;
+
+        fn kernel(radius: f32, dst: f32) -> f32 {
+            let value = (1.0 - dst / radius * dst / radius).max(0.0);
+            value * value
+        }
+
+        // Neighbors of a dragged transaction wait for it to be released.
+        let dragged: Vec<Pos2> = self
+            .nodes
+            .values()
+            .filter(|node| node.dragged)
+            .map(|node| node.pos)
+            .collect();
+        let held: HashSet<Txid> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| {
+                dragged
+                    .iter()
+                    .any(|pos| pos.distance(node.pos) <= params.drag_radius)
+            })
+            .map(|(txid, _)| *txid)
+            .collect();
+
+        for (txid, rect) in &rects.outer {
+            for (other_txid, other_rect) in &rects.outer {
+                if *other_txid == *txid {
+                    continue;
+                }
+
+                let spacing = clear_spacing(rect, other_rect);
+
+                // If the spacing is outside of the force radius we don't need to calculate the force
+                if spacing > tx_repulsion_radius {
+                    continue;
+                }
+
+                let diff = other_rect.center() - rect.center();
+                let force = -scale2 / spacing
+                    * kernel(tx_repulsion_radius, spacing)
+                    * diff.normalized();
+
+                self.nodes.get_mut(txid).unwrap().velocity += force * params.dt;
+            }
+        }
+
+        // Calculate edge multiplicities to deal with transactions sharing
+        // multiple inputs/outputs.
+        let mut edge_multiplicities: HashMap<(Txid, Txid), usize> = HashMap::new();
+        for edge in self.model.edges() {
+            let key = (edge.source, edge.target);
+            *edge_multiplicities.entry(key).or_insert(0) += 1;
+        }
+
+        for edge in self.model.edges() {
+            let (Some(from_rect), Some(to_rect)) = (
+                rects.outputs.get(&(edge.source, edge.source_pos)),
+                rects.inputs.get(&(edge.target, edge.target_pos)),
+            ) else {
+                continue;
+            };
+
+            // Attraction force between nodes
+            let diff = to_rect.center_top() - from_rect.center_bottom();
+            let mut force = diff.length_sq() / params.scale as f32 * diff.normalized();
+
+            // Repulsion force between layers
+            force -= Vec2::new(0.0, scale2 / diff.y.max(2.0));
+
+            // Take edge multiplicity into account
+            force /= edge_multiplicities[&(edge.source, edge.target)] as f32;
+
+            let (source, target) = (self.shown(edge.source), self.shown(edge.target));
+            if let Some(source) = self.nodes.get_mut(&source) {
+                source.velocity += force * params.dt;
+            }
+            if let Some(target) = self.nodes.get_mut(&target) {
+                target.velocity -= force * params.dt;
+            }
+        }
+
+        // UPDATE POSITIONS //
+
+        let mut moving = false;
+
+        for (txid, node) in &mut self.nodes {
+            if held.contains(txid) {
+                node.velocity = Vec2::ZERO;
+                continue;
+            }
+            node.velocity *= params.cooloff;
+            if node.velocity.length() > 0.2 {
+                moving = true;
+            }
+            if !node.dragged && !self.pinned.contains(txid) {
+                node.pos += node.velocity * params.dt;
+            }
+        }
+
+        if params.parents_above {
+            self.project_parents_above(rects);
+        }
+
+        moving
+    }
+
+    /// Run extra time steps at once, so that the graph relaxes around a
+    /// transaction that was just dropped instead of drifting for a while.
+    fn settle(&mut self, style: &Style, params: &ForceParams) {
+        for _ in 0..params.settle_steps {
+            let rects = self.rects(style);
+            if !self.step(&rects, params) {
+                break;
+            }
+        }
+    }
+
+    /// Move nodes so that every spending transaction sits below its funding
+    /// transaction. Each violated edge pushes both ends apart (or only the
+    /// free end if the other one is being dragged or pinned). A few
+    /// Gauss-Seidel passes are enough since the transaction graph is acyclic.
+    fn project_parents_above(&mut self, rects: &Rects) {
+        const ITERATIONS: usize = 10;
+
+        let Some(height) = rects.outer.values().next().map(|r| r.height()) else {
+            return;
+        };
+        let min_dist = height + 10.0;
+
+        for _ in 0..ITERATIONS {
+            let mut violated = false;
+
+            for edge in self.model.edges() {
+                // Edges of a collapsed group go to the transaction standing in
+                // for it, or are hidden inside it.
+                let (source_txid, target_txid) = (self.shown(edge.source), self.shown(edge.target));
+                if source_txid == target_txid {
+                    continue;
+                }
+                let (Some(source), Some(target)) =
+                    (self.nodes.get(&source_txid), self.nodes.get(&target_txid))
+                else {
+                    continue;
+                };
+                let violation = source.pos.y + min_dist - target.pos.y;
+                if violation <= 0.0 {
+                    continue;
+                }
+                violated = true;
+
+                let source_fixed = source.dragged || self.pinned.contains(&source_txid);
+                let target_fixed = target.dragged || self.pinned.contains(&target_txid);
+                let (source_shift, target_shift) = match (source_fixed, target_fixed) {
+                    (true, true) => continue,
+                    (true, false) => (0.0, violation),
+                    (false, true) => (violation, 0.0),
+                    (false, false) => (violation / 2.0, violation / 2.0),
+                };
+
+                if let Some(source) = self.nodes.get_mut(&source_txid) {
+                    source.pos.y -= source_shift;
+                }
+                if let Some(target) = self.nodes.get_mut(&target_txid) {
+                    target.pos.y += target_shift;
+                }
+            }
+
+            if !violated {
+                break;
+            }
+        }
+    }
+
+    /// The [LayoutMetrics] of the graph as it's drawn. They are only measured
+    /// again once something moved.
+    pub fn metrics(&mut self, style: &Style) -> &LayoutMetrics {
+        let rects = self.rects(style);
+        let fingerprint = layout_fingerprint(&rects, self.model.edges().len());
+        if self.metrics.as_ref().map(|(f, _)| *f) != Some(fingerprint) {
+            self.metrics = Some((fingerprint, self.layout_metrics(&rects)));
+        }
+        &self.metrics.as_ref().unwrap().1
+    }
+
+    fn layout_metrics(&self, rects: &Rects) -> LayoutMetrics {
+        let segments: Vec<(&model::Edge, Pos2, Pos2)> = self
+            .model
+            .edges()
+            .iter()
+            .filter_map(|edge| {
+                let from = rects.outputs.get(&(edge.source, edge.source_pos))?;
+                let to = rects.inputs.get(&(edge.target, edge.target_pos))?;
+                Some((edge, from.center_bottom(), to.center_top()))
+            })
+            .collect();
+
+        let mut crossings = 0;
+        for (i, (a, a0, a1)) in segments.iter().enumerate() {
+            for (b, b0, b1) in &segments[i + 1..] {
+                let adjacent = [a.source, a.target]
+                    .iter()
+                    .any(|txid| *txid == b.source || *txid == b.target);
+                if !adjacent && segments_intersect(*a0, *a1, *b0, *b1) {
+                    crossings += 1;
+                }
+            }
+        }
+
+        let outer: Vec<&Rect> = rects.outer.values().collect();
+        let mut overlaps = 0;
+        for (i, a) in outer.iter().enumerate() {
+            for b in &outer[i + 1..] {
+                if a.intersects(**b) {
+                    overlaps += 1;
+                }
+            }
+        }
+
+        let lengths: Vec<f32> = segments
+            .iter()
+            .map(|(_, a, b)| (*b - *a).length())
+            .collect();
+        let edge_length_variance = if lengths.is_empty() {
+            0.0
+        } else {
+            let n = lengths.len() as f32;
+            let mean = lengths.iter().sum::<f32>() / n;
+            let variance = lengths.iter().map(|l| (l - mean).powi(2)).sum::<f32>() / n;
+            variance / mean.powi(2).max(1.0)
+        };
+
+        LayoutMetrics {
+            crossings,
+            overlaps,
+            edge_length_variance,
+        }
+    }
+}
+
+enum Io {
+    Input(usize),
+    Output(usize),
+}
+
+/// `order` if it covers all `len` inputs/outputs, blockchain order otherwise.
+fn display_order(order: &[usize], len: usize) -> Vec<usize> {
+    if order.len() == len {
+        order.to_vec()
+    } else {
+        (0..len).collect()
+    }
+}
+
+/// Move `index` within `order` to the slot where `x` falls between the centers
+/// of the other `spans`. With `pinned_last`, the last entry stays in place.
+fn move_to_slot(
+    mut order: Vec<usize>,
+    spans: &[(f32, f32)],
+    index: usize,
+    x: f32,
+    pinned_last: bool,
+) -> Vec<usize> {
+    let pinned = if pinned_last { order.pop() } else { None };
+    if pinned != Some(index) {
+        order.retain(|i| *i != index);
+        let slot = order
+            .iter()
+            .filter(|i| (spans[**i].0 + spans[**i].1) / 2.0 < x)
+            .count();
+        order.insert(slot, index);
+    }
+    order.extend(pinned);
+    order
+}
+
+/// Stable sort of `order` by `keys`. With `pinned_last`, the last entry stays
+/// in place.
+fn sort_by_keys(mut order: Vec<usize>, keys: &[f32], pinned_last: bool) -> Vec<usize> {
+    let pinned = if pinned_last { order.pop() } else { None };
+    order.sort_by(|a, b| keys[*a].total_cmp(&keys[*b]));
+    order.extend(pinned);
+    order
+}
+
+#[derive(Default)]
+struct Rects {
+    inputs: HashMap<(Txid, usize), Rect>,
+    outputs: HashMap<(Txid, usize), Rect>,
+    inner: HashMap<Txid, Rect>,
+    outer: HashMap<Txid, Rect>,
+}
+
+/// Changes whenever what [LayoutMetrics] are measured on does: where the
+/// transactions and their coins are drawn, and the edges between them.
+fn layout_fingerprint(rects: &Rects, edges: usize) -> u64 {
+    fn entry(key: impl Hash, rect: &Rect) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        for v in [rect.min.x, rect.min.y, rect.max.x, rect.max.y] {
+            v.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+    // The maps iterate in a different order each time they are built.
+    let outer = rects.outer.iter().map(|(txid, rect)| entry(txid, rect));
+    let inputs = rects.inputs.iter().map(|(io, rect)| entry((0, io), rect));
+    let outputs = rects.outputs.iter().map(|(io, rect)| entry((1, io), rect));
+    outer
+        .chain(inputs)
+        .chain(outputs)
+        .fold(edges as u64, u64::wrapping_add)
+}
+
+pub struct LayoutMetrics {
+    pub crossings: usize,
+    pub overlaps: usize,
+    /// Variance of the edge lengths relative to the squared mean length.
+    pub edge_length_variance: f32,
+}
+
+impl LayoutMetrics {
+    /// Lower is better.
+    pub fn score(&self) -> f32 {
+        self.crossings as f32 + 10.0 * self.overlaps as f32 + self.edge_length_variance
+    }
+}
+
+fn segments_intersect(a0: Pos2, a1: Pos2, b0: Pos2, b1: Pos2) -> bool {
+    fn orientation(p: Pos2, q: Pos2, r: Pos2) -> f32 {
+        (q - p).x * (r - p).y - (q - p).y * (r - p).x
+    }
+    orientation(a0, a1, b0) * orientation(a0, a1, b1) < 0.0
+        && orientation(b0, b1, a0) * orientation(b0, b1, a1) < 0.0
+}
+
+/// Where to put a transaction that is loaded from the input or output at
+/// `rect` of the transaction at `outer`. The new transaction is at least as
+/// wide as the coin's `value`, so the gap grows with both sizes.
+fn spawn_pos(
+    layout: &Layout,
+    style: &Style,
+    outer: Rect,
+    rect: Rect,
+    value: u64,
+    ancestor: bool,
+) -> Pos2 {
+    let new_size = layout.scale.estimate(value) as f32;
+    let gap = 5.0 + layout.spawn.distance * (outer.width() + new_size) / 2.0;
+    let sign = if ancestor { -1.0 } else { 1.0 };
+
+    match layout.spawn.direction {
+        SpawnDirection::Vertical => {
+            let from = if ancestor {
+                rect.center_top()
+            } else {
+                rect.center_bottom()
+            };
+            from + sign * Vec2::new(0.0, style.io_width + style.tx_width / 2.0 + gap)
+        }
+        SpawnDirection::Horizontal => {
+            let x = if ancestor {
+                outer.left() - gap - new_size / 2.0
+            } else {
+                outer.right() + gap + new_size / 2.0
+            };
+            Pos2::new(x, outer.center().y + sign * outer.height() / 2.0)
+        }
+    }
+}
+
+/// Which way to expand the graph from a transaction.
+#[derive(Clone, Copy, PartialEq)]
+pub enum HopDirection {
+    Ancestors,
+    Descendants,
+    Both,
+}
+
+impl HopDirection {
+    const ALL: [HopDirection; 3] = [
+        HopDirection::Ancestors,
+        HopDirection::Descendants,
+        HopDirection::Both,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            HopDirection::Ancestors => "Ancestors",
+            HopDirection::Descendants => "Descendants",
+            HopDirection::Both => "Both Directions",
+        }
+    }
+
+    /// The transactions one hop away from `tx`, each with whether it's an
+    /// ancestor. Unspent outputs lead nowhere.
+    pub fn neighbors(self, tx: &Transaction) -> Vec<(Txid, bool)> {
+        let mut neighbors: Vec<(Txid, bool)> = vec![];
+        if self != HopDirection::Descendants {
+            neighbors.extend(tx.inputs.iter().map(|input| (input.txid, true)));
+        }
+        if self != HopDirection::Ancestors {
+            neighbors.extend(
+                tx.outputs
+                    .iter()
+                    .filter_map(|output| Some((output.spending_txid?, false))),
+            );
+        }
+        let mut seen = HashSet::default();
+        neighbors.retain(|(txid, _)| seen.insert(*txid));
+        neighbors
+    }
+}
+
+/// Where to put the `n` ancestors or descendants of the transaction at `pos`
+/// when they are loaded together: a row one step along the spawn direction,
+/// spread across it so they don't land in a pile.
+pub fn hop_positions(pos: Pos2, n: usize, ancestor: bool, direction: SpawnDirection) -> Vec<Pos2> {
+    const ALONG: f32 = 150.0;
+    const ACROSS: f32 = 60.0;
+    let sign = if ancestor { -1.0 } else { 1.0 };
+    let start = -(n.saturating_sub(1) as f32) * ACROSS / 2.0;
+
+    (0..n)
+        .map(|i| {
+            let across = start + i as f32 * ACROSS;
+            match direction {
+                SpawnDirection::Vertical => pos + Vec2::new(across, sign * ALONG),
+                SpawnDirection::Horizontal => pos + Vec2::new(sign * ALONG, across),
+            }
+        })
+        .collect()
+}
+
+/// Where to put `n` transactions so they read like a history: in order along
+/// the spawn direction, centered on `center`, and zigzagging a little so the
+/// layout can untangle their edges.
+pub fn chronological_positions(n: usize, center: Pos2, direction: SpawnDirection) -> Vec<Pos2> {
+    const GAP: f32 = 120.0;
+    let start = -(n.saturating_sub(1) as f32) * GAP / 2.0;
+
+    (0..n)
+        .map(|i| {
+            let along = start + i as f32 * GAP;
+            let across = if i % 2 == 0 { -GAP / 4.0 } else { GAP / 4.0 };
+            match direction {
+                SpawnDirection::Vertical => center + Vec2::new(across, along),
+                SpawnDirection::Horizontal => center + Vec2::new(along, across),
+            }
+        })
+        .collect()
+}
+
+/// Space between an expanded cluster and its frame.
+const CLUSTER_MARGIN: f32 = 10.0;
+
+/// Long enough to still be seen after flying to the transaction.
+const FLASH_SECONDS: f64 = 3.0;
+
+/// Opacity of what's out of focus, see [crate::layout::Focus].
+const DIMMED: f32 = 0.2;
+
+/// Write the amounts of edges at their middle, or just above or below, largest
+/// first. Amounts that would overlap others are left out.
+fn draw_edge_labels(
+    painter: &Painter,
+    mut labels: Vec<(Pos2, u64)>,
+    style: &Style,
+    format: NumberFormat,
+) {
+    labels.sort_by_key(|(_, value)| Reverse(*value));
+    let mut placed: Vec<Rect> = vec![];
+    for (pos, value) in labels {
+        let mut job = LayoutJob::default();
+        sats_layout(&mut job, &Sats(value), style, format);
+        let galley = painter.layout_job(job);
+        let size = galley.size() + Vec2::splat(4.0);
+        let Some(rect) = [0.0, -1.0, 1.0]
+            .into_iter()
+            .map(|shift| Rect::from_center_size(pos + Vec2::new(0.0, shift * size.y), size))
+            .find(|rect| !placed.iter().any(|other| other.intersects(*rect)))
+        else {
+            continue;
+        };
+        let bg = style.egui_style.visuals.extreme_bg_color;
+        painter.rect_filled(rect, Rounding::same(2.0), bg.gamma_multiply(0.8));
+        painter.galley(rect.min + Vec2::splat(2.0), galley, Color32::TRANSPARENT);
+        placed.push(rect);
+    }
+}
+
+fn fill_rect(painter: &Painter, batch: &mut Option<RectBatch>, rect: Rect, color: Color32) {
+    match batch {
+        Some(batch) => batch.rect_filled(rect, color.gamma_multiply(painter.opacity())),
+        None => {
+            painter.rect_filled(rect, Rounding::ZERO, color);
+        }
+    }
+}
+
+fn clear_spacing(a: &Rect, b: &Rect) -> f32 {
+    let x = (a.center().x - b.center().x).abs() - (b.width() + a.width()) / 2.0;
+    let y = (a.center().y - b.center().y).abs() - (b.height() + a.height()) / 2.0;
+    x.max(y).max(2.0)
+}
+
+fn tx_content(
+    txid: &Txid,
+    label: &Option<String>,
+    timestamp: &str,
+    sats: &Sats,
+    number_format: NumberFormat,
+    style: &Style,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let font_id = style.font_id();
+    let format = TextFormat {
+        font_id: font_id.clone(),
+        color: style.black_text_color(),
+        ..Default::default()
+    };
+
+    if let Some(label) = label {
+        job.append(label, 0.0, format.clone());
+    } else {
+        txid_layout(&mut job, txid, style);
+    }
+    newline(&mut job, &font_id);
+    sats_layout(&mut job, sats, style, number_format);
+    newline(&mut job, &font_id);
+    job.append(timestamp, 0.0, format);
+    job
+}
+
+/// Text of a collapsed group: its name, the value going out and a detail.
+fn group_content(
+    title: &str,
+    sats: &Sats,
+    number_format: NumberFormat,
+    detail: &str,
+    style: &Style,
+) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let font_id = style.font_id();
+    let format = TextFormat {
+        font_id: font_id.clone(),
+        color: style.black_text_color(),
+        ..Default::default()
+    };
+
+    job.append(title, 0.0, format.clone());
+    newline(&mut job, &font_id);
+    sats_layout(&mut job, sats, style, number_format);
+    newline(&mut job, &font_id);
+    job.append(detail, 0.0, format);
+    job
+}
+
+fn newline(job: &mut LayoutJob, font_id: &FontId) {
+    job.append(
+        "\n",
+        0.0,
+        TextFormat {
+            font_id: font_id.clone(),
+            ..Default::default()
+        },
+    );
+}
+
+const SPACING: f32 = 3.0;
+
+/// Hex characters of a script without an address that are shown.
+const MAX_SCRIPT_CHARS: usize = 64;
+
+fn txid_layout(job: &mut LayoutJob, txid: &Txid, style: &Style) {
+    let black_format = TextFormat {
+        font_id: style.font_id(),
+        color: style.black_text_color(),
+        ..Default::default()
+    };
+    let white_format = TextFormat {
+        font_id: style.font_id(),
+        color: style.white_text_color(),
+        ..Default::default()
+    };
+
+    let mut first = true;
+    let mut black = true;
+
+    for chunk in txid.chunks() {
+        job.append(
+            &chunk,
+            if first { 0.0 } else { SPACING },
+            if black {
+                black_format.clone()
+            } else {
+                white_format.clone()
+            },
+        );
+        first = false;
+        black = !black;
+    }
+}
+
+pub fn sats_layout(job: &mut LayoutJob, sats: &Sats, style: &Style, format: NumberFormat) {
+    let font_id = style.font_id();
+    let btc_font = FontId::new(font_id.size, egui::FontFamily::Name("btc".into()));
+    let btc_format = TextFormat {
+        font_id: btc_font,
+        color: style.btc_color(),
+        ..Default::default()
+    };
+    job.append("\u{E9A8}", 0.0, btc_format);
+
+    let amount = sats.0;
+
+    let black_format = TextFormat {
+        font_id: font_id.clone(),
+        color: style.black_text_color(),
+        ..Default::default()
+    };
+    let white_format = TextFormat {
+        font_id: font_id.clone(),
+        color: style.white_text_color(),
+        ..Default::default()
+    };
+
+    let AmountComponents {
+        sats,
+        ksats,
+        msats,
+        btc,
+    } = sats.components();
+
+    let mut started = false;
+
+    if !btc.is_empty() {
+        job.append(&format!("{}", btc[0]), SPACING, black_format.clone());
+        started = true;
+
+        for amount in btc.iter().skip(1) {
+            job.append(&format!("{:03}", amount), SPACING, black_format.clone());
+        }
+    } else {
+        job.append(
+            "0",
+            SPACING,
+            if amount % 1_000_000 == 0 && amount > 100_000 {
+                black_format.clone()
+            } else {
+                white_format.clone()
+            },
+        );
+    }
+
+    #[allow(clippy::collapsible_else_if)]
+    job.append(
+        &format.decimal().to_string(),
+        0.0,
+        if started {
+            if amount % 100_000_000 == 0 {
+                white_format.clone()
+            } else {
+                black_format.clone()
+            }
+        } else {
+            if amount % 1_000_000 == 0 && amount > 100_000 {
+                black_format.clone()
+            } else {
+                white_format.clone()
+            }
+        },
+    );
+
+    if started {
+        job.append(
+            &format!("{:02}", msats.unwrap_or(0)),
+            0.0,
+            if amount % 100_000_000 == 0 {
+                white_format.clone()
+            } else {
+                black_format.clone()
+            },
+        );
+    } else if let Some(m) = msats {
+        if m < 10 {
+            job.append(
+                "0",
+                0.0,
+                if amount % 1_000_000 == 0 {
+                    black_format.clone()
+                } else {
+                    white_format.clone()
+                },
+            );
+        }
+        job.append(&format!("{}", m), 0.0, black_format.clone());
+        started = true;
+    } else {
+        job.append("00", 0.0, white_format.clone());
+    }
+
+    job.append("", SPACING, white_format.clone());
+    if started {
+        job.append(
+            &format!("{:03}", ksats.unwrap_or(0)),
+            0.0,
+            if amount % 1_000_000 == 0 {
+                white_format.clone()
+            } else {
+                black_format.clone()
+            },
+        );
+    } else if let Some(k) = ksats {
+        if k < 10 {
+            job.append("00", 0.0, white_format.clone());
+        } else if k < 100 {
+            job.append("0", 0.0, white_format.clone());
+        }
+        job.append(&format!("{}", k), 0.0, black_format.clone());
+        started = true;
+    } else {
+        job.append("000", 0.0, white_format.clone());
+    }
+
+    job.append("", SPACING, white_format.clone());
+    if started {
+        job.append(
+            &format!("{:03}", sats),
+            0.0,
+            if amount % 1_000_000 == 0 {
+                white_format.clone()
+            } else {
+                black_format.clone()
+            },
+        );
+    } else {
+        if sats < 10 {
+            job.append("00", 0.0, white_format);
+        } else if sats < 100 {
+            job.append("0", 0.0, white_format);
+        }
+        job.append(&format!("{}", sats), 0.0, black_format.clone());
+    }
+
+    job.append("sats", SPACING, black_format);
+}
+
+fn address_layout(job: &mut LayoutJob, address: &str, address_type: AddressType, style: &Style) {
+    let black_format = TextFormat {
+        font_id: style.font_id(),
+        color: style.black_text_color(),
+        ..Default::default()
+    };
+    let white_format = TextFormat {
+        color: style.white_text_color(),
+        ..black_format.clone()
+    };
+    let highlight_format = TextFormat {
+        color: style.tx_bg,
+        ..black_format.clone()
+    };
+    let mut small = style.font_id();
+    small.size *= 0.7;
+    let type_format = TextFormat {
+        font_id: small,
+        valign: Align::Center,
+        ..black_format
+    };
+
+    let highlight = match address_type {
+        AddressType::P2TR => 4,   // "bc1p"
+        AddressType::P2PKH => 1,  // "1"
+        AddressType::P2SH => 1,   // "3"
+        AddressType::P2WPKH => 4, // "bc1q"
+        AddressType::P2WSH => 4,  // "bc1q"
+        AddressType::Unknown => 0,
+    };
+
+    // Scripts without an address are identified by their hex, see
+    // [crate::bitcoin::coin_address].
+    let script_type = match address_type {
+        AddressType::Unknown if address.is_empty() => {
+            job.append("unknown script", 0.0, white_format);
+            job.append(" (?)", 0.0, type_format);
+            return;
+        }
+        AddressType::Unknown => Some(ScriptType::from_hex(address)),
+        _ => None,
+    };
+
+    // Backends send whatever they make of unusual scripts, so count in
+    // characters and stop at the end.
+    let at = |address: &str, n: usize| {
+        address
+            .char_indices()
+            .nth(n)
+            .map_or(address.len(), |(i, _)| i)
+    };
+    // Data carriers can be long.
+    let full = address;
+    let address = match script_type {
+        Some(_) => &full[..at(full, MAX_SCRIPT_CHARS)],
+        None => full,
+    };
+    let at = |n: usize| at(address, n);
+    job.append(&address[..at(highlight)], 0.0, highlight_format);
+    job.append(&address[at(highlight)..at(4)], 0.0, black_format.clone());
+
+    let mut black = false;
+    for i in 1..=(address.chars().count() / 4) {
+        job.append(
+            &address[at(i * 4)..at(i * 4 + 4)],
+            SPACING,
+            if black {
+                black_format.clone()
+            } else {
+                white_format.clone()
+            },
+        );
+        black = !black;
+    }
+    if address.len() < full.len() {
+        job.append("…", 0.0, white_format);
+    }
+
+    let type_ = match address_type {
+        AddressType::P2PKH => "p2pkh",
+        AddressType::P2SH => "p2sh",
+        AddressType::P2WPKH => "p2wpkh",
+        AddressType::P2WSH => "p2wsh",
+        AddressType::P2TR => "p2tr",
+        AddressType::Unknown => script_type.map_or("?", ScriptType::short_name),
+    };
+
+    job.append(&format!(" ({})", type_), 0.0, type_format);
+}
+
+/// Fill the given rect with an animated striped pattern.
+fn rect_striped(ui: &egui::Ui, rect: Rect, color: Color32) {
+    let width: f32 = 6.;
+    let speed = 20.;
+    let time = ui.input(|i| i.time);
+
+    let mut mesh = Mesh::default();
+    let mut start = rect.left_top() + Vec2::new((time as f32 * speed % width * 2.) - width, 0.);
+    let mut i0 = 0;
+
+    loop {
+        let nw = start;
+        let sw = nw + Vec2::new(-rect.height(), rect.height());
+        let ne = nw + Vec2::new(width, 0.);
+        let se = sw + Vec2::new(width, 0.);
+
+        mesh.colored_vertex(nw, color);
+        mesh.colored_vertex(ne, color);
+        mesh.colored_vertex(se, color);
+        mesh.colored_vertex(sw, color);
+        mesh.add_triangle(i0, i0 + 1, i0 + 2);
+        mesh.add_triangle(i0, i0 + 2, i0 + 3);
+
+        start += Vec2::new(width * 2.0, 0.);
+        i0 += 4;
+
+        if start.x > rect.right_top().x + rect.height() + width {
+            break;
+        }
+    }
+
+    ui.painter().with_clip_rect(rect).add(mesh);
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    fn style() -> Style {
+        Style::light(Arc::new(egui::Style::default()))
+    }
+
+    #[test]
+    fn sats_layout_spells_the_amount() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4526);
+        let mut amounts = vec![0, 1, 999, 1_000, 100_000, 1_000_000, 100_000_000, u64::MAX];
+        // Spread over all magnitudes, with some round numbers.
+        amounts.extend((0..1000).map(|_| rng.gen::<u64>() >> rng.gen_range(0..64)));
+        amounts.extend((0..100).map(|_| rng.gen_range(0..21_000_000) * 1_000_000));
+
+        let format = NumberFormat::German;
+        for amount in amounts {
+            let mut job = LayoutJob::default();
+            sats_layout(&mut job, &Sats(amount), &style(), format);
+            let digits: String = job
+                .text
+                .trim_start_matches('\u{E9A8}')
+                .trim_end_matches("sats")
+                .chars()
+                .filter(|c| *c != format.decimal())
+                .collect();
+            assert_eq!(digits.parse::<u64>(), Ok(amount), "{}", job.text);
+        }
+    }
+
+    #[test]
+    fn address_layout_of_any_string() {
+        const CHARS: [char; 6] = ['b', 'c', '1', 'q', 'é', '€'];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4526);
+        let types = [
+            AddressType::P2PKH,
+            AddressType::P2SH,
+            AddressType::P2WPKH,
+            AddressType::P2WSH,
+            AddressType::P2TR,
+            AddressType::Unknown,
+        ];
+        for _ in 0..1000 {
+            let len = rng.gen_range(0..70);
+            let address: String = (0..len)
+                .map(|_| CHARS[rng.gen_range(0..CHARS.len())])
+                .collect();
+            let address_type = types[rng.gen_range(0..types.len())];
+            let mut job = LayoutJob::default();
+            address_layout(&mut job, &address, address_type, &style());
+            if address_type != AddressType::Unknown {
+                assert!(
+                    job.text.starts_with(&format!("{} (", address)),
+                    "{}",
+                    job.text
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn address_layout_of_scripts() {
+        let layout = |script: &str| {
+            let mut job = LayoutJob::default();
+            address_layout(&mut job, script, AddressType::Unknown, &style());
+            job.text
+        };
+        assert_eq!(layout(""), "unknown script (?)");
+        assert_eq!(layout("6a0568656c6c6f"), "6a0568656c6c6f (op_return)");
+        let data = format!("6a4c50{}", "00".repeat(80));
+        assert_eq!(
+            layout(&data),
+            format!("{}… (op_return)", &data[..MAX_SCRIPT_CHARS])
+        );
+    }
+}
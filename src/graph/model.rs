@@ -0,0 +1,1195 @@
+//! The transactions of a graph and how they are connected, independent of how
+//! they are laid out and drawn.
+
+use std::{collections::HashMap, fmt::Write};
+
+use ahash::HashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bitcoin::{
+        coin_address, AddressType, CoinJoin, FeeRate, Multisig, Sats, TaprootSpend, Transaction,
+        Txid,
+    },
+    export,
+};
+
+/// How many times larger than the payout the continuing output of a peel
+/// chain is at least.
+pub const PEEL_RATIO: u64 = 4;
+
+/// Fewer peels in a row happen by chance, with any payment and its change.
+pub const MIN_PEEL_CHAIN: usize = 3;
+
+/// The addresses of a transaction's inputs and outputs, as far as needed for
+/// [crate::components::ownership::classify].
+pub struct TxView<'a> {
+    pub txid: Txid,
+    pub inputs: Vec<(&'a str, AddressType)>,
+    pub outputs: Vec<(&'a str, AddressType)>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Model {
+    txs: HashMap<Txid, Tx>,
+    edges: Vec<Edge>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Tx {
+    pub value: u64,
+    /// Block time, formatted for display with [crate::settings::Settings].
+    #[serde(default)]
+    pub timestamp: i64,
+    pub block_height: u32,
+    pub inputs: Vec<Input>,
+    /// Blockchain order, followed by the fees unless it's a coinbase
+    /// transaction.
+    pub outputs: Vec<Output>,
+    #[serde(default)]
+    pub coinjoin: Option<CoinJoin>,
+    /// Problems with the amounts, see [Transaction::validate].
+    #[serde(default)]
+    pub issues: Vec<String>,
+    /// Unknown if the backend doesn't send the size of the transaction.
+    #[serde(default)]
+    pub fee_rate: Option<FeeRate>,
+}
+
+/// An output spent by an input, where both transactions are in the graph.
+#[derive(Clone, Hash, Serialize, Deserialize)]
+pub struct Edge {
+    pub source: Txid,
+    pub source_pos: usize,
+    pub target: Txid,
+    pub target_pos: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Input {
+    pub value: u64,
+    pub address: String,
+    pub address_type: AddressType,
+    pub funding_txid: Txid, // TODO: coinbase tx?
+    pub funding_vout: u32,
+    pub taproot_spend: Option<TaprootSpend>,
+    pub multisig: Option<Multisig>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Output {
+    pub value: u64,
+    pub output_type: OutputType,
+}
+
+impl Output {
+    pub fn address(&self) -> Option<&str> {
+        match &self.output_type {
+            OutputType::Utxo { address, .. } => Some(address),
+            OutputType::Spent { address, .. } => Some(address),
+            OutputType::Fees => None,
+        }
+    }
+
+    pub fn address_type(&self) -> Option<AddressType> {
+        match &self.output_type {
+            OutputType::Utxo { address_type, .. } => Some(*address_type),
+            OutputType::Spent { address_type, .. } => Some(*address_type),
+            OutputType::Fees => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum OutputType {
+    Utxo {
+        address: String,
+        address_type: AddressType,
+    },
+    Spent {
+        spending_txid: Txid,
+        address: String,
+        address_type: AddressType,
+    },
+    Fees,
+}
+
+impl Tx {
+    fn new(tx: &Transaction) -> Self {
+        let inputs = tx
+            .inputs
+            .iter()
+            .map(|i| Input {
+                value: i.value,
+                address: coin_address(&i.address, i.address_type, &i.script_pubkey),
+                address_type: i.address_type,
+                funding_txid: i.txid,
+                funding_vout: i.vout,
+                taproot_spend: i.taproot_spend(),
+                multisig: i.multisig(),
+            })
+            .collect();
+
+        let mut outputs: Vec<Output> = tx
+            .outputs
+            .iter()
+            .map(|o| {
+                let address = coin_address(&o.address, o.address_type, &o.script_pubkey);
+                Output {
+                    value: o.value,
+                    output_type: match o.spending_txid {
+                        None => OutputType::Utxo {
+                            address,
+                            address_type: o.address_type,
+                        },
+                        Some(txid) => OutputType::Spent {
+                            spending_txid: txid,
+                            address,
+                            address_type: o.address_type,
+                        },
+                    },
+                }
+            })
+            .collect();
+
+        // Coinbase txs don't have fees
+        if !tx.is_coinbase() {
+            outputs.push(Output {
+                value: tx.fees(),
+                output_type: OutputType::Fees,
+            });
+        }
+
+        Self {
+            value: tx.amount(),
+            timestamp: tx.timestamp,
+            block_height: tx.block_height,
+            inputs,
+            outputs,
+            coinjoin: tx.coinjoin(),
+            issues: tx.validate(),
+            fee_rate: tx.fee_rate(),
+        }
+    }
+
+    pub fn summary(&self) -> export::Summary {
+        export::Summary {
+            value: self.value,
+            block_height: self.block_height,
+            timestamp: self.timestamp,
+        }
+    }
+
+    /// The fees are always the last output.
+    pub fn has_fees(&self) -> bool {
+        matches!(
+            self.outputs.last().map(|o| &o.output_type),
+            Some(OutputType::Fees)
+        )
+    }
+
+    pub fn fees(&self) -> u64 {
+        match self.outputs.last() {
+            Some(Output {
+                value,
+                output_type: OutputType::Fees,
+            }) => *value,
+            _ => 0,
+        }
+    }
+
+    pub fn export_beancount(&self, txid: &Txid, label: Option<String>) -> String {
+        let mut s = String::new();
+        writeln!(
+            s,
+            "{} * \"{}\" ^{}",
+            &format_timestamp(self.timestamp)[0..10],
+            label.unwrap_or("".to_string()),
+            txid.hex_string()
+        )
+        .unwrap();
+        for input in &self.inputs {
+            writeln!(
+                s,
+                "  Assets:Bitcoin:{:<72} {:>20.8} BTC",
+                input.address,
+                -(input.value as f64) / 100_000_000.0
+            )
+            .unwrap();
+        }
+        for output in &self.outputs {
+            let account = match &output.output_type {
+                OutputType::Fees => format!("Expenses:Bitcoin:Fees{:66}", " "),
+                OutputType::Spent {
+                    spending_txid: _,
+                    address,
+                    address_type: _,
+                } => format!("Assets:Bitcoin:{:<72}", address),
+                OutputType::Utxo {
+                    address,
+                    address_type: _,
+                } => format!("Assets:Bitcoin:{:<72}", address),
+            };
+            if output.value > 0 {
+                writeln!(
+                    s,
+                    "  {} {:>20.8} BTC",
+                    account,
+                    (output.value as f64) / 100_000_000.0
+                )
+                .unwrap();
+            }
+        }
+        s
+    }
+}
+
+impl Model {
+    pub fn tx(&self, txid: &Txid) -> Option<&Tx> {
+        self.txs.get(txid)
+    }
+
+    pub fn txs(&self) -> impl Iterator<Item = (&Txid, &Tx)> {
+        self.txs.iter()
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    /// The input at the end of `edge`.
+    pub fn edge_input(&self, edge: &Edge) -> &Input {
+        &self.txs[&edge.target].inputs[edge.target_pos]
+    }
+
+    /// Blocks the coin on `edge` rested between its funding and spending
+    /// transactions.
+    pub fn blocks_rested(&self, edge: &Edge) -> u32 {
+        let funded = self.txs[&edge.source].block_height;
+        self.txs[&edge.target].block_height.saturating_sub(funded)
+    }
+
+    /// Seconds between the funding and spending transactions of the coin on
+    /// `edge`.
+    pub fn seconds_rested(&self, edge: &Edge) -> i64 {
+        let funded = self.txs[&edge.source].timestamp;
+        (self.txs[&edge.target].timestamp - funded).max(0)
+    }
+
+    /// Add `tx` and connect it to the transactions it spends from or that
+    /// spend from it. Inputs that spend outputs which don't exist, because of
+    /// inconsistent data from the backend, aren't connected. They are
+    /// described in the result instead.
+    pub fn add_tx(&mut self, txid: Txid, tx: &Transaction) -> Vec<String> {
+        // Replace it rather than connecting it twice.
+        self.remove_tx(txid);
+        self.txs.insert(txid, Tx::new(tx));
+
+        let mut edges = vec![];
+        for (i, input) in tx.inputs.iter().enumerate() {
+            if self.txs.contains_key(&input.txid) {
+                edges.push(Edge {
+                    source: input.txid,
+                    source_pos: input.vout as usize,
+                    target: txid,
+                    target_pos: i,
+                });
+            }
+        }
+
+        // Go by the inputs of the other transactions rather than the spending
+        // txids of the outputs. Those may be out of date, and a coin can be
+        // spent by more than one loaded transaction, see [Self::conflicts].
+        for (spending_txid, spending_tx) in &self.txs {
+            for (i, input) in spending_tx.inputs.iter().enumerate() {
+                if input.funding_txid == txid {
+                    edges.push(Edge {
+                        source: txid,
+                        source_pos: input.funding_vout as usize,
+                        target: *spending_txid,
+                        target_pos: i,
+                    });
+                }
+            }
+        }
+
+        let mut dropped = vec![];
+        for edge in edges {
+            let exists = self
+                .txs
+                .get(&edge.source)
+                .and_then(|tx| tx.outputs.get(edge.source_pos))
+                .is_some_and(|output| !matches!(output.output_type, OutputType::Fees));
+            if exists {
+                self.edges.push(edge);
+            } else {
+                dropped.push(format!(
+                    "Input {} of {} spends {}:{}, which doesn't exist.",
+                    edge.target_pos, edge.target, edge.source, edge.source_pos
+                ));
+            }
+        }
+        dropped
+    }
+
+    /// Coins spent by more than one loaded transaction, with those
+    /// transactions. Only one of them can be confirmed, the others have been
+    /// replaced or come from stale data.
+    pub fn conflicts(&self) -> HashMap<(Txid, usize), Vec<Txid>> {
+        let mut spenders: HashMap<(Txid, usize), Vec<Txid>> = HashMap::new();
+        for (txid, tx) in &self.txs {
+            for input in &tx.inputs {
+                let coin = (input.funding_txid, input.funding_vout as usize);
+                spenders.entry(coin).or_default().push(*txid);
+            }
+        }
+        spenders.retain(|_, txids| txids.len() > 1);
+        for txids in spenders.values_mut() {
+            txids.sort();
+        }
+        spenders
+    }
+
+    /// Whether `txid` has one input and one output besides the fees, so that
+    /// it only moves a coin along.
+    fn is_hop(&self, txid: &Txid) -> bool {
+        self.txs.get(txid).is_some_and(|tx| {
+            let outputs = tx
+                .outputs
+                .iter()
+                .filter(|o| !matches!(o.output_type, OutputType::Fees));
+            tx.inputs.len() == 1 && outputs.count() == 1
+        })
+    }
+
+    /// The longest run of hops through `txid` where each one spends the output
+    /// of the one before, in spending order. Empty if `txid` isn't a hop. A
+    /// double spend ends the run.
+    pub fn hops_through(&self, txid: Txid) -> Vec<Txid> {
+        if !self.is_hop(&txid) {
+            return vec![];
+        }
+        // The only transaction connected on one side, if it's a hop too.
+        let neighbor = |txid: Txid, forward: bool| {
+            let mut neighbors = self.edges.iter().filter_map(|edge| match forward {
+                true => (edge.source == txid).then_some(edge.target),
+                false => (edge.target == txid).then_some(edge.source),
+            });
+            match (neighbors.next(), neighbors.next()) {
+                (Some(neighbor), None) if self.is_hop(&neighbor) => Some(neighbor),
+                _ => None,
+            }
+        };
+        let linked = |from: Txid, forward: bool| {
+            neighbor(from, forward).filter(|to| neighbor(*to, !forward) == Some(from))
+        };
+
+        let mut seen = HashSet::from_iter([txid]);
+        let mut hops = vec![txid];
+        while let Some(funding) = linked(hops[0], false).filter(|t| seen.insert(*t)) {
+            hops.insert(0, funding);
+        }
+        while let Some(spending) = linked(hops[hops.len() - 1], true).filter(|t| seen.insert(*t)) {
+            hops.push(spending);
+        }
+        hops
+    }
+
+    /// All runs of at least `min_len` hops, see [Self::hops_through].
+    pub fn hop_chains(&self, min_len: usize) -> Vec<Vec<Txid>> {
+        let mut seen = HashSet::default();
+        let mut chains = vec![];
+        for txid in self.txs.keys() {
+            if seen.contains(txid) {
+                continue;
+            }
+            let hops = self.hops_through(*txid);
+            seen.extend(hops.iter().copied());
+            if hops.len() >= min_len {
+                chains.push(hops);
+            }
+        }
+        chains
+    }
+
+    /// The continuing output of `txid` if it looks like a step of a peel
+    /// chain: one input, and two outputs besides the fees of which one is at
+    /// least [PEEL_RATIO] times the other. The small one is the payout.
+    pub fn peel(&self, txid: &Txid) -> Option<usize> {
+        let tx = self.txs.get(txid)?;
+        if tx.inputs.len() != 1 {
+            return None;
+        }
+        let mut outputs = tx
+            .outputs
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| !matches!(o.output_type, OutputType::Fees));
+        let (Some((a, first)), Some((b, second)), None) =
+            (outputs.next(), outputs.next(), outputs.next())
+        else {
+            return None;
+        };
+        if first.value >= second.value.saturating_mul(PEEL_RATIO) {
+            Some(a)
+        } else if second.value >= first.value.saturating_mul(PEEL_RATIO) {
+            Some(b)
+        } else {
+            None
+        }
+    }
+
+    /// The run of peels through `txid` where each one spends the continuing
+    /// output of the one before, in spending order. Empty if `txid` isn't a
+    /// peel.
+    pub fn peels_through(&self, txid: Txid) -> Vec<Txid> {
+        if self.peel(&txid).is_none() {
+            return vec![];
+        }
+        let next = |txid: Txid| {
+            let vout = self.peel(&txid)?;
+            match self.txs[&txid].outputs[vout].output_type {
+                OutputType::Spent { spending_txid, .. } => {
+                    self.peel(&spending_txid).map(|_| spending_txid)
+                }
+                _ => None,
+            }
+        };
+        // Of a double spend, only the one the funding transaction knows about
+        // continues the run.
+        let previous = |txid: Txid| {
+            let funding = self.txs[&txid].inputs[0].funding_txid;
+            (next(funding) == Some(txid)).then_some(funding)
+        };
+
+        let mut seen = HashSet::from_iter([txid]);
+        let mut peels = vec![txid];
+        while let Some(funding) = previous(peels[0]).filter(|t| seen.insert(*t)) {
+            peels.insert(0, funding);
+        }
+        while let Some(spending) = next(peels[peels.len() - 1]).filter(|t| seen.insert(*t)) {
+            peels.push(spending);
+        }
+        peels
+    }
+
+    /// All runs of at least `min_len` peels, see [Self::peels_through], the
+    /// longest first.
+    pub fn peel_chains(&self, min_len: usize) -> Vec<Vec<Txid>> {
+        let mut seen = HashSet::default();
+        let mut chains = vec![];
+        for txid in self.txs.keys() {
+            if seen.contains(txid) {
+                continue;
+            }
+            let peels = self.peels_through(*txid);
+            seen.extend(peels.iter().copied());
+            if peels.len() >= min_len {
+                chains.push(peels);
+            }
+        }
+        chains.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        chains
+    }
+
+    pub fn remove_tx(&mut self, txid: Txid) {
+        self.txs.remove(&txid);
+        self.edges
+            .retain(|edge| edge.source != txid && edge.target != txid);
+    }
+
+    /// Mark an unspent output as spent by `spending_txid`. Returns false if the
+    /// output isn't known as unspent. The edge to the spending transaction,
+    /// if it's in the model, exists already as it's added from the inputs.
+    pub fn set_spent(&mut self, coin: (Txid, usize), spending_txid: Txid) -> bool {
+        let (txid, vout) = coin;
+        let Some(output) = self
+            .txs
+            .get_mut(&txid)
+            .and_then(|tx| tx.outputs.get_mut(vout))
+        else {
+            return false;
+        };
+        let OutputType::Utxo {
+            address,
+            address_type,
+        } = &output.output_type
+        else {
+            return false;
+        };
+        output.output_type = OutputType::Spent {
+            spending_txid,
+            address: address.clone(),
+            address_type: *address_type,
+        };
+        true
+    }
+
+    /// All transactions reachable from `txid` via edges, in either direction.
+    pub fn connected_component(&self, txid: Txid) -> HashSet<Txid> {
+        let mut component = HashSet::default();
+        let mut todo = vec![txid];
+        while let Some(txid) = todo.pop() {
+            if !self.txs.contains_key(&txid) || !component.insert(txid) {
+                continue;
+            }
+            for edge in &self.edges {
+                if edge.source == txid {
+                    todo.push(edge.target);
+                } else if edge.target == txid {
+                    todo.push(edge.source);
+                }
+            }
+        }
+        component
+    }
+
+    /// Number the connected components in the order of their smallest
+    /// transaction id, so that they mostly keep their number as the graph
+    /// grows.
+    pub fn components(&self) -> HashMap<Txid, usize> {
+        let mut neighbors: HashMap<Txid, Vec<Txid>> = HashMap::new();
+        for edge in &self.edges {
+            neighbors.entry(edge.source).or_default().push(edge.target);
+            neighbors.entry(edge.target).or_default().push(edge.source);
+        }
+        let mut txids: Vec<&Txid> = self.txs.keys().collect();
+        txids.sort();
+
+        let mut components = HashMap::new();
+        let mut count = 0;
+        for txid in txids {
+            if components.contains_key(txid) {
+                continue;
+            }
+            let mut todo = vec![*txid];
+            while let Some(txid) = todo.pop() {
+                if components.insert(txid, count).is_none() {
+                    todo.extend(neighbors.get(&txid).into_iter().flatten());
+                }
+            }
+            count += 1;
+        }
+        components
+    }
+
+    /// The transactions at most `hops` edges away from any of `txids`, in
+    /// either direction, including themselves.
+    pub fn within_hops(&self, txids: impl IntoIterator<Item = Txid>, hops: usize) -> HashSet<Txid> {
+        let mut within: HashSet<Txid> = txids
+            .into_iter()
+            .filter(|txid| self.txs.contains_key(txid))
+            .collect();
+        let mut frontier = within.clone();
+        for _ in 0..hops {
+            let mut next = HashSet::default();
+            for edge in &self.edges {
+                for (from, to) in [(edge.source, edge.target), (edge.target, edge.source)] {
+                    if frontier.contains(&from) && within.insert(to) {
+                        next.insert(to);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        within
+    }
+
+    /// The address type of the outputs of `txid`, unless they are of
+    /// different types. Fees don't count.
+    pub fn output_address_type(&self, txid: &Txid) -> Option<AddressType> {
+        let mut types = self
+            .txs
+            .get(txid)?
+            .outputs
+            .iter()
+            .filter_map(Output::address_type);
+        let first = types.next()?;
+        types.all(|t| t == first).then_some(first)
+    }
+
+    /// `txid` and all transactions its coins flowed into, following edges
+    /// forward.
+    pub fn descendants(&self, txid: Txid) -> HashSet<Txid> {
+        let mut descendants = HashSet::default();
+        let mut todo = vec![txid];
+        while let Some(txid) = todo.pop() {
+            if !self.txs.contains_key(&txid) || !descendants.insert(txid) {
+                continue;
+            }
+            for edge in self.edges.iter().filter(|e| e.source == txid) {
+                todo.push(edge.target);
+            }
+        }
+        descendants
+    }
+
+    /// Coins that flowed from one of the given transactions to another.
+    pub fn coins_between(&self, txids: &HashSet<Txid>) -> Vec<(Txid, usize)> {
+        self.edges
+            .iter()
+            .filter(|e| txids.contains(&e.source) && txids.contains(&e.target))
+            .map(|e| (e.source, e.source_pos))
+            .collect()
+    }
+
+    /// Coins spent or created by the given transactions.
+    pub fn coins(&self, txids: &HashSet<Txid>) -> HashSet<(Txid, usize)> {
+        let mut coins = HashSet::default();
+        for txid in txids {
+            if let Some(tx) = self.txs.get(txid) {
+                for input in &tx.inputs {
+                    coins.insert((input.funding_txid, input.funding_vout as usize));
+                }
+                for o in 0..tx.outputs.len() {
+                    coins.insert((*txid, o));
+                }
+            }
+        }
+        coins
+    }
+
+    /// Problems with the amounts of `txid`, followed by a cross-check of its
+    /// inputs against the outputs they spend, for those funding transactions
+    /// that are loaded as well. A mismatch points to a bug in the backend
+    /// index.
+    pub fn discrepancies(&self, txid: Txid) -> Vec<String> {
+        let Some(tx) = self.txs.get(&txid) else {
+            return vec![];
+        };
+
+        let mut result = tx.issues.clone();
+
+        for (i, input) in tx.inputs.iter().enumerate() {
+            let Some(funding) = self.txs.get(&input.funding_txid) else {
+                continue;
+            };
+            let vout = input.funding_vout as usize;
+            match funding
+                .outputs
+                .get(vout)
+                .and_then(|o| o.address().map(|a| (o, a)))
+            {
+                None => result.push(format!(
+                    "Input {} spends {}:{} which doesn't exist.",
+                    i, input.funding_txid, vout
+                )),
+                Some((output, address)) => {
+                    if output.value != input.value {
+                        result.push(format!(
+                            "Input {} has value {} but {}:{} has value {}.",
+                            i,
+                            Sats(input.value),
+                            input.funding_txid,
+                            vout,
+                            Sats(output.value)
+                        ));
+                    }
+                    if address != input.address {
+                        result.push(format!(
+                            "Input {} has address {} but {}:{} has address {}.",
+                            i, input.address, input.funding_txid, vout, address
+                        ));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Run [Self::discrepancies] over all transactions in the graph.
+    pub fn check_integrity(&self) -> Vec<String> {
+        let mut txids: Vec<_> = self.txs.keys().copied().collect();
+        txids.sort();
+        txids
+            .into_iter()
+            .flat_map(|txid| {
+                self.discrepancies(txid)
+                    .into_iter()
+                    .map(move |d| format!("{}: {}", txid, d))
+            })
+            .collect()
+    }
+
+    /// Addresses of the inputs and outputs of all transactions, sorted by
+    /// txid. Outputs are indexed by vout, the fees are left out.
+    pub fn tx_views(&self) -> Vec<TxView<'_>> {
+        let mut views: Vec<_> = self
+            .txs
+            .iter()
+            .map(|(txid, tx)| TxView {
+                txid: *txid,
+                inputs: tx
+                    .inputs
+                    .iter()
+                    .map(|input| (input.address.as_str(), input.address_type))
+                    .collect(),
+                outputs: tx
+                    .outputs
+                    .iter()
+                    .filter_map(|output| match &output.output_type {
+                        OutputType::Utxo {
+                            address,
+                            address_type,
+                        }
+                        | OutputType::Spent {
+                            address,
+                            address_type,
+                            ..
+                        } => Some((address.as_str(), *address_type)),
+                        OutputType::Fees => None,
+                    })
+                    .collect(),
+            })
+            .collect();
+        views.sort_by_key(|view| view.txid);
+        views
+    }
+}
+
+pub fn format_timestamp(timestamp: i64) -> String {
+    chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Duration in the largest whole unit, e.g. "3 days".
+pub fn format_duration(seconds: i64) -> String {
+    let (n, unit) = match seconds {
+        s if s < 60 => (s, "second"),
+        s if s < 60 * 60 => (s / 60, "minute"),
+        s if s < 24 * 60 * 60 => (s / (60 * 60), "hour"),
+        s => (s / (24 * 60 * 60), "day"),
+    };
+    if n == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+/// How much later or earlier something happened than something else, e.g.
+/// "3 days later".
+pub fn format_relative(seconds: i64) -> String {
+    match seconds {
+        0 => "At the same time".to_string(),
+        s if s > 0 => format!("{} later", format_duration(s)),
+        s => format!("{} earlier", format_duration(-s)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bitcoin::{self, dummy_txid};
+
+    use super::*;
+
+    const TXID_A: u8 = 1;
+    const TXID_B: u8 = 2;
+    const TXID_C: u8 = 3;
+    const TXID_D: u8 = 4;
+    const TXID_E: u8 = 5;
+
+    fn input(funding: u8, vout: u32, value: u64, address: &str) -> bitcoin::Input {
+        bitcoin::Input {
+            txid: dummy_txid(funding),
+            vout,
+            value,
+            address: address.to_string(),
+            address_type: AddressType::P2WPKH,
+            witness: vec![],
+            script_sig: String::new(),
+            script_pubkey: String::new(),
+        }
+    }
+
+    fn output(spending: Option<u8>, value: u64, address: &str) -> bitcoin::Output {
+        bitcoin::Output {
+            spending_txid: spending.map(dummy_txid),
+            value,
+            address: address.to_string(),
+            address_type: AddressType::P2WPKH,
+            script_pubkey: String::new(),
+        }
+    }
+
+    /// A spends from C, B spends output 1 of A.
+    fn transactions() -> Vec<Transaction> {
+        vec![
+            Transaction {
+                timestamp: 1231731025,
+                txid: dummy_txid(TXID_A),
+                block_height: 170,
+                vsize: 0,
+                inputs: vec![input(TXID_C, 0, 5000, "c0")],
+                outputs: vec![output(None, 1000, "a0"), output(Some(TXID_B), 3000, "a1")],
+            },
+            Transaction {
+                timestamp: 1231731026,
+                txid: dummy_txid(TXID_B),
+                block_height: 171,
+                vsize: 0,
+                inputs: vec![input(TXID_D, 3, 700, "d3"), input(TXID_A, 1, 3000, "a1")],
+                outputs: vec![output(None, 3500, "b0")],
+            },
+            Transaction {
+                timestamp: 1231731024,
+                txid: dummy_txid(TXID_C),
+                block_height: 169,
+                vsize: 0,
+                inputs: vec![],
+                outputs: vec![output(Some(TXID_A), 5000, "c0")],
+            },
+        ]
+    }
+
+    fn model(order: &[usize]) -> Model {
+        let txs = transactions();
+        let mut model = Model::default();
+        for i in order {
+            model.add_tx(txs[*i].txid, &txs[*i]);
+        }
+        model
+    }
+
+    fn edges(model: &Model) -> Vec<(Txid, usize, Txid, usize)> {
+        let mut edges: Vec<_> = model
+            .edges()
+            .iter()
+            .map(|e| (e.source, e.source_pos, e.target, e.target_pos))
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn edges_independent_of_order() {
+        let expected = edges(&model(&[0, 1, 2]));
+        assert_eq!(expected.len(), 2);
+        assert!(expected.contains(&(dummy_txid(TXID_A), 1, dummy_txid(TXID_B), 1)));
+        assert!(expected.contains(&(dummy_txid(TXID_C), 0, dummy_txid(TXID_A), 0)));
+
+        for order in [[2, 1, 0], [1, 0, 2], [0, 2, 1]] {
+            assert_eq!(edges(&model(&order)), expected);
+        }
+    }
+
+    #[test]
+    fn fees_are_the_last_output() {
+        let model = model(&[0, 1, 2]);
+
+        let a = model.tx(&dummy_txid(TXID_A)).unwrap();
+        assert!(a.has_fees());
+        assert_eq!(a.fees(), 1000);
+        assert_eq!(a.outputs.len(), 3);
+
+        // Coinbase
+        let c = model.tx(&dummy_txid(TXID_C)).unwrap();
+        assert!(!c.has_fees());
+        assert_eq!(c.fees(), 0);
+        assert_eq!(c.value, 5000);
+    }
+
+    #[test]
+    fn remove_tx_removes_its_edges() {
+        let mut model = model(&[0, 1, 2]);
+        model.remove_tx(dummy_txid(TXID_A));
+        assert_eq!(model.len(), 2);
+        assert!(model.edges().is_empty());
+        assert_eq!(
+            model.connected_component(dummy_txid(TXID_B)),
+            HashSet::from_iter([dummy_txid(TXID_B)])
+        );
+    }
+
+    #[test]
+    fn missing_outputs_are_not_connected() {
+        let mut txs = transactions();
+        // B claims to spend output 5 of A, which only has two.
+        txs[1].inputs[1].vout = 5;
+        let mut model = Model::default();
+        assert!(model.add_tx(txs[0].txid, &txs[0]).is_empty());
+        let dropped = model.add_tx(txs[1].txid, &txs[1]);
+        assert_eq!(dropped.len(), 1);
+        assert!(model.edges().is_empty());
+
+        // The same from the other side.
+        let mut model = Model::default();
+        model.add_tx(txs[1].txid, &txs[1]);
+        assert_eq!(model.add_tx(txs[0].txid, &txs[0]).len(), 1);
+        assert!(model.edges().is_empty());
+    }
+
+    #[test]
+    fn adding_twice_replaces() {
+        let mut model = model(&[0, 1, 2]);
+        let txs = transactions();
+        model.add_tx(txs[0].txid, &txs[0]);
+        assert_eq!(model.len(), 3);
+        assert_eq!(model.edges().len(), 2);
+    }
+
+    #[test]
+    fn conflicting_spends() {
+        let mut txs = transactions();
+        // E spends the same coin as B, and A only knows about B.
+        let e = Transaction {
+            timestamp: 1231731027,
+            txid: dummy_txid(TXID_E),
+            block_height: 0,
+            vsize: 0,
+            inputs: vec![input(TXID_A, 1, 3000, "a1")],
+            outputs: vec![output(None, 2900, "e0")],
+        };
+        txs.push(e);
+
+        for order in [[0, 1, 3], [3, 1, 0], [1, 3, 0]] {
+            let mut model = Model::default();
+            for i in order {
+                model.add_tx(txs[i].txid, &txs[i]);
+            }
+            assert_eq!(
+                edges(&model),
+                vec![
+                    (dummy_txid(TXID_A), 1, dummy_txid(TXID_B), 1),
+                    (dummy_txid(TXID_A), 1, dummy_txid(TXID_E), 0),
+                ]
+            );
+            let conflicts = model.conflicts();
+            assert_eq!(conflicts.len(), 1);
+            let mut spenders = vec![dummy_txid(TXID_B), dummy_txid(TXID_E)];
+            spenders.sort();
+            assert_eq!(conflicts[&(dummy_txid(TXID_A), 1)], spenders);
+        }
+    }
+
+    #[test]
+    fn chains_of_hops() {
+        // C funds a run of A, B and D, each with one input and one output.
+        let hop = |id: u8, funding: u8, spending: Option<u8>, value: u64| Transaction {
+            timestamp: 1231731025,
+            txid: dummy_txid(id),
+            block_height: 170,
+            vsize: 0,
+            inputs: vec![input(funding, 0, value + 100, "in")],
+            outputs: vec![output(spending, value, "out")],
+        };
+        let mut txs = transactions();
+        txs[2].outputs[0].value = 5100;
+        txs[0] = hop(TXID_A, TXID_C, Some(TXID_B), 5000);
+        txs[1] = hop(TXID_B, TXID_A, Some(TXID_D), 4900);
+        txs.push(hop(TXID_D, TXID_B, None, 4800));
+
+        let mut model = Model::default();
+        for tx in &txs {
+            model.add_tx(tx.txid, tx);
+        }
+        let chain = vec![dummy_txid(TXID_A), dummy_txid(TXID_B), dummy_txid(TXID_D)];
+        assert_eq!(model.hops_through(dummy_txid(TXID_B)), chain);
+        assert_eq!(model.hops_through(dummy_txid(TXID_D)), chain);
+        assert!(model.hops_through(dummy_txid(TXID_C)).is_empty());
+        assert_eq!(model.hop_chains(3), vec![chain]);
+        assert!(model.hop_chains(4).is_empty());
+
+        // E spends the output of B as well, so the run ends at B.
+        let e = hop(TXID_E, TXID_B, None, 4800);
+        model.add_tx(e.txid, &e);
+        assert_eq!(
+            model.hops_through(dummy_txid(TXID_A)),
+            vec![dummy_txid(TXID_A), dummy_txid(TXID_B)]
+        );
+        assert_eq!(
+            model.hops_through(dummy_txid(TXID_D)),
+            vec![dummy_txid(TXID_D)]
+        );
+    }
+
+    #[test]
+    fn chains_of_peels() {
+        // C funds A, which peels off 500, then B peels off 400 and D 900.
+        let peel = |id: u8, funding: u8, vout: u32, outputs| Transaction {
+            timestamp: 1231731025,
+            txid: dummy_txid(id),
+            block_height: 170,
+            vsize: 0,
+            inputs: vec![input(funding, vout, 10000, "in")],
+            outputs,
+        };
+        let mut txs = transactions();
+        txs[2].outputs[0].value = 10000;
+        txs[0] = peel(
+            TXID_A,
+            TXID_C,
+            0,
+            vec![output(Some(TXID_B), 9000, "a0"), output(None, 500, "a1")],
+        );
+        txs[1] = peel(
+            TXID_B,
+            TXID_A,
+            0,
+            vec![output(None, 400, "b0"), output(Some(TXID_D), 8000, "b1")],
+        );
+        txs.push(peel(
+            TXID_D,
+            TXID_B,
+            1,
+            vec![output(None, 7000, "d0"), output(None, 900, "d1")],
+        ));
+
+        let mut model = Model::default();
+        for tx in &txs {
+            model.add_tx(tx.txid, tx);
+        }
+        assert_eq!(model.peel(&dummy_txid(TXID_A)), Some(0));
+        assert_eq!(model.peel(&dummy_txid(TXID_B)), Some(1));
+        assert_eq!(model.peel(&dummy_txid(TXID_C)), None);
+        let chain = vec![dummy_txid(TXID_A), dummy_txid(TXID_B), dummy_txid(TXID_D)];
+        assert_eq!(model.peels_through(dummy_txid(TXID_B)), chain);
+        assert_eq!(model.peel_chains(3), vec![chain]);
+        assert!(model.peel_chains(4).is_empty());
+
+        // The outputs of D are too close in value for a payout.
+        let d = peel(
+            TXID_D,
+            TXID_B,
+            1,
+            vec![output(None, 6000, "d0"), output(None, 1900, "d1")],
+        );
+        model.add_tx(d.txid, &d);
+        assert_eq!(model.peel(&dummy_txid(TXID_D)), None);
+        assert_eq!(
+            model.peels_through(dummy_txid(TXID_A)),
+            vec![dummy_txid(TXID_A), dummy_txid(TXID_B)]
+        );
+    }
+
+    #[test]
+    fn set_spent_of_stale_tx() {
+        let mut txs = transactions();
+        // A stale copy of A that doesn't know about B yet.
+        txs[0].outputs[1].spending_txid = None;
+        let mut model = Model::default();
+        model.add_tx(txs[0].txid, &txs[0]);
+        model.add_tx(txs[1].txid, &txs[1]);
+
+        assert!(model.set_spent((dummy_txid(TXID_A), 1), dummy_txid(TXID_B)));
+        assert!(matches!(
+            model.tx(&dummy_txid(TXID_A)).unwrap().outputs[1].output_type,
+            OutputType::Spent { spending_txid, .. } if spending_txid == dummy_txid(TXID_B)
+        ));
+        assert_eq!(
+            edges(&model),
+            vec![(dummy_txid(TXID_A), 1, dummy_txid(TXID_B), 1)]
+        );
+        // Already spent, and fees can't be.
+        assert!(!model.set_spent((dummy_txid(TXID_A), 1), dummy_txid(TXID_B)));
+        assert!(!model.set_spent((dummy_txid(TXID_A), 2), dummy_txid(TXID_B)));
+    }
+
+    #[test]
+    fn resting_time() {
+        let model = model(&[0, 1, 2]);
+        assert_eq!(model.edges().len(), 2);
+        for edge in model.edges() {
+            assert_eq!(model.blocks_rested(edge), 1);
+            assert_eq!(model.seconds_rested(edge), 1);
+        }
+
+        assert_eq!(format_duration(1), "1 second");
+        assert_eq!(format_duration(150), "2 minutes");
+        assert_eq!(format_duration(3 * 24 * 60 * 60 + 5), "3 days");
+        assert_eq!(format_relative(-150), "2 minutes earlier");
+    }
+
+    #[test]
+    fn connected_component_and_coins() {
+        let model = model(&[0, 1, 2]);
+        assert_eq!(model.connected_component(dummy_txid(TXID_C)).len(), 3);
+        assert!(model.connected_component(dummy_txid(TXID_D)).is_empty());
+        assert_eq!(
+            model.components().into_values().collect::<HashSet<_>>(),
+            HashSet::from_iter([0])
+        );
+        assert_eq!(
+            model.within_hops([dummy_txid(TXID_B)], 1),
+            HashSet::from_iter([dummy_txid(TXID_A), dummy_txid(TXID_B)])
+        );
+        assert_eq!(model.within_hops([dummy_txid(TXID_B)], 2).len(), 3);
+        assert!(model.within_hops([dummy_txid(TXID_D)], 2).is_empty());
+        // Without A, B and C aren't connected anymore.
+        let components = self::model(&[1, 2]).components();
+        assert_ne!(
+            components[&dummy_txid(TXID_B)],
+            components[&dummy_txid(TXID_C)]
+        );
+        assert_eq!(
+            model.descendants(dummy_txid(TXID_A)),
+            HashSet::from_iter([dummy_txid(TXID_A), dummy_txid(TXID_B)])
+        );
+        assert_eq!(model.descendants(dummy_txid(TXID_C)).len(), 3);
+        assert_eq!(
+            model.coins_between(&model.descendants(dummy_txid(TXID_A))),
+            vec![(dummy_txid(TXID_A), 1)]
+        );
+
+        let coins = model.coins(&HashSet::from_iter([dummy_txid(TXID_B)]));
+        assert_eq!(
+            coins,
+            HashSet::from_iter([
+                (dummy_txid(TXID_D), 3),
+                (dummy_txid(TXID_A), 1),
+                (dummy_txid(TXID_B), 0),
+                (dummy_txid(TXID_B), 1)
+            ])
+        );
+    }
+
+    #[test]
+    fn discrepancies() {
+        let mut txs = transactions();
+        txs[1].inputs[1].value = 2999;
+        txs[1].inputs[1].address = "elsewhere".to_string();
+        txs[1].outputs[0].value = 4000;
+
+        let mut model = Model::default();
+        for tx in &txs {
+            model.add_tx(tx.txid, tx);
+        }
+
+        assert!(model.discrepancies(dummy_txid(TXID_A)).is_empty());
+        let discrepancies = model.discrepancies(dummy_txid(TXID_B));
+        assert_eq!(discrepancies.len(), 3);
+        assert!(discrepancies[0].starts_with("Outputs"));
+        assert_eq!(model.check_integrity().len(), 3);
+
+        // Negative fees are drawn as no fees.
+        assert_eq!(model.tx(&dummy_txid(TXID_B)).unwrap().fees(), 0);
+    }
+
+    #[test]
+    fn beancount() {
+        let model = model(&[0]);
+        let a = dummy_txid(TXID_A);
+        let beancount = model
+            .tx(&a)
+            .unwrap()
+            .export_beancount(&a, Some("Test".to_string()));
+        let lines: Vec<&str> = beancount.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            format!("2009-01-12 * \"Test\" ^{}", a.hex_string())
+        );
+        assert!(lines[1].contains("Assets:Bitcoin:c0") && lines[1].ends_with("-0.00005000 BTC"));
+        assert!(lines[4].contains("Expenses:Bitcoin:Fees") && lines[4].ends_with("0.00001000 BTC"));
+    }
+}
@@ -0,0 +1,313 @@
+//! How well a transaction hides which inputs paid which outputs, in the style
+//! of the Boltzmann analysis on kycp.org: every way to split it into
+//! independent sub-transactions is an interpretation, and links between an
+//! input and an output that hold in all of them are deterministic.
+
+use std::collections::HashMap;
+
+use crate::bitcoin::Txid;
+
+use super::model::{Model, OutputType, Tx};
+
+/// The search keeps sets of inputs and outputs as bit masks, and goes
+/// through their subsets, so larger transactions are never worth trying.
+const MAX_IOS: usize = 20;
+
+/// The interpretations of a transaction.
+#[derive(Debug, PartialEq)]
+pub struct Analysis {
+    pub interpretations: u64,
+    /// In how many interpretations each input, by row, is linked to each
+    /// output, by column.
+    links: Vec<Vec<u64>>,
+}
+
+impl Analysis {
+    /// Count the ways to split the inputs and outputs into sub-transactions
+    /// that each spend at least as much as they pay, the rest going to fees.
+    /// `None` if the search takes longer than `budget` steps, or if there is
+    /// no interpretation because the outputs are worth more than the inputs.
+    pub fn new(inputs: &[u64], outputs: &[u64], budget: usize) -> Option<Self> {
+        Self::with_steps(inputs, outputs, budget).0
+    }
+
+    /// Like [Self::new], together with the steps the search took.
+    fn with_steps(inputs: &[u64], outputs: &[u64], budget: usize) -> (Option<Self>, usize) {
+        if inputs.is_empty()
+            || outputs.is_empty()
+            || inputs.len() > MAX_IOS
+            || outputs.len() > MAX_IOS
+        {
+            return (None, 0);
+        }
+        let mut search = Search {
+            inputs,
+            outputs,
+            steps: budget,
+            blocks: vec![],
+            analysis: Analysis {
+                interpretations: 0,
+                links: vec![vec![0; outputs.len()]; inputs.len()],
+            },
+        };
+        let all = |n: usize| (u64::MAX >> (64 - n)) as u32;
+        let done = search.split(all(inputs.len()), all(outputs.len()));
+        let steps = budget - search.steps;
+        let analysis = done
+            .map(|()| search.analysis)
+            .filter(|analysis| analysis.interpretations > 0);
+        (analysis, steps)
+    }
+
+    /// Bits of entropy, zero if there's only one interpretation.
+    pub fn entropy(&self) -> f64 {
+        (self.interpretations as f64).log2()
+    }
+
+    /// How likely `input` paid `output`.
+    pub fn link_probability(&self, input: usize, output: usize) -> f64 {
+        self.links[input][output] as f64 / self.interpretations as f64
+    }
+
+    /// Links between an input and an output that hold in every
+    /// interpretation.
+    pub fn deterministic_links(&self) -> Vec<(usize, usize)> {
+        let mut links = vec![];
+        for (input, row) in self.links.iter().enumerate() {
+            for (output, count) in row.iter().enumerate() {
+                if *count == self.interpretations {
+                    links.push((input, output));
+                }
+            }
+        }
+        links
+    }
+
+    pub fn num_links(&self) -> usize {
+        self.links.len() * self.links[0].len()
+    }
+}
+
+struct Search<'a> {
+    inputs: &'a [u64],
+    outputs: &'a [u64],
+    /// Left before giving up.
+    steps: usize,
+    /// Sub-transactions of the interpretation so far, as masks of inputs and
+    /// outputs.
+    blocks: Vec<(u32, u32)>,
+    analysis: Analysis,
+}
+
+fn sum(values: &[u64], mask: u32) -> u64 {
+    values
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| mask & (1 << i) != 0)
+        .map(|(_, value)| value)
+        .sum()
+}
+
+fn bits(mask: u32) -> impl Iterator<Item = usize> {
+    (0..32).filter(move |i| mask & (1 << i) != 0)
+}
+
+impl Search<'_> {
+    /// One step of the budget, `None` once it's used up.
+    fn charge(&mut self) -> Option<()> {
+        self.steps = self.steps.checked_sub(1)?;
+        Some(())
+    }
+
+    /// Split the remaining inputs and outputs into sub-transactions. The one
+    /// with the first remaining input is chosen here, so that each
+    /// interpretation is found once. Every subset tried costs a step.
+    fn split(&mut self, inputs: u32, outputs: u32) -> Option<()> {
+        self.charge()?;
+        if inputs == 0 {
+            if outputs == 0 {
+                self.record();
+            }
+            return Some(());
+        }
+        let first = inputs & inputs.wrapping_neg();
+        let rest = inputs & !first;
+        let mut others = rest;
+        loop {
+            self.charge()?;
+            let block_inputs = first | others;
+            let value = sum(self.inputs, block_inputs);
+            let mut block_outputs = outputs;
+            while block_outputs != 0 {
+                self.charge()?;
+                if sum(self.outputs, block_outputs) <= value {
+                    self.blocks.push((block_inputs, block_outputs));
+                    self.split(inputs & !block_inputs, outputs & !block_outputs)?;
+                    self.blocks.pop();
+                }
+                block_outputs = (block_outputs - 1) & outputs;
+            }
+            if others == 0 {
+                break;
+            }
+            others = (others - 1) & rest;
+        }
+        Some(())
+    }
+
+    fn record(&mut self) {
+        self.analysis.interpretations += 1;
+        for (inputs, outputs) in &self.blocks {
+            for input in bits(*inputs) {
+                for output in bits(*outputs) {
+                    self.analysis.links[input][output] += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Possible links of one input or output, the likeliest first, e.g.
+/// "output #1 (deterministic), #0 (50%)".
+fn describe_links(kind: &str, links: impl Iterator<Item = (usize, f64)>) -> String {
+    let mut links: Vec<(usize, f64)> = links.filter(|(_, p)| *p > 0.0).collect();
+    links.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let links: Vec<String> = links
+        .into_iter()
+        .map(|(i, p)| {
+            if p == 1.0 {
+                format!("#{} (deterministic)", i)
+            } else {
+                format!("#{} ({:.0}%)", i, p * 100.0)
+            }
+        })
+        .collect();
+    format!("{} {}", kind, links.join(", "))
+}
+
+/// The analyses of the transactions of a graph, computed once each. `None`
+/// for transactions that are too complex.
+#[derive(Default)]
+pub struct Privacy {
+    analyses: HashMap<Txid, Option<Analysis>>,
+    /// Steps each analysis could take, from the settings.
+    budget: usize,
+}
+
+/// Transactions analyzed per frame, so that opening a large workspace doesn't
+/// stall the UI.
+const PER_FRAME: usize = 10;
+
+impl Privacy {
+    /// Analyze some of the transactions that are new since the last call, and
+    /// forget those that left the graph. If the budget grew, those that were
+    /// too complex are tried again. Returns whether any are left for later.
+    pub fn update(&mut self, model: &Model, budget: usize) -> bool {
+        if budget > self.budget {
+            self.analyses.retain(|_, analysis| analysis.is_some());
+        }
+        self.budget = budget;
+        self.analyses.retain(|txid, _| model.tx(txid).is_some());
+        let pending: Vec<(&Txid, &Tx)> = model
+            .txs()
+            .filter(|(txid, tx)| !tx.inputs.is_empty() && !self.analyses.contains_key(*txid))
+            .collect();
+        for (txid, tx) in pending.iter().take(PER_FRAME) {
+            let inputs: Vec<u64> = tx.inputs.iter().map(|input| input.value).collect();
+            let outputs: Vec<u64> = tx
+                .outputs
+                .iter()
+                .filter(|output| !matches!(output.output_type, OutputType::Fees))
+                .map(|output| output.value)
+                .collect();
+            self.analyses
+                .insert(**txid, Analysis::new(&inputs, &outputs, budget));
+        }
+        pending.len() > PER_FRAME
+    }
+
+    /// `None` if the transaction wasn't analyzed, `Some(None)` if it was too
+    /// complex or its outputs are worth more than its inputs.
+    pub fn get(&self, txid: &Txid) -> Option<Option<&Analysis>> {
+        self.analyses.get(txid).map(Option::as_ref)
+    }
+
+    pub fn describe(&self, txid: &Txid) -> Option<String> {
+        Some(match self.get(txid)? {
+            Some(analysis) => format!(
+                "Entropy {:.2} bits, {} of {} links deterministic",
+                analysis.entropy(),
+                analysis.deterministic_links().len(),
+                analysis.num_links()
+            ),
+            None => "Privacy not scored: too complex, or outputs exceed inputs".to_string(),
+        })
+    }
+
+    /// The outputs that input `vin` of `txid` may have paid.
+    pub fn input_links(&self, txid: &Txid, vin: usize) -> Option<String> {
+        let analysis = self.get(txid)??;
+        let links = (0..analysis.links[vin].len())
+            .map(|output| (output, analysis.link_probability(vin, output)));
+        Some(format!("Pays {}", describe_links("output", links)))
+    }
+
+    /// The inputs that may have paid output `vout` of `txid`.
+    pub fn output_links(&self, txid: &Txid, vout: usize) -> Option<String> {
+        let analysis = self.get(txid)??;
+        let links =
+            (0..analysis.links.len()).map(|input| (input, analysis.link_probability(input, vout)));
+        Some(format!("Paid by {}", describe_links("input", links)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BUDGET: usize = 50_000;
+
+    #[test]
+    fn interpretations() {
+        // Paying from one coin links it to everything.
+        let analysis = Analysis::new(&[10], &[3, 6], BUDGET).unwrap();
+        assert_eq!(analysis.interpretations, 1);
+        assert_eq!(analysis.entropy(), 0.0);
+        assert_eq!(analysis.deterministic_links(), vec![(0, 0), (0, 1)]);
+
+        // Equal amounts: all together, or either pairing.
+        let analysis = Analysis::new(&[10, 10], &[10, 10], BUDGET).unwrap();
+        assert_eq!(analysis.interpretations, 3);
+        assert!(analysis.deterministic_links().is_empty());
+        assert_eq!(analysis.link_probability(0, 1), 2.0 / 3.0);
+
+        // The small input can't have paid the large output alone.
+        let analysis = Analysis::new(&[5, 10], &[5, 9], BUDGET).unwrap();
+        assert_eq!(analysis.interpretations, 2);
+        assert_eq!(analysis.deterministic_links(), vec![(0, 0), (1, 1)]);
+        assert_eq!(analysis.link_probability(0, 1), 0.5);
+        assert_eq!(
+            describe_links("output", [(0, 1.0), (1, 0.5), (2, 0.0)].into_iter()),
+            "output #0 (deterministic), #1 (50%)"
+        );
+
+        // Ten equal coins in and out split in more ways than the budget.
+        assert_eq!(Analysis::new(&[1; 10], &[1; 10], BUDGET), None);
+        assert_eq!(Analysis::new(&[], &[1], BUDGET), None);
+        // Bad data from the backend.
+        assert_eq!(Analysis::new(&[5], &[3, 3], BUDGET), None);
+    }
+
+    #[test]
+    fn budget_bounds_the_subsets() {
+        // Many inputs but a single interpretation: the subsets of inputs
+        // that are tried cost steps too.
+        assert_eq!(
+            Analysis::with_steps(&[1; 19], &[19], BUDGET),
+            (None, BUDGET)
+        );
+        assert_eq!(Analysis::with_steps(&[1; 30], &[30], BUDGET), (None, 0));
+        let (analysis, steps) = Analysis::with_steps(&[10], &[3, 6], BUDGET);
+        assert!(analysis.is_some() && steps < BUDGET);
+    }
+}
@@ -0,0 +1,86 @@
+//! Addresses that received more than one coin, linked across the graph so
+//! that reuse doesn't go unnoticed.
+
+use std::collections::HashMap;
+
+use egui::{Rect, Shape, Stroke};
+
+use crate::{bitcoin::Txid, style, transform::Transform};
+
+use super::{Graph, Rects};
+
+impl Graph {
+    /// Where each reused address of the graph shows up, by the coin it's the
+    /// address of. An input and the output it spends are the same coin.
+    #[allow(clippy::type_complexity)]
+    fn address_reuse<'a>(
+        &'a self,
+        rects: &Rects,
+    ) -> Vec<(&'a str, HashMap<(Txid, usize), Vec<Rect>>)> {
+        let mut by_address: HashMap<&str, HashMap<(Txid, usize), Vec<Rect>>> = HashMap::new();
+        for (txid, tx) in self.model.txs() {
+            for (i, input) in tx.inputs.iter().enumerate() {
+                let Some(rect) = rects.inputs.get(&(*txid, i)) else {
+                    continue;
+                };
+                let coin = (input.funding_txid, input.funding_vout as usize);
+                by_address
+                    .entry(&input.address)
+                    .or_default()
+                    .entry(coin)
+                    .or_default()
+                    .push(*rect);
+            }
+            for (o, output) in tx.outputs.iter().enumerate() {
+                let (Some(address), Some(rect)) =
+                    (output.address(), rects.outputs.get(&(*txid, o)))
+                else {
+                    continue;
+                };
+                by_address
+                    .entry(address)
+                    .or_default()
+                    .entry((*txid, o))
+                    .or_default()
+                    .push(*rect);
+            }
+        }
+
+        let mut reused: Vec<_> = by_address
+            .into_iter()
+            .filter(|(address, coins)| !address.is_empty() && coins.len() > 1)
+            .collect();
+        reused.sort_by_key(|(address, _)| *address);
+        reused
+    }
+
+    /// Connect the inputs and outputs of each reused address with a dashed
+    /// line in a color of its own, from left to right. The address of the
+    /// hovered coin stands out.
+    pub(super) fn draw_address_reuse(&self, ui: &egui::Ui, transform: &Transform, rects: &Rects) {
+        let painter = ui.painter();
+        for (i, (_, coins)) in self.address_reuse(rects).into_iter().enumerate() {
+            let color = style::component_color(i);
+            let color = if self
+                .hovered_coin
+                .is_some_and(|coin| coins.contains_key(&coin))
+            {
+                color
+            } else {
+                color.gamma_multiply(0.5)
+            };
+            let stroke = Stroke::new(1.5, color);
+            let mut rects: Vec<Rect> = coins
+                .into_values()
+                .flatten()
+                .map(|rect| transform.rect_to_screen(rect))
+                .collect();
+            rects.sort_by(|a, b| a.center().x.total_cmp(&b.center().x));
+            for rect in &rects {
+                painter.rect_stroke(rect.expand(1.0), 0.0, stroke);
+            }
+            let points: Vec<_> = rects.iter().map(Rect::center).collect();
+            painter.extend(Shape::dashed_line(&points, stroke, 6.0, 4.0));
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Taint analysis: how much of the value of a marked coin ended up in each
+//! coin downstream of it, as far as the graph is loaded.
+
+use std::collections::HashMap;
+
+use egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::{bitcoin::Txid, layout::Layout, style};
+
+use super::{model::Model, Graph};
+
+/// How a transaction passes the taint of its inputs on to its outputs.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TaintModel {
+    /// Every output is tainted in proportion to the tainted share of all
+    /// inputs.
+    #[default]
+    Haircut,
+    /// The value of the inputs fills the outputs in order, first in first
+    /// out, with the fees last.
+    Fifo,
+}
+
+impl TaintModel {
+    pub const ALL: [TaintModel; 2] = [TaintModel::Haircut, TaintModel::Fifo];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TaintModel::Haircut => "Haircut",
+            TaintModel::Fifo => "FIFO",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            TaintModel::Haircut => {
+                "Every output gets the same share of taint as the inputs have in total."
+            }
+            TaintModel::Fifo => {
+                "The inputs fill the outputs in order, so taint goes to the outputs \
+                 lined up with the tainted inputs."
+            }
+        }
+    }
+
+    /// The tainted share of each output, given the value and tainted share of
+    /// each input and the value of each output.
+    fn split(self, inputs: &[(u64, f64)], outputs: &[u64]) -> Vec<f64> {
+        match self {
+            TaintModel::Haircut => {
+                let total: u64 = inputs.iter().map(|(value, _)| value).sum();
+                let tainted: f64 = inputs
+                    .iter()
+                    .map(|(value, share)| *value as f64 * share)
+                    .sum();
+                let share = if total == 0 {
+                    0.0
+                } else {
+                    tainted / total as f64
+                };
+                vec![share; outputs.len()]
+            }
+            TaintModel::Fifo => {
+                let mut inputs = inputs.iter().copied();
+                let mut current = inputs.next();
+                outputs
+                    .iter()
+                    .map(|value| {
+                        let mut left = *value;
+                        let mut tainted = 0.0;
+                        while left > 0 {
+                            let Some((remaining, share)) = &mut current else {
+                                break;
+                            };
+                            let take = left.min(*remaining);
+                            tainted += take as f64 * *share;
+                            left -= take;
+                            *remaining -= take;
+                            if *remaining == 0 {
+                                current = inputs.next();
+                            }
+                        }
+                        if *value == 0 {
+                            0.0
+                        } else {
+                            tainted / *value as f64
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+pub struct Taint {
+    pub source: (Txid, usize),
+    pub model: TaintModel,
+}
+
+/// The tainted share of the value of the coins downstream of the source.
+pub struct Tainted {
+    shares: HashMap<(Txid, usize), f64>,
+}
+
+impl Taint {
+    pub fn compute(&self, model: &Model) -> Tainted {
+        let mut shares = HashMap::from([(self.source, 1.0)]);
+        let (source, _) = self.source;
+
+        // Transactions are ready once all their inputs from downstream
+        // transactions are known.
+        let downstream = model.descendants(source);
+        let mut spending: HashMap<Txid, Vec<Txid>> = HashMap::new();
+        let mut waiting: HashMap<Txid, usize> = HashMap::new();
+        for edge in model.edges() {
+            if downstream.contains(&edge.source) && downstream.contains(&edge.target) {
+                spending.entry(edge.source).or_default().push(edge.target);
+                *waiting.entry(edge.target).or_default() += 1;
+            }
+        }
+
+        let mut ready = vec![source];
+        while let Some(txid) = ready.pop() {
+            let Some(tx) = model.tx(&txid) else {
+                continue;
+            };
+            if txid != source {
+                let inputs: Vec<(u64, f64)> = tx
+                    .inputs
+                    .iter()
+                    .map(|input| {
+                        let coin = (input.funding_txid, input.funding_vout as usize);
+                        (input.value, shares.get(&coin).copied().unwrap_or(0.0))
+                    })
+                    .collect();
+                let outputs: Vec<u64> = tx.outputs.iter().map(|output| output.value).collect();
+                for (vout, share) in self.model.split(&inputs, &outputs).into_iter().enumerate() {
+                    if share > 0.0 {
+                        shares.insert((txid, vout), share);
+                    }
+                }
+            }
+            for target in spending.remove(&txid).unwrap_or_default() {
+                let count = waiting.get_mut(&target).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(target);
+                }
+            }
+        }
+
+        Tainted { shares }
+    }
+}
+
+impl Tainted {
+    pub fn coin(&self, coin: (Txid, usize)) -> f64 {
+        self.shares.get(&coin).copied().unwrap_or(0.0)
+    }
+
+    /// The tainted share of the value going into `txid`.
+    pub fn tx(&self, model: &Model, txid: &Txid) -> f64 {
+        let Some(tx) = model.tx(txid) else {
+            return 0.0;
+        };
+        let total: u64 = tx.inputs.iter().map(|input| input.value).sum();
+        let tainted: f64 = tx
+            .inputs
+            .iter()
+            .map(|input| {
+                let coin = (input.funding_txid, input.funding_vout as usize);
+                input.value as f64 * self.coin(coin)
+            })
+            .sum();
+        if total == 0 {
+            0.0
+        } else {
+            tainted / total as f64
+        }
+    }
+}
+
+pub fn color(share: f64) -> Color32 {
+    style::TAINT.gamma_multiply(0.1 + 0.9 * share as f32)
+}
+
+pub fn legend() -> Vec<(Color32, String)> {
+    [1.0, 0.5, 0.1, 0.0]
+        .into_iter()
+        .map(|share| (color(share), describe(share)))
+        .collect()
+}
+
+pub fn describe(share: f64) -> String {
+    format!("{:.1}% tainted", share * 100.0)
+}
+
+impl Graph {
+    pub fn taint_source(&self) -> Option<(Txid, usize)> {
+        self.taint
+    }
+
+    /// Mark the coin whose value to follow, or `None` to stop.
+    pub fn set_taint_source(&mut self, coin: Option<(Txid, usize)>) {
+        self.taint = coin;
+    }
+
+    pub(super) fn taint(&self, layout: &Layout) -> Option<Taint> {
+        Some(Taint {
+            source: self.taint?,
+            model: layout.taint_model,
+        })
+    }
+}
+
+/// Mark `coin` as the source of taint, or stop if it is already. Returns the
+/// new source if the button was clicked.
+pub(super) fn taint_button(
+    ui: &mut egui::Ui,
+    coin: (Txid, usize),
+    source: Option<(Txid, usize)>,
+) -> Option<Option<(Txid, usize)>> {
+    let (label, new) = if source == Some(coin) {
+        ("Clear Taint", None)
+    } else {
+        ("Mark as Tainted", Some(coin))
+    };
+    let clicked = ui
+        .button(label)
+        .on_hover_text("Color the coins downstream by how much of this coin's value they carry.")
+        .clicked();
+    if clicked {
+        ui.close_menu();
+    }
+    clicked.then_some(new)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_by_model() {
+        // A tainted input of 600 followed by a clean one of 400.
+        let inputs = [(600, 1.0), (400, 0.0)];
+        let outputs = [500, 300, 200];
+
+        assert_eq!(
+            TaintModel::Haircut.split(&inputs, &outputs),
+            vec![0.6, 0.6, 0.6]
+        );
+        assert_eq!(
+            TaintModel::Fifo.split(&inputs, &outputs),
+            vec![1.0, 1.0 / 3.0, 0.0]
+        );
+
+        // Half tainted input, spread over two outputs that straddle it.
+        let inputs = [(100, 0.5), (100, 0.0)];
+        assert_eq!(
+            TaintModel::Fifo.split(&inputs, &[50, 100, 50]),
+            vec![0.5, 0.25, 0.0]
+        );
+        assert_eq!(TaintModel::Haircut.split(&[], &[0]), vec![0.0]);
+    }
+}
@@ -0,0 +1,118 @@
+//! Searching for the force scale that lays out the current graph best. The
+//! trials run a few time steps per frame next to the live layout, which stays
+//! where it is in the meantime.
+
+use egui::{Pos2, Ui, Vec2};
+
+use crate::{bitcoin::Txid, layout::ForceParams, style};
+
+use super::Graph;
+
+const CANDIDATES: [u64; 9] = [10, 20, 35, 50, 75, 100, 125, 150, 200];
+
+/// Time steps a trial gets to settle.
+const MAX_STEPS: usize = 500;
+
+/// Time steps per frame, so that a large graph doesn't freeze the app.
+const STEPS_PER_FRAME: usize = 20;
+
+type Positions = Vec<(Txid, Pos2, Vec2)>;
+
+pub struct Tuning {
+    params: ForceParams,
+    /// Where each trial starts.
+    start: Positions,
+    /// Where the current trial left off in the last frame.
+    trial: Positions,
+    candidate: usize,
+    steps: usize,
+    /// Scale and score of the best trial so far.
+    best: (u64, f32),
+}
+
+impl Graph {
+    fn positions(&self) -> Positions {
+        self.nodes
+            .iter()
+            .map(|(txid, node)| (*txid, node.pos, node.velocity))
+            .collect()
+    }
+
+    /// Transactions that were removed in the meantime are skipped, new ones
+    /// stay where they are.
+    fn set_positions(&mut self, positions: &Positions) {
+        for (txid, pos, velocity) in positions {
+            if let Some(node) = self.nodes.get_mut(txid) {
+                node.pos = *pos;
+                node.velocity = *velocity;
+            }
+        }
+    }
+
+    /// Start searching for the force scale that gives the best
+    /// [super::LayoutMetrics], with each trial starting from the current
+    /// positions. See [Self::tune].
+    pub fn auto_tune(&mut self, params: &ForceParams) {
+        let start: Positions = self
+            .positions()
+            .into_iter()
+            .map(|(txid, pos, _)| (txid, pos, Vec2::ZERO))
+            .collect();
+        self.tuning = Some(Tuning {
+            params: ForceParams {
+                scale: CANDIDATES[0],
+                dt: params.dt,
+                cooloff: params.cooloff,
+                active: true,
+                parents_above: params.parents_above,
+                drag_radius: 0.0,
+                settle_steps: 0,
+            },
+            trial: start.clone(),
+            start,
+            candidate: 0,
+            steps: 0,
+            best: (params.scale, f32::INFINITY),
+        });
+    }
+
+    pub fn is_tuning(&self) -> bool {
+        self.tuning.is_some()
+    }
+
+    /// Run the next few time steps of [Self::auto_tune]. Returns the best
+    /// scale once all candidates are tried.
+    pub fn tune(&mut self, ui: &Ui) -> Option<u64> {
+        let mut tuning = self.tuning.take()?;
+        ui.ctx().request_repaint();
+        let style = style::get(ui);
+
+        let live = self.positions();
+        self.set_positions(&tuning.trial);
+        for _ in 0..STEPS_PER_FRAME {
+            let rects = self.rects(&style);
+            let moving = self.step(&rects, &tuning.params);
+            tuning.steps += 1;
+            if moving && tuning.steps < MAX_STEPS {
+                continue;
+            }
+
+            let score = self.layout_metrics(&self.rects(&style)).score();
+            if score < tuning.best.1 {
+                tuning.best = (tuning.params.scale, score);
+            }
+            tuning.candidate += 1;
+            tuning.steps = 0;
+            let Some(scale) = CANDIDATES.get(tuning.candidate) else {
+                self.set_positions(&live);
+                return Some(tuning.best.0);
+            };
+            tuning.params.scale = *scale;
+            self.set_positions(&tuning.start);
+        }
+        tuning.trial = self.positions();
+        self.set_positions(&live);
+        self.tuning = Some(tuning);
+        None
+    }
+}
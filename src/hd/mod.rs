@@ -0,0 +1,381 @@
+//! Watch-only HD wallets: derive the addresses behind an extended public key
+//! or output descriptor client-side, so that loading a wallet's history
+//! doesn't require handing the xpub to anyone.
+
+mod hashes;
+
+use bech32::{hrp, segwit, Hrp};
+use k256::{
+    elliptic_curve::{ff::PrimeField, sec1::ToEncodedPoint},
+    ProjectivePoint, PublicKey, Scalar,
+};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScriptType {
+    P2pkh,
+    P2shP2wpkh,
+    P2wpkh,
+    P2tr,
+}
+
+impl std::fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ScriptType::P2pkh => "P2PKH",
+            ScriptType::P2shP2wpkh => "P2SH-P2WPKH",
+            ScriptType::P2wpkh => "P2WPKH",
+            ScriptType::P2tr => "P2TR",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Chain {
+    Receive,
+    Change,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Network {
+    Mainnet,
+    Testnet,
+}
+
+#[derive(Clone, Debug)]
+struct ExtendedKey {
+    key: PublicKey,
+    chain_code: [u8; 32],
+}
+
+impl ExtendedKey {
+    /// Parse a base58 extended public key. The version bytes tell the network
+    /// and, for SLIP-132 keys (ypub, zpub, ...), the script type.
+    fn parse(input: &str) -> Result<(ExtendedKey, Network, ScriptType), String> {
+        let data = base58ck::decode_check(input)
+            .map_err(|e| format!("Invalid extended public key: {}", e))?;
+        if data.len() != 78 {
+            return Err("Invalid extended public key: wrong length".to_string());
+        }
+        let (network, script) = match u32::from_be_bytes(data[..4].try_into().unwrap()) {
+            0x0488B21E => (Network::Mainnet, ScriptType::P2pkh),
+            0x049D7CB2 => (Network::Mainnet, ScriptType::P2shP2wpkh),
+            0x04B24746 => (Network::Mainnet, ScriptType::P2wpkh),
+            0x043587CF => (Network::Testnet, ScriptType::P2pkh),
+            0x044A5262 => (Network::Testnet, ScriptType::P2shP2wpkh),
+            0x045F1CF6 => (Network::Testnet, ScriptType::P2wpkh),
+            0x0488ADE4 | 0x049D7878 | 0x04B2430C | 0x04358394 | 0x044A4E28 | 0x045F18BC => {
+                return Err("That's a private key. Only the public key is needed.".to_string())
+            }
+            _ => return Err("Unknown extended key version".to_string()),
+        };
+        let key = ExtendedKey {
+            key: PublicKey::from_sec1_bytes(&data[45..])
+                .map_err(|_| "Invalid extended public key: invalid key".to_string())?,
+            chain_code: data[13..45].try_into().unwrap(),
+        };
+        Ok((key, network, script))
+    }
+
+    /// BIP32 public child derivation. `None` for the rare invalid indices.
+    fn child(&self, index: u32) -> Option<ExtendedKey> {
+        let mut data = compressed(&self.key).to_vec();
+        data.extend(index.to_be_bytes());
+        let i = hashes::hmac_sha512(&self.chain_code, &data);
+        Some(ExtendedKey {
+            key: add_tweak(&self.key, i[..32].try_into().unwrap())?,
+            chain_code: i[32..].try_into().unwrap(),
+        })
+    }
+
+    fn derive(&self, path: &[u32]) -> Option<ExtendedKey> {
+        path.iter()
+            .try_fold(self.clone(), |key, index| key.child(*index))
+    }
+}
+
+/// What to derive addresses from.
+#[derive(Debug)]
+pub struct Wallet {
+    key: ExtendedKey,
+    network: Network,
+    script: ScriptType,
+    /// Derivation paths below the key, one per chain (receive, change).
+    paths: Vec<Vec<u32>>,
+    /// Whether the paths end in a wildcard index.
+    ranged: bool,
+}
+
+impl Wallet {
+    /// Accepts a bare extended public key, which derives receive and change
+    /// addresses, or a single-key `pkh()`, `wpkh()`, `sh(wpkh())` or `tr()`
+    /// descriptor, optionally with key origin, `<0;1>` multipath and checksum.
+    pub fn parse(input: &str) -> Result<Wallet, String> {
+        let input = input.trim();
+        let input = input.split('#').next().unwrap_or_default();
+
+        let (script, expression) = if let Some(inner) = unwrap_call(input, "sh") {
+            let Some(inner) = unwrap_call(inner, "wpkh") else {
+                return Err(unsupported());
+            };
+            (Some(ScriptType::P2shP2wpkh), inner)
+        } else if let Some(inner) = unwrap_call(input, "wpkh") {
+            (Some(ScriptType::P2wpkh), inner)
+        } else if let Some(inner) = unwrap_call(input, "pkh") {
+            (Some(ScriptType::P2pkh), inner)
+        } else if let Some(inner) = unwrap_call(input, "tr") {
+            (Some(ScriptType::P2tr), inner)
+        } else if input.contains('(') {
+            return Err(unsupported());
+        } else {
+            (None, input)
+        };
+        if expression.contains(',') {
+            return Err(unsupported());
+        }
+
+        // Key origin info only matters to signers.
+        let expression = match expression.strip_prefix('[') {
+            Some(rest) => rest.split_once(']').ok_or("Unclosed key origin")?.1,
+            None => expression,
+        };
+        let mut steps = expression.split('/');
+        let (key, network, default_script) = ExtendedKey::parse(steps.next().unwrap_or_default())?;
+        let steps: Vec<&str> = steps.collect();
+
+        let (paths, ranged) = match (script, steps.split_last()) {
+            // A bare key is an account: receive and change chains.
+            (None, None) => (vec![vec![0], vec![1]], true),
+            (Some(_), None) => (vec![vec![]], false),
+            (_, Some((last, init))) => {
+                let ranged = *last == "*";
+                let fixed = if ranged { init } else { &steps[..] };
+                let mut paths = vec![vec![]];
+                for step in fixed {
+                    if let Some(multi) = step.strip_prefix('<').and_then(|s| s.strip_suffix('>')) {
+                        let indices = multi
+                            .split(';')
+                            .map(parse_index)
+                            .collect::<Result<Vec<_>, _>>()?;
+                        paths = indices
+                            .iter()
+                            .flat_map(|index| {
+                                paths.iter().map(|path: &Vec<u32>| {
+                                    let mut path = path.clone();
+                                    path.push(*index);
+                                    path
+                                })
+                            })
+                            .collect();
+                    } else {
+                        let index = parse_index(step)?;
+                        paths.iter_mut().for_each(|path| path.push(index));
+                    }
+                }
+                (paths, ranged)
+            }
+        };
+
+        Ok(Wallet {
+            key,
+            network,
+            script: script.unwrap_or(default_script),
+            paths,
+            ranged,
+        })
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        self.script
+    }
+
+    /// The first `per_chain` addresses of every chain, or the single address
+    /// of a descriptor without wildcard.
+    pub fn addresses(&self, per_chain: u32) -> Vec<String> {
+        self.chains(per_chain)
+            .into_iter()
+            .flat_map(|(_, addresses)| addresses)
+            .collect()
+    }
+
+    /// Like [Self::addresses], but grouped by chain.
+    pub fn chains(&self, per_chain: u32) -> Vec<(Chain, Vec<String>)> {
+        let indices = if self.ranged { per_chain } else { 1 };
+        let mut chains = vec![];
+        for path in &self.paths {
+            let Some(chain) = self.key.derive(path) else {
+                continue;
+            };
+            let mut addresses = vec![];
+            for index in 0..indices {
+                let key = if self.ranged {
+                    chain.child(index)
+                } else {
+                    Some(chain.clone())
+                };
+                if let Some(address) = key.and_then(|key| self.address(&key.key)) {
+                    addresses.push(address);
+                }
+            }
+            // By BIP44 convention, the step before the address index is 0 for
+            // receive and 1 for change addresses.
+            let step = if self.ranged {
+                path.last()
+            } else {
+                path.iter().nth_back(1)
+            };
+            let kind = if step == Some(&1) {
+                Chain::Change
+            } else {
+                Chain::Receive
+            };
+            chains.push((kind, addresses));
+        }
+        chains
+    }
+
+    fn address(&self, key: &PublicKey) -> Option<String> {
+        let (pkh, sh, hrp) = match self.network {
+            Network::Mainnet => (0x00, 0x05, hrp::BC),
+            Network::Testnet => (0x6f, 0xc4, hrp::TB),
+        };
+        let base58 = |version: u8, hash: [u8; 20]| {
+            let mut payload = vec![version];
+            payload.extend(hash);
+            base58ck::encode_check(&payload)
+        };
+        let key_hash = hashes::hash160(&compressed(key));
+
+        Some(match self.script {
+            ScriptType::P2pkh => base58(pkh, key_hash),
+            ScriptType::P2shP2wpkh => {
+                let mut redeem_script = vec![0x00, 0x14];
+                redeem_script.extend(key_hash);
+                base58(sh, hashes::hash160(&redeem_script))
+            }
+            ScriptType::P2wpkh => segwit_address(hrp, segwit::VERSION_0, &key_hash)?,
+            ScriptType::P2tr => segwit_address(hrp, segwit::VERSION_1, &taproot_output(key)?)?,
+        })
+    }
+}
+
+/// Bech32 for witness version 0, bech32m from version 1 on (BIP173/BIP350).
+fn segwit_address(hrp: Hrp, version: bech32::Fe32, program: &[u8]) -> Option<String> {
+    segwit::encode(hrp, version, program).ok()
+}
+
+fn compressed(key: &PublicKey) -> [u8; 33] {
+    key.to_encoded_point(true).as_bytes().try_into().unwrap()
+}
+
+fn x_only(key: &PublicKey) -> [u8; 32] {
+    compressed(key)[1..].try_into().unwrap()
+}
+
+/// `key + tweak * G`. `None` if the tweak isn't a valid scalar or the result
+/// is infinity, which is astronomically unlikely.
+fn add_tweak(key: &PublicKey, tweak: &[u8; 32]) -> Option<PublicKey> {
+    let tweak = Option::<Scalar>::from(Scalar::from_repr((*tweak).into()))?;
+    let point = key.to_projective() + ProjectivePoint::GENERATOR * tweak;
+    PublicKey::from_affine(point.to_affine()).ok()
+}
+
+/// BIP86: the output key of a key path only spend, tweaked with an empty
+/// script tree.
+fn taproot_output(key: &PublicKey) -> Option<[u8; 32]> {
+    // The internal key is the one with the same x coordinate and an even y.
+    let internal = if compressed(key)[0] == 3 {
+        PublicKey::from_affine((-key.to_projective()).to_affine()).ok()?
+    } else {
+        *key
+    };
+    let tweak = hashes::tagged_hash("TapTweak", &x_only(&internal));
+    Some(x_only(&add_tweak(&internal, &tweak)?))
+}
+
+fn unwrap_call<'a>(input: &'a str, name: &str) -> Option<&'a str> {
+    input
+        .strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')
+}
+
+fn unsupported() -> String {
+    "Only single-key pkh(), wpkh(), sh(wpkh()) and tr() descriptors are supported".to_string()
+}
+
+fn parse_index(step: &str) -> Result<u32, String> {
+    if step.ends_with('\'') || step.ends_with('h') {
+        return Err("Hardened derivation needs the private key".to_string());
+    }
+    step.parse::<u32>()
+        .ok()
+        .filter(|index| *index < 1 << 31)
+        .ok_or_else(|| format!("Invalid derivation step '{}'", step))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Account keys of the "abandon abandon ... about" test mnemonic.
+    const XPUB_44: &str = "xpub6BosfCnifzxcFwrSzQiqu2DBVTshkCXacvNsWGYJVVhhawA7d4R5WSWGFNbi8Aw6ZRc1brxMyWMzG3DSSSSoekkudhUd9yLb6qx39T9nMdj";
+    const ZPUB_84: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+    #[test]
+    fn derive_addresses() {
+        let wallet = Wallet::parse(XPUB_44).unwrap();
+        assert_eq!(wallet.script_type(), ScriptType::P2pkh);
+        let addresses = wallet.addresses(1);
+        assert_eq!(addresses.len(), 2);
+        assert_eq!(addresses[0], "1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA");
+
+        let wallet = Wallet::parse(ZPUB_84).unwrap();
+        assert_eq!(wallet.script_type(), ScriptType::P2wpkh);
+        let addresses = wallet.addresses(2);
+        assert_eq!(addresses[0], "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(addresses[1], "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g");
+        assert_eq!(addresses[2], "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+
+        // BIP341 wallet test vector without scripts.
+        let internal =
+            hex::decode("d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d");
+        let mut compressed = vec![2];
+        compressed.extend(internal.unwrap());
+        let output = taproot_output(&PublicKey::from_sec1_bytes(&compressed).unwrap()).unwrap();
+        assert_eq!(
+            segwit_address(hrp::BC, segwit::VERSION_1, &output).unwrap(),
+            "bc1p2wsldez5mud2yam29q22wgfh9439spgduvct83k3pm50fcxa5dps59h4z5"
+        );
+        let wallet = Wallet::parse(&format!("tr([73c5da0a/86'/0'/0']{}/0/*)", XPUB_44)).unwrap();
+        assert!(wallet.addresses(1)[0].starts_with("bc1p"));
+    }
+
+    #[test]
+    fn descriptors() {
+        let receive = Wallet::parse(&format!("wpkh({}/0/*)", ZPUB_84)).unwrap();
+        assert_eq!(receive.addresses(5).len(), 5);
+        let both = Wallet::parse(&format!("wpkh({}/<0;1>/*)#abcdefgh", ZPUB_84)).unwrap();
+        assert_eq!(both.addresses(5).len(), 10);
+        let chains: Vec<_> = both.chains(1).into_iter().map(|(chain, _)| chain).collect();
+        assert_eq!(chains, vec![Chain::Receive, Chain::Change]);
+        assert_eq!(
+            both.addresses(2),
+            Wallet::parse(ZPUB_84).unwrap().addresses(2)
+        );
+
+        let sh = Wallet::parse(&format!("sh(wpkh({}/0/*))", XPUB_44)).unwrap();
+        assert_eq!(sh.script_type(), ScriptType::P2shP2wpkh);
+        assert!(sh.addresses(1)[0].starts_with('3'));
+        let single = Wallet::parse(&format!("pkh({}/0/0)", XPUB_44)).unwrap();
+        assert_eq!(
+            single.addresses(20),
+            vec!["1LqBGSKuX5yYUonjxT5qGfpUsXKYYWeabA"]
+        );
+
+        assert!(Wallet::parse(&format!("wpkh({}/0'/*)", XPUB_44)).is_err());
+        assert!(Wallet::parse(&format!("wsh(multi(1,{}/*))", XPUB_44)).is_err());
+        assert!(Wallet::parse(&XPUB_44.replace('x', "y")).is_err());
+        assert!(Wallet::parse("bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu").is_err());
+    }
+}
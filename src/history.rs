@@ -0,0 +1,228 @@
+use crate::{annotations::Annotations, export, graph::Graph};
+
+/// Edits of the same kind are one undo step while they follow each other
+/// within this many seconds, e.g. the transactions of an expansion arriving or
+/// the keystrokes of a label.
+const MERGE_SECONDS: f64 = 1.0;
+
+const MAX_STEPS: usize = 100;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Edit {
+    /// Transactions were added, removed or grouped.
+    Graph,
+    /// Transactions were moved by hand.
+    Drag,
+    Annotations,
+}
+
+/// The parts of a workspace that undo and redo bring back. The camera,
+/// selection and layout settings aren't included.
+pub struct Snapshot {
+    pub graph: GraphState,
+    pub annotations: Annotations,
+}
+
+/// The transactions of the graph, where they are, and how they are grouped.
+pub struct GraphState {
+    transactions: Vec<export::Transaction>,
+    groups: Vec<export::Group>,
+}
+
+impl GraphState {
+    pub fn new(graph: &Graph) -> Self {
+        Self {
+            transactions: graph.export(),
+            groups: graph.export_groups(),
+        }
+    }
+
+    pub fn restore(&self, graph: &mut Graph) {
+        graph.restore(&self.transactions);
+        graph.restore_groups(&self.groups);
+    }
+}
+
+/// Undo and redo stacks of the graph and its annotations. Edits of the graph
+/// are reported before they happen, with the graph as it was. Annotations
+/// are edited in many places, so their [Annotations::revision] is checked
+/// at the end of each frame instead, against a copy from the last edit.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// The graph before the first edit reported this frame.
+    before: Option<GraphState>,
+    edit: Option<Edit>,
+    /// The annotations after the last recorded edit, and their revision.
+    /// `None` after the history was cleared or restored.
+    annotations: Option<(Annotations, u64)>,
+    /// The last recorded edit and when it happened, to merge with.
+    last: Option<(Edit, f64)>,
+}
+
+impl History {
+    /// Report an edit of the graph that is about to happen.
+    pub fn edited(&mut self, edit: Edit, graph: &Graph) {
+        self.edited_since(edit, || GraphState::new(graph));
+    }
+
+    /// Like [Self::edited], with the graph from before an edit that already
+    /// started. Only called for the first edit of a frame.
+    pub fn edited_since(&mut self, edit: Edit, before: impl FnOnce() -> GraphState) {
+        if self.edit.is_none() {
+            self.before = Some(before());
+            self.edit = Some(edit);
+        }
+    }
+
+    pub fn end_frame(&mut self, now: f64, graph: &Graph, annotations: &Annotations) {
+        let (before_annotations, revision) = self
+            .annotations
+            .get_or_insert_with(|| (annotations.clone(), annotations.revision()));
+        let edit = self
+            .edit
+            .take()
+            .or_else(|| (*revision != annotations.revision()).then_some(Edit::Annotations));
+        let Some(edit) = edit else {
+            return;
+        };
+        let before = Snapshot {
+            graph: self.before.take().unwrap_or_else(|| GraphState::new(graph)),
+            annotations: std::mem::replace(before_annotations, annotations.clone()),
+        };
+        *revision = annotations.revision();
+
+        let merge = self.last.is_some_and(|(last, time)| {
+            last == edit && edit != Edit::Drag && now - time < MERGE_SECONDS
+        });
+        self.last = Some((edit, now));
+        if merge {
+            return;
+        }
+        self.undo.push(before);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    /// The state to go back to, if any.
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.restored();
+        Some(snapshot)
+    }
+
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.restored();
+        Some(snapshot)
+    }
+
+    /// Restoring a snapshot isn't an edit itself.
+    fn restored(&mut self) {
+        self.before = None;
+        self.edit = None;
+        self.annotations = None;
+        self.last = None;
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use egui::Pos2;
+
+    use crate::bitcoin::{dummy_transactions, dummy_txid, Txid};
+
+    use super::*;
+
+    fn snapshot(annotations: &Annotations) -> Snapshot {
+        Snapshot {
+            graph: GraphState::new(&Graph::default()),
+            annotations: annotations.clone(),
+        }
+    }
+
+    fn label(snapshot: &Snapshot) -> String {
+        snapshot.annotations.labels().collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let graph = Graph::default();
+        let mut history = History::default();
+        let mut annotations = Annotations::default();
+        annotations.set_tx_label(dummy_txid(1), "a".to_string());
+        history.end_frame(0.0, &graph, &annotations);
+
+        // Typing is one step.
+        annotations.set_tx_label(dummy_txid(1), "ab".to_string());
+        history.end_frame(0.1, &graph, &annotations);
+        annotations.set_tx_label(dummy_txid(1), "abc".to_string());
+        history.end_frame(0.5, &graph, &annotations);
+        // A drag is another, even right after.
+        history.edited(Edit::Drag, &graph);
+        history.end_frame(0.6, &graph, &annotations);
+        // Frames without edits aren't recorded.
+        annotations.set_tx_label(dummy_txid(1), "abc".to_string());
+        history.end_frame(5.0, &graph, &annotations);
+
+        let undone = history.undo(snapshot(&annotations)).unwrap();
+        assert_eq!(label(&undone), "abc");
+        let undone = history.undo(undone).unwrap();
+        assert_eq!(label(&undone), "a");
+        assert!(history.undo(snapshot(&undone.annotations)).is_none());
+
+        let redone = history.redo(undone).unwrap();
+        assert_eq!(label(&redone), "abc");
+
+        // A new edit discards what could be redone.
+        let mut annotations = redone.annotations;
+        history.end_frame(10.0, &graph, &annotations);
+        annotations.set_tx_label(dummy_txid(1), "x".to_string());
+        history.end_frame(10.1, &graph, &annotations);
+        assert!(history.redo(snapshot(&annotations)).is_none());
+    }
+
+    #[test]
+    fn test_undo_redo_graph() {
+        let mut graph = Graph::default();
+        let annotations = Annotations::default();
+        let mut history = History::default();
+        history.end_frame(0.0, &graph, &annotations);
+        let current = |graph: &Graph| Snapshot {
+            graph: GraphState::new(graph),
+            annotations: annotations.clone(),
+        };
+
+        let txs = dummy_transactions();
+        let txids: Vec<Txid> = txs.keys().copied().collect();
+        history.edited(Edit::Graph, &graph);
+        for (i, (txid, tx)) in txs.into_iter().enumerate() {
+            graph.add_tx(txid, tx, Pos2::new(i as f32 * 100.0, 0.0));
+        }
+        history.end_frame(0.1, &graph, &annotations);
+        history.edited(Edit::Graph, &graph);
+        graph.remove_tx(txids[0]);
+        history.end_frame(5.0, &graph, &annotations);
+
+        let removed = history.undo(current(&graph)).unwrap();
+        removed.graph.restore(&mut graph);
+        assert!(txids.iter().all(|txid| graph.contains(*txid)));
+
+        let loaded = history.undo(current(&graph)).unwrap();
+        loaded.graph.restore(&mut graph);
+        assert!(!txids.iter().any(|txid| graph.contains(*txid)));
+
+        let redone = history.redo(current(&graph)).unwrap();
+        redone.graph.restore(&mut graph);
+        assert!(txids.iter().all(|txid| graph.contains(*txid)));
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use eframe::{
+    egui_glow::{self, ShaderVersion},
+    glow::{self, HasContext},
+};
+use egui::{mutex::Mutex, Color32, Context, Id, PaintCallback, Rect};
+
+/// Draws many filled rects with a single instanced draw call. Used for the
+/// bodies and inputs/outputs of transactions in large graphs, where issuing a
+/// `painter.rect` per rect dominates the frame time.
+pub struct RectRenderer {
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+    instance_buffer: glow::Buffer,
+}
+
+/// Floats per instance: min x, min y, max x, max y, r, g, b, a.
+const INSTANCE_SIZE: usize = 8;
+
+impl RectRenderer {
+    /// Returns `None` if the context doesn't support instancing (e.g. WebGL1),
+    /// in which case we keep painting with egui.
+    fn new(gl: &glow::Context) -> Option<Self> {
+        let shader_version = ShaderVersion::get(gl);
+        if !shader_version.is_new_shader_interface() {
+            log::info!("Instanced rendering not supported with {shader_version:?}");
+            return None;
+        }
+
+        let vertex_shader_source = r#"
+            precision highp float;
+            uniform vec4 u_viewport;
+            in vec4 i_rect;
+            in vec4 i_color;
+            out vec4 v_color;
+            void main() {
+                vec2 corner = vec2(float(gl_VertexID & 1), float((gl_VertexID >> 1) & 1));
+                vec2 pos = (mix(i_rect.xy, i_rect.zw, corner) - u_viewport.xy) / u_viewport.zw;
+                gl_Position = vec4(2.0 * pos.x - 1.0, 1.0 - 2.0 * pos.y, 0.0, 1.0);
+                v_color = i_color;
+            }
+        "#;
+
+        let fragment_shader_source = r#"
+            precision mediump float;
+            in vec4 v_color;
+            out vec4 out_color;
+            void main() {
+                out_color = v_color;
+            }
+        "#;
+
+        unsafe {
+            let program = gl.create_program().ok()?;
+
+            let shaders: Vec<_> = [
+                (glow::VERTEX_SHADER, vertex_shader_source),
+                (glow::FRAGMENT_SHADER, fragment_shader_source),
+            ]
+            .iter()
+            .map(|(kind, source)| {
+                let shader = gl.create_shader(*kind).ok()?;
+                gl.shader_source(
+                    shader,
+                    &format!("{}\n{}", shader_version.version_declaration(), source),
+                );
+                gl.compile_shader(shader);
+                if !gl.get_shader_compile_status(shader) {
+                    log::error!(
+                        "Failed to compile shader: {}",
+                        gl.get_shader_info_log(shader)
+                    );
+                    return None;
+                }
+                gl.attach_shader(program, shader);
+                Some(shader)
+            })
+            .collect::<Option<_>>()?;
+
+            gl.link_program(program);
+            if !gl.get_program_link_status(program) {
+                log::error!(
+                    "Failed to link program: {}",
+                    gl.get_program_info_log(program)
+                );
+                return None;
+            }
+
+            for shader in shaders {
+                gl.detach_shader(program, shader);
+                gl.delete_shader(shader);
+            }
+
+            let vertex_array = gl.create_vertex_array().ok()?;
+            let instance_buffer = gl.create_buffer().ok()?;
+
+            gl.bind_vertex_array(Some(vertex_array));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(instance_buffer));
+
+            let stride = (INSTANCE_SIZE * std::mem::size_of::<f32>()) as i32;
+            for (name, offset) in [("i_rect", 0), ("i_color", 4)] {
+                let location = gl.get_attrib_location(program, name)?;
+                gl.enable_vertex_attrib_array(location);
+                gl.vertex_attrib_pointer_f32(
+                    location,
+                    4,
+                    glow::FLOAT,
+                    false,
+                    stride,
+                    offset * std::mem::size_of::<f32>() as i32,
+                );
+                gl.vertex_attrib_divisor(location, 1);
+            }
+
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+
+            Some(Self {
+                program,
+                vertex_array,
+                instance_buffer,
+            })
+        }
+    }
+
+    fn paint(&self, gl: &glow::Context, viewport: Rect, instances: &[f32]) {
+        let bytes: Vec<u8> = instances.iter().flat_map(|f| f.to_ne_bytes()).collect();
+
+        unsafe {
+            gl.use_program(Some(self.program));
+            gl.uniform_4_f32(
+                gl.get_uniform_location(self.program, "u_viewport").as_ref(),
+                viewport.min.x,
+                viewport.min.y,
+                viewport.width(),
+                viewport.height(),
+            );
+
+            // Same blending as egui: premultiplied alpha in gamma space.
+            gl.enable(glow::BLEND);
+            gl.blend_equation(glow::FUNC_ADD);
+            gl.blend_func_separate(
+                glow::ONE,
+                glow::ONE_MINUS_SRC_ALPHA,
+                glow::ONE_MINUS_DST_ALPHA,
+                glow::ONE,
+            );
+
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.instance_buffer));
+            gl.buffer_data_u8_slice(glow::ARRAY_BUFFER, &bytes, glow::STREAM_DRAW);
+            gl.draw_arrays_instanced(
+                glow::TRIANGLE_STRIP,
+                0,
+                4,
+                (instances.len() / INSTANCE_SIZE) as i32,
+            );
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(glow::ARRAY_BUFFER, None);
+        }
+    }
+
+    fn destroy(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_program(self.program);
+            gl.delete_vertex_array(self.vertex_array);
+            gl.delete_buffer(self.instance_buffer);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct SharedRenderer(Arc<Mutex<RectRenderer>>);
+
+impl SharedRenderer {
+    /// Set up the renderer once the GL context is known. Without a call to
+    /// this, [RectBatch::new] always returns `None`.
+    pub fn install(ctx: &Context, gl: &glow::Context) -> Option<Self> {
+        let shared = Self(Arc::new(Mutex::new(RectRenderer::new(gl)?)));
+        ctx.data_mut(|d| d.insert_temp(Id::NULL, shared.clone()));
+        Some(shared)
+    }
+
+    pub fn destroy(&self, gl: &glow::Context) {
+        self.0.lock().destroy(gl);
+    }
+}
+
+/// Collects filled rects (in screen coordinates) during a frame and turns them
+/// into one paint callback.
+pub struct RectBatch {
+    renderer: Arc<Mutex<RectRenderer>>,
+    instances: Vec<f32>,
+}
+
+impl RectBatch {
+    pub fn new(ctx: &Context) -> Option<Self> {
+        let SharedRenderer(renderer) = ctx.data(|d| d.get_temp(Id::NULL))?;
+        Some(Self {
+            renderer,
+            instances: Vec::new(),
+        })
+    }
+
+    pub fn rect_filled(&mut self, rect: Rect, color: Color32) {
+        let [r, g, b, a] = color.to_array();
+        self.instances.extend([
+            rect.min.x,
+            rect.min.y,
+            rect.max.x,
+            rect.max.y,
+            r as f32 / 255.0,
+            g as f32 / 255.0,
+            b as f32 / 255.0,
+            a as f32 / 255.0,
+        ]);
+    }
+
+    /// `rect` is the area that the callback paints into, usually the clip rect
+    /// of the graph.
+    pub fn into_callback(self, rect: Rect) -> PaintCallback {
+        let Self {
+            renderer,
+            instances,
+        } = self;
+        PaintCallback {
+            rect,
+            callback: Arc::new(egui_glow::CallbackFn::new(move |info, painter| {
+                renderer
+                    .lock()
+                    .paint(painter.gl(), info.viewport, &instances);
+            })),
+        }
+    }
+}
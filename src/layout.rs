@@ -1,7 +1,13 @@
-use egui::Grid;
+use egui::{Color32, Grid};
 use serde::{Deserialize, Serialize};
 
-use crate::{bitcoin::Sats, export, widgets::UiExt};
+use crate::{
+    bitcoin::{FeeRate, NumberFormat, Sats},
+    export,
+    graph::{TaintModel, Tx},
+    style::ColorBy,
+    widgets::UiExt,
+};
 
 #[derive(Deserialize, Serialize, Default)]
 #[serde(default)]
@@ -10,24 +16,56 @@ pub struct Layout {
     pub scale: Scale,
     #[serde(default = "default_as_true")]
     pub show_arrows: bool,
+    /// Draw parallel edges between the same two transactions as one.
+    pub merge_edges: bool,
+    /// Write the amount of each edge at its middle.
+    pub edge_labels: bool,
+    pub show_time_axis: bool,
+    /// Link the inputs and outputs of addresses that received more than one
+    /// coin.
+    pub address_reuse: bool,
+    /// Paint transactions with instanced draw calls instead of egui shapes.
+    pub gpu_rendering: bool,
+    pub spawn: Spawn,
+    pub timeline: Timeline,
+    pub resting: Resting,
+    pub color_by: ColorBy,
+    pub focus: Focus,
+    pub taint_model: TaintModel,
 }
 
-fn default_as_true() -> bool {
+pub fn default_as_true() -> bool {
     true
 }
 
 impl Layout {
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ui: &mut egui::Ui, format: NumberFormat) {
         self.force_params.ui(ui);
         ui.separator();
-        self.scale.ui(ui);
+        self.scale.ui(ui, format);
+        ui.separator();
+        self.spawn.ui(ui);
+        ui.separator();
+        self.timeline.ui(ui);
+        ui.separator();
+        self.resting.ui(ui);
         ui.separator();
         ui.bold("Misc:");
         ui.checkbox(&mut self.show_arrows, "Show arrows on edges");
+        ui.checkbox(&mut self.merge_edges, "Merge parallel edges");
+        ui.checkbox(&mut self.edge_labels, "Show amounts on edges")
+            .on_hover_text("Amounts that would overlap others are left out.");
+        ui.checkbox(&mut self.show_time_axis, "Show time axis");
+        ui.checkbox(&mut self.address_reuse, "Link reused addresses")
+            .on_hover_text("Dashed lines connect coins sent to the same address.");
+        ui.checkbox(&mut self.gpu_rendering, "GPU rendering")
+            .on_hover_text("Faster for very large graphs. Hides text when zoomed out far.");
     }
 
     pub fn import(&mut self, layout: &export::Layout0) {
         self.force_params.scale = layout.scale;
+        self.force_params.active = layout.active;
+        self.force_params.parents_above = layout.parents_above;
         self.scale.x1 = layout.x1;
         self.scale.y1 = layout.y1;
         self.scale.x2 = layout.x2;
@@ -37,6 +75,8 @@ impl Layout {
     pub fn export(&self) -> export::Layout0 {
         export::Layout0 {
             scale: self.force_params.scale,
+            active: self.force_params.active,
+            parents_above: self.force_params.parents_above,
             x1: self.scale.x1,
             y1: self.scale.y1,
             x2: self.scale.x2,
@@ -52,6 +92,13 @@ pub struct ForceParams {
     pub dt: f32,
     pub cooloff: f32,
     pub active: bool,
+    /// Never let a spending transaction float above its funding transaction.
+    pub parents_above: bool,
+    /// Transactions this close to a dragged one stand still until it's
+    /// released.
+    pub drag_radius: f32,
+    /// Time steps run at once when a dragged transaction is released.
+    pub settle_steps: usize,
 }
 
 impl Default for ForceParams {
@@ -61,6 +108,9 @@ impl Default for ForceParams {
             dt: 0.08,
             cooloff: 0.85,
             active: true,
+            parents_above: false,
+            drag_radius: 150.0,
+            settle_steps: 20,
         }
     }
 }
@@ -71,7 +121,8 @@ impl ForceParams {
 
         Grid::new("Layout").num_columns(2).show(ui, |ui| {
             ui.label("Layout Algorithm:");
-            ui.checkbox(&mut self.active, "Active");
+            ui.checkbox(&mut self.active, "Active")
+                .on_hover_text("Toggle with Space.");
             ui.end_row();
 
             ui.label("Scale:");
@@ -85,10 +136,247 @@ impl ForceParams {
             ui.label("Cooloff:");
             ui.add(egui::Slider::new(&mut self.cooloff, 0.5..=0.99));
             ui.end_row();
+
+            ui.label("Constraints:");
+            ui.checkbox(&mut self.parents_above, "Parents above children")
+                .on_hover_text("Place spending transactions below the ones they spend from.");
+            ui.end_row();
+
+            ui.label("Hold while dragging:");
+            ui.add(egui::Slider::new(&mut self.drag_radius, 0.0..=1000.0))
+                .on_hover_text("Transactions within this distance of a dragged one stand still.");
+            ui.end_row();
+
+            ui.label("Settle on release:");
+            ui.add(egui::Slider::new(&mut self.settle_steps, 0..=200).suffix(" steps"))
+                .on_hover_text("Extra time steps that relax the graph around a dropped one.");
+            ui.end_row();
         });
     }
 }
 
+/// Where transactions loaded by clicking an input or output are placed.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Spawn {
+    /// Gap to the clicked transaction, relative to the mean width of the two.
+    pub distance: f32,
+    pub direction: SpawnDirection,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum SpawnDirection {
+    /// Ancestors above, descendants below.
+    Vertical,
+    /// Ancestors to the left, descendants to the right.
+    Horizontal,
+}
+
+impl Default for Spawn {
+    fn default() -> Self {
+        Self {
+            distance: 0.2,
+            direction: SpawnDirection::Vertical,
+        }
+    }
+}
+
+impl Spawn {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.bold("New transactions:");
+
+        Grid::new("Spawn").num_columns(2).show(ui, |ui| {
+            ui.label("Distance:")
+                .on_hover_text("Relative to the size of the transactions.");
+            ui.add(egui::Slider::new(&mut self.distance, 0.0..=2.0).text("×"));
+            ui.end_row();
+
+            ui.label("Direction:");
+            ui.vertical(|ui| {
+                ui.radio_value(
+                    &mut self.direction,
+                    SpawnDirection::Vertical,
+                    "Ancestors above, descendants below",
+                );
+                ui.radio_value(
+                    &mut self.direction,
+                    SpawnDirection::Horizontal,
+                    "Ancestors left, descendants right",
+                );
+            });
+            ui.end_row();
+        });
+    }
+}
+
+/// Lay transactions out from left to right by when they were mined, and
+/// only simulate their vertical positions.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Timeline {
+    pub active: bool,
+    pub spacing: TimelineSpacing,
+    /// Gap between two columns, in points.
+    pub gap: f32,
+    /// Width of a day, in points.
+    pub day_width: f32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum TimelineSpacing {
+    /// One column per block height with transactions in the graph. Blocks
+    /// without any take no space.
+    Blocks,
+    /// Proportional to the block times.
+    Time,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            active: false,
+            spacing: TimelineSpacing::Blocks,
+            gap: 20.0,
+            day_width: 100.0,
+        }
+    }
+}
+
+impl Timeline {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.bold("Timeline:");
+
+        Grid::new("Timeline").num_columns(2).show(ui, |ui| {
+            ui.label("Layout:");
+            ui.checkbox(&mut self.active, "Left to right by block")
+                .on_hover_text("Only the vertical positions are simulated.");
+            ui.end_row();
+
+            ui.label("Spacing:");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.spacing, TimelineSpacing::Blocks, "Blocks");
+                ui.radio_value(&mut self.spacing, TimelineSpacing::Time, "Time");
+            });
+            ui.end_row();
+
+            match self.spacing {
+                TimelineSpacing::Blocks => {
+                    ui.label("Gap:");
+                    ui.add(egui::Slider::new(&mut self.gap, 0.0..=200.0).text("points"));
+                }
+                TimelineSpacing::Time => {
+                    ui.label("Day:");
+                    ui.add(
+                        egui::Slider::new(&mut self.day_width, 0.1..=10_000.0)
+                            .logarithmic(true)
+                            .text("points"),
+                    );
+                }
+            }
+            ui.end_row();
+        });
+    }
+}
+
+/// How long coins sat unspent between two transactions. Coins that move on
+/// within minutes are a common sign of mixing.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Resting {
+    /// Dim the edges of coins that rested longer than `minutes`.
+    pub filter: bool,
+    pub minutes: u32,
+}
+
+impl Default for Resting {
+    fn default() -> Self {
+        Self {
+            filter: false,
+            minutes: 10,
+        }
+    }
+}
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+impl Resting {
+    /// Upper bounds in seconds, with their legend and color.
+    pub const BUCKETS: [(i64, &'static str, Color32); 7] = [
+        (10 * 60, "< 10 min", Color32::from_rgb(0xd6, 0x5f, 0x5f)),
+        (HOUR, "< 1 hour", Color32::from_rgb(0xe0, 0xa4, 0x58)),
+        (DAY, "< 1 day", Color32::from_rgb(0xd6, 0xc9, 0x5f)),
+        (7 * DAY, "< 1 week", Color32::from_rgb(0x6f, 0xc0, 0x7a)),
+        (30 * DAY, "< 1 month", Color32::from_rgb(0x5f, 0xbf, 0xbf)),
+        (365 * DAY, "< 1 year", Color32::from_rgb(0x5f, 0x9e, 0xd6)),
+        (i64::MAX, "Longer", Color32::from_rgb(0x8f, 0x6f, 0xd6)),
+    ];
+
+    pub fn color(seconds: i64) -> Color32 {
+        Self::BUCKETS
+            .iter()
+            .find(|(max, _, _)| seconds < *max)
+            .map_or(Color32::GRAY, |(_, _, color)| *color)
+    }
+
+    pub fn hides(&self, seconds: i64) -> bool {
+        self.filter && seconds > 60 * self.minutes as i64
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.bold("Coin resting time:");
+
+        Grid::new("Resting").num_columns(2).show(ui, |ui| {
+            ui.label("Filter:")
+                .on_hover_text("Dim the coins that rested longer.");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.filter, "Moved within");
+                ui.add_enabled(
+                    self.filter,
+                    egui::DragValue::new(&mut self.minutes)
+                        .range(1..=60 * 24 * 30)
+                        .suffix(" min"),
+                );
+            });
+            ui.end_row();
+        });
+    }
+}
+
+/// Dim everything that isn't within a few hops of the selected transaction
+/// or coin, so that the flow around it stands out.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Focus {
+    pub active: bool,
+    pub hops: usize,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Self {
+            active: false,
+            hops: 2,
+        }
+    }
+}
+
+impl Focus {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.active, "Focus on the selection within");
+            ui.add_enabled(
+                self.active,
+                egui::DragValue::new(&mut self.hops)
+                    .range(0..=20)
+                    .suffix(" hops"),
+            );
+        })
+        .response
+        .on_hover_text("Dim the transactions and coins that aren't connected to it.");
+    }
+}
+
 /// Fit `y = a x^b` through `(x1, y1)` and `(x2, y2)`.
 #[derive(Serialize, Deserialize)]
 pub struct Scale {
@@ -96,6 +384,52 @@ pub struct Scale {
     y1: u64,
     x2: u64,
     y2: u64,
+    #[serde(default)]
+    pub size_by: SizeBy,
+}
+
+/// What the size of a transaction shows. Only [SizeBy::Value] has a
+/// configurable range, the others go from `y1` to `y2` over a fixed one.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+pub enum SizeBy {
+    #[default]
+    Value,
+    Fees,
+    FeeRate,
+    /// Number of inputs and outputs.
+    Ios,
+    Uniform,
+}
+
+impl SizeBy {
+    pub const ALL: [SizeBy; 5] = [
+        SizeBy::Value,
+        SizeBy::Fees,
+        SizeBy::FeeRate,
+        SizeBy::Ios,
+        SizeBy::Uniform,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SizeBy::Value => "Value",
+            SizeBy::Fees => "Fees",
+            SizeBy::FeeRate => "Fee Rate",
+            SizeBy::Ios => "Inputs & Outputs",
+            SizeBy::Uniform => "Uniform",
+        }
+    }
+
+    /// From the smallest to the largest transaction, for the modes without
+    /// a configurable range.
+    fn range(self) -> (f64, f64) {
+        match self {
+            SizeBy::Fees => (1_000.0, 1_000_000.0),
+            SizeBy::FeeRate => (1.0, 1_000.0),
+            SizeBy::Ios => (2.0, 200.0),
+            SizeBy::Value | SizeBy::Uniform => (1.0, 1.0),
+        }
+    }
 }
 
 #[allow(clippy::inconsistent_digit_grouping)]
@@ -106,28 +440,88 @@ impl Default for Scale {
             y1: 30,
             x2: 100_000_00_000_000,
             y2: 500,
+            size_by: SizeBy::Value,
         }
     }
 }
 
 impl Scale {
+    fn fit(&self, (x1, x2): (f64, f64), x: f64) -> f64 {
+        let (y1, y2) = (self.y1 as f64, self.y2 as f64);
+        let b = (y2 / y1).ln() / (x2 / x1).ln();
+        let a = y1 / x1.powf(b);
+
+        (a * x.powf(b)).max(10.0)
+    }
+
+    /// Size of an amount of `x` sats, whatever the transactions are sized by.
+    /// Inputs and outputs split their transaction by it.
     pub fn apply(&self, x: u64) -> f64 {
-        let b = -(self.y2 as f64 / self.y1 as f64).ln() / ((self.x1 as f64).ln() - (self.x2 as f64).ln());
-        let a = self.y1 as f64 / (self.x1 as f64).powf(b);
+        self.fit((self.x1 as f64, self.x2 as f64), x as f64)
+    }
+
+    pub fn size(&self, tx: &Tx) -> f64 {
+        let range = self.size_by.range();
+        match self.size_by {
+            SizeBy::Value => self.apply(tx.value),
+            SizeBy::Fees => self.fit(range, tx.fees() as f64),
+            SizeBy::FeeRate => match tx.fee_rate {
+                Some(FeeRate(rate)) => self.fit(range, rate),
+                None => self.y1 as f64,
+            },
+            SizeBy::Ios => {
+                let outputs = tx.outputs.len() - usize::from(tx.has_fees());
+                self.fit(range, (tx.inputs.len() + outputs) as f64)
+            }
+            SizeBy::Uniform => self.y1 as f64,
+        }
+    }
 
-        (a * (x as f64).powf(b)).max(10.0)
+    /// Size of a transaction that isn't loaded yet, from its value.
+    pub fn estimate(&self, value: u64) -> f64 {
+        match self.size_by {
+            SizeBy::Value => self.apply(value),
+            _ => self.y1 as f64,
+        }
     }
 
     #[allow(clippy::inconsistent_digit_grouping)]
-    pub fn ui(&mut self, ui: &mut egui::Ui) {
+    pub fn ui(&mut self, ui: &mut egui::Ui, format: NumberFormat) {
         ui.bold("Display size of transactions:");
 
         Grid::new("Scale").num_columns(2).show(ui, |ui| {
+            ui.label("Size by:");
+            egui::ComboBox::from_id_source("size_by")
+                .selected_text(self.size_by.name())
+                .show_ui(ui, |ui| {
+                    for size_by in SizeBy::ALL {
+                        ui.selectable_value(&mut self.size_by, size_by, size_by.name());
+                    }
+                });
+            ui.end_row();
+
+            if self.size_by == SizeBy::Uniform {
+                ui.label("Size:");
+                ui.add(egui::Slider::new(&mut self.y1, 30..=500).text("points"));
+                ui.end_row();
+                return;
+            }
+            if self.size_by != SizeBy::Value {
+                ui.label("Smallest:");
+                ui.add(egui::Slider::new(&mut self.y1, 30..=500).text("points"));
+                ui.end_row();
+
+                ui.label("Largest:");
+                ui.add(egui::Slider::new(&mut self.y2, 30..=500).text("points"));
+                ui.end_row();
+                return;
+            }
+
             ui.label("From:")
                 .on_hover_text("The size of the smallest transaction you want to investigate.");
             ui.add(
                 egui::Slider::new(&mut self.x1, 10_000..=100_000_00_000_000)
-                    .custom_formatter(|x, _| format!("{}", Sats(x as u64)))
+                    .custom_formatter(|x, _| Sats(x as u64).format(format))
                     .logarithmic(true)
                     .text("sats"),
             );
@@ -142,7 +536,7 @@ impl Scale {
                 .on_hover_text("The size of the largest transaction you want to investigate.");
             ui.add(
                 egui::Slider::new(&mut self.x2, 10_000..=100_000_00_000_000)
-                    .custom_formatter(|x, _| format!("{}", Sats(x as u64)))
+                    .custom_formatter(|x, _| Sats(x as u64).format(format))
                     .logarithmic(true)
                     .text("sats"),
             );
@@ -2,22 +2,34 @@
 
 mod annotations;
 mod app;
+mod axis;
 mod bezier;
 mod bitcoin;
+#[cfg(not(target_arch = "wasm32"))]
+mod bitcoind;
 mod client;
 mod components;
+mod evidence;
 mod export;
 mod flight;
 mod framerate;
 mod graph;
+mod hd;
+mod history;
+mod instanced;
 mod layout;
 mod loading;
 mod modal;
 mod notifications;
 mod platform;
+mod report;
+mod settings;
+mod spends;
+mod status_bar;
 mod style;
 mod transform;
 mod tx_cache;
+mod watches;
 mod widgets;
 mod workspaces;
 pub use app::App;
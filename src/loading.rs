@@ -8,17 +8,30 @@ use egui::{
 
 use crate::bitcoin::Txid;
 
+/// How many of the transactions requested since the last time nothing was
+/// loading have arrived.
+#[derive(Default)]
+struct Progress {
+    fetched: usize,
+    requested: usize,
+}
+
 #[derive(Clone)]
 struct State {
     txids: Arc<Mutex<HashSet<Txid>>>,
+    progress: Arc<Mutex<Progress>>,
     other: bool,
+    /// Operation spanning many requests, described for the user.
+    task: Arc<Mutex<Option<String>>>,
 }
 
 impl State {
     fn new() -> Self {
         Self {
             txids: Arc::new(Mutex::new(HashSet::new())),
+            progress: Arc::new(Mutex::new(Progress::default())),
             other: false,
+            task: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -31,15 +44,24 @@ impl State {
     }
 
     fn start_loading_txid(&self, txid: Txid) {
-        self.txids.lock().insert(txid);
+        if self.txids.lock().insert(txid) {
+            self.progress.lock().requested += 1;
+        }
     }
 
     fn loading_txid_done(&self, txid: Txid) {
-        self.txids.lock().remove(&txid);
+        let mut txids = self.txids.lock();
+        if txids.remove(&txid) {
+            let mut progress = self.progress.lock();
+            progress.fetched += 1;
+            if txids.is_empty() {
+                *progress = Progress::default();
+            }
+        }
     }
 
     fn is_loading(&self) -> bool {
-        !self.txids.lock().is_empty() || self.other
+        !self.txids.lock().is_empty() || self.other || self.task.lock().is_some()
     }
 
     fn is_txid_loading(&self, txid: &Txid) -> bool {
@@ -72,10 +94,26 @@ impl Loading {
         Self::modify(ctx, |store| store.other = false);
     }
 
+    /// Describe a longer running operation next to the spinner, until it's
+    /// set to `None`.
+    pub fn set_task(ctx: &Context, task: Option<String>) {
+        Self::modify(ctx, |store| *store.task.lock() = task);
+    }
+
     pub fn spinner(ui: &mut Ui) {
         let state = State::load(ui.ctx());
         if state.is_loading() {
             ui.spinner();
+            if let Some(task) = &*state.task.lock() {
+                ui.weak(task);
+            }
+            let progress = state.progress.lock();
+            if progress.requested > 1 {
+                ui.weak(format!(
+                    "{}/{} fetched",
+                    progress.fetched, progress.requested
+                ));
+            }
         }
     }
 
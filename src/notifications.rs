@@ -1,8 +1,14 @@
-use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::{
+    fmt::Debug,
+    sync::{
+        mpsc::{Receiver, Sender, TryRecvError},
+        Arc,
+    },
+};
 
 use egui::{
-    lerp, Align2, Area, Color32, Context, Frame, Id, Order, Pos2, Rect, Response, RichText, Sense,
-    Shape, Stroke, Ui, Vec2, WidgetText,
+    lerp, mutex::Mutex, Align2, Area, Color32, Context, Frame, Id, Order, Pos2, Rect, Response,
+    RichText, Sense, Shape, Stroke, Ui, Vec2, WidgetText,
 };
 
 const FIRST_OFFSET: f32 = 0.0;
@@ -44,11 +50,42 @@ impl Kind {
     }
 }
 
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// A button on a toast, e.g. to retry what failed. Can only be run once.
+#[derive(Clone)]
+pub struct Action {
+    label: String,
+    run: Arc<Mutex<Option<Callback>>>,
+}
+
+impl Action {
+    pub fn new(label: impl ToString, run: impl 'static + Send + FnOnce()) -> Self {
+        Self {
+            label: label.to_string(),
+            run: Arc::new(Mutex::new(Some(Box::new(run)))),
+        }
+    }
+
+    fn run(&self) {
+        if let Some(run) = self.run.lock().take() {
+            run();
+        }
+    }
+}
+
+impl Debug for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Action({})", self.label)
+    }
+}
+
 #[derive(Clone, Debug)]
 struct Toast {
     kind: Kind,
     message: String,
     detail: Option<String>,
+    action: Option<Action>,
     offset: f32,
     velocity: f32,
     ttl_sec: f32,
@@ -60,11 +97,18 @@ struct Toast {
 }
 
 impl Toast {
-    fn new(kind: Kind, message: String, detail: Option<String>, ttl_sec: f32) -> Self {
+    fn new(
+        kind: Kind,
+        message: String,
+        detail: Option<String>,
+        action: Option<Action>,
+        ttl_sec: f32,
+    ) -> Self {
         Self {
             kind,
             message,
             detail,
+            action,
             offset: FIRST_OFFSET - INITIAL_FRAME_HEIGHT - PADDING,
             velocity: 0.0,
             ttl_sec,
@@ -205,6 +249,12 @@ impl Notifications {
                                             .rect
                                             .right();
                                     }
+                                    if let Some(action) = &toast.action {
+                                        if ui.button(action.label.clone()).clicked() {
+                                            action.run();
+                                            toast.ttl_sec = 0.0;
+                                        }
+                                    }
                                 });
                             });
 
@@ -261,14 +311,40 @@ impl Notifications {
 }
 
 pub trait NotifyExt {
-    fn notify(&self, kind: Kind, message: impl ToString, detail: Option<impl ToString>, ttl_sec: f32);
+    fn notify(
+        &self,
+        kind: Kind,
+        message: impl ToString,
+        detail: Option<impl ToString>,
+        action: Option<Action>,
+        ttl_sec: f32,
+    );
 
     fn notify_error(&self, message: impl ToString, detail: Option<impl ToString>) {
-        self.notify(Kind::Error, message, detail, 8.0);
+        self.notify(Kind::Error, message, detail, None, 8.0);
+    }
+
+    /// An error the user can do something about, so it stays a bit longer.
+    fn notify_error_with_action(
+        &self,
+        message: impl ToString,
+        detail: Option<impl ToString>,
+        action: Action,
+    ) {
+        self.notify(Kind::Error, message, detail, Some(action), 15.0);
+    }
+
+    /// Something the user asked to be told about, so it stays a while.
+    fn notify_alert(&self, message: impl ToString, detail: Option<impl ToString>, action: Action) {
+        self.notify(Kind::Info, message, detail, Some(action), 60.0);
+    }
+
+    fn notify_warn(&self, message: impl ToString, detail: Option<impl ToString>) {
+        self.notify(Kind::Warn, message, detail, None, 8.0);
     }
 
     fn notify_success(&self, message: impl ToString) {
-        self.notify(Kind::Success, message, None::<&str>, 6.0);
+        self.notify(Kind::Success, message, None::<&str>, None, 6.0);
     }
 }
 
@@ -276,13 +352,21 @@ pub trait NotifyExt {
 struct NotificationSender(Sender<Toast>);
 
 impl NotifyExt for Context {
-    fn notify(&self, kind: Kind, message: impl ToString, detail: Option<impl ToString>, ttl_sec: f32) {
+    fn notify(
+        &self,
+        kind: Kind,
+        message: impl ToString,
+        detail: Option<impl ToString>,
+        action: Option<Action>,
+        ttl_sec: f32,
+    ) {
         if let Some(NotificationSender(sender)) = self.data(|d| d.get_temp(Id::NULL)) {
             sender
                 .send(Toast::new(
                     kind,
                     message.to_string(),
                     detail.map(|d| d.to_string()),
+                    action,
                     ttl_sec,
                 ))
                 .unwrap();
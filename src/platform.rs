@@ -6,7 +6,7 @@ pub mod inner {
     use wasm_bindgen::{closure::Closure, prelude::wasm_bindgen};
 
     use crate::app::Update;
-    use crate::bitcoin::Txid;
+    use crate::bitcoin::TxRef;
     use crate::notifications::NotifyExt;
 
     #[wasm_bindgen]
@@ -17,6 +17,9 @@ pub mod inner {
         #[wasm_bindgen(js_name = pushHistoryState)]
         pub fn push_history_state(url: &str);
 
+        #[wasm_bindgen(js_name = setTitle)]
+        pub fn set_title(title: &str);
+
         #[wasm_bindgen(js_name = getRandom)]
         fn get_random() -> f64;
     }
@@ -32,12 +35,19 @@ pub mod inner {
 
     pub fn add_route_listener(sender: Sender<Update>, ctx: egui::Context) {
         let closure = Closure::new(move |url: String| {
-            if let Some(txid) = url.strip_prefix("/tx/") {
-                match Txid::new(txid) {
-                    Ok(txid) => {
+            if url.starts_with("/tx/") {
+                match TxRef::parse_url(&url) {
+                    Ok(TxRef { txid, vout }) => {
                         sender
                             .send(Update::LoadOrSelectTx { txid, pos: None })
                             .unwrap();
+                        if let Some(vout) = vout {
+                            sender
+                                .send(Update::SelectCoin {
+                                    coin: (txid, vout as usize),
+                                })
+                                .unwrap();
+                        }
                         ctx.request_repaint();
                     }
                     Err(err) => {
@@ -54,6 +64,14 @@ pub mod inner {
         closure.forget();
     }
 
+    /// Turn a route like `tx/<txid>` into a URL that can be shared.
+    pub fn absolute_url(path: &str) -> String {
+        let origin = web_sys::window()
+            .and_then(|w| w.location().origin().ok())
+            .unwrap_or_default();
+        format!("{}/{}", origin, path)
+    }
+
     pub fn get_viewport_dimensions() -> Option<Vec2> {
         let window = web_sys::window()?;
         let width = window.inner_width().ok()?.as_f64()?;
@@ -71,11 +89,22 @@ pub mod inner {
     pub fn get_random_int(max: usize) -> usize {
         (get_random() * max as f64) as usize
     }
+
+    /// Seconds since some fixed point in time, for measuring durations.
+    pub fn now() -> f64 {
+        web_sys::window()
+            .and_then(|w| w.performance())
+            .map(|p| p.now() / 1000.0)
+            .unwrap_or_default()
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 pub mod inner {
-    use std::sync::mpsc::Sender;
+    use std::{
+        sync::{mpsc::Sender, OnceLock},
+        time::Instant,
+    };
 
     use egui::Vec2;
     use rand::{rngs::ThreadRng, Rng};
@@ -84,8 +113,14 @@ pub mod inner {
 
     pub fn push_history_state(_url: &str) {}
 
+    pub fn set_title(_title: &str) {}
+
     pub fn add_route_listener(_sender: Sender<Update>, _ctx: egui::Context) {}
 
+    pub fn absolute_url(path: &str) -> String {
+        format!("https://txgraph.info/{}", path)
+    }
+
     pub fn get_viewport_dimensions() -> Option<Vec2> {
         None
     }
@@ -100,4 +135,10 @@ pub mod inner {
         let mut rng = ThreadRng::default();
         rng.gen_range(0..max)
     }
+
+    /// Seconds since some fixed point in time, for measuring durations.
+    pub fn now() -> f64 {
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_secs_f64()
+    }
 }
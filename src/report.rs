@@ -0,0 +1,419 @@
+//! Human-readable investigation reports. A report is a standalone HTML page
+//! with a drawing of the graph, the key transactions, a summary of the flows
+//! between labeled entities and the raw workspace as an appendix. Printing the
+//! page from a browser gives a PDF.
+
+use std::{collections::HashMap, fmt::Write};
+
+use egui::{Context, Pos2, Rect};
+
+use crate::{
+    annotations::Annotations,
+    bitcoin::{NumberFormat, Sats, Txid},
+    export::Workspace,
+    modal,
+    notifications::NotifyExt,
+};
+
+/// A transaction as drawn in the report.
+pub struct ReportTx {
+    pub txid: Txid,
+    pub pos: Pos2,
+    pub width: f32,
+    pub value: u64,
+    pub fees: u64,
+    pub timestamp: String,
+    pub block_height: u32,
+}
+
+/// Value moved from an output of `source` to an input of `target`.
+pub struct ReportFlow {
+    pub source: Txid,
+    pub target: Txid,
+    pub value: u64,
+}
+
+pub struct GraphData {
+    pub txs: Vec<ReportTx>,
+    pub flows: Vec<ReportFlow>,
+}
+
+/// Placeholders are replaced by [render].
+pub const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+td, th { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }
+.mono { font-family: monospace; }
+pre { white-space: pre-wrap; word-break: break-all; font-size: 10px; }
+svg { border: 1px solid #ccc; max-width: 100%; height: auto; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<p>Generated {{date}} from a workspace with {{tx_count}} transactions.</p>
+<h2>Graph</h2>
+{{graph}}
+<h2>Key Transactions</h2>
+{{transactions}}
+<h2>Flows Between Entities</h2>
+{{flows}}
+<h2>Appendix: Raw Workspace</h2>
+<pre>{{raw}}</pre>
+</body>
+</html>
+"#;
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn hex_color(color: egui::Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+const TX_HEIGHT: f32 = 20.0;
+const MARGIN: f32 = 50.0;
+
+fn graph_svg(data: &GraphData, annotations: &Annotations) -> String {
+    let Some(bounds) = data
+        .txs
+        .iter()
+        .map(|tx| Rect::from_center_size(tx.pos, egui::vec2(tx.width, TX_HEIGHT)))
+        .reduce(|a, b| a.union(b))
+    else {
+        return "<p>The graph is empty.</p>".to_string();
+    };
+    let bounds = bounds.expand(MARGIN);
+    let positions: HashMap<Txid, Pos2> = data.txs.iter().map(|tx| (tx.txid, tx.pos)).collect();
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}">"#,
+        bounds.min.x,
+        bounds.min.y,
+        bounds.width(),
+        bounds.height(),
+        bounds.width().min(1000.0)
+    );
+    for flow in &data.flows {
+        if let (Some(from), Some(to)) = (positions.get(&flow.source), positions.get(&flow.target)) {
+            let from = *from + egui::vec2(0.0, TX_HEIGHT / 2.0);
+            let to = *to - egui::vec2(0.0, TX_HEIGHT / 2.0);
+            let dy = (to.y - from.y).abs() / 2.0;
+            write!(
+                svg,
+                r##"<path d="M {} {} C {} {} {} {} {} {}" fill="none" stroke="#888"/>"##,
+                from.x,
+                from.y,
+                from.x,
+                from.y + dy,
+                to.x,
+                to.y - dy,
+                to.x,
+                to.y
+            )
+            .unwrap();
+        }
+    }
+    for tx in &data.txs {
+        let rect = Rect::from_center_size(tx.pos, egui::vec2(tx.width, TX_HEIGHT));
+        let fill = annotations
+            .tx_color(tx.txid)
+            .map_or("#ddd".to_string(), hex_color);
+        write!(
+            svg,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="#444"/>"##,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+            fill
+        )
+        .unwrap();
+        let text = annotations
+            .tx_label(tx.txid)
+            .unwrap_or_else(|| tx.txid.to_string()[..8].to_string());
+        write!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="10" text-anchor="middle">{}</text>"#,
+            tx.pos.x,
+            rect.max.y + 12.0,
+            escape(&text)
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Labeled or colored transactions, or all of them if nothing is annotated.
+fn transactions_table(data: &GraphData, annotations: &Annotations, format: NumberFormat) -> String {
+    let annotated: Vec<_> = data
+        .txs
+        .iter()
+        .filter(|tx| {
+            annotations.tx_label(tx.txid).is_some() || annotations.tx_color(tx.txid).is_some()
+        })
+        .collect();
+    let txs = if annotated.is_empty() {
+        data.txs.iter().collect()
+    } else {
+        annotated
+    };
+
+    let mut table = "<table><tr><th>Txid</th><th>Block</th><th>Time</th><th>Value</th>\
+                     <th>Fees</th><th>Label</th></tr>"
+        .to_string();
+    for tx in txs {
+        write!(
+            table,
+            r#"<tr><td class="mono">{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            tx.txid,
+            tx.block_height,
+            escape(&tx.timestamp),
+            Sats(tx.value).format(format),
+            Sats(tx.fees).format(format),
+            escape(&annotations.tx_label(tx.txid).unwrap_or_default())
+        )
+        .unwrap();
+    }
+    table.push_str("</table>");
+    table
+}
+
+/// Sum of the values flowing between transactions with different labels.
+/// Unlabeled transactions count as one entity.
+fn entity_flows(data: &GraphData, annotations: &Annotations) -> Vec<(String, String, u64)> {
+    let entity = |txid| {
+        annotations
+            .tx_label(txid)
+            .unwrap_or_else(|| "Unlabeled".to_string())
+    };
+    let mut sums: HashMap<(String, String), u64> = HashMap::new();
+    for flow in &data.flows {
+        let (from, to) = (entity(flow.source), entity(flow.target));
+        if from != to {
+            *sums.entry((from, to)).or_default() += flow.value;
+        }
+    }
+    let mut flows: Vec<_> = sums
+        .into_iter()
+        .map(|((from, to), value)| (from, to, value))
+        .collect();
+    flows.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1))));
+    flows
+}
+
+fn flows_table(data: &GraphData, annotations: &Annotations, format: NumberFormat) -> String {
+    let flows = entity_flows(data, annotations);
+    if flows.is_empty() {
+        return "<p>No value flows between differently labeled transactions.</p>".to_string();
+    }
+    let mut table = "<table><tr><th>From</th><th>To</th><th>Value</th></tr>".to_string();
+    for (from, to, value) in flows {
+        write!(
+            table,
+            "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&from),
+            escape(&to),
+            Sats(value).format(format)
+        )
+        .unwrap();
+    }
+    table.push_str("</table>");
+    table
+}
+
+pub fn render(
+    template: &str,
+    title: &str,
+    date: &str,
+    format: NumberFormat,
+    data: &GraphData,
+    workspace: &Workspace,
+) -> String {
+    let raw = serde_json::to_string_pretty(workspace).unwrap();
+    substitute(
+        template,
+        &[
+            ("title", escape(title)),
+            ("date", escape(date)),
+            ("tx_count", data.txs.len().to_string()),
+            ("graph", graph_svg(data, &workspace.annotations)),
+            (
+                "transactions",
+                transactions_table(data, &workspace.annotations, format),
+            ),
+            ("flows", flows_table(data, &workspace.annotations, format)),
+            ("raw", escape(&raw)),
+        ],
+    )
+}
+
+/// Replace the `{{name}}` placeholders of the template in a single pass, so
+/// that placeholders in the values (e.g. in a label) are left as they are.
+/// Unknown placeholders are kept, too.
+fn substitute(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find("}}").and_then(|end| {
+            let (_, value) = values.iter().find(|(name, _)| *name == &rest[2..end])?;
+            Some((value, end + 2))
+        });
+        match value {
+            Some((value, len)) => {
+                out.push_str(value);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[derive(Default)]
+pub struct ReportBuilder {
+    title: Option<String>,
+}
+
+impl ReportBuilder {
+    pub fn open(&mut self) {
+        self.title = Some("Investigation Report".to_string());
+    }
+
+    /// `render` is called with the title when the user exports.
+    pub fn show(&mut self, ctx: &Context, render: impl FnOnce(&str) -> String) {
+        let Some(title) = &mut self.title else {
+            return;
+        };
+
+        let mut close = false;
+
+        modal::show(ctx, "Export Report", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Title:");
+                ui.text_edit_singleline(title);
+            });
+            ui.label("The report is copied to the clipboard as HTML. Save it as a .html file and print it from a browser to get a PDF.");
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+                if ui.button("Copy HTML").clicked() {
+                    ui.output_mut(|o| o.copied_text = render(title));
+                    ui.ctx().notify_success("Copied report to clipboard.");
+                    close = true;
+                }
+            });
+        });
+
+        if close {
+            self.title = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bitcoin::dummy_txid;
+
+    use super::*;
+
+    fn tx(n: u8) -> ReportTx {
+        ReportTx {
+            txid: dummy_txid(n),
+            pos: Pos2::new(0.0, n as f32 * 100.0),
+            width: 50.0,
+            value: 1000,
+            fees: 10,
+            timestamp: "2024-01-01".to_string(),
+            block_height: 800_000,
+        }
+    }
+
+    #[test]
+    fn report() {
+        let mut workspace = Workspace::default();
+        workspace
+            .annotations
+            .set_tx_label(dummy_txid(1), "Exchange <A>".to_string());
+        workspace
+            .annotations
+            .set_tx_label(dummy_txid(2), "Mixer".to_string());
+        let data = GraphData {
+            txs: vec![tx(1), tx(2), tx(3)],
+            flows: vec![
+                ReportFlow {
+                    source: dummy_txid(1),
+                    target: dummy_txid(2),
+                    value: 500,
+                },
+                ReportFlow {
+                    source: dummy_txid(1),
+                    target: dummy_txid(2),
+                    value: 300,
+                },
+                ReportFlow {
+                    source: dummy_txid(2),
+                    target: dummy_txid(3),
+                    value: 100,
+                },
+            ],
+        };
+
+        assert_eq!(
+            entity_flows(&data, &workspace.annotations),
+            vec![
+                ("Exchange <A>".to_string(), "Mixer".to_string(), 800),
+                ("Mixer".to_string(), "Unlabeled".to_string(), 100),
+            ]
+        );
+
+        let html = render(
+            TEMPLATE,
+            "Case 1",
+            "2024-06-01",
+            NumberFormat::English,
+            &data,
+            &workspace,
+        );
+        assert!(!html.contains("{{"));
+        assert!(html.contains("<title>Case 1</title>"));
+        assert!(html.contains("Exchange &lt;A&gt;"));
+        assert!(!html.contains("Exchange <A>"));
+        assert_eq!(html.matches("<rect").count(), 3);
+
+        // Placeholders in user content aren't expanded.
+        workspace
+            .annotations
+            .set_tx_label(dummy_txid(3), "{{raw}} {{title}".to_string());
+        let html = render(
+            TEMPLATE,
+            "{{date}}",
+            "2024-06-01",
+            NumberFormat::English,
+            &data,
+            &workspace,
+        );
+        assert!(html.contains("<title>{{date}}</title>"));
+        assert!(html.contains("{{raw}} {{title}"));
+        assert_eq!(html.matches("&quot;version&quot;").count(), 1);
+    }
+}
@@ -0,0 +1,371 @@
+use chrono::{Local, NaiveDateTime, TimeZone as _, Utc};
+use egui::{Grid, TextEdit};
+use serde::{Deserialize, Serialize};
+
+use crate::{bitcoin::NumberFormat, notifications::NotifyExt, style::Theme, widgets::UiExt};
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Seconds after which API requests are given up.
+    pub request_timeout: u32,
+    /// How many transactions to fetch at the same time.
+    pub max_concurrent_fetches: usize,
+    /// Minutes between checks of the alert rules.
+    pub alert_interval: u32,
+    /// Minutes between checks whether unspent outputs have been spent, or 0
+    /// to only check on demand.
+    pub spend_refresh_interval: u32,
+    /// Also ask the OS for attention when an alert fires (native only).
+    pub desktop_alerts: bool,
+    pub io_click: IoClick,
+    /// Seconds the pointer has to rest on something before its tooltip shows.
+    pub tooltip_delay: f32,
+    pub tooltip_anchor: TooltipAnchor,
+    /// Hide the tooltips of the graph while Alt is held.
+    pub alt_hides_tooltips: bool,
+    /// How many hops "Expand" in the context menu of a transaction loads.
+    pub expand_depth: usize,
+    /// Steps the privacy analysis of a transaction may take before it counts
+    /// as too complex.
+    pub privacy_budget: usize,
+    pub time_zone: TimeZone,
+    pub date_format: DateFormat,
+    /// Show the time of a transaction relative to the selected one in its
+    /// tooltip.
+    pub relative_times: bool,
+    pub number_format: NumberFormat,
+    /// Settings pasted from another device, until they are applied.
+    #[serde(skip)]
+    pasted: String,
+}
+
+/// What a click on an input or output does. Removing the transaction on the
+/// other side is always possible with a command-click or the context menu.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum IoClick {
+    /// Load the transaction on the other side, or select it if it's loaded.
+    Expand,
+    /// Load the transaction on the other side, or remove it if it's loaded.
+    Toggle,
+    /// Select the coin.
+    SelectCoin,
+}
+
+impl IoClick {
+    const ALL: [IoClick; 3] = [IoClick::Expand, IoClick::Toggle, IoClick::SelectCoin];
+
+    fn name(self) -> &'static str {
+        match self {
+            IoClick::Expand => "Expand",
+            IoClick::Toggle => "Expand or remove",
+            IoClick::SelectCoin => "Select coin",
+        }
+    }
+}
+
+/// Where the tooltips of the graph show up.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum TooltipAnchor {
+    /// Next to the pointer.
+    Pointer,
+    /// In a panel in the bottom right corner, out of the way of the graph.
+    Panel,
+}
+
+impl TooltipAnchor {
+    const ALL: [TooltipAnchor; 2] = [TooltipAnchor::Pointer, TooltipAnchor::Panel];
+
+    fn name(self) -> &'static str {
+        match self {
+            TooltipAnchor::Pointer => "At pointer",
+            TooltipAnchor::Panel => "Fixed panel",
+        }
+    }
+}
+
+/// Time zone of the times in the graph. Reports and exports always use UTC.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum TimeZone {
+    Utc,
+    Local,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+pub enum DateFormat {
+    Iso,
+    European,
+    American,
+}
+
+impl DateFormat {
+    const ALL: [DateFormat; 3] = [DateFormat::Iso, DateFormat::European, DateFormat::American];
+
+    fn name(self) -> &'static str {
+        match self {
+            DateFormat::Iso => "2009-01-31 13:05",
+            DateFormat::European => "31.01.2009 13:05",
+            DateFormat::American => "01/31/2009 1:05 PM",
+        }
+    }
+
+    /// With a two-digit year if `short`.
+    fn date(self, short: bool) -> &'static str {
+        match (self, short) {
+            (DateFormat::Iso, false) => "%Y-%m-%d",
+            (DateFormat::Iso, true) => "%y-%m-%d",
+            (DateFormat::European, false) => "%d.%m.%Y",
+            (DateFormat::European, true) => "%d.%m.%y",
+            (DateFormat::American, false) => "%m/%d/%Y",
+            (DateFormat::American, true) => "%m/%d/%y",
+        }
+    }
+
+    fn time(self) -> &'static str {
+        match self {
+            DateFormat::Iso | DateFormat::European => "%H:%M:%S",
+            DateFormat::American => "%-I:%M:%S %p",
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            request_timeout: 20,
+            max_concurrent_fetches: 6,
+            alert_interval: 5,
+            spend_refresh_interval: 0,
+            desktop_alerts: true,
+            io_click: IoClick::Expand,
+            tooltip_delay: 0.5,
+            tooltip_anchor: TooltipAnchor::Pointer,
+            alt_hides_tooltips: true,
+            expand_depth: 2,
+            privacy_budget: 50_000,
+            time_zone: TimeZone::Utc,
+            date_format: DateFormat::Iso,
+            relative_times: true,
+            number_format: NumberFormat::English,
+            pasted: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    fn format(&self, timestamp: i64, format: &str) -> String {
+        let utc = Utc.from_utc_datetime(
+            &NaiveDateTime::from_timestamp_opt(timestamp, 0).unwrap_or_default(),
+        );
+        match self.time_zone {
+            TimeZone::Utc => utc.format(format).to_string(),
+            TimeZone::Local => utc.with_timezone(&Local).format(format).to_string(),
+        }
+    }
+
+    /// Date and time of a block timestamp in the user's time zone and format.
+    /// With `short`, the year has two digits to fit into a transaction.
+    pub fn format_time(&self, timestamp: i64, short: bool) -> String {
+        let format = self.date_format;
+        let pattern = format!("{} {}", format.date(short), format.time());
+        self.format(timestamp, &pattern)
+    }
+
+    pub fn format_date(&self, timestamp: i64) -> String {
+        self.format(timestamp, self.date_format.date(false))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.bold("Network:");
+
+        Grid::new("Network").num_columns(2).show(ui, |ui| {
+            ui.label("Request timeout:")
+                .on_hover_text("Give up on requests the backend doesn't answer in time.");
+            ui.add(egui::Slider::new(&mut self.request_timeout, 5..=120).text("s"));
+            ui.end_row();
+
+            ui.label("Parallel fetches:")
+                .on_hover_text("Fetch fewer transactions at once if the backend rate-limits you.");
+            ui.add(egui::Slider::new(&mut self.max_concurrent_fetches, 1..=32));
+            ui.end_row();
+
+            ui.label("Refresh spends:")
+                .on_hover_text("Check whether unspent outputs have been spent. 0 turns it off.");
+            ui.add(egui::Slider::new(&mut self.spend_refresh_interval, 0..=60).text("min"));
+            ui.end_row();
+        });
+
+        ui.bold("Interaction:");
+
+        Grid::new("Interaction").num_columns(2).show(ui, |ui| {
+            ui.label("Click on input/output:").on_hover_text(
+                "Command-click or the context menu remove the transaction on the other side.",
+            );
+            egui::ComboBox::from_id_source("io_click")
+                .selected_text(self.io_click.name())
+                .show_ui(ui, |ui| {
+                    for click in IoClick::ALL {
+                        ui.selectable_value(&mut self.io_click, click, click.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Tooltip delay:");
+            ui.add(egui::Slider::new(&mut self.tooltip_delay, 0.0..=2.0).text("s"));
+            ui.end_row();
+
+            ui.label("Tooltips:");
+            ui.horizontal(|ui| {
+                for anchor in TooltipAnchor::ALL {
+                    ui.radio_value(&mut self.tooltip_anchor, anchor, anchor.name());
+                }
+            });
+            ui.end_row();
+
+            ui.label("");
+            ui.checkbox(&mut self.alt_hides_tooltips, "Hold Alt to hide tooltips");
+            ui.end_row();
+
+            ui.label("Expand depth:")
+                .on_hover_text("Hops loaded at once from the context menu of a transaction.");
+            ui.add(egui::Slider::new(&mut self.expand_depth, 1..=6).text("hops"));
+            ui.end_row();
+        });
+
+        ui.bold("Analysis:");
+
+        Grid::new("Analysis").num_columns(2).show(ui, |ui| {
+            ui.label("Privacy analysis:").on_hover_text(
+                "How long to search for the interpretations of a transaction. More covers \
+                 larger CoinJoins, but they take longer to load.",
+            );
+            ui.add(
+                egui::Slider::new(&mut self.privacy_budget, 1_000..=10_000_000)
+                    .logarithmic(true)
+                    .text("steps"),
+            );
+            ui.end_row();
+        });
+
+        ui.bold("Formats:");
+
+        Grid::new("Formats").num_columns(2).show(ui, |ui| {
+            ui.label("Time zone:")
+                .on_hover_text("Reports and exports always use UTC.");
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut self.time_zone, TimeZone::Utc, "UTC");
+                ui.radio_value(&mut self.time_zone, TimeZone::Local, "Local");
+            });
+            ui.end_row();
+
+            ui.label("Date format:");
+            egui::ComboBox::from_id_source("date_format")
+                .selected_text(self.date_format.name())
+                .show_ui(ui, |ui| {
+                    for format in DateFormat::ALL {
+                        ui.selectable_value(&mut self.date_format, format, format.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Amounts:")
+                .on_hover_text("Separators of the whole bitcoins and the decimal mark.");
+            egui::ComboBox::from_id_source("number_format")
+                .selected_text(self.number_format.name())
+                .show_ui(ui, |ui| {
+                    for format in NumberFormat::ALL {
+                        ui.selectable_value(&mut self.number_format, format, format.name());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("");
+            ui.checkbox(&mut self.relative_times, "Time relative to selection")
+                .on_hover_text("E.g. \"3 days later\" in the tooltips of transactions.");
+            ui.end_row();
+        });
+
+        ui.bold("Alerts:");
+
+        Grid::new("Alerts").num_columns(2).show(ui, |ui| {
+            ui.label("Check every:");
+            ui.add(egui::Slider::new(&mut self.alert_interval, 1..=60).text("min"));
+            ui.end_row();
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.label("Desktop:");
+                ui.checkbox(&mut self.desktop_alerts, "Flash the window on alerts");
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Carry the preferences over to another device by hand until there are
+    /// accounts to sync them with. Credentials are never included.
+    // TODO: Sync with the user's account on the server, resolving conflicting
+    // changes from several devices, once there are accounts.
+    pub fn transfer_ui(&mut self, ui: &mut egui::Ui, theme: &mut Theme) {
+        ui.bold("Transfer:");
+
+        ui.horizontal(|ui| {
+            if ui.button("Copy settings").clicked() {
+                let json = serde_json::json!({ "theme": theme, "settings": self });
+                ui.output_mut(|o| o.copied_text = json.to_string());
+                ui.ctx().notify_success("Copied settings to clipboard.");
+                ui.close_menu();
+            }
+
+            ui.add(
+                TextEdit::singleline(&mut self.pasted)
+                    .hint_text("Paste settings here...")
+                    .desired_width(150.0),
+            );
+            let apply = ui
+                .add_enabled(!self.pasted.trim().is_empty(), egui::Button::new("Apply"))
+                .on_hover_text("Replaces all settings and the theme.");
+            if apply.clicked() {
+                match serde_json::from_str::<Transfer>(&self.pasted) {
+                    Ok(transfer) => {
+                        *theme = transfer.theme;
+                        *self = transfer.settings;
+                        ui.ctx().notify_success("Applied pasted settings.");
+                        ui.close_menu();
+                    }
+                    Err(e) => ui.ctx().notify_error("Could not read settings", Some(e)),
+                }
+            }
+        });
+    }
+}
+
+#[derive(Deserialize)]
+struct Transfer {
+    #[serde(default)]
+    theme: Theme,
+    #[serde(default)]
+    settings: Settings,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_time() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            settings.format_time(1231006505, false),
+            "2009-01-03 18:15:05"
+        );
+        assert_eq!(settings.format_time(1231006505, true), "09-01-03 18:15:05");
+
+        settings.date_format = DateFormat::American;
+        assert_eq!(
+            settings.format_time(1231006505, false),
+            "01/03/2009 6:15:05 PM"
+        );
+        settings.date_format = DateFormat::European;
+        assert_eq!(settings.format_date(1231006505), "03.01.2009");
+    }
+}
@@ -0,0 +1,190 @@
+use std::{sync::mpsc::Sender, time::Duration};
+
+use egui::{ahash::HashSet, Context};
+use serde::Deserialize;
+
+use crate::{
+    app::Update, bitcoin::Txid, graph::Graph, notifications::NotifyExt, tx_cache::TxCache,
+};
+
+/// Response of the backend's `/tx/:txid/outspend/:vout` endpoint, and one
+/// element per output of `/tx/:txid/outspends`.
+#[derive(Deserialize)]
+pub struct Outspend {
+    pub spent: bool,
+    pub txid: Option<Txid>,
+}
+
+impl Outspend {
+    pub fn spending_txid(&self) -> Option<Txid> {
+        self.txid.filter(|_| self.spent)
+    }
+}
+
+/// Re-checks the unspent outputs of the graph. Cached transactions and saved
+/// workspaces don't learn about later spends by themselves.
+#[derive(Default)]
+pub struct SpendRefresh {
+    /// Transactions whose outspends haven't arrived yet.
+    pending: HashSet<Txid>,
+    /// Outputs found spent since the pending checks started.
+    spent: Vec<(Txid, usize)>,
+    failed: usize,
+    /// Tell about the result even if nothing changed, when the user asked for
+    /// the refresh.
+    report: bool,
+    /// Check transactions as their placeholders are filled, after a workspace
+    /// has been opened.
+    on_hydrate: bool,
+    next_poll: Option<f64>,
+}
+
+impl SpendRefresh {
+    pub fn start(&mut self, ctx: &Context, graph: &Graph, sender: Sender<Update>, report: bool) {
+        self.report |= report;
+        for txid in graph.txids_with_utxos() {
+            self.fetch(ctx, txid, sender.clone());
+        }
+        if report && !self.is_running() {
+            ctx.notify_success("There are no unspent outputs in the graph.");
+            self.report = false;
+        }
+    }
+
+    /// Workspaces may have been saved long ago, so their transactions are
+    /// checked when they are opened, including the ones that are only
+    /// fetched later as they come into view.
+    pub fn workspace_opened(&mut self, ctx: &Context, graph: &Graph, sender: Sender<Update>) {
+        *self = Self {
+            next_poll: self.next_poll,
+            on_hydrate: true,
+            ..Default::default()
+        };
+        self.start(ctx, graph, sender, false);
+    }
+
+    pub fn hydrated(&mut self, ctx: &Context, graph: &Graph, txid: Txid, sender: Sender<Update>) {
+        if self.on_hydrate && graph.has_utxos(txid) {
+            self.fetch(ctx, txid, sender);
+        }
+    }
+
+    /// The backend has no `/tx/:txid/spends` endpoint with only the spending
+    /// txids, so this uses the Esplora-style `/tx/:txid/outspends`, which is
+    /// just as small. Failures are summed up by [Self::finish].
+    fn fetch(&mut self, ctx: &Context, txid: Txid, sender: Sender<Update>) {
+        if !self.pending.insert(txid) {
+            return;
+        }
+        TxCache::poll_json::<Vec<Outspend>>(
+            ctx,
+            move |base_url| ehttp::Request::get(format!("{}/tx/{}/outspends", base_url, txid)),
+            move |result| {
+                let spending = result
+                    .ok()
+                    .map(|outspends| outspends.iter().map(Outspend::spending_txid).collect());
+                sender
+                    .send(Update::OutspendsChecked { txid, spending })
+                    .unwrap();
+            },
+        );
+    }
+
+    /// Refresh every `interval` minutes, unless it's 0.
+    pub fn poll(&mut self, ctx: &Context, graph: &Graph, interval: u32, sender: Sender<Update>) {
+        if interval == 0 {
+            self.next_poll = None;
+            return;
+        }
+        let interval = 60.0 * interval as f64;
+        let now = ctx.input(|i| i.time);
+        let next_poll = self.next_poll.get_or_insert(now + interval);
+        if now < *next_poll {
+            return;
+        }
+        *next_poll = now + interval;
+        ctx.request_repaint_after(Duration::from_secs_f64(interval));
+        self.start(ctx, graph, sender, false);
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Apply the spending transactions of the outputs of `txid`, by vout, or
+    /// `None` if the check failed. Once all checks are in, a notification
+    /// sums up what changed.
+    pub fn checked(
+        &mut self,
+        ctx: &Context,
+        graph: &mut Graph,
+        txid: Txid,
+        spending: Option<Vec<Option<Txid>>>,
+    ) {
+        if !self.pending.remove(&txid) {
+            // From before another workspace was opened.
+            return;
+        }
+        match spending {
+            Some(spending) => {
+                for (vout, spending_txid) in spending.into_iter().enumerate() {
+                    let Some(spending_txid) = spending_txid else {
+                        continue;
+                    };
+                    if graph.set_spent((txid, vout), spending_txid) {
+                        TxCache::set_spent(ctx, (txid, vout), spending_txid);
+                        self.spent.push((txid, vout));
+                    }
+                }
+            }
+            None => self.failed += 1,
+        }
+        if self.pending.is_empty() {
+            self.finish(ctx);
+        }
+    }
+
+    fn finish(&mut self, ctx: &Context) {
+        let spent = std::mem::take(&mut self.spent);
+        let failed = std::mem::take(&mut self.failed);
+        let report = std::mem::take(&mut self.report);
+
+        let failed_note = (failed > 0).then(|| {
+            format!(
+                "{} transaction{} couldn't be checked.",
+                failed,
+                if failed == 1 { "" } else { "s" }
+            )
+        });
+        if !spent.is_empty() {
+            let mut coins: Vec<String> = spent
+                .iter()
+                .take(5)
+                .map(|(txid, vout)| format!("{}:{}", &txid.hex_string()[..8], vout))
+                .collect();
+            if spent.len() > 5 {
+                coins.push(format!("and {} more", spent.len() - 5));
+            }
+            let detail = [Some(coins.join(", ")), failed_note].into_iter().flatten();
+            ctx.notify_warn(
+                format!(
+                    "{} output{} spent since {} loaded",
+                    spent.len(),
+                    if spent.len() == 1 { " was" } else { "s were" },
+                    if spent.len() == 1 {
+                        "it was"
+                    } else {
+                        "they were"
+                    }
+                ),
+                Some(detail.collect::<Vec<_>>().join(" ")),
+            );
+        } else if failed > 0 {
+            if report {
+                ctx.notify_error("Couldn't refresh all spends", failed_note);
+            }
+        } else if report {
+            ctx.notify_success("All unspent outputs are still unspent.");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::{sync::Arc, time::Duration};
+
+use egui::{mutex::Mutex, Context, Frame, RichText};
+use serde::Deserialize;
+
+use crate::{bitcoin::Network, client::Client, framerate::FrameRate, graph::Graph, layout::Layout};
+
+/// How often to ask the backend for the chain tip, and for its status until
+/// it answers, in seconds.
+const TIP_INTERVAL: f64 = 60.0;
+
+/// Bottom bar with the state of the backend connection and the graph.
+#[derive(Default)]
+pub struct StatusBar {
+    tip: Arc<Mutex<Option<u32>>>,
+    next_tip_fetch: f64,
+    /// Asked for along with the tip until the backend reports it. Stays
+    /// `None` with backends that don't.
+    status: Arc<Mutex<Option<Status>>>,
+}
+
+/// What the backend's `/status` endpoint reports about itself.
+#[derive(Clone, Deserialize)]
+struct Status {
+    network: Network,
+}
+
+impl StatusBar {
+    /// The network the backend serves, once it is known.
+    pub fn network(&self) -> Option<Network> {
+        self.status.lock().as_ref().map(|s| s.network)
+    }
+
+    fn fetch_status(&self, ctx: &Context) {
+        let status = self.status.clone();
+        Client::poll_json::<Status>(
+            |base_url| ehttp::Request::get(format!("{}/status", base_url)),
+            ctx,
+            move |result| {
+                if let Ok(result) = result {
+                    *status.lock() = Some(result);
+                }
+            },
+        );
+    }
+
+    fn fetch_tip(&self, ctx: &Context) {
+        let tip = self.tip.clone();
+        Client::poll_json::<u32>(
+            |base_url| ehttp::Request::get(format!("{}/blocks/tip/height", base_url)),
+            ctx,
+            move |result| {
+                if let Ok(height) = result {
+                    *tip.lock() = Some(height);
+                }
+            },
+        );
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        graph: &Graph,
+        layout: &Layout,
+        framerate: &mut FrameRate,
+    ) {
+        let now = ctx.input(|i| i.time);
+        if now >= self.next_tip_fetch {
+            self.next_tip_fetch = now + TIP_INTERVAL;
+            self.fetch_tip(ctx);
+            if self.network().is_none() {
+                self.fetch_status(ctx);
+            }
+        }
+        ctx.request_repaint_after(Duration::from_secs_f64(self.next_tip_fetch - now));
+
+        let frame = Frame::side_top_panel(&ctx.style()).inner_margin(4.0);
+
+        egui::TopBottomPanel::bottom("status_bar")
+            .frame(frame)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let last_fetch = Client::last_fetch(ctx);
+                    let base_url = Client::base_url(ctx);
+                    let backend = base_url
+                        .split_once("://")
+                        .map_or(base_url.as_str(), |(_, host)| host);
+
+                    let (color, hover) = match last_fetch {
+                        Some(fetch) if !fetch.ok => {
+                            (ui.visuals().error_fg_color, "The last request failed.")
+                        }
+                        Some(_) => (ui.visuals().weak_text_color(), "Connected."),
+                        None => (ui.visuals().weak_text_color(), "No requests yet."),
+                    };
+                    ui.label(RichText::new(format!("● {}", backend)).small().color(color))
+                        .on_hover_text(hover);
+
+                    match self.network() {
+                        Some(Network::Mainnet) => {}
+                        Some(network) => {
+                            ui.colored_label(
+                                egui::Color32::LIGHT_RED,
+                                network.to_string().to_uppercase(),
+                            );
+                        }
+                        None => {
+                            #[cfg(testnet)]
+                            ui.colored_label(egui::Color32::LIGHT_RED, "TESTNET");
+                        }
+                    }
+
+                    ui.separator();
+                    let tip = match *self.tip.lock() {
+                        Some(height) => format!("Tip #{}", height),
+                        None => "Tip unknown".to_string(),
+                    };
+                    ui.label(RichText::new(tip).weak().small());
+
+                    if let Some(fetch) = last_fetch {
+                        ui.separator();
+                        ui.label(
+                            RichText::new(format!("{:.0} ms", 1e3 * fetch.latency))
+                                .weak()
+                                .small(),
+                        )
+                        .on_hover_text("Latency of the last request.");
+                    }
+
+                    ui.separator();
+                    ui.label(
+                        RichText::new(format!(
+                            "{} txs, {} edges",
+                            graph.num_transactions(),
+                            graph.num_edges()
+                        ))
+                        .weak()
+                        .small(),
+                    );
+
+                    ui.separator();
+                    let simulation = if layout.force_params.active {
+                        "Layout running"
+                    } else {
+                        "Layout paused (Space to resume)"
+                    };
+                    ui.label(RichText::new(simulation).weak().small());
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        framerate.ui(ui);
+                    });
+                });
+            });
+    }
+}
@@ -1,7 +1,20 @@
 use std::sync::Arc;
 
 use egui::{Color32, FontId, Response, Stroke, Widget};
+use serde::{Deserialize, Serialize};
 
+use crate::{bitcoin::AddressType, layout::Resting};
+
+/// Size of the text on transactions at zoom level 1.
+const FONT_SIZE: f32 = 10.0;
+/// Zoomed text stays within these sizes, so that it's still readable when
+/// zoomed out a bit and doesn't fill the screen when zoomed in.
+const MIN_FONT_SIZE: f32 = 7.0;
+const MAX_FONT_SIZE: f32 = 20.0;
+/// Text that would be smaller fades out until it's gone at this size.
+const FADED_FONT_SIZE: f32 = 4.0;
+
+#[derive(Clone)]
 pub struct Style {
     pub tx_width: f32,
     pub tx_stroke_width: f32,
@@ -13,7 +26,12 @@ pub struct Style {
     pub utxo_bg: Color32,
     pub btc: Color32,
     pub tx_bg: Color32,
+    pub warn: Color32,
+    /// Transactions spending the same coin.
+    pub conflict: Color32,
     pub egui_style: Arc<egui::Style>,
+    font_size: f32,
+    text_opacity: f32,
 }
 
 impl Style {
@@ -29,7 +47,11 @@ impl Style {
             utxo_bg: Color32::from_gray(128),
             btc: Color32::from_rgb(255, 153, 0),
             tx_bg: Color32::from_rgb(0x1d, 0x9b, 0xf0),
+            warn: Color32::from_rgb(255, 171, 83),
+            conflict: Color32::from_rgb(255, 83, 83),
             egui_style,
+            font_size: FONT_SIZE,
+            text_opacity: 1.0,
         }
     }
 
@@ -46,11 +68,21 @@ impl Style {
     }
 
     pub fn black_text_color(&self) -> Color32 {
-        self.egui_style.visuals.strong_text_color()
+        self.egui_style
+            .visuals
+            .strong_text_color()
+            .gamma_multiply(self.text_opacity)
     }
 
     pub fn white_text_color(&self) -> Color32 {
-        self.egui_style.visuals.text_color()
+        self.egui_style
+            .visuals
+            .text_color()
+            .gamma_multiply(self.text_opacity)
+    }
+
+    pub fn btc_color(&self) -> Color32 {
+        self.btc.gamma_multiply(self.text_opacity)
     }
 
     pub fn tx_stroke(&self) -> Stroke {
@@ -73,7 +105,25 @@ impl Style {
     }
 
     pub fn font_id(&self) -> FontId {
-        FontId::monospace(10.0)
+        FontId::monospace(self.font_size)
+    }
+
+    /// The style for text that is drawn on transactions at zoom level `zoom`,
+    /// which grows and shrinks with them, or `None` if it would be too small
+    /// to read. It fades out before that.
+    pub fn zoomed_text(&self, zoom: f32) -> Option<Style> {
+        let size = FONT_SIZE * zoom;
+        let opacity = (size - FADED_FONT_SIZE) / (MIN_FONT_SIZE - FADED_FONT_SIZE);
+        (opacity > 0.0).then(|| Self {
+            font_size: size.clamp(MIN_FONT_SIZE, MAX_FONT_SIZE),
+            text_opacity: opacity.min(1.0),
+            ..self.clone()
+        })
+    }
+
+    /// Stroke for inputs/outputs linked to a hovered edge.
+    pub fn io_linked_stroke(&self) -> Stroke {
+        Stroke::new(self.tx_stroke_width * 2.0, self.io_highlight_color)
     }
 
     pub fn io_stroke(&self, response: &Response) -> Stroke {
@@ -96,6 +146,169 @@ pub fn get(ui: &egui::Ui) -> Style {
     }
 }
 
+/// What transactions and the coins between them are colored by. Annotated
+/// colors only show in [ColorBy::Annotation] mode.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub enum ColorBy {
+    #[default]
+    Annotation,
+    /// Coins by how long they rested, transactions by their fastest coin in.
+    CoinAge,
+    Value,
+    /// Coins by the type of their address, transactions by the type of their
+    /// outputs.
+    AddressType,
+    Component,
+    /// Coins by the wallet their address was spent with, transactions by the
+    /// wallet that signed their inputs, by common-input ownership.
+    Cluster,
+    /// Transactions by how many ways their inputs could have paid their
+    /// outputs.
+    Privacy,
+}
+
+impl ColorBy {
+    pub const ALL: [ColorBy; 7] = [
+        ColorBy::Annotation,
+        ColorBy::CoinAge,
+        ColorBy::Value,
+        ColorBy::AddressType,
+        ColorBy::Component,
+        ColorBy::Cluster,
+        ColorBy::Privacy,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorBy::Annotation => "Annotation",
+            ColorBy::CoinAge => "Coin Age",
+            ColorBy::Value => "Value",
+            ColorBy::AddressType => "Address Type",
+            ColorBy::Component => "Connected Component",
+            ColorBy::Cluster => "Wallet Cluster",
+            ColorBy::Privacy => "Privacy",
+        }
+    }
+
+    /// Title and entries of the legend, or `None` if there's nothing to
+    /// explain.
+    pub fn legend(self) -> Option<(&'static str, Vec<(Color32, String)>)> {
+        let entries = match self {
+            ColorBy::Annotation => return None,
+            ColorBy::CoinAge => Resting::BUCKETS
+                .iter()
+                .map(|(_, label, color)| (*color, label.to_string()))
+                .collect(),
+            ColorBy::Value => VALUE_BUCKETS
+                .iter()
+                .map(|(_, label, color)| (*color, label.to_string()))
+                .collect(),
+            ColorBy::AddressType => ADDRESS_TYPES
+                .iter()
+                .map(|address_type| (address_type_color(*address_type), address_type.to_string()))
+                .chain([(MIXED, "Mixed".to_string())])
+                .collect(),
+            ColorBy::Privacy => ENTROPY_BUCKETS
+                .iter()
+                .map(|(_, label, color)| (*color, label.to_string()))
+                .chain([(MIXED, "Too complex".to_string())])
+                .collect(),
+            ColorBy::Component | ColorBy::Cluster => vec![],
+        };
+        let title = match self {
+            ColorBy::CoinAge => "Coin rested",
+            ColorBy::Value => "Value",
+            ColorBy::AddressType => "Address type",
+            ColorBy::Cluster => "One color per set of addresses spent together",
+            ColorBy::Privacy => "Entropy of the links from inputs to outputs",
+            _ => "One color per component",
+        };
+        Some((title, entries))
+    }
+}
+
+/// Upper bounds in sats, with their legend and color.
+const VALUE_BUCKETS: [(u64, &str, Color32); 6] = [
+    (100_000, "< 0.001 BTC", Color32::from_rgb(0x8f, 0x6f, 0xd6)),
+    (1_000_000, "< 0.01 BTC", Color32::from_rgb(0x5f, 0x9e, 0xd6)),
+    (10_000_000, "< 0.1 BTC", Color32::from_rgb(0x5f, 0xbf, 0xbf)),
+    (100_000_000, "< 1 BTC", Color32::from_rgb(0x6f, 0xc0, 0x7a)),
+    (
+        1_000_000_000,
+        "< 10 BTC",
+        Color32::from_rgb(0xe0, 0xa4, 0x58),
+    ),
+    (u64::MAX, "More", Color32::from_rgb(0xd6, 0x5f, 0x5f)),
+];
+
+pub fn value_color(sats: u64) -> Color32 {
+    VALUE_BUCKETS
+        .iter()
+        .find(|(max, _, _)| sats < *max)
+        .map_or(MIXED, |(_, _, color)| *color)
+}
+
+/// Upper bounds of entropy in bits, with their legend and color.
+const ENTROPY_BUCKETS: [(f64, &str, Color32); 4] = [
+    (0.5, "Deterministic", Color32::from_rgb(0xd6, 0x5f, 0x5f)),
+    (2.0, "< 2 bits", Color32::from_rgb(0xe0, 0xa4, 0x58)),
+    (4.0, "< 4 bits", Color32::from_rgb(0x6f, 0xc0, 0x7a)),
+    (f64::INFINITY, "More", Color32::from_rgb(0x5f, 0x9e, 0xd6)),
+];
+
+/// `None` for transactions too complex to analyze.
+pub fn entropy_color(bits: Option<f64>) -> Color32 {
+    let Some(bits) = bits else {
+        return MIXED;
+    };
+    ENTROPY_BUCKETS
+        .iter()
+        .find(|(max, _, _)| bits < *max)
+        .map_or(MIXED, |(_, _, color)| *color)
+}
+
+pub const ADDRESS_TYPES: [AddressType; 6] = [
+    AddressType::P2PKH,
+    AddressType::P2SH,
+    AddressType::P2WPKH,
+    AddressType::P2WSH,
+    AddressType::P2TR,
+    AddressType::Unknown,
+];
+
+pub fn address_type_color(address_type: AddressType) -> Color32 {
+    match address_type {
+        AddressType::P2PKH => Color32::from_rgb(0xd6, 0x5f, 0x5f),
+        AddressType::P2SH => Color32::from_rgb(0xe0, 0xa4, 0x58),
+        AddressType::P2WPKH => Color32::from_rgb(0x5f, 0x9e, 0xd6),
+        AddressType::P2WSH => Color32::from_rgb(0x3f, 0x6f, 0xa8),
+        AddressType::P2TR => Color32::from_rgb(0x6f, 0xc0, 0x7a),
+        AddressType::Unknown => Color32::GRAY,
+    }
+}
+
+/// Transactions whose outputs are of different address types.
+pub const MIXED: Color32 = Color32::from_gray(96);
+
+/// Coins carrying the value of a coin marked as tainted, faded by how much.
+pub const TAINT: Color32 = Color32::from_rgb(0xd6, 0x3f, 0x3f);
+
+/// Colors of the connected components, repeated once there are more.
+const PALETTE: [Color32; 8] = [
+    Color32::from_rgb(0x5f, 0x9e, 0xd6),
+    Color32::from_rgb(0xe0, 0xa4, 0x58),
+    Color32::from_rgb(0x6f, 0xc0, 0x7a),
+    Color32::from_rgb(0xd6, 0x5f, 0x5f),
+    Color32::from_rgb(0x8f, 0x6f, 0xd6),
+    Color32::from_rgb(0x5f, 0xbf, 0xbf),
+    Color32::from_rgb(0xd6, 0x7f, 0xb8),
+    Color32::from_rgb(0xd6, 0xc9, 0x5f),
+];
+
+pub fn component_color(component: usize) -> Color32 {
+    PALETTE[component % PALETTE.len()]
+}
+
 #[derive(Default, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum Theme {
     Light,
@@ -163,3 +376,32 @@ impl<'a> Widget for ThemeSwitch<'a> {
         response
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zoomed_text() {
+        let style = Style::light(Default::default());
+        let size = |zoom: f32| style.zoomed_text(zoom).map(|s| s.font_id().size);
+
+        assert_eq!(size(1.0), Some(10.0));
+        assert_eq!(size(1.5), Some(15.0));
+        assert_eq!(size(10.0), Some(MAX_FONT_SIZE));
+        assert_eq!(size(0.6), Some(MIN_FONT_SIZE));
+        assert_eq!(size(0.3), None);
+
+        let faded = style.zoomed_text(0.55).unwrap();
+        assert!(faded.black_text_color().a() < style.black_text_color().a());
+    }
+
+    #[test]
+    fn value_buckets() {
+        assert_eq!(value_color(0), VALUE_BUCKETS[0].2);
+        assert_eq!(value_color(99_999), VALUE_BUCKETS[0].2);
+        assert_eq!(value_color(100_000), VALUE_BUCKETS[1].2);
+        assert_eq!(value_color(50_000_000), VALUE_BUCKETS[3].2);
+        assert_eq!(value_color(21_000_000 * 100_000_000), VALUE_BUCKETS[5].2);
+    }
+}
@@ -1,4 +1,4 @@
-use egui::{Pos2, Rect, Vec2};
+use egui::{emath::TSTransform, Pos2, Rect, Vec2};
 
 use crate::export::Transform0;
 
@@ -20,6 +20,11 @@ impl Default for Transform {
 }
 
 impl Transform {
+    /// How much bigger things are on screen than in the graph.
+    pub fn scale(&self) -> f32 {
+        self.z
+    }
+
     pub fn pos_to_screen(&self, pos: Pos2) -> Pos2 {
         Pos2::new(self.z * pos.x + self.t_x, self.z * pos.y + self.t_y)
     }
@@ -37,6 +42,10 @@ impl Transform {
         Vec2::new(vec.x / self.z, vec.y / self.z)
     }
 
+    pub fn to_ts_transform(&self) -> TSTransform {
+        TSTransform::new(Vec2::new(self.t_x, self.t_y), self.z)
+    }
+
     pub fn rect_to_screen(&self, rect: Rect) -> Rect {
         Rect::from_min_max(self.pos_to_screen(rect.min), self.pos_to_screen(rect.max))
     }
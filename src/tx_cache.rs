@@ -1,17 +1,76 @@
-use std::{num::NonZeroUsize, sync::Arc};
+use std::{collections::VecDeque, num::NonZeroUsize, sync::Arc};
 
 use egui::{ahash::HashMap, mutex::Mutex, Context, Id};
 use ehttp::Request;
 use lru::LruCache;
+use serde::Deserialize;
 
 use crate::{
     bitcoin::{Transaction, Txid},
-    client::Client,
+    client::{Client, FetchError},
     loading::Loading,
+    notifications::{Action, NotifyExt},
+    settings::Settings,
 };
 
 const CACHE_SIZE: usize = 500;
 
+struct QueueState {
+    waiting: VecDeque<Box<dyn FnOnce() + Send>>,
+    active: usize,
+    limit: usize,
+}
+
+/// Fetches waiting for one of `limit` slots, so that expanding a big
+/// transaction doesn't fire hundreds of requests at once.
+#[derive(Clone)]
+struct Queue(Arc<Mutex<QueueState>>);
+
+impl Default for Queue {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(QueueState {
+            waiting: VecDeque::new(),
+            active: 0,
+            limit: Settings::default().max_concurrent_fetches,
+        })))
+    }
+}
+
+impl Queue {
+    fn get(ctx: &Context) -> Self {
+        ctx.data_mut(|d| d.get_temp_mut_or_default::<Self>(Id::NULL).clone())
+    }
+
+    fn push(&self, fetch: impl 'static + Send + FnOnce()) {
+        self.0.lock().waiting.push_back(Box::new(fetch));
+        self.pump();
+    }
+
+    /// Called when a fetch has finished and its slot is free again.
+    fn release(&self) {
+        self.0.lock().active -= 1;
+        self.pump();
+    }
+
+    fn pump(&self) {
+        loop {
+            // Don't hold the lock while starting the fetch.
+            let fetch = {
+                let mut state = self.0.lock();
+                if state.active >= state.limit {
+                    return;
+                }
+                let Some(fetch) = state.waiting.pop_front() else {
+                    return;
+                };
+                state.active += 1;
+                fetch
+            };
+            fetch();
+        }
+    }
+}
+
 #[derive(Clone)]
 struct State {
     cache: Arc<Mutex<LruCache<Txid, Transaction>>>,
@@ -55,42 +114,62 @@ impl State {
         self.cache.lock().put(txid, tx);
     }
 
+    /// Call `on_done` right away if `txid` is cached, otherwise once it has
+    /// been fetched.
+    fn get_or_fetch_one(
+        &self,
+        ctx: &Context,
+        txid: Txid,
+        on_done: impl 'static + Send + FnOnce(Result<Transaction, FetchError>),
+    ) {
+        if let Some(tx) = self.get(&txid) {
+            on_done(Ok(tx));
+            return;
+        }
+
+        // Fetch tx from server
+        Loading::start_loading_txid(ctx, txid);
+        let slf = self.clone();
+        let ctx2 = ctx.clone();
+        let queue = Queue::get(ctx);
+        let queue2 = queue.clone();
+        queue.push(move || {
+            let ctx3 = ctx2.clone();
+            Client::fetch_json::<Transaction>(
+                move |base_url| {
+                    let mut req = ehttp::Request::get(&format!("{}/tx/{}", base_url, txid));
+                    authenticate(&mut req, &txid);
+                    req
+                },
+                &ctx3,
+                move |result| {
+                    Loading::loading_txid_done(&ctx2, txid);
+                    if let Ok(ref tx) = result {
+                        slf.insert(txid, tx.clone());
+                    }
+                    on_done(result);
+                    queue2.release();
+                },
+            );
+        });
+    }
+
     fn get_or_fetch(
         &self,
         ctx: &Context,
         txids: &[Txid],
-        on_success: impl 'static + FnOnce(HashMap<Txid, Transaction>),
+        on_success: impl 'static + Send + FnOnce(HashMap<Txid, Transaction>),
     ) {
         let (sender, receiver) = flume::unbounded();
 
         for &txid in txids {
-            let slf = self.clone();
-            let ctx2 = ctx.clone();
             let sender = sender.clone();
-            if let Some(tx) = self.get(&txid) {
-                sender.send(Ok(tx)).unwrap();
-            } else {
-                // Fetch tx from server
-                Loading::start_loading_txid(ctx, txid);
-                Client::fetch_json::<Transaction>(
-                    move |base_url| {
-                        let mut req = ehttp::Request::get(&format!("{}/tx/{}", base_url, txid));
-                        authenticate(&mut req, &txid);
-                        req
-                    },
-                    ctx,
-                    move |result| {
-                        Loading::loading_txid_done(&ctx2, txid);
-                        if let Ok(ref tx) = result {
-                            slf.insert(txid, tx.clone());
-                        }
-                        sender.send(result).unwrap();
-                    },
-                );
-            }
+            self.get_or_fetch_one(ctx, txid, move |result| sender.send(result).unwrap());
         }
 
         let ctx2 = ctx.clone();
+        let slf = self.clone();
+        let txids = txids.to_vec();
 
         let len_expected = txids.len();
         wasm_bindgen_futures::spawn_local(async move {
@@ -101,10 +180,34 @@ impl State {
                     break;
                 }
             }
-            if let Ok(txs) = results.into_iter().collect::<Result<Vec<_>, _>>() {
-                let map: HashMap<_, _> = txs.into_iter().map(|tx| (tx.txid, tx)).collect();
-                ctx2.request_repaint();
-                on_success(map)
+            let timeouts: Vec<f64> = results
+                .iter()
+                .filter_map(|result| result.as_ref().err()?.timeout())
+                .collect();
+            match results.into_iter().collect::<Result<Vec<_>, _>>() {
+                Ok(txs) => {
+                    let map: HashMap<_, _> = txs.into_iter().map(|tx| (tx.txid, tx)).collect();
+                    ctx2.request_repaint();
+                    on_success(map)
+                }
+                // The transactions that did arrive are cached by now, so a
+                // retry only fetches the missing ones.
+                Err(_) if !timeouts.is_empty() => {
+                    let ctx3 = ctx2.clone();
+                    ctx2.notify_error_with_action(
+                        "The backend didn't respond in time",
+                        Some(format!(
+                            "{} of {} transactions timed out after {} s.",
+                            timeouts.len(),
+                            len_expected,
+                            timeouts[0]
+                        )),
+                        Action::new("Retry", move || {
+                            slf.get_or_fetch(&ctx3, &txids, on_success);
+                        }),
+                    );
+                }
+                Err(_) => {}
             }
         });
     }
@@ -113,11 +216,17 @@ impl State {
 pub struct TxCache;
 
 impl TxCache {
+    pub fn set_max_concurrent(ctx: &Context, limit: usize) {
+        let queue = Queue::get(ctx);
+        queue.0.lock().limit = limit.max(1);
+        queue.pump();
+    }
+
     /// [on_success] is only called when all transactions have been fetched successfully.
     pub fn get_batch(
         ctx: &Context,
         txids: &[Txid],
-        on_success: impl 'static + FnOnce(HashMap<Txid, Transaction>),
+        on_success: impl 'static + Send + FnOnce(HashMap<Txid, Transaction>),
     ) {
         let state = State::load(ctx);
         let ctx2 = ctx.clone();
@@ -128,7 +237,76 @@ impl TxCache {
         });
     }
 
-    pub fn get(ctx: &Context, txid: Txid, on_success: impl 'static + FnOnce(Transaction)) {
+    /// Unlike [Self::get_batch], calls [on_done] for every transaction as
+    /// soon as it is available (or failed to load), in no particular order.
+    pub fn get_each(
+        ctx: &Context,
+        txids: &[Txid],
+        on_done: impl 'static + Send + Sync + Fn(Txid, Result<Transaction, FetchError>),
+    ) {
+        let state = State::load(ctx);
+        let on_done = Arc::new(on_done);
+        let remaining = Arc::new(Mutex::new(txids.len()));
+        for &txid in txids {
+            let on_done = on_done.clone();
+            let remaining = remaining.clone();
+            let ctx2 = ctx.clone();
+            let state2 = state.clone();
+            state.get_or_fetch_one(ctx, txid, move |result| {
+                on_done(txid, result);
+                let mut remaining = remaining.lock();
+                *remaining -= 1;
+                if *remaining == 0 {
+                    state2.store(&ctx2);
+                }
+            });
+        }
+    }
+
+    /// Like [Client::poll_json], but waits for a slot among the transaction
+    /// fetches. For background requests that are made for many transactions
+    /// at once, e.g. when a large workspace is opened.
+    pub fn poll_json<T: 'static + for<'de> Deserialize<'de>>(
+        ctx: &Context,
+        mk_request: impl 'static + Send + FnOnce(&str) -> ehttp::Request,
+        on_done: impl 'static + Send + FnOnce(Result<T, FetchError>),
+    ) {
+        let ctx2 = ctx.clone();
+        let queue = Queue::get(ctx);
+        let queue2 = queue.clone();
+        queue.push(move || {
+            Client::poll_json::<T>(mk_request, &ctx2, move |result| {
+                on_done(result);
+                queue2.release();
+            });
+        });
+    }
+
+    /// Record that an output of a cached transaction has been spent since it
+    /// was fetched.
+    pub fn set_spent(ctx: &Context, coin: (Txid, usize), spending_txid: Txid) {
+        let state = State::load(ctx);
+        let Some(mut tx) = state.get(&coin.0) else {
+            return;
+        };
+        if let Some(output) = tx.outputs.get_mut(coin.1) {
+            output.spending_txid = Some(spending_txid);
+            state.insert(coin.0, tx);
+            state.store(ctx);
+        }
+    }
+
+    /// Seed the cache with transactions that came from elsewhere, e.g. a
+    /// self-contained workspace, so they don't have to be fetched.
+    pub fn insert(ctx: &Context, txs: impl IntoIterator<Item = Transaction>) {
+        let state = State::load(ctx);
+        for tx in txs {
+            state.insert(tx.txid, tx);
+        }
+        state.store(ctx);
+    }
+
+    pub fn get(ctx: &Context, txid: Txid, on_success: impl 'static + Send + FnOnce(Transaction)) {
         Self::get_batch(ctx, &vec![txid], move |txs| {
             if let Some(tx) = txs.get(&txid) {
                 on_success(tx.clone());
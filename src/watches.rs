@@ -0,0 +1,469 @@
+use std::{sync::mpsc::Sender, time::Duration};
+
+use egui::{ahash::HashSet, Context};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app::Update,
+    bitcoin::{parse_address, NumberFormat, Sats, TxRef, Txid},
+    client::FetchError,
+    notifications::{Action, NotifyExt},
+    spends::Outspend,
+    tx_cache::TxCache,
+};
+
+/// What to be alerted about.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Rule {
+    /// The coin is spent.
+    Spent { coin: (Txid, usize) },
+    /// The address receives more than `threshold` sats in one transaction.
+    Received { address: String, threshold: u64 },
+}
+
+impl Rule {
+    pub fn describe(&self, format: NumberFormat) -> String {
+        match self {
+            Rule::Spent { coin } => format!("{}:{} moves", coin.0, coin.1),
+            Rule::Received { address, threshold } => {
+                format!(
+                    "{} receives more than {}",
+                    address,
+                    Sats(*threshold).format(format)
+                )
+            }
+        }
+    }
+
+    /// One line of a plain watch list: an outpoint, or an address followed by
+    /// the threshold in sats.
+    pub fn to_line(&self) -> String {
+        match self {
+            Rule::Spent { coin } => format!("{}:{}", coin.0, coin.1),
+            Rule::Received { address, threshold } => format!("{} {}", address, threshold),
+        }
+    }
+
+    /// Reads what [Self::to_line] writes. The threshold is optional and
+    /// defaults to alerting on any amount.
+    pub fn parse_line(line: &str) -> Result<Rule, String> {
+        let mut words = line.split_whitespace();
+        let first = words.next().ok_or("Empty line")?;
+        let threshold = words.next();
+        if words.next().is_some() {
+            return Err("Expected an outpoint or an address and threshold".to_string());
+        }
+
+        if let Ok(TxRef { txid, vout }) = TxRef::parse(first) {
+            let vout = vout.ok_or("Expected an outpoint (txid:vout), not a txid")?;
+            if threshold.is_some() {
+                return Err("Outpoints don't take a threshold".to_string());
+            }
+            return Ok(Rule::Spent {
+                coin: (txid, vout as usize),
+            });
+        }
+        Ok(Rule::Received {
+            address: parse_address(first)?,
+            threshold: threshold
+                .map(|t| t.parse().map_err(|_| format!("Invalid threshold '{}'", t)))
+                .transpose()?
+                .unwrap_or(0),
+        })
+    }
+}
+
+/// Parse a plain watch list as written by [Watches::to_text]. Blank lines and
+/// `#` comments are skipped.
+pub fn parse_list(text: &str) -> Result<Vec<Rule>, String> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.split('#').next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| Rule::parse_line(line).map_err(|e| format!("Line {}: {}", i + 1, e)))
+        .collect()
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Watch {
+    id: Uuid,
+    pub rule: Rule,
+    /// Spent rules only fire once.
+    #[serde(default)]
+    triggered: bool,
+    /// Transactions of the address that have already been checked. `None`
+    /// until the first check, which only takes stock and doesn't alert.
+    #[serde(default)]
+    seen: Option<HashSet<Txid>>,
+    #[serde(default)]
+    status: Status,
+}
+
+/// What the checks found out so far, for the watch list.
+#[derive(Clone, Default, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Status {
+    /// The backend answered at least once.
+    pub checked: bool,
+    /// Transaction that spent a watched coin.
+    pub spending_txid: Option<Txid>,
+    /// Balance of a watched address, including unconfirmed transactions.
+    pub balance: Option<u64>,
+    /// Most recent transaction of a watched address.
+    pub last_txid: Option<Txid>,
+    /// Why the last check failed, until one succeeds again.
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+/// Result of checking a watch against the backend.
+pub enum Check {
+    Spent {
+        spending_txid: Option<Txid>,
+    },
+    /// The address's most recent transactions and how much each paid to it.
+    Received {
+        txs: Vec<(Txid, u64)>,
+    },
+    Balance {
+        balance: u64,
+    },
+    /// The backend couldn't be asked. Background checks don't notify, so this
+    /// is shown on the watch instead.
+    Failed {
+        error: String,
+    },
+}
+
+/// Alert rules, checked by polling the backend while the app is open.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Watches {
+    watches: Vec<Watch>,
+    #[serde(skip)]
+    next_poll: f64,
+}
+
+impl Check {
+    fn failed(err: FetchError) -> Self {
+        Self::Failed {
+            error: err.describe(),
+        }
+    }
+}
+
+impl Watch {
+    pub fn new(rule: Rule) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            rule,
+            triggered: false,
+            seen: None,
+            status: Status::default(),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn status(&self) -> &Status {
+        &self.status
+    }
+}
+
+impl Watches {
+    pub fn add(&mut self, ctx: &Context, rule: Rule, format: NumberFormat) {
+        if self.watches.iter().any(|w| w.rule == rule) {
+            return;
+        }
+        ctx.notify_success(format!("You'll be alerted when {}.", rule.describe(format)));
+        self.watches.push(Watch::new(rule));
+        // Take stock right away.
+        self.next_poll = 0.0;
+    }
+
+    /// The watches of a workspace.
+    pub fn import(watches: Vec<Watch>) -> Self {
+        Self {
+            watches,
+            next_poll: 0.0,
+        }
+    }
+
+    pub fn export(&self) -> Vec<Watch> {
+        self.watches.clone()
+    }
+
+    /// Take over the watches of `other` that aren't here yet.
+    pub fn extend(&mut self, other: Watches) {
+        for watch in other.watches {
+            if !self.watches.iter().any(|w| w.rule == watch.rule) {
+                self.watches.push(watch);
+            }
+        }
+    }
+
+    /// Add the rules that aren't watched yet, returning how many that were.
+    pub fn add_all(&mut self, rules: Vec<Rule>) -> usize {
+        let before = self.watches.len();
+        for rule in rules {
+            if !self.watches.iter().any(|w| w.rule == rule) {
+                self.watches.push(Watch::new(rule));
+            }
+        }
+        self.next_poll = 0.0;
+        self.watches.len() - before
+    }
+
+    /// The rules as a plain list, one per line, for other analysts and
+    /// monitoring tools.
+    pub fn to_text(&self) -> String {
+        self.watches
+            .iter()
+            .map(|w| w.rule.to_line() + "\n")
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watches.is_empty()
+    }
+
+    pub fn remove(&mut self, id: Uuid) {
+        self.watches.retain(|w| w.id != id);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Watch> {
+        self.watches.iter()
+    }
+
+    /// Check all watches every `interval` seconds. The checks wait for a slot
+    /// among the transaction fetches, so many watches don't flood the backend.
+    pub fn poll(&mut self, ctx: &Context, interval: f64, sender: Sender<Update>) {
+        let now = ctx.input(|i| i.time);
+        if now < self.next_poll {
+            return;
+        }
+        self.next_poll = now + interval;
+        ctx.request_repaint_after(Duration::from_secs_f64(interval));
+
+        for watch in self.watches.iter().filter(|w| !w.triggered) {
+            let id = watch.id;
+            let sender = sender.clone();
+            match &watch.rule {
+                Rule::Spent { coin: (txid, vout) } => {
+                    let (txid, vout) = (*txid, *vout);
+                    TxCache::poll_json::<Outspend>(
+                        ctx,
+                        move |base_url| {
+                            ehttp::Request::get(format!(
+                                "{}/tx/{}/outspend/{}",
+                                base_url, txid, vout
+                            ))
+                        },
+                        move |result| {
+                            let check = match result {
+                                Ok(outspend) => Check::Spent {
+                                    spending_txid: outspend.spending_txid(),
+                                },
+                                Err(err) => Check::failed(err),
+                            };
+                            sender.send(Update::WatchChecked { id, check }).unwrap();
+                        },
+                    );
+                }
+                Rule::Received { address, .. } => {
+                    let address = address.clone();
+                    let address2 = address.clone();
+                    let address3 = address.clone();
+                    let sender2 = sender.clone();
+                    TxCache::poll_json::<AddressStats>(
+                        ctx,
+                        move |base_url| {
+                            ehttp::Request::get(format!("{}/address/{}", base_url, address3))
+                        },
+                        move |result| {
+                            let check = match result {
+                                Ok(stats) => Check::Balance {
+                                    balance: stats.balance(),
+                                },
+                                Err(err) => Check::failed(err),
+                            };
+                            sender2.send(Update::WatchChecked { id, check }).unwrap();
+                        },
+                    );
+                    TxCache::poll_json::<Vec<AddressTx>>(
+                        ctx,
+                        move |base_url| {
+                            ehttp::Request::get(format!("{}/address/{}/txs", base_url, address))
+                        },
+                        move |result| {
+                            let check = match result {
+                                Ok(txs) => Check::Received {
+                                    txs: txs
+                                        .into_iter()
+                                        .map(|tx| {
+                                            let received = tx
+                                                .vout
+                                                .iter()
+                                                .filter(|o| {
+                                                    o.scriptpubkey_address.as_ref()
+                                                        == Some(&address2)
+                                                })
+                                                .map(|o| o.value)
+                                                .sum();
+                                            (tx.txid, received)
+                                        })
+                                        .collect(),
+                                },
+                                Err(err) => Check::failed(err),
+                            };
+                            sender.send(Update::WatchChecked { id, check }).unwrap();
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Evaluate the result of a check and raise an alert if the rule fired.
+    pub fn checked(
+        &mut self,
+        ctx: &Context,
+        id: Uuid,
+        check: Check,
+        sender: Sender<Update>,
+        desktop_alerts: bool,
+        format: NumberFormat,
+    ) {
+        let Some(watch) = self.watches.iter_mut().find(|w| w.id == id) else {
+            return;
+        };
+
+        if let Check::Failed { error } = check {
+            watch.status.error = Some(error);
+            return;
+        }
+
+        let mut alerts = vec![];
+        watch.status.checked = true;
+        watch.status.error = None;
+
+        match (&watch.rule, check) {
+            (
+                Rule::Spent { .. },
+                Check::Spent {
+                    spending_txid: Some(spending_txid),
+                },
+            ) => {
+                watch.triggered = true;
+                watch.status.spending_txid = Some(spending_txid);
+                alerts.push(("Watched coin moved", spending_txid));
+            }
+            (Rule::Received { .. }, Check::Balance { balance }) => {
+                watch.status.balance = Some(balance);
+            }
+            (Rule::Received { threshold, .. }, Check::Received { txs }) => {
+                watch.status.last_txid = txs.first().map(|(txid, _)| *txid);
+                if let Some(seen) = &mut watch.seen {
+                    for (txid, received) in &txs {
+                        if seen.insert(*txid) && received > threshold {
+                            alerts.push(("Watched address received funds", *txid));
+                        }
+                    }
+                } else {
+                    watch.seen = Some(txs.iter().map(|(txid, _)| *txid).collect());
+                }
+            }
+            _ => {}
+        }
+
+        // Does nothing on the web.
+        if desktop_alerts && !alerts.is_empty() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+                egui::UserAttentionType::Critical,
+            ));
+        }
+
+        for (message, txid) in alerts {
+            let sender = sender.clone();
+            ctx.notify_alert(
+                message,
+                Some(format!("{} (in {}).", watch.rule.describe(format), txid)),
+                Action::new("Show", move || {
+                    sender
+                        .send(Update::LoadOrSelectTx { txid, pos: None })
+                        .unwrap();
+                }),
+            );
+        }
+    }
+}
+
+/// The part of the backend's `/address/:address/txs` response we need.
+#[derive(Deserialize)]
+struct AddressTx {
+    txid: Txid,
+    vout: Vec<AddressTxOutput>,
+}
+
+#[derive(Deserialize)]
+struct AddressTxOutput {
+    scriptpubkey_address: Option<String>,
+    value: u64,
+}
+
+/// Response of the backend's `/address/:address` endpoint.
+#[derive(Deserialize)]
+struct AddressStats {
+    chain_stats: TxoStats,
+    mempool_stats: TxoStats,
+}
+
+#[derive(Deserialize)]
+struct TxoStats {
+    funded_txo_sum: u64,
+    spent_txo_sum: u64,
+}
+
+impl AddressStats {
+    /// Including unconfirmed transactions.
+    fn balance(&self) -> u64 {
+        let funded = self.chain_stats.funded_txo_sum + self.mempool_stats.funded_txo_sum;
+        let spent = self.chain_stats.spent_txo_sum + self.mempool_stats.spent_txo_sum;
+        funded.saturating_sub(spent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TXID: &str = "0627052b6f28912f2703066a912ea577f2ce4da4caa5a5fbd8a57286c345c2f2";
+    const ADDRESS: &str = "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu";
+
+    #[test]
+    fn plain_list() {
+        let text = format!("# Cold storage\n{TXID}:1\n\n{ADDRESS} 100000 # exchange\n{ADDRESS}\n");
+        let rules = parse_list(&text).unwrap();
+        let spent = Rule::Spent {
+            coin: (Txid::new(TXID).unwrap(), 1),
+        };
+        let received = |threshold| Rule::Received {
+            address: ADDRESS.to_string(),
+            threshold,
+        };
+        assert_eq!(rules, vec![spent.clone(), received(100_000), received(0)]);
+
+        let mut watches = Watches::default();
+        assert_eq!(watches.add_all(rules.clone()), 3);
+        assert_eq!(watches.add_all(vec![spent]), 0);
+        assert_eq!(parse_list(&watches.to_text()), Ok(rules));
+
+        assert_eq!(
+            parse_list(&format!("{ADDRESS}\n{TXID}")),
+            Err("Line 2: Expected an outpoint (txid:vout), not a txid".to_string())
+        );
+        assert!(parse_list(&format!("{TXID}:0 5")).is_err());
+        assert!(parse_list("nonsense").is_err());
+    }
+}
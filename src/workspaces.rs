@@ -1,28 +1,56 @@
-use std::sync::{
-    mpsc::{channel, Receiver, Sender},
-    Arc,
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
 };
 
 use chrono::{DateTime, Local, Utc};
-use egui::{mutex::Mutex, Button, Context, Id, Label, TextEdit, Ui};
+use egui::{
+    mutex::Mutex, Button, Context, FontFamily, Grid, Id, Label, RichText, Sense, TextEdit, Ui,
+};
 use egui_extras::{Column, TableBuilder};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::{app::Update, export, modal, notifications::NotifyExt, style, widgets::UiExt};
+use crate::{
+    app::Update, bitcoin::Txid, export, modal, notifications::NotifyExt, style, tx_cache::TxCache,
+    widgets::UiExt,
+};
+
+/// How long deleted workspaces are kept in the trash.
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// Permanently deleting workspaces with more transactions than this requires
+/// typing their name.
+const LARGE_WORKSPACE: usize = 20;
 
 pub struct Workspaces {
     sender: Sender<Msg>,
     receiver: Arc<Mutex<Receiver<Msg>>>,
     update_sender: Sender<Update>,
     workspaces: Vec<Workspace>,
+    /// Deleted workspaces, most recently deleted last.
+    trash: Vec<Workspace>,
+    templates: Vec<Template>,
     current_workspace: Uuid,
     window_open: bool,
     input_new_name: Option<String>,
+    /// Template to create the new workspace from.
+    input_new_template: Option<Uuid>,
+    input_save_template: Option<NewTemplate>,
     input_import_json: Option<String>,
     input_rename: Option<String>,
     input_confirm_delete: bool,
+    /// Trashed workspace to delete for good, and the name typed to confirm.
+    input_delete_forever: Option<(Uuid, String)>,
     request_focus: bool,
+    search: String,
+    /// Only show the workspaces in this folder.
+    folder_filter: Option<String>,
+    sort_by: SortBy,
+    sort_descending: bool,
 }
 
 /// This is a bit of a hack. Ideally, we'd like this to be part of [AppStore].
@@ -30,6 +58,19 @@ pub struct Workspaces {
 struct WorkspacesStore {
     current_workspace: Uuid,
     window_open: bool,
+    #[serde(default)]
+    sort_by: SortBy,
+    #[serde(default)]
+    sort_descending: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+enum SortBy {
+    Name,
+    #[default]
+    Created,
+    Modified,
+    Transactions,
 }
 
 impl Workspaces {
@@ -45,19 +86,30 @@ impl Workspaces {
             receiver: Arc::new(Mutex::new(receiver)),
             update_sender,
             workspaces: vec![workspace],
+            trash: vec![],
+            templates: vec![],
             current_workspace,
             window_open: false,
             input_new_name: None,
+            input_new_template: None,
+            input_save_template: None,
             input_import_json: None,
             input_rename: None,
             input_confirm_delete: false,
+            input_delete_forever: None,
             request_focus: false,
+            search: String::new(),
+            folder_filter: None,
+            sort_by: SortBy::default(),
+            sort_descending: false,
         }
     }
 
     pub fn save(&self, storage: &mut dyn eframe::Storage) {
         // We ideally don't want to break the data in this key, ever:
         eframe::set_value(storage, "workspaces", &self.workspaces);
+        eframe::set_value(storage, "workspaces_trash", &self.trash);
+        eframe::set_value(storage, "workspace_templates", &self.templates);
 
         eframe::set_value(
             storage,
@@ -65,6 +117,8 @@ impl Workspaces {
             &WorkspacesStore {
                 current_workspace: self.current_workspace,
                 window_open: self.window_open,
+                sort_by: self.sort_by,
+                sort_descending: self.sort_descending,
             },
         );
     }
@@ -80,11 +134,25 @@ impl Workspaces {
             result.workspaces = workspaces;
         }
 
+        if let Some(trash) = eframe::get_value::<Vec<Workspace>>(storage, "workspaces_trash") {
+            let cutoff = Utc::now() - chrono::Duration::days(TRASH_RETENTION_DAYS);
+            result.trash = trash
+                .into_iter()
+                .filter(|w| w.deleted_at.is_some_and(|t| t > cutoff))
+                .collect();
+        }
+
+        if let Some(templates) = eframe::get_value(storage, "workspace_templates") {
+            result.templates = templates;
+        }
+
         if let Some(workspaces_store) =
             eframe::get_value::<WorkspacesStore>(storage, "workspaces_store")
         {
             result.window_open = workspaces_store.window_open;
             result.current_workspace = workspaces_store.current_workspace;
+            result.sort_by = workspaces_store.sort_by;
+            result.sort_descending = workspaces_store.sort_descending;
         }
 
         if result.workspaces.is_empty() {
@@ -101,9 +169,28 @@ impl Workspaces {
             result.current_workspace = result.workspaces.first().unwrap().id;
         }
 
+        result.reindex(ctx);
         result
     }
 
+    /// Record which transactions the workspaces other than the current one
+    /// contain, for [WorkspacesHandle::containing].
+    fn reindex(&self, ctx: &Context) {
+        let mut index: TxIndex = HashMap::new();
+        for workspace in &self.workspaces {
+            if workspace.id == self.current_workspace {
+                continue;
+            }
+            for t in &workspace.data.transactions {
+                index
+                    .entry(t.txid)
+                    .or_default()
+                    .push((workspace.id, workspace.name.clone()));
+            }
+        }
+        ctx.data_mut(|d| d.insert_temp(Id::NULL, Arc::new(index)));
+    }
+
     fn with_current(&mut self, f: impl FnOnce(&mut Workspace)) {
         let i = self
             .workspaces
@@ -121,11 +208,15 @@ impl Workspaces {
             .unwrap()
     }
 
+    pub fn current_name(&self) -> &str {
+        &self.current().name
+    }
+
     pub fn current_data(&self) -> export::Workspace {
         self.current().data.clone()
     }
 
-    fn apply_update(&mut self, msg: Msg) {
+    fn apply_update(&mut self, ctx: &Context, msg: Msg) {
         match msg {
             Msg::New { name, data } => {
                 let mut p = Workspace::new(name);
@@ -134,10 +225,15 @@ impl Workspaces {
                 }
                 let id = p.id;
                 self.workspaces.push(p);
-                self.apply_update(Msg::Select { id });
+                self.apply_update(ctx, Msg::Select { id });
             }
             Msg::UpdateData { data } => {
-                self.with_current(|p| p.data = data);
+                self.with_current(|p| {
+                    if p.data != data {
+                        p.data = data;
+                        p.modified_at = Some(Utc::now());
+                    }
+                });
             }
             Msg::Select { id } => {
                 self.current_workspace = id;
@@ -150,20 +246,83 @@ impl Workspaces {
             Msg::Rename { name } => {
                 self.with_current(|p| p.name = name);
             }
+            Msg::SetFolder { folder } => {
+                self.with_current(|p| p.folder = folder);
+            }
             // Msg::TogglePublic => {
             //     self.with_current(|p| p.is_public = !p.is_public);
             // }
             Msg::Delete => {
-                self.workspaces.retain(|p| p.id != self.current_workspace);
+                if let Some(i) = self
+                    .workspaces
+                    .iter()
+                    .position(|p| p.id == self.current_workspace)
+                {
+                    let mut p = self.workspaces.remove(i);
+                    p.deleted_at = Some(Utc::now());
+                    self.trash.push(p);
+                }
                 if let Some(p) = self.workspaces.first() {
-                    self.apply_update(Msg::Select { id: p.id });
+                    self.apply_update(ctx, Msg::Select { id: p.id });
                 } else {
-                    self.apply_update(Msg::New {
-                        name: "Unnamed".to_string(),
-                        data: None,
-                    });
+                    self.apply_update(
+                        ctx,
+                        Msg::New {
+                            name: "Unnamed".to_string(),
+                            data: None,
+                        },
+                    );
                 }
             }
+            Msg::Restore { id } => {
+                if let Some(i) = self.trash.iter().position(|p| p.id == id) {
+                    let mut p = self.trash.remove(i);
+                    p.deleted_at = None;
+                    self.workspaces.push(p);
+                    self.apply_update(ctx, Msg::Select { id });
+                }
+            }
+            Msg::DeleteForever { id } => {
+                self.trash.retain(|p| p.id != id);
+            }
+            Msg::SaveTemplate {
+                name,
+                with_transactions,
+            } => {
+                let mut data = self.current_data();
+                if !with_transactions {
+                    // Annotations are tied to transactions, so they go too.
+                    data.transactions.clear();
+                    data.annotations = Default::default();
+                    data.selections.clear();
+                    data.groups.clear();
+                }
+                ctx.notify_success(format!("Saved template `{}`.", name));
+                self.templates.push(Template {
+                    id: Uuid::now_v7(),
+                    name,
+                    data,
+                });
+            }
+            Msg::DeleteTemplate { id } => {
+                self.templates.retain(|t| t.id != id);
+            }
+            Msg::ImportAnnotations { id, txid } => {
+                if let Some(p) = self.workspaces.iter().find(|p| p.id == id) {
+                    self.update_sender
+                        .send(Update::MergeAnnotations {
+                            annotations: p.data.annotations.of_tx(txid),
+                        })
+                        .unwrap();
+                }
+            }
+            Msg::CopySelfContained { name, data } => {
+                ctx.output_mut(|o| o.copied_text = serde_json::to_string(&data).unwrap());
+                ctx.notify_success(format!(
+                    "Exported workspace `{}` with all transaction data to clipboard.",
+                    name
+                ));
+            }
         }
     }
 
@@ -177,6 +336,17 @@ impl Workspaces {
     }
 
     pub fn show_window(&mut self, ctx: &Context) {
+        let receiver = self.receiver.clone();
+        let mut reindex = false;
+        for msg in receiver.lock().try_iter() {
+            // Only the current workspace's data changes, and it isn't indexed.
+            reindex |= !matches!(msg, Msg::UpdateData { .. });
+            self.apply_update(ctx, msg);
+        }
+        if reindex {
+            self.reindex(ctx);
+        }
+
         let mut open = self.window_open;
         egui::Window::new("Workspaces")
             .open(&mut open)
@@ -184,12 +354,153 @@ impl Workspaces {
         self.window_open = open;
     }
 
+    fn folders(&self) -> Vec<String> {
+        let mut folders: Vec<String> = self
+            .workspaces
+            .iter()
+            .filter(|w| !w.folder.is_empty())
+            .map(|w| w.folder.clone())
+            .collect();
+        folders.sort();
+        folders.dedup();
+        folders
+    }
+
+    fn show_trash(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new(format!("Trash ({})", self.trash.len()))
+            .id_source("Workspace Trash")
+            .show(ui, |ui| {
+                Grid::new("Workspace Trash")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for workspace in self.trash.iter().rev() {
+                            ui.label(&workspace.name);
+                            ui.weak(
+                                workspace.deleted_at.map_or(String::new(), |t| {
+                                    format!("Deleted {}", format_date(t))
+                                }),
+                            );
+                            ui.horizontal(|ui| {
+                                if ui.button("Restore").clicked() {
+                                    self.sender.send(Msg::Restore { id: workspace.id }).unwrap();
+                                }
+                                if ui.button("Delete forever").clicked() {
+                                    if workspace.data.transactions.len() > LARGE_WORKSPACE {
+                                        self.input_delete_forever =
+                                            Some((workspace.id, String::new()));
+                                        self.request_focus = true;
+                                    } else {
+                                        self.sender
+                                            .send(Msg::DeleteForever { id: workspace.id })
+                                            .unwrap();
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        let Some((id, typed)) = &mut self.input_delete_forever else {
+            return;
+        };
+        let Some(workspace) = self.trash.iter().find(|w| w.id == *id) else {
+            self.input_delete_forever = None;
+            return;
+        };
+        let mut close = false;
+        modal::show(ui.ctx(), "Delete Workspace Forever", |ui| {
+            ui.label(format!(
+                "`{}` has {} transactions and can't be restored once deleted. Type its name to confirm.",
+                workspace.name,
+                workspace.data.transactions.len()
+            ));
+            let resp = ui.add(TextEdit::singleline(typed).hint_text(&workspace.name));
+            if self.request_focus {
+                resp.request_focus();
+                self.request_focus = false;
+            }
+
+            ui.add_space(3.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Cancel").clicked() {
+                    close = true;
+                }
+                if ui
+                    .add_enabled(*typed == workspace.name, Button::new("Delete forever"))
+                    .clicked()
+                {
+                    self.sender
+                        .send(Msg::DeleteForever { id: workspace.id })
+                        .unwrap();
+                    close = true;
+                }
+            });
+        });
+        if close {
+            self.input_delete_forever = None;
+        }
+    }
+
     fn show_ui(&mut self, ui: &mut Ui) {
-        let receiver = self.receiver.clone();
-        for msg in receiver.lock().try_iter() {
-            self.apply_update(msg);
+        let folders = self.folders();
+        if self
+            .folder_filter
+            .as_ref()
+            .is_some_and(|f| !folders.contains(f))
+        {
+            self.folder_filter = None;
         }
 
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.search)
+                    .hint_text("Search names and labels...")
+                    .desired_width(200.0),
+            );
+            if !folders.is_empty() {
+                egui::ComboBox::from_id_source("Workspace Folder Filter")
+                    .selected_text(self.folder_filter.as_deref().unwrap_or("All folders"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.folder_filter, None, "All folders");
+                        for folder in &folders {
+                            ui.selectable_value(
+                                &mut self.folder_filter,
+                                Some(folder.clone()),
+                                folder,
+                            );
+                        }
+                    });
+            }
+        });
+
+        ui.add_space(3.0);
+
+        let mut workspaces: Vec<&Workspace> = self
+            .workspaces
+            .iter()
+            .filter(|w| self.search.is_empty() || w.matches(&self.search))
+            .filter(|w| {
+                self.folder_filter
+                    .as_ref()
+                    .map_or(true, |folder| &w.folder == folder)
+            })
+            .collect();
+        match self.sort_by {
+            SortBy::Name => workspaces.sort_by_key(|w| w.name.to_lowercase()),
+            SortBy::Created => workspaces.sort_by_key(|w| w.created_at),
+            SortBy::Modified => workspaces.sort_by_key(|w| w.modified_at()),
+            SortBy::Transactions => workspaces.sort_by_key(|w| w.data.transactions.len()),
+        }
+        if self.sort_descending {
+            workspaces.reverse();
+        }
+
+        let sort_by = &mut self.sort_by;
+        let sort_descending = &mut self.sort_descending;
+
         TableBuilder::new(ui)
             .striped(true)
             .resizable(false)
@@ -201,37 +512,54 @@ impl Workspaces {
                     .resizable(false),
             )
             .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
+            .column(Column::auto())
             // .column(Column::auto().at_least(10.0))
             .sense(egui::Sense::click())
             .header(20.0, |mut header| {
                 header.col(|ui| {
-                    ui.bold("Name");
+                    sort_header(ui, "Name", SortBy::Name, sort_by, sort_descending);
                 });
                 header.col(|ui| {
-                    ui.bold("Created");
+                    ui.bold("Folder");
+                });
+                header.col(|ui| {
+                    sort_header(ui, "Created", SortBy::Created, sort_by, sort_descending);
+                });
+                header.col(|ui| {
+                    sort_header(ui, "Modified", SortBy::Modified, sort_by, sort_descending);
+                });
+                header.col(|ui| {
+                    sort_header(ui, "Txs", SortBy::Transactions, sort_by, sort_descending);
                 });
                 // header.col(|ui| {
                 //     ui.bold("Public");
                 // });
             })
             .body(|mut body| {
-                for workspace in &self.workspaces {
+                for workspace in workspaces {
                     body.row(20.0, |mut row| {
                         row.set_selected(workspace.id == self.current_workspace);
 
                         row.col(|ui| {
                             ui.add(Label::new(workspace.name.clone()).selectable(false));
                         });
+                        row.col(|ui| {
+                            ui.add(Label::new(workspace.folder.clone()).selectable(false));
+                        });
+                        row.col(|ui| {
+                            ui.add(Label::new(format_date(workspace.created_at)).selectable(false));
+                        });
                         row.col(|ui| {
                             ui.add(
-                                Label::new(
-                                    workspace
-                                        .created_at
-                                        .with_timezone(&Local)
-                                        .format("%Y-%m-%d %H:%M")
-                                        .to_string(),
-                                )
-                                .selectable(false),
+                                Label::new(format_date(workspace.modified_at())).selectable(false),
+                            );
+                        });
+                        row.col(|ui| {
+                            ui.add(
+                                Label::new(workspace.data.transactions.len().to_string())
+                                    .selectable(false),
                             );
                         });
                         // row.col(|ui| {
@@ -255,6 +583,7 @@ impl Workspaces {
         ui.horizontal(|ui| {
             if ui.button("New Workspace").clicked() {
                 self.input_new_name = Some("".to_string());
+                self.input_new_template = None;
                 self.request_focus = true;
             }
             if let Some(name) = &self.input_new_name {
@@ -268,6 +597,39 @@ impl Workspaces {
                         self.request_focus = false;
                     }
 
+                    if !self.templates.is_empty() {
+                        ui.add_space(3.0);
+                        ui.horizontal(|ui| {
+                            ui.label("Template:");
+                            let selected = self
+                                .templates
+                                .iter()
+                                .find(|t| Some(t.id) == self.input_new_template);
+                            egui::ComboBox::from_id_source("New Workspace Template")
+                                .selected_text(selected.map_or("None", |t| t.name.as_str()))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.input_new_template, None, "None");
+                                    for template in &self.templates {
+                                        ui.selectable_value(
+                                            &mut self.input_new_template,
+                                            Some(template.id),
+                                            &template.name,
+                                        );
+                                    }
+                                });
+                            if let Some(id) = self.input_new_template {
+                                if ui
+                                    .button("🗑")
+                                    .on_hover_text("Delete this template.")
+                                    .clicked()
+                                {
+                                    self.sender.send(Msg::DeleteTemplate { id }).unwrap();
+                                    self.input_new_template = None;
+                                }
+                            }
+                        });
+                    }
+
                     ui.add_space(3.0);
 
                     ui.horizontal(|ui| {
@@ -278,10 +640,15 @@ impl Workspaces {
                             .add_enabled(!new_name.is_empty(), Button::new("Create"))
                             .clicked()
                         {
+                            let data = self
+                                .templates
+                                .iter()
+                                .find(|t| Some(t.id) == self.input_new_template)
+                                .map(|t| t.data.clone());
                             self.sender
                                 .send(Msg::New {
                                     name: new_name.clone(),
-                                    data: None,
+                                    data,
                                 })
                                 .unwrap();
                             self.input_new_name = None;
@@ -362,6 +729,11 @@ impl Workspaces {
             }
         });
 
+        if !self.trash.is_empty() {
+            ui.add_space(3.0);
+            self.show_trash(ui);
+        }
+
         ui.separator();
         ui.bold("Current Workspace:");
 
@@ -410,7 +782,10 @@ impl Workspaces {
             }
             if self.input_confirm_delete {
                 modal::show(&ui.ctx(), "Delete Workspace", |ui| {
-                    ui.label("Are you sure you want to delete the current workspace?");
+                    ui.label(format!(
+                        "Move the current workspace to the trash? It is kept there for {} days.",
+                        TRASH_RETENTION_DAYS
+                    ));
 
                     ui.add_space(3.0);
 
@@ -426,6 +801,82 @@ impl Workspaces {
                 });
             }
 
+            ui.menu_button("Folder", |ui| {
+                let mut folder = self.current().folder.clone();
+                if ui
+                    .add(TextEdit::singleline(&mut folder).hint_text("Folder name..."))
+                    .changed()
+                {
+                    self.sender.send(Msg::SetFolder { folder }).unwrap();
+                }
+                for folder in &folders {
+                    if ui.button(folder).clicked() {
+                        self.sender
+                            .send(Msg::SetFolder {
+                                folder: folder.clone(),
+                            })
+                            .unwrap();
+                        ui.close_menu();
+                    }
+                }
+                if !self.current().folder.is_empty() && ui.button("Remove from folder").clicked() {
+                    self.sender
+                        .send(Msg::SetFolder {
+                            folder: String::new(),
+                        })
+                        .unwrap();
+                    ui.close_menu();
+                }
+            });
+
+            if ui.button("Save as Template").clicked() {
+                self.input_save_template = Some(NewTemplate {
+                    name: self.current().name.to_string(),
+                    with_transactions: false,
+                });
+                self.request_focus = true;
+            }
+            if let Some(template) = &mut self.input_save_template {
+                let mut close = false;
+                modal::show(ui.ctx(), "Save as Template", |ui| {
+                    let resp = ui.add(
+                        TextEdit::singleline(&mut template.name).hint_text("Template name..."),
+                    );
+                    if self.request_focus {
+                        resp.request_focus();
+                        self.request_focus = false;
+                    }
+                    ui.checkbox(&mut template.with_transactions, "Include transactions")
+                        .on_hover_text(
+                            "Keep the transactions and their annotations as a starting point. \
+                            Otherwise only the layout settings and the view are saved.",
+                        );
+
+                    ui.add_space(3.0);
+
+                    ui.horizontal(|ui| {
+                        if ui.button("Cancel").clicked() {
+                            close = true;
+                        }
+                        if ui
+                            .add_enabled(!template.name.is_empty(), Button::new("Save"))
+                            .clicked()
+                        {
+                            self.sender
+                                .send(Msg::SaveTemplate {
+                                    name: template.name.clone(),
+                                    with_transactions: template.with_transactions,
+                                })
+                                .unwrap();
+                            close = true;
+                        }
+                    });
+                });
+                if close {
+                    self.input_save_template = None;
+                }
+            }
+
             // let mut is_public = self.current().is_public;
             // if ui.checkbox(&mut is_public, "Public").clicked() {
             //     self.sender.send(Msg::TogglePublic).unwrap();
@@ -439,6 +890,22 @@ impl Workspaces {
                     current.name
                 ));
             }
+
+            if ui
+                .button("Export self-contained JSON")
+                .on_hover_text("Include the transaction data so the workspace can be opened without a backend.")
+                .clicked()
+            {
+                let current = self.current();
+                let name = current.name.clone();
+                let mut data = current.data.clone();
+                let txids: Vec<_> = data.transactions.iter().map(|t| t.txid).collect();
+                let sender = self.sender.clone();
+                TxCache::get_batch(ui.ctx(), &txids, move |txs| {
+                    data.embed(txs.into_values());
+                    sender.send(Msg::CopySelfContained { name, data }).unwrap();
+                });
+            }
         });
 
         ui.add_space(3.0);
@@ -464,8 +931,35 @@ enum Msg {
     Rename {
         name: String,
     },
+    SetFolder {
+        folder: String,
+    },
     // TogglePublic,
+    /// Move the current workspace to the trash.
     Delete,
+    Restore {
+        id: Uuid,
+    },
+    DeleteForever {
+        id: Uuid,
+    },
+    SaveTemplate {
+        name: String,
+        with_transactions: bool,
+    },
+    DeleteTemplate {
+        id: Uuid,
+    },
+    /// Copy the annotations of a transaction from another workspace.
+    ImportAnnotations {
+        id: Uuid,
+        txid: Txid,
+    },
+    /// The transaction data for a self-contained export has arrived.
+    CopySelfContained {
+        name: String,
+        data: export::Workspace,
+    },
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -476,6 +970,14 @@ struct Workspace {
     id: Uuid,
     name: String,
     created_at: DateTime<Utc>,
+    #[serde(default)]
+    modified_at: Option<DateTime<Utc>>,
+    /// Empty if the workspace isn't in a folder.
+    #[serde(default)]
+    folder: String,
+    /// When the workspace was moved to the trash.
+    #[serde(default)]
+    deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Workspace {
@@ -487,19 +989,117 @@ impl Workspace {
             id: Uuid::now_v7(),
             name,
             created_at: Utc::now(),
+            modified_at: None,
+            folder: String::new(),
+            deleted_at: None,
         }
     }
+
+    fn modified_at(&self) -> DateTime<Utc> {
+        self.modified_at.unwrap_or(self.created_at)
+    }
+
+    /// Case-insensitive match against the name, folder and labels.
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        std::iter::once(self.name.as_str())
+            .chain(std::iter::once(self.folder.as_str()))
+            .chain(self.data.annotations.labels())
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn sort_header(
+    ui: &mut Ui,
+    text: &str,
+    column: SortBy,
+    sort_by: &mut SortBy,
+    descending: &mut bool,
+) {
+    let arrow = match (*sort_by == column, *descending) {
+        (false, _) => "",
+        (true, false) => " ⏶",
+        (true, true) => " ⏷",
+    };
+    let label = RichText::new(format!("{}{}", text, arrow)).family(FontFamily::Name("bold".into()));
+    if ui
+        .add(Label::new(label).selectable(false).sense(Sense::click()))
+        .clicked()
+    {
+        if *sort_by == column {
+            *descending = !*descending;
+        } else {
+            *sort_by = column;
+            *descending = false;
+        }
+    }
+}
+
+/// Starting point for new workspaces, e.g. a team's standard case setup.
+#[derive(Clone, Deserialize, Serialize)]
+struct Template {
+    id: Uuid,
+    name: String,
+    data: export::Workspace,
+}
+
+struct NewTemplate {
+    name: String,
+    with_transactions: bool,
 }
 
 #[derive(Clone)]
 struct WorkspacesSender(Sender<Msg>);
 
+/// Ids and names of the other workspaces that contain a transaction.
+type TxIndex = HashMap<Txid, Vec<(Uuid, String)>>;
+
 pub struct WorkspacesHandle;
 
 impl WorkspacesHandle {
+    /// Create a new workspace with the given data and switch to it.
+    pub fn new_workspace(ctx: &Context, name: String, data: export::Workspace) {
+        if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
+            sender
+                .send(Msg::New {
+                    name,
+                    data: Some(data),
+                })
+                .unwrap();
+        }
+    }
+
     pub fn update_workspace(ctx: &Context, data: export::Workspace) {
         if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
             sender.send(Msg::UpdateData { data }).unwrap();
         }
     }
+
+    /// Ids and names of the workspaces other than the current one that
+    /// contain the transaction.
+    pub fn containing(ctx: &Context, txid: Txid) -> Vec<(Uuid, String)> {
+        ctx.data(|d| d.get_temp::<Arc<TxIndex>>(Id::NULL))
+            .and_then(|index| index.get(&txid).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn select(ctx: &Context, id: Uuid) {
+        if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
+            sender.send(Msg::Select { id }).unwrap();
+        }
+    }
+
+    /// Add the annotations of a transaction in another workspace to the
+    /// current one, keeping those set already.
+    pub fn import_annotations(ctx: &Context, id: Uuid, txid: Txid) {
+        if let Some(WorkspacesSender(sender)) = ctx.data(|d| d.get_temp(Id::NULL)) {
+            sender.send(Msg::ImportAnnotations { id, txid }).unwrap();
+        }
+    }
 }